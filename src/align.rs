@@ -0,0 +1,88 @@
+use std::fs;
+
+use transcript::error::Error;
+use transcript::output::{self, Format};
+use transcript::{audio, decode, models, Transcriber};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Model file or name, as for transcribe
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Audio the script was read out in
+    #[arg(short, long)]
+    input: String,
+
+    /// The script as plain text, each line of it a cue
+    #[arg(long, value_name = "FILE")]
+    text: String,
+
+    /// Output path without an extension; without one the first of the formats is printed to stdout
+    #[arg(short, long)]
+    output: Option<String>,
+
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "srt,json")]
+    formats: Vec<Format>,
+
+    /// Replace output files that already exist instead of refusing to run
+    #[arg(long)]
+    overwrite: bool,
+
+    #[command(flatten)]
+    decode_options: decode::Options,
+
+    #[command(flatten)]
+    audio_options: audio::Options,
+
+    #[command(flatten)]
+    output_options: output::Options,
+}
+
+// whisper transcribes the audio with word times, and the script's words take the times of the
+// words they line up with
+pub fn run(args: Args) -> Result<(), Error> {
+    let script = fs::read_to_string(&args.text).map_err(|error| Error::input(format!("failed to read {}", args.text), error))?;
+    if script.split_whitespace().next().is_none() {
+        return Err(Error::Input(format!("{} has no words to align", args.text)));
+    }
+    let model = match args.model.clone() {
+        Some(model) => model,
+        None => models::discover()?,
+    };
+    if let Some(output_path_raw) = args.output.as_deref().filter(|_| !args.overwrite) {
+        let language = args.decode_options.output_language();
+        let existing: Vec<String> = args.formats.iter().flat_map(|format| output::existing(output_path_raw, *format, &args.output_options, language)).collect();
+        if !existing.is_empty() {
+            for path in &existing {
+                tracing::error!("{} already exists", path);
+            }
+            return Err(Error::Output("pass --overwrite to replace existing outputs".to_string()));
+        }
+    }
+
+    let mut audio = audio::load(&args.input, &args.audio_options, Some(args.audio_options.channel))?;
+    let samples = audio.channels.remove(0);
+    let mut decode_options = decode::Options { word_timestamps: true, ..args.decode_options };
+    let mut transcriber = Transcriber::new(model).options(decode_options.clone());
+    if decode_options.language.as_deref() == Some("auto") {
+        let (language, probability) = transcriber.detect_language(&samples)?;
+        tracing::info!("detected language {} ({:.2})", language, probability);
+        decode_options.language = Some(language.to_string());
+        transcriber = transcriber.language(language);
+    }
+    let mut heard = transcriber.transcribe(&samples)?;
+    heard.shift(audio.start_ms as i64);
+
+    let aligned = transcript::alignment::align(&script, &heard);
+    let mut output_options = args.output_options;
+    output_options.language = Some(decode_options.output_language().to_string());
+    output_options.keep_existing = !args.overwrite;
+    match &args.output {
+        Some(output_path_raw) => output::write_outputs(&aligned, output_path_raw, &args.formats, &output_options, decode_options.output_language()),
+        None => {
+            print!("{}", args.formats[0].render(&aligned, &output_options));
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::glossary::levenshtein;
+use crate::transcript::{spread, Segment, Transcript, Word};
+
+// how far either side of the diagonal the alignment looks, in words, on top of the difference
+// between the script's length and what was heard
+const BAND: usize = 500;
+
+// a word left out on either side costs this much, a heard word that isn't the script's up to as
+// much by how far apart they're spelt, so "hole" lines up with "whole" rather than "little"
+const GAP: u32 = 10;
+
+// what alignment compares, so "Well," in the script matches whisper's "well"
+fn normalize(word: &str) -> Vec<char> {
+    word.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+fn substitution(a: &[char], b: &[char]) -> u32 {
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 0;
+    }
+    (GAP as usize * levenshtein(a, b)).div_ceil(longest) as u32
+}
+
+// the heard word each script word lines up with, if any, by the cheapest edits between the two
+// (dynamic time warping over words); only a band around the diagonal is searched so an hour's
+// script stays cheap
+fn pair(script: &[Vec<char>], heard: &[Vec<char>]) -> Vec<Option<usize>> {
+    let (n, m) = (script.len(), heard.len());
+    let band = BAND + n.abs_diff(m);
+    let low = |i: usize| (i * m / n.max(1)).saturating_sub(band);
+    let high = |i: usize| (i * m / n.max(1) + band).min(m);
+    // from where each cell was reached: 0 the diagonal, 1 a script word left out, 2 a heard one
+    let mut from: Vec<Vec<u8>> = Vec::with_capacity(n + 1);
+    let mut previous: Vec<u32> = (low(0)..=high(0)).map(|j| j as u32 * GAP).collect();
+    from.push(vec![2; previous.len()]);
+    for i in 1..=n {
+        let (lo, hi, previous_lo) = (low(i), high(i), low(i - 1));
+        let above = |j: usize| j.checked_sub(previous_lo).and_then(|at| previous.get(at)).copied().unwrap_or(u32::MAX);
+        let mut row: Vec<u32> = Vec::with_capacity(hi - lo + 1);
+        let mut pointers = Vec::with_capacity(hi - lo + 1);
+        for j in lo..=hi {
+            let mut best = (above(j).saturating_add(GAP), 1);
+            if j > 0 {
+                best = best.min((above(j - 1).saturating_add(substitution(&script[i - 1], &heard[j - 1])), 0));
+                if j > lo {
+                    best = best.min((row[j - 1 - lo].saturating_add(GAP), 2));
+                }
+            }
+            row.push(best.0);
+            pointers.push(best.1);
+        }
+        from.push(pointers);
+        previous = row;
+    }
+
+    let mut pairs = vec![None; n];
+    let (mut i, mut j) = (n, m);
+    while i > 0 {
+        match from[i][j - low(i)] {
+            0 => {
+                pairs[i - 1] = Some(j - 1);
+                i -= 1;
+                j -= 1;
+            }
+            1 => i -= 1,
+            _ => j -= 1,
+        }
+    }
+    pairs
+}
+
+// the script timed against what whisper heard in the same audio: every non-empty line of the
+// script becomes a segment, its words taking the times of the heard words they line up with; a
+// word nothing lines up with shares out the time between its neighbours. A word's probability is
+// the heard word's when they're the same word and 0 otherwise, so a misread script shows
+pub fn align(script: &str, transcript: &Transcript) -> Transcript {
+    let lines: Vec<Vec<&str>> = script.lines().map(|line| line.split_whitespace().collect::<Vec<_>>()).filter(|line| !line.is_empty()).collect();
+    let texts: Vec<&str> = lines.iter().flatten().copied().collect();
+    let heard = transcript.timed_words();
+    let pairs = pair(&texts.iter().map(|text| normalize(text)).collect::<Vec<_>>(), &heard.iter().map(|word| normalize(&word.text)).collect::<Vec<_>>());
+
+    let mut words: Vec<Word> = texts
+        .iter()
+        .zip(&pairs)
+        .map(|(text, pair)| match pair {
+            Some(at) => {
+                let word = &heard[*at];
+                let probability = if normalize(text) == normalize(&word.text) { word.probability } else { 0.0 };
+                Word { start_ms: word.start_ms, end_ms: word.end_ms, text: text.to_string(), probability }
+            }
+            None => Word { start_ms: 0, end_ms: 0, text: text.to_string(), probability: 0.0 },
+        })
+        .collect();
+    let end_ms = transcript.segments.iter().map(|segment| segment.end_ms).max().unwrap_or(0);
+    let mut i = 0;
+    while i < words.len() {
+        if pairs[i].is_some() {
+            i += 1;
+            continue;
+        }
+        let gap = i..(i..words.len()).find(|&k| pairs[k].is_some()).unwrap_or(words.len());
+        let start_ms = gap.start.checked_sub(1).map_or(0, |k| words[k].end_ms);
+        let stop_ms = words.get(gap.end).map_or(end_ms, |word| word.start_ms).max(start_ms);
+        for (k, (from, to)) in gap.clone().zip(spread(&texts[gap.clone()], start_ms, stop_ms)) {
+            words[k].start_ms = from;
+            words[k].end_ms = to;
+        }
+        i = gap.end;
+    }
+
+    let mut words = words.into_iter();
+    let segments = lines
+        .iter()
+        .map(|line| {
+            let words: Vec<Word> = words.by_ref().take(line.len()).collect();
+            let mut segment = Segment::new(words[0].start_ms, words[words.len() - 1].end_ms, &format!(" {}", line.join(" ")));
+            segment.words = words;
+            segment
+        })
+        .collect();
+    Transcript { segments }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(start_ms: i64, end_ms: i64, text: &str) -> Word {
+        Word { start_ms, end_ms, text: text.to_string(), probability: 0.9 }
+    }
+
+    #[test]
+    fn _align() {
+        // whisper misheard "whole", left out "little" and heard an "um" that isn't in the script
+        let mut segment = Segment::new(0, 3000, " The hole lamb was, um, white.");
+        segment.words = vec![word(0, 200, "The"), word(200, 600, "hole"), word(1000, 1400, "lamb"), word(1400, 1700, "was,"), word(1700, 1800, "um,"), word(1800, 2400, "white.")];
+        // no word times at all here
+        let transcript = Transcript { segments: vec![segment, Segment::new(3000, 5000, " And then it ran")] };
+
+        let aligned = align("The whole little lamb\n\n  was white.\nAnd then it ran away.\n", &transcript);
+        let times: Vec<Vec<(i64, i64, &str, f32)>> = aligned.segments.iter().map(|segment| segment.words.iter().map(|word| (word.start_ms, word.end_ms, word.text.as_str(), word.probability)).collect()).collect();
+        assert_eq!(
+            times,
+            vec![
+                vec![(0, 200, "The", 0.9), (200, 600, "whole", 0.0), (600, 1000, "little", 0.0), (1000, 1400, "lamb", 0.9)],
+                vec![(1400, 1700, "was", 0.9), (1800, 2400, "white.", 0.9)],
+                vec![(3000, 3500, "And", 0.0), (3500, 4166, "then", 0.0), (4166, 4500, "it", 0.0), (4500, 5000, "ran", 0.0), (5000, 5000, "away.", 0.0)],
+            ]
+        );
+        assert_eq!(aligned.segments[1].text, " was white.");
+        assert_eq!((aligned.segments[2].start_ms, aligned.segments[2].end_ms), (3000, 5000));
+    }
+}
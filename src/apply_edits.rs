@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use transcript::error::Error;
+use transcript::output::{self, parse, Format};
+use transcript::Transcript;
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Transcript the corrections are to: the json transcript, or srt, vtt or sbv
+    input: String,
+
+    /// Corrections as a json object of segment ids to their new text, e.g. {"3": "Hello there."};
+    /// an id is the segment's place in the transcript counting from 0, or the cue's number less
+    /// 1, and empty text removes the segment
+    #[arg(long, value_name = "FILE")]
+    edits: String,
+
+    /// Output path without an extension, the input's own by default, so its outputs are written
+    /// anew over the old ones
+    #[arg(short, long)]
+    output: Option<String>,
+
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "txt,srt,json")]
+    formats: Vec<Format>,
+
+    /// Language the transcript is in, the json's own language or en by default
+    #[arg(short, long)]
+    language: Option<String>,
+
+    #[command(flatten)]
+    output_options: output::Options,
+}
+
+// the corrected text of each segment edited, in place; a segment keeps its times, and its word
+// times too while it has as many words as before. The ids returned are those whose word times
+// had to go.
+fn apply(transcript: &mut Transcript, edits: &BTreeMap<usize, String>) -> Result<Vec<usize>, String> {
+    if let Some(id) = edits.keys().find(|id| **id >= transcript.segments.len()) {
+        return Err(format!("there's no segment {}, the transcript has {} counting from 0", id, transcript.segments.len()));
+    }
+    let mut words_dropped = Vec::new();
+    for (id, text) in edits {
+        let segment = &mut transcript.segments[*id];
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        segment.text = format!(" {}", words.join(" "));
+        if segment.words.len() == words.len() {
+            for (word, text) in segment.words.iter_mut().zip(&words) {
+                word.text = format!(" {}", text);
+            }
+        } else if !segment.words.is_empty() {
+            segment.words.clear();
+            words_dropped.push(*id);
+        }
+        // the tokens were what whisper heard, and a person has now looked the text over
+        segment.tokens.clear();
+        segment.low_confidence = false;
+        segment.hallucination = false;
+    }
+    let mut id = 0;
+    transcript.segments.retain(|_| {
+        id += 1;
+        edits.get(&(id - 1)).is_none_or(|text| !text.trim().is_empty())
+    });
+    Ok(words_dropped)
+}
+
+// the language a json transcript records, as its format writes it
+fn language(path: &str) -> Option<String> {
+    let contents = fs::read_to_string(path).ok()?;
+    let value: serde_json::Value = serde_json::from_str(&contents).ok()?;
+    value.get("language")?.as_str().map(String::from)
+}
+
+// the ids are taken as the text json writes them, so they're parsed here
+fn read_edits(path: &str) -> Result<BTreeMap<usize, String>, Error> {
+    let failed = |error: String| Error::input(format!("failed to read edits {}", path), error);
+    let contents = fs::read_to_string(path).map_err(|error| failed(error.to_string()))?;
+    let edits: BTreeMap<String, String> = serde_json::from_str(&contents).map_err(|error| failed(format!("expected a json object of segment ids to text, {}", error)))?;
+    edits.into_iter().map(|(id, text)| Ok((id.trim().parse().map_err(|_| failed(format!("{} isn't a segment id", id)))?, text))).collect()
+}
+
+pub fn run(args: Args) -> Result<(), Error> {
+    let mut transcript = parse::read(&args.input)?;
+    let edits = read_edits(&args.edits)?;
+    let words_dropped = apply(&mut transcript, &edits).map_err(|error| Error::input(format!("failed to apply {}", args.edits), error))?;
+    if !words_dropped.is_empty() {
+        let ids: Vec<String> = words_dropped.iter().map(usize::to_string).collect();
+        tracing::warn!("segments {} have a different number of words now, so their word times were left out", ids.join(", "));
+    }
+    tracing::info!("applied {} edits to {}", edits.len(), args.input);
+    let language = args.language.or_else(|| language(&args.input)).unwrap_or_else(|| "en".to_string());
+    let output_path_raw = args.output.unwrap_or_else(|| Path::new(&args.input).with_extension("").to_string_lossy().into_owned());
+    let output_options = output::Options { language: Some(language.clone()), ..args.output_options };
+    output::write_outputs(&transcript, &output_path_raw, &args.formats, &output_options, &language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transcript::transcript::{Token, Word};
+    use transcript::Segment;
+
+    #[test]
+    fn _apply() {
+        let word = |start_ms, end_ms, text: &str| Word { start_ms, end_ms, text: text.to_string(), probability: 0.5 };
+        let mut transcript = Transcript {
+            segments: vec![
+                Segment { words: vec![word(0, 500, " Hello"), word(500, 1000, " their.")], tokens: vec![Token::new(" Hello", 0.5)], low_confidence: true, ..Segment::new(0, 1000, " Hello their.") },
+                Segment { words: vec![word(1000, 2000, " Um.")], ..Segment::new(1000, 2000, " Um.") },
+                Segment { words: vec![word(2000, 3000, " Byebye.")], ..Segment::new(2000, 3000, " Byebye.") },
+            ],
+        };
+        let edits = BTreeMap::from([(0, "Hello  there. ".to_string()), (1, String::new()), (2, "Bye bye.".to_string())]);
+        assert_eq!(apply(&mut transcript, &edits), Ok(vec![2]));
+        let segments = &transcript.segments;
+        assert_eq!(segments.iter().map(|segment| (segment.start_ms, segment.text.as_str())).collect::<Vec<_>>(), vec![(0, " Hello there."), (2000, " Bye bye.")]);
+        assert_eq!(segments[0].words[1], word(500, 1000, " there."));
+        assert!(segments[0].tokens.is_empty() && !segments[0].low_confidence && segments[1].words.is_empty());
+        assert!(apply(&mut transcript, &BTreeMap::from([(2, "Hi.".to_string())])).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use super::sink::Sink;
+use super::Audio;
+
+// wav, flac, ogg vorbis, mp3 and m4a/aac/alac through symphonia
+pub fn decode(path: &Path, mut sink: Sink) -> Result<Audio, Error> {
+    let file = File::open(path)?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe().format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())?;
+    let mut format = probed.format;
+    let track = format.default_track().ok_or(Error::Unsupported("no audio track"))?;
+    let track_id = track.id;
+    let sample_rate = track.codec_params.sample_rate.ok_or(Error::Unsupported("unknown sample rate"))?;
+    let frames = track.codec_params.n_frames;
+    let mut decoder = symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    // one conversion buffer for the whole file, only replaced when a packet outgrows it
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(error)) if error.kind() == ErrorKind::UnexpectedEof => break,
+            Err(error) => return Err(error),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // a corrupt packet only loses its own few milliseconds
+            Err(Error::DecodeError(_)) => continue,
+            Err(error) => return Err(error),
+        };
+
+        let spec = *decoded.spec();
+        if !sink.started() {
+            sink.start(sample_rate, spec.channels.count(), frames);
+        }
+        if buffer.as_ref().is_none_or(|buffer| buffer.capacity() < decoded.capacity() * spec.channels.count()) {
+            buffer = Some(SampleBuffer::<f32>::new(decoded.capacity() as u64, spec));
+        }
+        let buffer = buffer.as_mut().expect("buffer was just made");
+        buffer.copy_interleaved_ref(decoded);
+        sink.push(buffer.samples());
+        if sink.full() {
+            break;
+        }
+    }
+
+    Ok(sink.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _decode() {
+        let samples: [i16; 4] = [0, 16384, -16384, 32767];
+        let data: Vec<u8> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+        let mut wav = Vec::new();
+        wav.extend(b"RIFF");
+        wav.extend((36 + data.len() as u32).to_le_bytes());
+        wav.extend(b"WAVEfmt ");
+        // pcm, mono, 16kHz, 32000 bytes a second, 2 byte frames, 16 bits
+        wav.extend(16u32.to_le_bytes());
+        wav.extend([1u16, 1].iter().flat_map(|value| value.to_le_bytes()));
+        wav.extend([16000u32, 32000].iter().flat_map(|value| value.to_le_bytes()));
+        wav.extend([2u16, 16].iter().flat_map(|value| value.to_le_bytes()));
+        wav.extend(b"data");
+        wav.extend((data.len() as u32).to_le_bytes());
+        wav.extend(data);
+
+        let path = std::env::temp_dir().join("transcript-decode-test.wav");
+        std::fs::write(&path, wav).unwrap();
+        let audio = decode(&path, Sink::new(None, None)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(audio.sample_rate, 16000);
+        assert_eq!(audio.channels.len(), 1);
+        let expected = [0.0, 0.5, -0.5, 32767.0 / 32768.0];
+        assert!(audio.channels[0].iter().zip(expected).all(|(sample, expected)| (sample - expected).abs() < 1e-4));
+    }
+}
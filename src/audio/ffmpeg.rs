@@ -0,0 +1,36 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use super::sink::Sink;
+use super::Audio;
+use crate::error::Error;
+
+pub fn available() -> bool {
+    Command::new("ffmpeg").arg("-version").stdout(Stdio::null()).stderr(Stdio::null()).status().is_ok_and(|status| status.success())
+}
+
+// has ffmpeg convert to 16kHz signed 16-bit pcm on a pipe, for containers and codecs symphonia lacks
+pub fn decode(path: &Path, channels: usize, mut sink: Sink) -> Result<Audio, Error> {
+    let mut child = Command::new("ffmpeg")
+        .args(["-nostdin", "-loglevel", "error", "-i"])
+        .arg(path)
+        .args(["-vn", "-f", "s16le", "-acodec", "pcm_s16le", "-ar", "16000", "-ac", &channels.to_string(), "-"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|error| Error::input("failed to run ffmpeg", error))?;
+
+    let stdout = child.stdout.take().expect("ffmpeg has no stdout");
+    sink.start(16000, channels, None);
+    sink.read(stdout, 2, |pair| i16::from_le_bytes([pair[0], pair[1]]) as f32 / 32768.0).map_err(|error| Error::input("failed to read from ffmpeg", error))?;
+    // stopping early leaves ffmpeg blocked on the pipe
+    if sink.full() {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Ok(sink.finish());
+    }
+
+    if !child.wait().map_err(|error| Error::input("failed to wait for ffmpeg", error))?.success() {
+        return Err(Error::Input(format!("ffmpeg could not decode {}", path.display())));
+    }
+    Ok(sink.finish())
+}
@@ -0,0 +1,115 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use super::sink::Sink;
+use super::Audio;
+
+const WAVE_FORMAT_ALAW: u16 = 6;
+const WAVE_FORMAT_MULAW: u16 = 7;
+
+fn alaw(byte: u8) -> i16 {
+    let byte = byte ^ 0x55;
+    let exponent = (byte >> 4) & 0x07;
+    let mantissa = (byte & 0x0F) as i16;
+    let magnitude = if exponent == 0 { (mantissa << 4) + 8 } else { ((mantissa << 4) + 0x108) << (exponent - 1) };
+    if byte & 0x80 != 0 { magnitude } else { -magnitude }
+}
+
+fn mulaw(byte: u8) -> i16 {
+    let byte = !byte;
+    let exponent = (byte >> 4) & 0x07;
+    let mantissa = (byte & 0x0F) as i16;
+    let magnitude = (((mantissa << 3) + 0x84) << exponent) - 0x84;
+    if byte & 0x80 != 0 { -magnitude } else { magnitude }
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+// telephony exports often write a 16 byte fmt chunk for a-law/µ-law, which symphonia rejects,
+// so these wavs get a reader that only cares about the format tag, rate and channel count
+pub fn decode(path: &Path, sink: Sink) -> Option<Audio> {
+    parse(BufReader::new(File::open(path).ok()?), sink)
+}
+
+fn parse(mut reader: impl Read, mut sink: Sink) -> Option<Audio> {
+    let mut header = [0u8; 12];
+    reader.read_exact(&mut header).ok()?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return None;
+    }
+    let mut format = None;
+    let mut chunk = [0u8; 8];
+    while reader.read_exact(&mut chunk).is_ok() {
+        let length = u32_at(&chunk, 4) as u64;
+        if &chunk[0..4] == b"fmt " && length >= 16 {
+            let mut body = vec![0u8; length as usize];
+            reader.read_exact(&mut body).ok()?;
+            format = Some((u16_at(&body, 0), u16_at(&body, 2) as usize, u32_at(&body, 4)));
+        } else if &chunk[0..4] == b"data" {
+            let (tag, count, sample_rate) = format?;
+            let expand = match tag {
+                WAVE_FORMAT_ALAW => alaw,
+                WAVE_FORMAT_MULAW => mulaw,
+                _ => return None,
+            };
+            if count == 0 {
+                return None;
+            }
+            sink.start(sample_rate, count, Some(length / count as u64));
+            sink.read(reader.take(length), 1, |byte| expand(byte[0]) as f32 / 32768.0).ok()?;
+            return Some(sink.finish());
+        } else {
+            io::copy(&mut (&mut reader).take(length), &mut io::sink()).ok()?;
+        }
+        // chunks are padded to an even length
+        if length % 2 == 1 {
+            io::copy(&mut (&mut reader).take(1), &mut io::sink()).ok()?;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(tag: u16, data: &[u8]) -> Vec<u8> {
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend((4 + 24 + 8 + data.len() as u32).to_le_bytes());
+        bytes.extend(b"WAVEfmt ");
+        bytes.extend(16u32.to_le_bytes());
+        for field in [tag, 1] {
+            bytes.extend(field.to_le_bytes());
+        }
+        bytes.extend(8000u32.to_le_bytes());
+        bytes.extend(8000u32.to_le_bytes());
+        for field in [1u16, 8] {
+            bytes.extend(field.to_le_bytes());
+        }
+        bytes.extend(b"data");
+        bytes.extend((data.len() as u32).to_le_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    #[test]
+    fn _parse() {
+        let audio = parse(wav(WAVE_FORMAT_MULAW, &[0xFF, 0x80, 0x00]).as_slice(), Sink::new(None, None)).expect("µ-law wav should parse");
+        assert_eq!(audio.sample_rate, 8000);
+        let samples: Vec<i32> = audio.channels[0].iter().map(|sample| (sample * 32768.0) as i32).collect();
+        assert_eq!(samples, vec![0, 32124, -32124]);
+
+        let audio = parse(wav(WAVE_FORMAT_ALAW, &[0xD5, 0x55, 0xAA]).as_slice(), Sink::new(None, None)).expect("a-law wav should parse");
+        let samples: Vec<i32> = audio.channels[0].iter().map(|sample| (sample * 32768.0) as i32).collect();
+        assert_eq!(samples, vec![8, -8, 32256]);
+
+        assert!(parse(wav(1, &[0, 0]).as_slice(), Sink::new(None, None)).is_none());
+    }
+}
@@ -0,0 +1,82 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use crate::error::Error;
+
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+// the decoders want a seekable file, so the download goes to a temp file named after the url's
+// last path segment, which keeps the extension for probing
+pub fn download(url: &str) -> Result<PathBuf, Error> {
+    let name = url.split(['?', '#']).next().unwrap_or(url).rsplit('/').find(|segment| !segment.is_empty()).unwrap_or("download");
+    let path = env::temp_dir().join(format!("transcript-{}-{}", process::id(), name));
+    save(url, &path, name, |_| {}).map_err(|error| Error::input(format!("failed to download {}", url), error))?;
+    Ok(path)
+}
+
+// ureq's own messages lead with the url, which callers already name
+pub fn describe(error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(status, response) => format!("{} {}", status, response.status_text()),
+        ureq::Error::Transport(transport) => {
+            let mut description = transport.kind().to_string();
+            for detail in transport.message().map(String::from).into_iter().chain(std::error::Error::source(&transport).map(|source| source.to_string())) {
+                description = format!("{}: {}", description, detail);
+            }
+            description
+        }
+    }
+}
+
+// streams url into path behind a progress bar, handing every chunk to inspect on the way
+pub fn save(url: &str, path: &Path, name: &str, mut inspect: impl FnMut(&[u8])) -> Result<(), String> {
+    let response = ureq::get(url).call().map_err(describe)?;
+    let total: Option<u64> = response.header("Content-Length").and_then(|length| length.parse().ok());
+
+    // the bar stays hidden under --quiet along with the logging
+    let bar = match total {
+        Some(total) => ProgressBar::new(total).with_style(ProgressStyle::with_template("{msg} [{bar:30}] {bytes}/{total_bytes} eta {eta}").expect("progress template is invalid").progress_chars("=> ")),
+        None => ProgressBar::new_spinner().with_style(ProgressStyle::with_template("{msg} {bytes}").expect("progress template is invalid")),
+    };
+    if !tracing::enabled!(tracing::Level::INFO) {
+        bar.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    bar.set_message(format!("downloading {}", name));
+
+    let mut file = File::create(path).map_err(|error| error.to_string())?;
+    let mut reader = response.into_reader();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error.to_string()),
+        };
+        file.write_all(&buffer[..read]).map_err(|error| error.to_string())?;
+        inspect(&buffer[..read]);
+        bar.inc(read as u64);
+    }
+    bar.finish_and_clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _is_url() {
+        assert!(is_url("https://example.com/episode.wav"));
+        assert!(is_url("http://example.com/episode.wav"));
+        assert!(!is_url("episode.wav"));
+        assert!(!is_url("/tmp/https/episode.wav"));
+    }
+}
@@ -0,0 +1,268 @@
+use std::io::{self, BufRead, BufReader};
+use std::fs::{self, File};
+use std::path::Path;
+
+use clap::{Args, Command, FromArgMatches, ValueEnum};
+
+use crate::error::Error;
+use crate::storage;
+use sink::Sink;
+
+pub mod decode;
+pub mod ffmpeg;
+pub mod g711;
+pub mod http;
+pub mod preprocess;
+pub mod probe;
+pub mod raw;
+pub mod resample;
+pub mod sink;
+pub mod stream;
+
+// whisper only takes 16kHz
+pub const SAMPLE_RATE: u32 = 16000;
+
+pub struct Audio {
+    pub sample_rate: u32,
+    // samples per channel
+    pub channels: Vec<Vec<f32>>,
+    // how far into the decoded audio the samples start, what --trim-silence cut off the front
+    pub start_ms: u64,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Left,
+    Right,
+    /// Average of every channel
+    Mix,
+}
+
+#[derive(Args, Debug, Clone)]
+#[group(id = "audio_options")]
+pub struct Options {
+    /// Decode the input with ffmpeg, which is otherwise only tried when the built-in decoders fail
+    #[arg(long)]
+    pub use_ffmpeg: bool,
+
+    /// Channel to transcribe from multi-channel input
+    #[arg(long, value_enum, default_value = "mix")]
+    pub channel: Channel,
+
+    /// Start this far into the audio, as 00:12:00, 12:00, 5m or 90
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub offset: Option<u64>,
+
+    /// Only take this much of the audio
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub duration: Option<u64>,
+
+    /// Read the input as headerless pcm, - reads it from stdin
+    #[arg(long, help_heading = "Raw input")]
+    pub raw: bool,
+
+    #[arg(long, default_value_t = 16000, value_parser = clap::value_parser!(u32).range(1..), requires = "raw", help_heading = "Raw input")]
+    pub rate: u32,
+
+    #[arg(long, default_value_t = 1, value_parser = parse_channels, requires = "raw", help_heading = "Raw input")]
+    pub channels: usize,
+
+    #[arg(long = "format", value_enum, default_value = "s16le", requires = "raw", help_heading = "Raw input")]
+    pub raw_format: raw::Format,
+
+    /// Decode a --raw file whose header says it's a wav, flac, mp3 or other container as the
+    /// file it is, instead of stopping
+    #[arg(long, requires = "raw", help_heading = "Raw input")]
+    pub auto_convert: bool,
+
+    /// Filter out rumble and hum below this many Hz before decoding, e.g. 80
+    #[arg(long, value_name = "HZ", value_parser = parse_cutoff, help_heading = "Preprocessing")]
+    pub highpass: Option<f32>,
+
+    /// Turn down steady background noise such as hum, hiss and air conditioning before decoding
+    #[arg(long, help_heading = "Preprocessing")]
+    pub denoise: bool,
+
+    /// Cut the silence off the start and end before decoding; the times still count from the
+    /// start of the file
+    #[arg(long, help_heading = "Preprocessing")]
+    pub trim_silence: bool,
+
+    /// Audio quieter than this many dBFS is silence to --trim-silence
+    #[arg(long, default_value_t = -45.0, value_name = "DB", allow_negative_numbers = true, requires = "trim_silence", help_heading = "Preprocessing")]
+    pub trim_threshold: f32,
+
+    /// Bring the audio to this EBU R128 loudness before decoding, -23 without a value
+    #[arg(long, value_name = "LUFS", num_args = 0..=1, default_missing_value = "-23", allow_negative_numbers = true, help_heading = "Preprocessing")]
+    pub normalize: Option<f64>,
+}
+
+// the clap defaults, as for output::Options
+impl Default for Options {
+    fn default() -> Options {
+        let matches = Options::augment_args(Command::new("options")).get_matches_from(["options"]);
+        Options::from_arg_matches(&matches).expect("option defaults are invalid")
+    }
+}
+
+fn parse_channels(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(0) | Err(_) => Err("expected a channel count of at least 1".to_string()),
+        Ok(channels) => Ok(channels),
+    }
+}
+
+// below nyquist, as whisper hears it
+fn parse_cutoff(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(cutoff) if cutoff > 0.0 && cutoff < SAMPLE_RATE as f32 / 2.0 => Ok(cutoff),
+        _ => Err(format!("expected a frequency between 0 and {}Hz", SAMPLE_RATE / 2)),
+    }
+}
+
+// a time as hh:mm:ss or mm:ss (with a fraction on the seconds), plain seconds, or units such as
+// 1h30m, 5m, 1.5s or 500ms; in ms
+pub fn parse_time(value: &str) -> Result<u64, String> {
+    let invalid = || format!("expected a time like 00:12:00, 5m or 90, got {}", value);
+    let number = |text: &str| text.parse::<f64>().ok().filter(|number| number.is_finite() && *number >= 0.0 && !text.starts_with('+')).ok_or_else(invalid);
+    let seconds = if value.is_empty() {
+        return Err(invalid());
+    } else if value.contains(':') {
+        let parts: Vec<&str> = value.split(':').collect();
+        if parts.len() > 3 {
+            return Err(invalid());
+        }
+        parts.iter().try_fold(0.0, |total, part| Ok::<f64, String>(total * 60.0 + number(part)?))?
+    } else if value.ends_with(|c: char| c.is_ascii_digit() || c == '.') {
+        number(value)?
+    } else {
+        let mut total = 0.0;
+        let mut rest = value;
+        while !rest.is_empty() {
+            let digits = rest.find(|c: char| !c.is_ascii_digit() && c != '.').ok_or_else(invalid)?;
+            let (amount, after) = rest.split_at(digits);
+            let unit = after.find(|c: char| c.is_ascii_digit()).unwrap_or(after.len());
+            let scale = match &after[..unit] {
+                "h" => 3600.0,
+                "m" => 60.0,
+                "s" => 1.0,
+                "ms" => 0.001,
+                _ => return Err(invalid()),
+            };
+            total += number(amount)? * scale;
+            rest = &after[unit..];
+        }
+        total
+    };
+    Ok((seconds * 1000.0).round() as u64)
+}
+
+fn sink(options: &Options, channel: Option<Channel>) -> Sink {
+    Sink::new(Some(SAMPLE_RATE), channel).clip(options.offset.unwrap_or(0), options.duration)
+}
+
+// 16kHz audio with just the chosen channel, or every channel when there's no choice, made as it's
+// decoded, cut down to --offset and --duration and then preprocessed
+pub fn load(path_raw: &str, options: &Options, channel: Option<Channel>) -> Result<Audio, Error> {
+    let mut audio = read(path_raw, options, channel)?;
+    if options.offset.is_some() && audio.channels[0].is_empty() {
+        return Err(Error::Input(format!("--offset is past the end of {}", path_raw)));
+    }
+    preprocess::apply(&mut audio, options);
+    Ok(audio)
+}
+
+// what --raw was told the pcm is, for errors about input that isn't
+fn raw_expected(options: &Options) -> String {
+    let format = options.raw_format.to_possible_value().map_or_else(String::new, |value| value.get_name().to_string());
+    format!("--raw reads headerless {} pcm, {} at {} Hz", format, probe::channels(options.channels), options.rate)
+}
+
+// the native decoders go first, then the g.711 reader for wavs they reject and ffmpeg for
+// whatever is left
+fn read(path_raw: &str, options: &Options, channel: Option<Channel>) -> Result<Audio, Error> {
+    if options.raw && path_raw == "-" {
+        let mut stdin = BufReader::with_capacity(512, io::stdin().lock());
+        let kind = probe::sniff(stdin.fill_buf().map_err(|error| Error::input("failed to read stdin", error))?);
+        if kind.is_container() {
+            return Err(Error::Input(format!("stdin has a header, it's {}, but {}; drop --raw to have it decoded as the file it is", kind, raw_expected(options))));
+        }
+        return raw::read(stdin, options.raw_format, options.rate, options.channels, sink(options, channel));
+    }
+    let download = if http::is_url(path_raw) {
+        Some(http::download(path_raw)?)
+    } else if storage::is_remote(path_raw) {
+        Some(storage::download(path_raw)?)
+    } else if !options.raw && stream::is_stream(path_raw) {
+        Some(stream::spool(path_raw)?)
+    } else {
+        None
+    };
+    if let Some(path) = download {
+        let audio = read(&path.to_string_lossy(), options, channel);
+        if let Err(error) = fs::remove_file(&path) {
+            tracing::warn!("failed to remove {}: {}", path.display(), error);
+        }
+        return audio;
+    }
+
+    let path = Path::new(path_raw);
+    if !path.exists() {
+        return Err(Error::Input(format!("audio file {} doesn't exist", path_raw)));
+    }
+    // pcm read past a header would come out as noise, and whisper would make words of it
+    let kind = probe::probe(path).ok();
+    match &kind {
+        Some(kind) if options.raw && kind.is_container() && !options.auto_convert => {
+            return Err(Error::Input(format!("{} has a header, it's {}, but {}; drop --raw or pass --auto-convert", path_raw, kind, raw_expected(options))));
+        }
+        Some(kind) if options.raw && kind.is_container() => tracing::info!("{} is {}, decoding it as that rather than raw pcm", path_raw, kind),
+        _ => {}
+    }
+    if options.raw && !kind.as_ref().is_some_and(probe::Kind::is_container) {
+        let file = File::open(path).map_err(|error| Error::input(format!("failed to open {}", path_raw), error))?;
+        return raw::read(file, options.raw_format, options.rate, options.channels, sink(options, channel));
+    }
+    // ffmpeg's raw output doesn't say how many channels there are, so it's told: it can do the
+    // mixing itself, and picking a side or keeping both (call recordings) needs two
+    let ffmpeg_channels = if channel == Some(Channel::Mix) { 1 } else { 2 };
+    if options.use_ffmpeg {
+        return ffmpeg::decode(path, ffmpeg_channels, sink(options, channel));
+    }
+    // each attempt starts over with its own sink
+    match decode::decode(path, sink(options, channel)) {
+        Ok(audio) => Ok(audio),
+        Err(error) => match g711::decode(path, sink(options, channel)) {
+            Some(audio) => Ok(audio),
+            None if ffmpeg::available() => {
+                tracing::warn!("decoding with ffmpeg instead: {}", error);
+                ffmpeg::decode(path, ffmpeg_channels, sink(options, channel))
+            }
+            None => {
+                let advice = kind.map_or_else(|| "install ffmpeg to have it try".to_string(), |kind| kind.advice(path_raw));
+                Err(Error::input(format!("failed to decode {}", path_raw), format!("{}; {}", error, advice)))
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _parse_time() {
+        assert_eq!(parse_time("00:12:00"), Ok(720_000));
+        assert_eq!(parse_time("1:02:03.5"), Ok(3_723_500));
+        assert_eq!(parse_time("12:00"), Ok(720_000));
+        assert_eq!(parse_time("90"), Ok(90_000));
+        assert_eq!(parse_time("2.25"), Ok(2_250));
+        assert_eq!(parse_time("5m"), Ok(300_000));
+        assert_eq!(parse_time("1h30m"), Ok(5_400_000));
+        assert_eq!(parse_time("1m30.5s"), Ok(90_500));
+        assert_eq!(parse_time("500ms"), Ok(500));
+        for invalid in ["", "-5", "+5", "5x", "m", "1:2:3:4", "1:-2", "abc"] {
+            assert!(parse_time(invalid).is_err(), "{} parsed", invalid);
+        }
+    }
+}
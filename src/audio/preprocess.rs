@@ -0,0 +1,281 @@
+use std::f64::consts::PI;
+use std::ops::Range;
+
+use realfft::num_complex::Complex;
+use realfft::RealFftPlanner;
+
+use super::{Audio, Options, SAMPLE_RATE};
+use crate::vad::Speech;
+
+// speech kept either side of what --trim-silence finds, so the first and last words aren't clipped
+const TRIM_PAD_MS: f32 = 250.0;
+
+// the denoiser's frames, 32ms each overlapping by half
+const FFT_SIZE: usize = 512;
+const HOP: usize = FFT_SIZE / 2;
+// the noise is what the quietest tenth of the frames have in common, and a bin is gated until
+// it's this many deviations above it
+const NOISE_SHARE: usize = 10;
+const GATE_DEVIATIONS: f32 = 1.5;
+// how much of a gated bin is let through, about -20dB
+const GATE_FLOOR: f32 = 0.1;
+
+// one biquad of bs.1770's k-weighting, in direct form 1
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 3]) -> Biquad {
+        Biquad { b, a, x: [0.0; 2], y: [0.0; 2] }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1] - self.a[1] * self.y[0] - self.a[2] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+// the high shelf and high pass that model the head, worked out for the sample rate rather than
+// taken from bs.1770's 48kHz table, as libebur128 does
+fn k_weighting(rate: f64) -> [Biquad; 2] {
+    let (f0, gain, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new([(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0], [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0]);
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new([1.0, -2.0, 1.0], [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0]);
+    [shelf, high_pass]
+}
+
+// a 4th order butterworth high pass at cutoff Hz, as two biquads
+pub fn highpass(samples: &mut [f32], cutoff: f32) {
+    let w0 = 2.0 * PI * cutoff as f64 / SAMPLE_RATE as f64;
+    let stages = [0.5411961001461969, 1.3065629648763764].map(|q: f64| {
+        let alpha = w0.sin() / (2.0 * q);
+        let a0 = 1.0 + alpha;
+        let cos = w0.cos();
+        Biquad::new([(1.0 + cos) / 2.0 / a0, -(1.0 + cos) / a0, (1.0 + cos) / 2.0 / a0], [1.0, -2.0 * cos / a0, (1.0 - alpha) / a0])
+    });
+    for mut stage in stages {
+        for sample in samples.iter_mut() {
+            *sample = stage.process(*sample as f64) as f32;
+        }
+    }
+}
+
+// spectral gating for steady noise such as hum, hiss and air conditioning: the noise's level is
+// learnt per frequency from the quietest frames, and whatever doesn't rise clearly above it is
+// turned down; the mask is smoothed across neighbouring bins and frames so what's left doesn't warble
+pub fn denoise(samples: &mut [f32]) {
+    if samples.len() < FFT_SIZE {
+        return;
+    }
+    let mut planner = RealFftPlanner::<f32>::new();
+    let (forward, inverse) = (planner.plan_fft_forward(FFT_SIZE), planner.plan_fft_inverse(FFT_SIZE));
+    // a square root hann on the way in and out adds back up to the input at half overlap
+    let window: Vec<f32> = (0..FFT_SIZE).map(|i| (std::f32::consts::PI * i as f32 / FFT_SIZE as f32).sin()).collect();
+    // the first and last samples get two frames' worth like the rest
+    let padded: Vec<f32> = [&[0.0; HOP][..], samples, &vec![0.0; FFT_SIZE]].concat();
+    let frames = (padded.len() - FFT_SIZE) / HOP + 1;
+    let mut input = forward.make_input_vec();
+    let mut spectrum = forward.make_output_vec();
+    let mut analyse = |frame: usize, spectrum: &mut Vec<Complex<f32>>| {
+        for ((input, sample), weight) in input.iter_mut().zip(&padded[frame * HOP..]).zip(&window) {
+            *input = sample * weight;
+        }
+        forward.process(&mut input, spectrum).expect("fft buffers are sized by the plan");
+    };
+    let level = |bin: &Complex<f32>| 20.0 * (bin.norm() + 1e-9).log10();
+
+    // frames of digital silence say nothing about the noise
+    let energy = |frame: usize| padded[frame * HOP..frame * HOP + FFT_SIZE].iter().zip(&window).map(|(sample, weight)| (sample * weight).powi(2)).sum::<f32>();
+    let mut quiet: Vec<(usize, f32)> = (0..frames).map(|frame| (frame, energy(frame))).filter(|(_, energy)| *energy > 0.0).collect();
+    if quiet.is_empty() {
+        return;
+    }
+    quiet.sort_by(|a, b| a.1.total_cmp(&b.1));
+    quiet.truncate((quiet.len() / NOISE_SHARE).max(1));
+    let bins = spectrum.len();
+    let (mut mean, mut square) = (vec![0.0f32; bins], vec![0.0f32; bins]);
+    for (frame, _) in &quiet {
+        analyse(*frame, &mut spectrum);
+        for (bin, value) in spectrum.iter().enumerate() {
+            mean[bin] += level(value);
+            square[bin] += level(value).powi(2);
+        }
+    }
+    let count = quiet.len() as f32;
+    let gate: Vec<f32> = mean.iter().zip(&square).map(|(mean, square)| {
+        let mean = mean / count;
+        mean + GATE_DEVIATIONS * (square / count - mean * mean).max(0.0).sqrt()
+    }).collect();
+
+    let mut output = vec![0.0f32; padded.len()];
+    let mut previous = vec![1.0f32; bins];
+    let mut frame_out = inverse.make_output_vec();
+    for frame in 0..frames {
+        analyse(frame, &mut spectrum);
+        let open: Vec<f32> = spectrum.iter().zip(&gate).map(|(value, gate)| if level(value) > *gate { 1.0 } else { GATE_FLOOR }).collect();
+        for (bin, value) in spectrum.iter_mut().enumerate() {
+            let around = &open[bin.saturating_sub(1)..(bin + 2).min(bins)];
+            let mask = (around.iter().sum::<f32>() / around.len() as f32 + previous[bin]) / 2.0;
+            previous[bin] = mask;
+            *value *= mask / FFT_SIZE as f32;
+        }
+        spectrum[0].im = 0.0;
+        spectrum[bins - 1].im = 0.0;
+        inverse.process(&mut spectrum, &mut frame_out).expect("fft buffers are sized by the plan");
+        for ((output, sample), weight) in output[frame * HOP..].iter_mut().zip(&frame_out).zip(&window) {
+            *output += sample * weight;
+        }
+    }
+    samples.copy_from_slice(&output[HOP..HOP + samples.len()]);
+}
+
+fn lufs(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+// the integrated loudness of 16kHz samples in LUFS, as EBU R128 measures it: 400ms blocks every
+// 100ms, gated at -70 LUFS and then at 10 LU below the loudness of what's left; none for audio
+// that's silent or shorter than a block
+pub fn loudness(samples: &[f32]) -> Option<f64> {
+    let [mut shelf, mut high_pass] = k_weighting(SAMPLE_RATE as f64);
+    let squares: Vec<f64> = samples.iter().map(|sample| high_pass.process(shelf.process(*sample as f64)).powi(2)).collect();
+    let (block, hop) = (SAMPLE_RATE as usize * 4 / 10, SAMPLE_RATE as usize / 10);
+    if squares.len() < block {
+        return None;
+    }
+    let mut blocks = Vec::new();
+    let mut sum: f64 = squares[..block].iter().sum();
+    blocks.push(sum / block as f64);
+    for start in (hop..=squares.len() - block).step_by(hop) {
+        sum += squares[start + block - hop..start + block].iter().sum::<f64>() - squares[start - hop..start].iter().sum::<f64>();
+        blocks.push(sum.max(0.0) / block as f64);
+    }
+    let mean_above = |gate: f64| {
+        let gated: Vec<f64> = blocks.iter().copied().filter(|block| lufs(*block) > gate).collect();
+        (!gated.is_empty()).then(|| gated.iter().sum::<f64>() / gated.len() as f64)
+    };
+    let relative = lufs(mean_above(-70.0)?) - 10.0;
+    mean_above(relative.max(-70.0)).map(lufs)
+}
+
+// brings the samples to target LUFS, clipping any peak that ends up past full scale; returns the
+// loudness they had, none when there's none to measure
+pub fn normalize(samples: &mut [f32], target: f64) -> Option<f64> {
+    let measured = loudness(samples)?;
+    let gain = 10f64.powf((target - measured) / 20.0) as f32;
+    for sample in samples.iter_mut() {
+        *sample = (*sample * gain).clamp(-1.0, 1.0);
+    }
+    Some(measured)
+}
+
+// the part of the channels from the first sound in any of them to the last, quieter than
+// threshold_db being silence; none when it's all silence
+pub fn trim(channels: &[Vec<f32>], threshold_db: f32) -> Option<Range<usize>> {
+    channels.iter().filter_map(|samples| Speech::detect(samples, threshold_db, f32::INFINITY, TRIM_PAD_MS).span()).reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+}
+
+// --highpass, --denoise, --trim-silence and then --normalize, on 16kHz audio; the samples trimmed
+// off the front go on start_ms
+pub fn apply(audio: &mut Audio, options: &Options) {
+    if let Some(cutoff) = options.highpass {
+        for channel in &mut audio.channels {
+            highpass(channel, cutoff);
+        }
+    }
+    if options.denoise {
+        for channel in &mut audio.channels {
+            denoise(channel);
+        }
+    }
+    if options.trim_silence {
+        if let Some(span) = trim(&audio.channels, options.trim_threshold) {
+            let length = audio.channels[0].len();
+            tracing::debug!("trimming {}ms of silence from the start and {}ms from the end", span.start * 1000 / SAMPLE_RATE as usize, (length - span.end) * 1000 / SAMPLE_RATE as usize);
+            for channel in &mut audio.channels {
+                channel.truncate(span.end);
+                channel.drain(..span.start);
+            }
+            audio.start_ms += (span.start * 1000 / SAMPLE_RATE as usize) as u64;
+        }
+    }
+    if let Some(target) = options.normalize {
+        for channel in &mut audio.channels {
+            if let Some(measured) = normalize(channel, target) {
+                tracing::debug!("normalized from {:.1} to {:.1} LUFS", measured, target);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(amplitude: f32, frequency: f32, ms: usize) -> Vec<f32> {
+        (0..ms * 16).map(|i| amplitude * (2.0 * std::f32::consts::PI * frequency * i as f32 / SAMPLE_RATE as f32).sin()).collect()
+    }
+
+    #[test]
+    fn _loudness() {
+        // a full scale 1kHz sine reads -3 LUFS, so one at a tenth of that -23
+        let measured = loudness(&sine(0.1, 1000.0, 2000)).unwrap();
+        assert!((measured - -23.0).abs() < 0.3, "{}", measured);
+        assert_eq!(loudness(&[0.0; 16000]), None);
+        assert_eq!(loudness(&[0.1; 100]), None);
+
+        let mut quiet = sine(0.01, 1000.0, 2000);
+        normalize(&mut quiet, -23.0);
+        assert!((loudness(&quiet).unwrap() - -23.0).abs() < 0.1);
+
+        let silence = vec![0.0; 14400];
+        let channels = vec![[silence.clone(), sine(0.1, 440.0, 960), silence.clone()].concat(), vec![0.0; 44160]];
+        assert_eq!(trim(&channels, -45.0), Some(10400..33760));
+        assert_eq!(trim(&[silence], -45.0), None);
+    }
+
+    fn rms_db(samples: &[f32]) -> f32 {
+        10.0 * (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).log10()
+    }
+
+    #[test]
+    fn _highpass() {
+        let mut hum = sine(0.1, 50.0, 2000);
+        highpass(&mut hum, 80.0);
+        let mut voice = sine(0.1, 1000.0, 2000);
+        highpass(&mut voice, 80.0);
+        assert!(rms_db(&hum[8000..]) < rms_db(&sine(0.1, 50.0, 1000)) - 15.0);
+        assert!((rms_db(&voice[8000..]) - rms_db(&sine(0.1, 1000.0, 1000))).abs() < 0.1);
+    }
+
+    #[test]
+    fn _denoise() {
+        // hiss throughout, with a tone in the middle second
+        let mut seed = 1u32;
+        let mut hiss = || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as f32 / (1 << 24) as f32 * 0.02 - 0.01
+        };
+        let tone = sine(0.1, 700.0, 1000);
+        let mut samples: Vec<f32> = (0..48000).map(|i| hiss() + if (16000..32000).contains(&i) { tone[i - 16000] } else { 0.0 }).collect();
+        let (noise, voiced) = (rms_db(&samples[..16000]), rms_db(&samples[16000..32000]));
+        denoise(&mut samples);
+        assert!(rms_db(&samples[..16000]) < noise - 12.0, "{} from {}", rms_db(&samples[..16000]), noise);
+        assert!((rms_db(&samples[17000..31000]) - voiced).abs() < 1.0);
+        assert!(samples.len() == 48000);
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+// enough of the start of a file to tell what it is, a wav's fmt chunk included
+const HEADER_BYTES: usize = 512;
+
+// what a file turns out to be from its first bytes, for errors that say what came in rather
+// than only that it couldn't be decoded
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    Wav { format: u16, channels: u16, rate: u32, bits: u16 },
+    Flac,
+    Ogg,
+    Mp3,
+    Mp4,
+    Matroska,
+    Amr,
+    Html,
+    Text,
+    Empty,
+    Unknown,
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+// the names wav format tags go by; the extensible tag's real one is further in, so it stays as is
+fn wav_format(format: u16) -> String {
+    match format {
+        1 => "16-bit pcm".to_string(),
+        2 => "Microsoft ADPCM".to_string(),
+        3 => "float pcm".to_string(),
+        6 => "a-law".to_string(),
+        7 => "µ-law".to_string(),
+        0x11 => "IMA ADPCM".to_string(),
+        0x31 => "GSM 6.10".to_string(),
+        0x55 => "mp3".to_string(),
+        0xfffe => "extensible".to_string(),
+        format => format!("format 0x{:04x}", format),
+    }
+}
+
+pub fn channels(count: usize) -> String {
+    format!("{} channel{}", count, if count == 1 { "" } else { "s" })
+}
+
+pub fn sniff(header: &[u8]) -> Kind {
+    let starts = |magic: &[u8]| header.starts_with(magic);
+    if header.is_empty() {
+        return Kind::Empty;
+    }
+    if header.len() >= 12 && starts(b"RIFF") && &header[8..12] == b"WAVE" {
+        let mut offset = 12;
+        while offset + 8 <= header.len() {
+            let size = u32_at(header, offset + 4) as usize;
+            if &header[offset..offset + 4] == b"fmt " && offset + 24 <= header.len() {
+                let fmt = &header[offset + 8..];
+                let bits = u16_at(fmt, 14);
+                let format = match (u16_at(fmt, 0), bits) {
+                    (1, bits) if bits != 16 => 0x1000 + bits,
+                    (format, _) => format,
+                };
+                return Kind::Wav { format, channels: u16_at(fmt, 2), rate: u32_at(fmt, 4), bits };
+            }
+            offset += 8 + size + size % 2;
+        }
+        return Kind::Wav { format: 0, channels: 0, rate: 0, bits: 0 };
+    }
+    if starts(b"fLaC") {
+        Kind::Flac
+    } else if starts(b"OggS") {
+        Kind::Ogg
+    } else if starts(b"ID3") || (header.len() >= 2 && header[0] == 0xff && header[1] & 0xe0 == 0xe0) {
+        Kind::Mp3
+    } else if header.len() >= 8 && &header[4..8] == b"ftyp" {
+        Kind::Mp4
+    } else if starts(&[0x1a, 0x45, 0xdf, 0xa3]) {
+        Kind::Matroska
+    } else if starts(b"#!AMR") {
+        Kind::Amr
+    } else if let Ok(text) = std::str::from_utf8(&header[..header.len() - header.len().min(3)]).or_else(|error| std::str::from_utf8(&header[..error.valid_up_to()])) {
+        let lower = text.trim_start().to_lowercase();
+        if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+            Kind::Html
+        } else if !text.is_empty() && text.chars().all(|c| !c.is_control() || c.is_whitespace()) {
+            Kind::Text
+        } else {
+            Kind::Unknown
+        }
+    } else {
+        Kind::Unknown
+    }
+}
+
+pub fn probe(path: &Path) -> io::Result<Kind> {
+    let mut header = Vec::with_capacity(HEADER_BYTES);
+    File::open(path)?.take(HEADER_BYTES as u64).read_to_end(&mut header)?;
+    Ok(sniff(&header))
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Kind::Wav { format: 0, .. } => write!(f, "a wav without a fmt chunk"),
+            Kind::Wav { format, channels, rate, bits } => {
+                let format = if *format > 0x1000 { format!("{}-bit pcm", bits) } else { wav_format(*format) };
+                write!(f, "a wav of {} audio, {} at {} Hz", format, self::channels(*channels as usize), rate)
+            }
+            Kind::Flac => write!(f, "flac"),
+            Kind::Ogg => write!(f, "an ogg file"),
+            Kind::Mp3 => write!(f, "mp3"),
+            Kind::Mp4 => write!(f, "an mp4/m4a file"),
+            Kind::Matroska => write!(f, "a matroska/webm file"),
+            Kind::Amr => write!(f, "amr"),
+            Kind::Html => write!(f, "an html page"),
+            Kind::Text => write!(f, "a text file"),
+            Kind::Empty => write!(f, "empty"),
+            Kind::Unknown => write!(f, "nothing recognisable from its first bytes"),
+        }
+    }
+}
+
+impl Kind {
+    // audio in some container, as opposed to headerless pcm or not audio at all
+    pub fn is_container(&self) -> bool {
+        !matches!(self, Kind::Html | Kind::Text | Kind::Empty | Kind::Unknown)
+    }
+
+    // what's wrong with the file and what to do about it, once the decoders have given up on it
+    pub fn advice(&self, path_raw: &str) -> String {
+        let convert = format!("ffmpeg -i {} -ar 16000 -ac 1 audio.wav", path_raw);
+        match self {
+            Kind::Html => format!("{} is {}, not audio; a download that went wrong saves the error page instead", path_raw, self),
+            Kind::Text => format!("{} is {}, not audio; convert, lint and eval are the commands that read subtitles and transcripts", path_raw, self),
+            Kind::Empty => format!("{} is empty", path_raw),
+            Kind::Unknown => format!("{} is {}; if it's headerless pcm, pass --raw with --rate, --channels and --format", path_raw, self),
+            Kind::Wav { .. } | Kind::Flac | Kind::Ogg | Kind::Mp3 | Kind::Mp4 | Kind::Matroska | Kind::Amr => {
+                format!("{} is {}, which the built-in decoders can't read; install ffmpeg, or convert it with `{}`", path_raw, self, convert)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _sniff() {
+        let mut wav = b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0".to_vec();
+        wav.extend([0x11, 0, 2, 0, 0x40, 0x1f, 0, 0, 0, 0, 0, 0, 0, 0, 4, 0]);
+        assert_eq!(sniff(&wav), Kind::Wav { format: 0x11, channels: 2, rate: 8000, bits: 4 });
+        assert_eq!(sniff(&wav).to_string(), "a wav of IMA ADPCM audio, 2 channels at 8000 Hz");
+        assert_eq!(sniff(b"fLaC\0\0\0\x22"), Kind::Flac);
+        assert_eq!(sniff(b"\0\0\0\x20ftypM4A "), Kind::Mp4);
+        assert_eq!(sniff(b"\xff\xfb\x90\x64"), Kind::Mp3);
+        assert_eq!(sniff(b"  <!DOCTYPE html><html>"), Kind::Html);
+        assert_eq!(sniff(b"1\n00:00:00,000 --> 00:00:01,000\nHi\n"), Kind::Text);
+        assert_eq!(sniff(&[0x12, 0x00, 0x34, 0x01, 0x00, 0x02]), Kind::Unknown);
+        assert_eq!(sniff(b""), Kind::Empty);
+        assert!(Kind::Mp3.is_container() && !Kind::Text.is_container());
+        assert!(Kind::Text.advice("talk.srt").contains("not audio"));
+    }
+}
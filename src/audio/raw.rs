@@ -0,0 +1,86 @@
+use std::io::Read;
+
+use clap::ValueEnum;
+
+use super::sink::Sink;
+use super::{Audio, Channel, SAMPLE_RATE};
+use crate::error::Error;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    U8,
+    S16le,
+    S32le,
+    F32le,
+}
+
+impl Format {
+    fn width(&self) -> usize {
+        match self {
+            Format::U8 => 1,
+            Format::S16le => 2,
+            Format::S32le | Format::F32le => 4,
+        }
+    }
+
+    fn sample(&self, bytes: &[u8]) -> f32 {
+        match self {
+            Format::U8 => (bytes[0] as f32 - 128.0) / 128.0,
+            Format::S16le => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            Format::S32le => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32 / 2147483648.0,
+            Format::F32le => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+}
+
+// headerless interleaved pcm, a trailing partial frame is dropped
+pub fn read(reader: impl Read, format: Format, sample_rate: u32, channels: usize, mut sink: Sink) -> Result<Audio, Error> {
+    sink.start(sample_rate, channels, None);
+    sink.read(reader, format.width(), |sample| format.sample(sample)).map_err(|error| Error::input("failed to read raw audio", error))?;
+    Ok(sink.finish())
+}
+
+// read for a stream with no end in sight, a capture piped in: the chosen channel at 16kHz is
+// handed to each a buffer at a time, until reader ends or each returns false
+pub fn follow(reader: impl Read, format: Format, sample_rate: u32, channels: usize, channel: Channel, mut each: impl FnMut(Vec<f32>) -> bool) -> Result<(), Error> {
+    let mut sink = Sink::new(Some(SAMPLE_RATE), Some(channel));
+    sink.start(sample_rate, channels, None);
+    let more = |sink: &mut Sink| {
+        let samples = sink.take().remove(0);
+        samples.is_empty() || each(samples)
+    };
+    sink.read_with(reader, format.width(), |sample| format.sample(sample), more).map_err(|error| Error::input("failed to read raw audio", error))?;
+    let rest = sink.finish().channels.remove(0);
+    if !rest.is_empty() {
+        each(rest);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _read() {
+        let bytes: Vec<u8> = [0i16, 16384, -16384, 0, 1].iter().flat_map(|sample| sample.to_le_bytes()).collect();
+        let audio = read(bytes.as_slice(), Format::S16le, 8000, 2, Sink::new(None, None)).unwrap();
+        assert_eq!(audio.sample_rate, 8000);
+        assert_eq!(audio.channels, vec![vec![0.0, -0.5], vec![0.5, 0.0]]);
+    }
+
+    #[test]
+    fn _follow() {
+        let bytes: Vec<u8> = (0..20000i16).flat_map(|sample| [sample, -sample]).flat_map(|sample| sample.to_le_bytes()).collect();
+        let mut followed = Vec::new();
+        let mut buffers = 0;
+        follow(bytes.as_slice(), Format::S16le, 16000, 2, Channel::Right, |samples| {
+            followed.extend(samples);
+            buffers += 1;
+            true
+        })
+        .unwrap();
+        assert!(buffers > 1);
+        assert_eq!((followed.len(), followed[19999]), (20000, -19999.0 / 32768.0));
+    }
+}
@@ -0,0 +1,93 @@
+use rubato::{FftFixedIn, Resampler};
+
+// converts audio a chunk at a time as it's decoded, so nothing but the resampler's own buffers
+// sits between the decoder and the output
+pub struct Stream {
+    resampler: FftFixedIn<f32>,
+    from: u32,
+    to: u32,
+    pending: Vec<Vec<f32>>,
+    // the resampler runs behind by a fixed number of frames, dropped off the front of the output
+    skip: usize,
+    frames: u64,
+    produced: u64,
+}
+
+impl Stream {
+    pub fn new(from: u32, to: u32, channels: usize) -> Stream {
+        let resampler = FftFixedIn::<f32>::new(from as usize, to as usize, 1024, 2, channels).expect("failed to set up resampler");
+        let skip = resampler.output_delay();
+        Stream { resampler, from, to, pending: vec![Vec::new(); channels], skip, frames: 0, produced: 0 }
+    }
+
+    // the output length the input so far comes to
+    pub fn expected(&self) -> usize {
+        (self.frames * self.to as u64).div_ceil(self.from as u64) as usize
+    }
+
+    fn append(&mut self, chunk: Vec<Vec<f32>>, output: &mut [Vec<f32>]) {
+        let skip = self.skip.min(chunk[0].len());
+        self.produced += (chunk[0].len() - skip) as u64;
+        for (channel, samples) in output.iter_mut().zip(chunk) {
+            channel.extend_from_slice(&samples[skip..]);
+        }
+        self.skip -= skip;
+    }
+
+    pub fn push(&mut self, input: &[Vec<f32>], output: &mut [Vec<f32>]) {
+        for (pending, samples) in self.pending.iter_mut().zip(input) {
+            pending.extend_from_slice(samples);
+        }
+        self.frames += input[0].len() as u64;
+        let mut position = 0;
+        while self.pending[0].len() - position >= self.resampler.input_frames_next() {
+            let next = position + self.resampler.input_frames_next();
+            let chunk: Vec<&[f32]> = self.pending.iter().map(|channel| &channel[position..next]).collect();
+            let resampled = self.resampler.process(&chunk, None).expect("failed to resample");
+            self.append(resampled, output);
+            position = next;
+        }
+        for pending in &mut self.pending {
+            pending.drain(..position);
+        }
+    }
+
+    // runs the tail through and trims the output to the length the input comes to; output only
+    // has to end with what the stream produced, the front may have been taken already
+    pub fn finish(mut self, output: &mut [Vec<f32>]) {
+        let expected = self.expected();
+        let rest: Vec<Vec<f32>> = std::mem::take(&mut self.pending);
+        let rest: Vec<&[f32]> = rest.iter().map(|channel| channel.as_slice()).collect();
+        let resampled = self.resampler.process_partial(Some(&rest), None).expect("failed to resample");
+        self.append(resampled, output);
+        while self.produced < expected as u64 {
+            let resampled = self.resampler.process_partial::<&[f32]>(None, None).expect("failed to resample");
+            self.append(resampled, output);
+        }
+        let excess = (self.produced - expected as u64) as usize;
+        for channel in output {
+            channel.truncate(channel.len().saturating_sub(excess));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _stream() {
+        // a second of a 440Hz tone at 48kHz, arriving in uneven pieces
+        let tone: Vec<f32> = (0..48000).map(|i| (i as f32 * 440.0 * std::f32::consts::TAU / 48000.0).sin()).collect();
+        let mut stream = Stream::new(48000, 16000, 1);
+        let mut output = vec![Vec::new()];
+        for piece in tone.chunks(777) {
+            stream.push(&[piece.to_vec()], &mut output);
+        }
+        stream.finish(&mut output);
+        assert_eq!(output[0].len(), 16000);
+        let expected = (8000.0 * 440.0 * std::f32::consts::TAU / 16000.0).sin();
+        // away from the edges it is still the same tone, give or take a fraction of a sample
+        assert!((output[0][8000] - expected).abs() < 0.1);
+    }
+}
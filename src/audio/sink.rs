@@ -0,0 +1,207 @@
+use std::io::{self, Read};
+
+use super::resample::Stream;
+use super::{Audio, Channel};
+
+// the decoders hand their audio over a packet at a time and only what whisper is given is kept:
+// the chosen channel (or all of them) at the target rate, never a full-length copy at the source
+// rate or format
+pub struct Sink {
+    rate: Option<u32>,
+    channel: Option<Channel>,
+    // the part of the audio to keep, in ms
+    offset_ms: u64,
+    duration_ms: Option<u64>,
+    // the same in output samples once the rate is known: how many are still to drop, and how many to keep
+    skip: usize,
+    limit: Option<usize>,
+    sample_rate: u32,
+    count: usize,
+    stream: Option<Stream>,
+    scratch: Vec<Vec<f32>>,
+    output: Vec<Vec<f32>>,
+}
+
+// mono input is kept as is whatever the choice
+fn select(frame: &[f32], channel: Channel) -> f32 {
+    match channel {
+        _ if frame.len() == 1 => frame[0],
+        Channel::Left => frame[0],
+        Channel::Right => frame[1],
+        Channel::Mix => frame.iter().sum::<f32>() / frame.len() as f32,
+    }
+}
+
+impl Sink {
+    // no rate keeps the source's, no channel keeps every channel
+    pub fn new(rate: Option<u32>, channel: Option<Channel>) -> Sink {
+        Sink { rate, channel, offset_ms: 0, duration_ms: None, skip: 0, limit: None, sample_rate: 0, count: 0, stream: None, scratch: Vec::new(), output: Vec::new() }
+    }
+
+    // only keeps the audio from offset_ms on, for duration_ms if there is one
+    pub fn clip(mut self, offset_ms: u64, duration_ms: Option<u64>) -> Sink {
+        self.offset_ms = offset_ms;
+        self.duration_ms = duration_ms;
+        self
+    }
+
+    // frames is the source length when the container says, to size the output once
+    pub fn start(&mut self, sample_rate: u32, count: usize, frames: Option<u64>) {
+        let kept = if self.channel.is_some() { 1 } else { count };
+        let rate = self.rate.unwrap_or(sample_rate);
+        self.skip = (self.offset_ms * rate as u64 / 1000) as usize;
+        self.limit = self.duration_ms.map(|ms| (ms * rate as u64 / 1000) as usize);
+        let capacity = frames.map_or(0, |frames| (frames * rate as u64).div_ceil(sample_rate as u64) as usize).saturating_sub(self.skip);
+        let capacity = self.limit.map_or(capacity, |limit| capacity.min(limit));
+        self.sample_rate = sample_rate;
+        self.count = count;
+        self.stream = (rate != sample_rate).then(|| Stream::new(sample_rate, rate, kept));
+        self.scratch = vec![Vec::new(); kept];
+        self.output = (0..kept).map(|_| Vec::with_capacity(capacity)).collect();
+    }
+
+    pub fn started(&self) -> bool {
+        self.count > 0
+    }
+
+    // everything wanted is in, the decoder can stop
+    pub fn full(&self) -> bool {
+        self.limit.is_some_and(|limit| self.skip == 0 && self.output[0].len() >= limit)
+    }
+
+    fn trim(&mut self) {
+        if self.skip > 0 {
+            let skip = self.skip.min(self.output[0].len());
+            for output in &mut self.output {
+                output.drain(..skip);
+            }
+            self.skip -= skip;
+        }
+        if let Some(limit) = self.limit {
+            for output in &mut self.output {
+                output.truncate(limit);
+            }
+        }
+    }
+
+    // interleaved frames, a trailing partial frame is dropped
+    pub fn push(&mut self, interleaved: &[f32]) {
+        assert!(self.started(), "audio pushed before the sink was started");
+        for scratch in &mut self.scratch {
+            scratch.clear();
+        }
+        for frame in interleaved.chunks_exact(self.count) {
+            match self.channel {
+                Some(channel) => self.scratch[0].push(select(frame, channel)),
+                None => {
+                    for (scratch, sample) in self.scratch.iter_mut().zip(frame) {
+                        scratch.push(*sample);
+                    }
+                }
+            }
+        }
+        match &mut self.stream {
+            Some(stream) => stream.push(&self.scratch, &mut self.output),
+            None => {
+                for (output, scratch) in self.output.iter_mut().zip(&self.scratch) {
+                    output.extend_from_slice(scratch);
+                }
+            }
+        }
+        self.trim();
+    }
+
+    // fixed width interleaved samples until the end of reader, a buffer at a time; a trailing
+    // partial frame is dropped
+    pub fn read(&mut self, reader: impl Read, width: usize, sample: impl Fn(&[u8]) -> f32) -> io::Result<()> {
+        self.read_with(reader, width, sample, |sink| !sink.full())
+    }
+
+    // read, asking more after every read from reader whether to carry on
+    pub fn read_with(&mut self, mut reader: impl Read, width: usize, sample: impl Fn(&[u8]) -> f32, mut more: impl FnMut(&mut Sink) -> bool) -> io::Result<()> {
+        let frame = width * self.count;
+        let mut buffer = vec![0u8; frame * 4096];
+        let mut filled = 0;
+        let mut samples = Vec::with_capacity(self.count * 4096);
+        loop {
+            let read = match reader.read(&mut buffer[filled..]) {
+                Ok(read) => read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            filled += read;
+            if read == 0 || filled == buffer.len() {
+                let whole = filled / frame * frame;
+                samples.clear();
+                samples.extend(buffer[..whole].chunks_exact(width).map(&sample));
+                self.push(&samples);
+                buffer.copy_within(whole..filled, 0);
+                filled -= whole;
+            }
+            if read == 0 || !more(self) {
+                return Ok(());
+            }
+        }
+    }
+
+    // hands over the audio so far, for a reader passing it on as it comes in; a clipped sink's
+    // duration counts only what it still holds
+    pub fn take(&mut self) -> Vec<Vec<f32>> {
+        self.output.iter_mut().map(std::mem::take).collect()
+    }
+
+    pub fn finish(mut self) -> Audio {
+        if !self.started() {
+            // nothing decoded still makes one silent channel
+            return Audio { sample_rate: self.rate.unwrap_or(16000), channels: vec![Vec::new()], start_ms: 0 };
+        }
+        // a full sink has all it wants, the resampler's tail would only be cut off again
+        if let Some(stream) = self.stream.take().filter(|_| !self.full()) {
+            stream.finish(&mut self.output);
+            self.trim();
+        }
+        Audio { sample_rate: self.rate.unwrap_or(self.sample_rate), channels: self.output, start_ms: 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sink(channel: Option<Channel>, interleaved: &[f32], count: usize) -> Vec<Vec<f32>> {
+        let mut sink = Sink::new(None, channel);
+        sink.start(16000, count, None);
+        sink.push(interleaved);
+        sink.finish().channels
+    }
+
+    #[test]
+    fn _sink() {
+        let stereo = [1.0, 0.0, 0.5, -0.5];
+        assert_eq!(sink(Some(Channel::Mix), &stereo, 2), vec![vec![0.5, 0.0]]);
+        assert_eq!(sink(Some(Channel::Left), &stereo, 2), vec![vec![1.0, 0.5]]);
+        assert_eq!(sink(Some(Channel::Right), &stereo, 2), vec![vec![0.0, -0.5]]);
+        assert_eq!(sink(None, &stereo, 2), vec![vec![1.0, 0.5], vec![0.0, -0.5]]);
+        assert_eq!(sink(Some(Channel::Right), &[0.25], 1), vec![vec![0.25]]);
+
+        let mut resampled = Sink::new(Some(16000), Some(Channel::Mix));
+        resampled.start(8000, 2, Some(4000));
+        for _ in 0..4 {
+            resampled.push(&[0.0; 2000]);
+        }
+        let audio = resampled.finish();
+        assert_eq!((audio.sample_rate, audio.channels.len(), audio.channels[0].len()), (16000, 1, 8000));
+
+        // a second from 0.25s in, out of a ramp 2s long
+        let ramp: Vec<f32> = (0..32000).map(|i| i as f32).collect();
+        let mut clipped = Sink::new(None, None).clip(250, Some(1000));
+        clipped.start(16000, 1, None);
+        for piece in ramp.chunks(3000) {
+            if !clipped.full() {
+                clipped.push(piece);
+            }
+        }
+        let samples = &clipped.finish().channels[0];
+        assert_eq!((samples.len(), samples[0], samples[15999]), (16000, 4000.0, 19999.0));
+    }
+}
@@ -0,0 +1,116 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::error::Error;
+
+static SPOOLED: AtomicUsize = AtomicUsize::new(0);
+
+// stdin, a named pipe or a process substitution's /dev/fd: read once, front to back, with no
+// way to seek to a header that's only right once the writer is done
+pub fn is_stream(path_raw: &str) -> bool {
+    if path_raw == "-" {
+        return true;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        fs::metadata(path_raw).is_ok_and(|metadata| metadata.file_type().is_fifo() || metadata.file_type().is_char_device() || metadata.file_type().is_socket())
+    }
+    #[cfg(not(unix))]
+    false
+}
+
+// the stream copied into a temporary file that the decoders can seek in, a wav's lengths made to
+// match what actually came
+pub fn spool(path_raw: &str) -> Result<PathBuf, Error> {
+    let path = env::temp_dir().join(format!("transcript-{}-stream-{}", process::id(), SPOOLED.fetch_add(1, Ordering::Relaxed)));
+    let failed = |error: io::Error| Error::input(format!("failed to read {}", if path_raw == "-" { "stdin" } else { path_raw }), error);
+    let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path).map_err(failed)?;
+    let copied = if path_raw == "-" {
+        if io::stdin().is_terminal() {
+            let _ = fs::remove_file(&path);
+            return Err(Error::Input("-i - reads the audio from stdin, pipe it in".to_string()));
+        }
+        io::copy(&mut io::stdin().lock(), &mut file)
+    } else {
+        File::open(path_raw).and_then(|mut stream| io::copy(&mut stream, &mut file))
+    };
+    let patched = copied.and_then(|_| patch_wav(&mut file));
+    if let Err(error) = patched {
+        let _ = fs::remove_file(&path);
+        return Err(failed(error));
+    }
+    Ok(path)
+}
+
+// a wav written to a pipe can't go back to fill in its lengths, so they're left as whatever the
+// writer put there first: 0, 0xffffffff (ffmpeg) or an estimate; the riff length and the data
+// chunk's are set from the file's, anything else is left alone
+pub fn patch_wav(file: &mut (impl Read + Write + Seek)) -> io::Result<()> {
+    let length = file.seek(SeekFrom::End(0))?;
+    let mut header = [0; 12];
+    file.seek(SeekFrom::Start(0))?;
+    if length < 12 || file.read_exact(&mut header).is_err() || &header[..4] != b"RIFF" || &header[8..] != b"WAVE" {
+        return Ok(());
+    }
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let mut chunk = [0; 8];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut chunk)?;
+        let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as u64;
+        if &chunk[..4] == b"data" {
+            let actual = (length - offset - 8).min(u32::MAX as u64);
+            if size != actual {
+                file.seek(SeekFrom::Start(offset + 4))?;
+                file.write_all(&(actual as u32).to_le_bytes())?;
+                file.seek(SeekFrom::Start(4))?;
+                file.write_all(&((length - 8).min(u32::MAX as u64) as u32).to_le_bytes())?;
+            }
+            return Ok(());
+        }
+        // chunks are padded to an even length
+        offset += 8 + size + size % 2;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn wav(riff: u32, data: u32, samples: usize) -> Vec<u8> {
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend(riff.to_le_bytes());
+        bytes.extend(b"WAVEfmt ");
+        bytes.extend(16u32.to_le_bytes());
+        bytes.extend([1, 0, 1, 0, 0x80, 0x3e, 0, 0, 0, 0x7d, 0, 0, 2, 0, 16, 0]);
+        bytes.extend(b"LIST");
+        bytes.extend(3u32.to_le_bytes());
+        bytes.extend([1, 2, 3, 0]);
+        bytes.extend(b"data");
+        bytes.extend(data.to_le_bytes());
+        bytes.extend(vec![0; samples * 2]);
+        bytes
+    }
+
+    #[test]
+    fn _patch_wav() {
+        let expected = wav(58, 10, 5);
+        for (riff, data) in [(u32::MAX, u32::MAX), (0, 0), (36, 4000)] {
+            let mut file = Cursor::new(wav(riff, data, 5));
+            patch_wav(&mut file).unwrap();
+            assert_eq!(file.into_inner(), expected);
+        }
+        let mut file = Cursor::new(b"ID3 not a wav".to_vec());
+        patch_wav(&mut file).unwrap();
+        assert_eq!(file.into_inner(), b"ID3 not a wav");
+        assert!(is_stream("-"));
+        assert!(!is_stream("Cargo.toml"));
+    }
+}
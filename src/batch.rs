@@ -0,0 +1,240 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use transcript::decode;
+use transcript::error::Error;
+use transcript::output::{self, Format, Options};
+use transcript::storage;
+
+// one input, the extensionless path its outputs are written to (stdout without one) and a
+// language overriding -l
+pub struct Job {
+    pub input: String,
+    pub output: Option<String>,
+    pub language: Option<String>,
+    // --tasks: whether this one's the translation, whatever --translate says
+    pub translate: Option<bool>,
+}
+
+impl Job {
+    pub fn outputs(&self, formats: &[Format], options: &Options, language: &str) -> Vec<String> {
+        match &self.output {
+            Some(output_path) => output::paths(output_path, formats, options, language),
+            None => Vec::new(),
+        }
+    }
+
+    // the output files already there, in any language when it's auto; the commands' and templates'
+    // only in the language given
+    pub fn existing(&self, formats: &[Format], options: &Options, language: &str) -> Vec<String> {
+        match &self.output {
+            Some(output_path) => {
+                let extra = output::extra_paths(output_path, options, language).into_iter().filter(|path| !storage::is_remote(path) && Path::new(path).exists());
+                formats.iter().flat_map(|format| output::existing(output_path, *format, options, language)).chain(extra).collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    // every format's file exists, and with check_mtime none is older than the input; remote
+    // outputs can't be checked so never count as done
+    pub fn is_done(&self, formats: &[Format], options: &Options, language: &str, check_mtime: bool) -> bool {
+        let input_modified = fs::metadata(&self.input).and_then(|metadata| metadata.modified()).ok();
+        let fresh = |path: &String| match fs::metadata(path) {
+            Ok(metadata) if check_mtime => match (input_modified, metadata.modified()) {
+                (Some(input_modified), Ok(output_modified)) => output_modified >= input_modified,
+                _ => true,
+            },
+            Ok(_) => true,
+            Err(_) => false,
+        };
+        let Some(output_path) = &self.output else { return false };
+        formats.iter().all(|format| output::existing(output_path, *format, options, language).iter().any(fresh))
+    }
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    input: String,
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    output: Option<String>,
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_else(|| "transcript".to_string())
+}
+
+// a single input writes to the output path as given unless it's a directory, several always
+// turn it into a directory of per-input files named by stem
+pub fn from_inputs(inputs: &[String], output: Option<&str>, directory: bool) -> Vec<Job> {
+    inputs
+        .iter()
+        .map(|input| Job {
+            input: input.clone(),
+            output: output.map(|output| if inputs.len() == 1 && !directory { output.to_string() } else { Path::new(output).join(stem(Path::new(input))).to_string_lossy().into_owned() }),
+            language: None,
+            translate: None,
+        })
+        .collect()
+}
+
+// --tasks transcribe,translate: every input twice, the transcript to <output>.orig and the
+// english translation to <output>.en
+pub fn with_tasks(jobs: Vec<Job>) -> Vec<Job> {
+    jobs.into_iter()
+        .flat_map(|job| {
+            [(false, "orig"), (true, "en")].map(|(translate, suffix)| Job {
+                input: job.input.clone(),
+                output: job.output.as_ref().map(|output| format!("{}.{}", output, suffix)),
+                language: job.language.clone(),
+                translate: Some(translate),
+            })
+        })
+        .collect()
+}
+
+pub fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    path.extension().is_some_and(|extension| extensions.iter().any(|wanted| extension.eq_ignore_ascii_case(wanted.as_str())))
+}
+
+fn walk(dir: &Path, recursive: bool, extensions: &[String], found: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let failed = |error: std::io::Error| Error::input(format!("failed to read {}", dir.display()), error);
+    for entry in fs::read_dir(dir).map_err(failed)? {
+        let path = entry.map_err(failed)?.path();
+        if path.is_dir() {
+            if recursive {
+                walk(&path, recursive, extensions, found)?;
+            }
+        } else if has_extension(&path, extensions) {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+// every matching file under dir, its outputs mirroring the tree under the output directory
+pub fn from_dir(dir: &str, recursive: bool, extensions: &[String], output: &str) -> Result<Vec<Job>, Error> {
+    let mut found = Vec::new();
+    walk(Path::new(dir), recursive, extensions, &mut found)?;
+    found.sort();
+    Ok(found.iter().map(|path| mirrored(dir, path, output)).collect())
+}
+
+// a file under dir, its outputs at the same place under the output directory
+pub fn mirrored(dir: &str, path: &Path, output: &str) -> Job {
+    let relative = path.strip_prefix(dir).expect("walked paths are under the input directory");
+    let mirrored = relative.parent().map(|parent| Path::new(output).join(parent)).unwrap_or_else(|| PathBuf::from(output));
+    Job {
+        input: path.to_string_lossy().into_owned(),
+        output: Some(mirrored.join(stem(path)).to_string_lossy().into_owned()),
+        language: None,
+        translate: None,
+    }
+}
+
+// a json array of entries, or csv with an input column and optional language and output columns;
+// without an output of its own an entry writes under the output directory, or next to its input
+fn parse_manifest(contents: &str, json: bool, output: Option<&str>) -> Result<Vec<Job>, String> {
+    let entries: Vec<Entry> = if json {
+        serde_json::from_str(contents).map_err(|error| format!("expected a json array of entries, {}", error))?
+    } else {
+        csv::Reader::from_reader(contents.as_bytes()).deserialize().collect::<Result<_, _>>().map_err(|error| format!("expected csv with an input column, {}", error))?
+    };
+    entries.into_iter().map(|entry| entry_job(entry, output)).collect()
+}
+
+fn entry_job(entry: Entry, output: Option<&str>) -> Result<Job, String> {
+    let input = Path::new(&entry.input);
+    let output = entry.output.filter(|output| !output.is_empty()).unwrap_or_else(|| match output {
+        Some(output) => Path::new(output).join(stem(input)).to_string_lossy().into_owned(),
+        None => input.with_extension("").to_string_lossy().into_owned(),
+    });
+    let language = entry.language.filter(|language| !language.is_empty()).map(|language| decode::parse_language(&language)).transpose()?;
+    Ok(Job { input: entry.input, output: Some(output), language, translate: None })
+}
+
+// --stdin-jobs: a line is one manifest entry as a json object, written where a manifest's would be
+pub fn from_line(line: &str, output: Option<&str>) -> Result<Job, String> {
+    let entry: Entry = serde_json::from_str(line).map_err(|error| format!("expected a json object with an input, {}", error))?;
+    entry_job(entry, output)
+}
+
+pub fn from_manifest(path: &str, output: Option<&str>) -> Result<Vec<Job>, Error> {
+    let failed = |error: &dyn std::fmt::Display| Error::input(format!("bad manifest {}", path), error);
+    let contents = fs::read_to_string(path).map_err(|error| failed(&error))?;
+    parse_manifest(&contents, path.ends_with(".json"), output).map_err(|error| failed(&error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _from_dir() {
+        let dir = std::env::temp_dir().join(format!("transcript-from-dir-{}", std::process::id()));
+        fs::create_dir_all(dir.join("2024/march")).unwrap();
+        for file in ["a.wav", "notes.txt", "2024/b.MP3", "2024/march/c.wav"] {
+            fs::write(dir.join(file), b"").unwrap();
+        }
+        let extensions = vec!["wav".to_string(), "mp3".to_string()];
+        let dir_raw = dir.to_string_lossy().into_owned();
+
+        let outputs = |recursive| -> Vec<String> { from_dir(&dir_raw, recursive, &extensions, "out").unwrap().into_iter().filter_map(|job| job.output).collect() };
+        assert_eq!(outputs(false), vec!["out/a"]);
+        assert_eq!(outputs(true), vec!["out/2024/b", "out/2024/march/c", "out/a"]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let jobs = from_inputs(&["x/one.wav".to_string(), "two.mp3".to_string()], Some("out"), false);
+        assert_eq!(jobs.iter().map(|job| job.output.as_deref()).collect::<Vec<_>>(), vec![Some("out/one"), Some("out/two")]);
+        assert_eq!(from_inputs(&["x/one.wav".to_string()], Some("out"), false)[0].output.as_deref(), Some("out"));
+        assert_eq!(from_inputs(&["x/one.wav".to_string()], Some("out"), true)[0].output.as_deref(), Some("out/one"));
+        assert_eq!(from_inputs(&["x/one.wav".to_string()], None, false)[0].output, None);
+
+        let jobs = with_tasks(from_inputs(&["x/one.wav".to_string()], Some("out"), true));
+        assert_eq!(jobs.iter().map(|job| (job.output.as_deref(), job.translate)).collect::<Vec<_>>(), vec![(Some("out/one.orig"), Some(false)), (Some("out/one.en"), Some(true))]);
+    }
+
+    #[test]
+    fn _is_done() {
+        let dir = std::env::temp_dir().join(format!("transcript-is-done-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("in.wav"), b"").unwrap();
+        fs::write(dir.join("out.txt"), b"").unwrap();
+        let job = Job { input: dir.join("in.wav").to_string_lossy().into_owned(), output: Some(dir.join("out").to_string_lossy().into_owned()), language: None, translate: None };
+        let options = Options::default();
+        assert!(job.is_done(&[Format::Txt], &options, "en", true));
+        assert!(!job.is_done(&[Format::Txt, Format::Srt], &options, "en", false));
+        let options = Options { name_template: "{stem}.{lang}.{ext}".to_string(), ..Options::default() };
+        assert!(!job.is_done(&[Format::Txt], &options, "en", false));
+
+        fs::write(dir.join("out.cards.csv"), b"").unwrap();
+        fs::write(dir.join("out.wc"), b"").unwrap();
+        let options = Options { templates: vec!["templates/cards.csv.j2".to_string()], format_commands: vec!["wc -c".to_string()], ..Options::default() };
+        let output = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        assert_eq!(job.outputs(&[Format::Srt], &options, "en"), vec![output("out.srt"), output("out.wc"), output("out.cards.csv")]);
+        assert_eq!(job.existing(&[Format::Srt], &options, "en"), vec![output("out.wc"), output("out.cards.csv")]);
+        fs::write(dir.join("out.meta.json"), b"").unwrap();
+        let options = Options { beside: vec!["meta.json".to_string()], ..Options::default() };
+        assert_eq!(job.existing(&[Format::Srt], &options, "en"), vec![output("out.meta.json")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn _parse_manifest() {
+        let jobs = parse_manifest("input,language,output\na/one.wav,de,\ntwo.mp3,,done/two\n", false, None).unwrap();
+        assert_eq!(jobs.iter().map(|job| (job.input.as_str(), job.output.as_deref(), job.language.as_deref())).collect::<Vec<_>>(), vec![("a/one.wav", Some("a/one"), Some("de")), ("two.mp3", Some("done/two"), None)]);
+
+        let jobs = parse_manifest(r#"[{"input": "a/one.wav"}, {"input": "two.mp3", "language": "fr"}]"#, true, Some("out")).unwrap();
+        assert!(parse_manifest("input\nx.wav\n", false, None).is_ok());
+        assert!(parse_manifest(r#"[{"input": "a.wav", "language": "xx"}]"#, true, None).is_err());
+        assert_eq!(jobs.iter().map(|job| (job.output.as_deref(), job.language.as_deref())).collect::<Vec<_>>(), vec![(Some("out/one"), None), (Some("out/two"), Some("fr"))]);
+
+        let job = from_line(r#"{"input": "a/one.wav", "language": "de"}"#, None).unwrap();
+        assert_eq!((job.input.as_str(), job.output.as_deref(), job.language.as_deref()), ("a/one.wav", Some("a/one"), Some("de")));
+        assert!(from_line("a/one.wav", None).is_err());
+    }
+}
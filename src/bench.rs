@@ -0,0 +1,170 @@
+use std::f32::consts::TAU;
+use std::time::Instant;
+
+use serde::Serialize;
+use whisper_rs::WhisperContext;
+
+use transcript::error::Error;
+use transcript::{audio, decode, models, transcriber};
+
+use crate::transcribe;
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Model file or name, as for transcribe
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Audio to time the decode on; without it a synthetic signal is made up, 30s of it unless
+    /// --duration says otherwise
+    #[arg(short, long)]
+    input: Option<String>,
+
+    /// Thread counts to try, e.g. 1,4,8; defaults to the powers of two up to the available
+    /// parallelism, and that itself
+    #[arg(long, value_delimiter = ',', value_parser = clap::value_parser!(u64).range(1..))]
+    threads: Vec<u64>,
+
+    /// Print the results as JSON
+    #[arg(long)]
+    json: bool,
+
+    #[command(flatten)]
+    audio_options: audio::Options,
+}
+
+// one thread count: the encoder over its 30s window alone, and the whole transcription as
+// transcribe runs it
+#[derive(Serialize, Debug, PartialEq)]
+struct Row {
+    threads: usize,
+    encode_seconds: f64,
+    transcribe_seconds: f64,
+    // time taken over the audio's length, below 1 is faster than realtime
+    realtime_factor: f64,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Report {
+    model: String,
+    model_load_seconds: f64,
+    audio_seconds: f64,
+    synthetic: bool,
+    rows: Vec<Row>,
+}
+
+// something shaped like speech for whisper to chew on: a voice's harmonics rising and falling a
+// few times a second, with a short pause every 3s
+fn signal(ms: u64) -> Vec<f32> {
+    let rate = audio::SAMPLE_RATE as f32;
+    (0..ms * audio::SAMPLE_RATE as u64 / 1000)
+        .map(|i| {
+            let t = i as f32 / rate;
+            if t % 3.0 > 2.6 {
+                return 0.0;
+            }
+            let pitch = 120.0 + 20.0 * (TAU * 0.5 * t).sin();
+            let voice: f32 = (1..=8).map(|harmonic| (TAU * pitch * harmonic as f32 * t).sin() / harmonic as f32).sum();
+            let syllables = 0.5 + 0.5 * (TAU * 4.0 * t).sin();
+            0.1 * voice * syllables
+        })
+        .collect()
+}
+
+fn default_threads() -> Vec<usize> {
+    let most = decode::default_threads();
+    let mut threads: Vec<usize> = (0..).map(|power| 1 << power).take_while(|threads| *threads < most).collect();
+    threads.push(most);
+    threads
+}
+
+fn row(ctx: &WhisperContext, samples: &[f32], threads: usize) -> Result<Row, Error> {
+    let window = &samples[..samples.len().min(30 * audio::SAMPLE_RATE as usize)];
+    let mut state = ctx.create_state().map_err(|error| Error::inference("failed to create state", error))?;
+    state.pcm_to_mel(window, threads).map_err(|error| Error::inference("failed to compute spectrogram", error))?;
+    let started = Instant::now();
+    state.encode(0, threads).map_err(|error| Error::inference("failed to run the encoder", error))?;
+    let encode_seconds = started.elapsed().as_secs_f64();
+
+    // no retries at a higher temperature, so every thread count does the same work
+    let options = decode::Options { threads: Some(threads), temperature_increment: 0.0, ..decode::Options::default() };
+    let started = Instant::now();
+    options.transcribe(ctx, samples, |_| {})?;
+    let transcribe_seconds = started.elapsed().as_secs_f64();
+    let audio_seconds = samples.len() as f64 / audio::SAMPLE_RATE as f64;
+    Ok(Row { threads, encode_seconds, transcribe_seconds, realtime_factor: transcribe_seconds / audio_seconds })
+}
+
+impl Report {
+    fn render(&self) -> String {
+        let source = if self.synthetic { "of a synthetic signal" } else { "of audio" };
+        let mut lines = vec![
+            format!("model  {}, loaded in {:.2}s", self.model, self.model_load_seconds),
+            format!("audio  {:.1}s {}", self.audio_seconds, source),
+            String::new(),
+            "threads   encode  transcribe  realtime factor  speed".to_string(),
+        ];
+        for row in &self.rows {
+            let speed = if row.realtime_factor > 0.0 { 1.0 / row.realtime_factor } else { 0.0 };
+            lines.push(format!("{:>7}  {:>6.2}s  {:>9.2}s  {:>15.3}  {:>4.1}x", row.threads, row.encode_seconds, row.transcribe_seconds, row.realtime_factor, speed));
+        }
+        lines.join("\n")
+    }
+}
+
+pub fn run(args: Args) -> Result<(), Error> {
+    let model = match args.model {
+        Some(model) => model,
+        None => models::discover()?,
+    };
+    let samples = match &args.input {
+        Some(input) => transcribe::load_audio(input, &args.audio_options)?,
+        None => signal(args.audio_options.duration.unwrap_or(30_000)),
+    };
+    if samples.is_empty() {
+        return Err(Error::Input("there's no audio to time the decode on".to_string()));
+    }
+    let started = Instant::now();
+    let ctx = transcriber::load_model(&model, decode::Options::default().context_params()?)?;
+    let model_load_seconds = started.elapsed().as_secs_f64();
+
+    let threads = match args.threads.is_empty() {
+        true => default_threads(),
+        false => args.threads.iter().map(|threads| *threads as usize).collect(),
+    };
+    let mut rows = Vec::new();
+    for threads in threads {
+        tracing::info!("timing {} threads", threads);
+        rows.push(row(&ctx, &samples, threads)?);
+    }
+    let report = Report { model, model_load_seconds, audio_seconds: samples.len() as f64 / audio::SAMPLE_RATE as f64, synthetic: args.input.is_none(), rows };
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report).expect("failed to serialize report"));
+    } else {
+        println!("{}", report.render());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _render() {
+        let samples = signal(3000);
+        assert_eq!(samples.len(), 48_000);
+        assert!(samples.iter().all(|sample| sample.abs() <= 1.0));
+        assert!(samples[2_800 * 16..2_900 * 16].iter().all(|sample| *sample == 0.0));
+        assert!(default_threads().starts_with(&[1]));
+
+        let report = Report {
+            model: "base.en".to_string(),
+            model_load_seconds: 0.25,
+            audio_seconds: 30.0,
+            synthetic: true,
+            rows: vec![Row { threads: 4, encode_seconds: 0.5, transcribe_seconds: 3.0, realtime_factor: 0.1 }],
+        };
+        assert_eq!(report.render(), "model  base.en, loaded in 0.25s\naudio  30.0s of a synthetic signal\n\nthreads   encode  transcribe  realtime factor  speed\n      4    0.50s       3.00s            0.100  10.0x");
+    }
+}
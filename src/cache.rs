@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use transcript::error::Error;
+use transcript::{output, Transcript};
+
+// a transcript as whisper gave it, before it's shifted, post-processed or written, so a run that
+// only changes those gets it back without decoding again
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Entry {
+    // the language it was decoded in, which for auto is the one that was detected
+    pub language: Option<String>,
+    pub transcript: Transcript,
+}
+
+// --cache-dir: entries are named by a hash of the audio as whisper hears it and of whatever else
+// decides what whisper says, the model and the decode options
+pub struct Cache {
+    path: PathBuf,
+}
+
+impl Cache {
+    pub fn new(dir: &str, channels: &[Vec<f32>], settings: &str) -> Cache {
+        let mut hasher = Sha256::new();
+        for channel in channels {
+            for chunk in channel.chunks(4096) {
+                hasher.update(chunk.iter().flat_map(|sample| sample.to_le_bytes()).collect::<Vec<u8>>());
+            }
+            hasher.update(b"\0");
+        }
+        hasher.update(settings.as_bytes());
+        let key: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+        Cache { path: Path::new(dir).join(format!("{}.json", key)) }
+    }
+
+    // one that can't be read back is as good as none, it's decoded again and overwritten
+    pub fn load(&self) -> Option<Entry> {
+        let contents = fs::read_to_string(&self.path).ok()?;
+        match serde_json::from_str(&contents) {
+            Ok(entry) => Some(entry),
+            Err(error) => {
+                tracing::warn!("ignoring cached transcript {}: {}", self.path.display(), error);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, entry: &Entry) -> Result<(), Error> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|error| Error::output(format!("failed to create {}", dir.display()), error))?;
+        }
+        output::write_to_file(&self.path, serde_json::to_string(entry).expect("failed to serialize cached transcript"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transcript::Segment;
+
+    #[test]
+    fn _new() {
+        let dir = std::env::temp_dir().join(format!("transcript-cache-test-{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+        let audio = vec![vec![0.0, 0.5, -0.5]];
+        let cache = Cache::new(dir, &audio, "tiny {}");
+        assert_eq!(cache.path(), Cache::new(dir, &audio, "tiny {}").path());
+        assert_ne!(cache.path(), Cache::new(dir, &audio, "base {}").path());
+        assert_ne!(cache.path(), Cache::new(dir, &[vec![0.0, 0.5, -0.25]], "tiny {}").path());
+
+        assert_eq!(cache.load(), None);
+        let entry = Entry { language: Some("de".to_string()), transcript: Transcript { segments: vec![Segment::new(0, 1000, " Hallo.")] } };
+        cache.save(&entry).unwrap();
+        assert_eq!(cache.load(), Some(entry));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
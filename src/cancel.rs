@@ -0,0 +1,49 @@
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use whisper_rs::FullParams;
+
+// stops a transcription from another thread: a clone is kept by whoever might cancel, and the
+// decode checks it through whisper's abort callback between each step of the model
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    // set_abort_callback_safe has the same boxing mix-up as the progress callback, so this goes
+    // through the raw callback; the token outlives the params, which only live for one decode
+    pub fn apply(&self, params: &mut FullParams) {
+        unsafe {
+            params.set_abort_callback(Some(abort));
+            params.set_abort_callback_user_data(Arc::as_ptr(&self.0) as *mut c_void);
+        }
+    }
+}
+
+unsafe extern "C" fn abort(user_data: *mut c_void) -> bool {
+    (*(user_data as *const AtomicBool)).load(Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _cancel() {
+        let token = CancellationToken::default();
+        let handle = token.clone();
+        assert!(!token.is_cancelled());
+        handle.cancel();
+        assert!(token.is_cancelled());
+        assert!(unsafe { abort(Arc::as_ptr(&token.0) as *mut c_void) });
+        assert!(!CancellationToken::default().is_cancelled());
+    }
+}
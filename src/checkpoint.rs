@@ -0,0 +1,81 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use transcript::decode::Part;
+use transcript::error::Error;
+use transcript::output;
+
+// how far a windowed run has got, saved beside its output after every window so --resume can
+// carry on from there instead of decoding hours of audio again
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Checkpoint {
+    // the input and whatever decides where the windows fall, a checkpoint from a run with other
+    // settings is no use
+    settings: String,
+    pub channels: Vec<Part>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Checkpoint {
+    pub fn path(output_path_raw: &str) -> PathBuf {
+        PathBuf::from(format!("{}.checkpoint.json", output_path_raw))
+    }
+
+    // a fresh checkpoint unless resuming finds one saved with the same settings; without a path
+    // nothing is saved
+    pub fn open(path: Option<PathBuf>, settings: String, channels: usize, resume: bool) -> Result<Checkpoint, Error> {
+        let fresh = Checkpoint { settings: settings.clone(), channels: (0..channels).map(|_| Part::default()).collect(), path: path.clone() };
+        let Some(existing) = path.as_ref().filter(|path| resume && path.exists()) else {
+            return Ok(fresh);
+        };
+        let contents = fs::read_to_string(existing).map_err(|error| Error::input(format!("failed to read checkpoint {}", existing.display()), error))?;
+        let checkpoint: Checkpoint = serde_json::from_str(&contents).map_err(|error| Error::input(format!("checkpoint {} is corrupt", existing.display()), error))?;
+        if checkpoint.settings != settings || checkpoint.channels.len() != channels {
+            tracing::warn!("{} was saved with other settings, starting over", existing.display());
+            return Ok(fresh);
+        }
+        let windows: usize = checkpoint.channels.iter().map(|part| part.windows).sum();
+        tracing::info!("resuming after {} windows from {}", windows, existing.display());
+        Ok(Checkpoint { path, ..checkpoint })
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        match &self.path {
+            Some(path) => output::write_to_file(path, serde_json::to_string(self).expect("failed to serialize checkpoint")),
+            None => Ok(()),
+        }
+    }
+
+    // once the outputs are written they hold everything it did
+    pub fn remove(&self) {
+        if let Some(path) = self.path.as_ref().filter(|path| path.exists()) {
+            if let Err(error) = fs::remove_file(path) {
+                tracing::warn!("failed to remove {}: {}", path.display(), error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transcript::Segment;
+
+    #[test]
+    fn _open() {
+        let path = std::env::temp_dir().join(format!("transcript-checkpoint-test-{}.json", std::process::id()));
+        let mut checkpoint = Checkpoint::open(Some(path.clone()), "a.wav 30 5".to_string(), 1, false).unwrap();
+        checkpoint.channels[0] = Part { windows: 2, segments: vec![Segment::new(0, 1000, " Hello.")] };
+        checkpoint.save().unwrap();
+
+        let resumed = Checkpoint::open(Some(path.clone()), "a.wav 30 5".to_string(), 1, true).unwrap();
+        assert_eq!(resumed, checkpoint);
+        assert_eq!(Checkpoint::open(Some(path.clone()), "a.wav 60 5".to_string(), 1, true).unwrap().channels, vec![Part::default()]);
+        assert_eq!(Checkpoint::open(Some(path.clone()), "a.wav 30 5".to_string(), 1, false).unwrap().channels, vec![Part::default()]);
+        resumed.remove();
+        assert!(!path.exists());
+    }
+}
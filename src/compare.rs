@@ -0,0 +1,88 @@
+// two transcripts lined up word by word, as eval scores them and diff shows them
+
+// how a word of the first lines up with the second, by their indices
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edit {
+    Same(usize, usize),
+    Changed(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+// what's compared: lowercase, without punctuation, though an apostrophe inside a word stays so
+// "it's" isn't "its"; strict compares the words as they're written
+pub fn words(text: &str, strict: bool) -> Vec<String> {
+    if strict {
+        return text.split_whitespace().map(str::to_string).collect();
+    }
+    let lowered: String = text.chars().flat_map(char::to_lowercase).map(|c| if c.is_alphanumeric() || c == '\'' { c } else { ' ' }).collect();
+    lowered.split_whitespace().map(|word| word.trim_matches('\'')).filter(|word| !word.is_empty()).map(str::to_string).collect()
+}
+
+// the fewest substitutions, removals and additions that turn a into b; the whole table of
+// a.len() by b.len() is kept to trace them back, a byte a cell
+pub fn edits<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Edit> {
+    let (n, m) = (a.len(), b.len());
+    // from where each cell was reached: 0 the diagonal, 1 a removal, 2 an addition
+    let mut from = vec![0u8; (n + 1) * (m + 1)];
+    let mut previous: Vec<usize> = (0..=m).collect();
+    from[..=m].fill(2);
+    for i in 1..=n {
+        let mut current = vec![i];
+        from[i * (m + 1)] = 1;
+        for j in 1..=m {
+            // on a tie a word is rather left out or added than changed, so the words either side
+            // still line up
+            let diagonal = match a[i - 1] == b[j - 1] {
+                true => (previous[j - 1], 0),
+                false => (previous[j - 1] + 1, 3),
+            };
+            let best = diagonal.min((previous[j] + 1, 1)).min((current[j - 1] + 1, 2));
+            current.push(best.0);
+            from[i * (m + 1) + j] = best.1 % 3;
+        }
+        previous = current;
+    }
+
+    let mut edits = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        match from[i * (m + 1) + j] {
+            0 => {
+                i -= 1;
+                j -= 1;
+                edits.push(if a[i] == b[j] { Edit::Same(i, j) } else { Edit::Changed(i, j) });
+            }
+            1 => {
+                i -= 1;
+                edits.push(Edit::Removed(i));
+            }
+            _ => {
+                j -= 1;
+                edits.push(Edit::Added(j));
+            }
+        }
+    }
+    edits.reverse();
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _edits() {
+        assert_eq!(words("It's a well-known \"fact\", isn't it?", false), vec!["it's", "a", "well", "known", "fact", "isn't", "it"]);
+        assert_eq!(words(" Hello,  World.", true), vec!["Hello,", "World."]);
+
+        let reference = words("the cat sat on the mat", false);
+        let hypothesis = words("the cat sat at a the mat today", false);
+        assert_eq!(
+            edits(&reference, &hypothesis),
+            vec![Edit::Same(0, 0), Edit::Same(1, 1), Edit::Same(2, 2), Edit::Changed(3, 3), Edit::Added(4), Edit::Same(4, 5), Edit::Same(5, 6), Edit::Added(7)]
+        );
+        assert_eq!(edits(&reference, &[]).len(), 6);
+        assert_eq!(edits::<String>(&[], &[]), vec![]);
+    }
+}
@@ -0,0 +1,123 @@
+use std::time::Instant;
+
+use transcript::compare::{self, Edit};
+use transcript::error::Error;
+use transcript::output::vtt;
+use transcript::{audio, decode, Transcriber, Transcript};
+
+// --models: the same audio transcribed with each model in turn, to weigh what a bigger one gets
+// right against how much longer it takes; the last is taken as the one to measure the rest by
+struct Run {
+    model: String,
+    load_seconds: f64,
+    decode_seconds: f64,
+    transcript: Transcript,
+}
+
+// a transcript's words as they're written, each with the segment it's in
+fn words(transcript: &Transcript) -> Vec<(usize, &str)> {
+    transcript.segments.iter().enumerate().flat_map(|(i, segment)| segment.text.split_whitespace().map(move |word| (i, word))).collect()
+}
+
+fn compared(words: &[(usize, &str)]) -> Vec<String> {
+    words.iter().map(|(_, word)| compare::words(word, false).join(" ")).collect()
+}
+
+// how far a transcript's words are from the reference's, over the reference's length
+fn word_error_rate(reference: &Transcript, transcript: &Transcript) -> f64 {
+    let reference = compared(&words(reference));
+    let edits = compare::edits(&reference, &compared(&words(transcript)));
+    let errors = edits.iter().filter(|edit| !matches!(edit, Edit::Same(..))).count();
+    if reference.is_empty() { f64::from(u8::from(errors > 0)) } else { errors as f64 / reference.len() as f64 }
+}
+
+// the transcript's words lined up under each of the reference's segments, [-left out-] and
+// {+added+}; words added between two segments go with the one after them
+fn lined_up(reference: &Transcript, transcript: &Transcript) -> Vec<String> {
+    let (a, b) = (words(reference), words(transcript));
+    let mut lines: Vec<Vec<String>> = vec![Vec::new(); reference.segments.len()];
+    let mut added = Vec::new();
+    for edit in compare::edits(&compared(&a), &compared(&b)) {
+        let (i, word) = match edit {
+            Edit::Same(i, j) => (i, b[j].1.to_string()),
+            Edit::Changed(i, j) => (i, format!("[-{}-]{{+{}+}}", a[i].1, b[j].1)),
+            Edit::Removed(i) => (i, format!("[-{}-]", a[i].1)),
+            Edit::Added(j) => {
+                added.push(format!("{{+{}+}}", b[j].1));
+                continue;
+            }
+        };
+        lines[a[i].0].append(&mut added);
+        lines[a[i].0].push(word);
+    }
+    if let Some(line) = lines.last_mut() {
+        line.append(&mut added);
+    }
+    lines.into_iter().map(|line| line.join(" ")).collect()
+}
+
+fn render(runs: &[Run], duration_seconds: f64) -> String {
+    let reference = &runs[runs.len() - 1];
+    let width = runs.iter().map(|run| run.model.chars().count()).max().unwrap_or(0).max("model".len());
+    let mut lines = vec![format!("{:<width$}  {:>7}  {:>7}  {:>8}  {:>6}  {:>8}  {:>10}  wer vs {}", "model", "load", "decode", "speed", "words", "segments", "confidence", reference.model)];
+    for run in runs {
+        let words = run.transcript.segments.iter().map(|segment| segment.text.split_whitespace().count()).sum::<usize>();
+        let confidences: Vec<f32> = run.transcript.segments.iter().filter_map(|segment| segment.confidence()).collect();
+        let confidence = match confidences.len() {
+            0 => "-".to_string(),
+            n => format!("{:.2}", confidences.iter().sum::<f32>() / n as f32),
+        };
+        let speed = if run.decode_seconds > 0.0 { duration_seconds / run.decode_seconds } else { 0.0 };
+        let wer = format!("{:.2}%", word_error_rate(&reference.transcript, &run.transcript) * 100.0);
+        lines.push(format!("{:<width$}  {:>6.1}s  {:>6.1}s  {:>7.1}x  {:>6}  {:>8}  {:>10}  {}", run.model, run.load_seconds, run.decode_seconds, speed, words, run.transcript.segments.len(), confidence, wer));
+    }
+
+    let others: Vec<(&Run, Vec<String>)> = runs[..runs.len() - 1].iter().map(|run| (run, lined_up(&reference.transcript, &run.transcript))).collect();
+    for (i, segment) in reference.transcript.segments.iter().enumerate() {
+        lines.push(String::new());
+        lines.push(format!("{} {:<width$}  {}", vtt::timestamp(segment.start_ms), reference.model, segment.text.trim()));
+        for (run, lined_up) in &others {
+            lines.push(format!("{:12} {:<width$}  {}", "", run.model, lined_up[i]));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+pub fn run(models: &[String], input: &str, decode_options: &decode::Options, audio_options: &audio::Options) -> Result<(), Error> {
+    let samples = crate::transcribe::load_audio(input, audio_options)?;
+    let duration_seconds = samples.len() as f64 / audio::SAMPLE_RATE as f64;
+    let mut runs = Vec::new();
+    for model in models {
+        tracing::info!("transcribing {} with {}", input, model);
+        let transcriber = Transcriber::new(model.as_str()).options(decode_options.clone()).cancel_with(decode_options.cancel.clone());
+        let started = Instant::now();
+        transcriber.load()?;
+        let load_seconds = started.elapsed().as_secs_f64();
+        let started = Instant::now();
+        let transcript = transcriber.transcribe(&samples)?;
+        runs.push(Run { model: model.clone(), load_seconds, decode_seconds: started.elapsed().as_secs_f64(), transcript });
+        if decode_options.cancel.is_cancelled() {
+            return Err(Error::Interrupted("interrupted before every model had its turn".to_string()));
+        }
+    }
+    print!("{}", render(&runs, duration_seconds));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transcript::Segment;
+
+    #[test]
+    fn _render() {
+        let transcript = |texts: &[&str]| Transcript { segments: texts.iter().enumerate().map(|(i, text)| Segment::new(i as i64 * 2000, i as i64 * 2000 + 1500, text)).collect() };
+        let run = |model: &str, decode_seconds, transcript| Run { model: model.to_string(), load_seconds: 0.5, decode_seconds, transcript };
+        let runs = [run("tiny.en", 1.0, transcript(&[" Hello their, my friend.", " Bye bye now."])), run("small.en", 4.0, transcript(&[" Hello there, my old friend.", " Bye now."]))];
+        assert_eq!(lined_up(&runs[1].transcript, &runs[0].transcript), vec!["Hello [-there,-]{+their,+} my [-old-] friend.", "{+Bye+} bye now."]);
+        assert_eq!(word_error_rate(&runs[1].transcript, &runs[0].transcript), 3.0 / 7.0);
+        let rendered = render(&runs, 8.0);
+        assert!(rendered.contains("\ntiny.en      0.5s     1.0s      8.0x       7         2           -  42.86%\n"), "{}", rendered);
+        assert!(rendered.ends_with("00:00:02.000 small.en  Bye now.\n             tiny.en   {+Bye+} bye now.\n"), "{}", rendered);
+    }
+}
@@ -0,0 +1,46 @@
+use std::fs;
+use std::io::{self, Write};
+
+use clap_complete::Shell;
+
+use transcript::error::Error;
+
+// the script for the shell, from the same clap definitions the arguments are parsed with
+pub fn completions(mut command: clap::Command, shell: Shell) -> Result<(), Error> {
+    let name = command.get_name().to_string();
+    // clap_complete panics on a write that fails, a closed pipe included, so it writes to memory
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, name, &mut script);
+    io::stdout().write_all(&script).map_err(|error| Error::output("failed to write the completion script", error))
+}
+
+// transcript.1 on stdout, or a page for every subcommand as well, transcript-transcribe.1 and so
+// on, written into dir
+pub fn manpage(command: clap::Command, dir: Option<&str>) -> Result<(), Error> {
+    match dir {
+        Some(dir) => {
+            let failed = |error: io::Error| Error::output(format!("failed to write man pages to {}", dir), error);
+            fs::create_dir_all(dir).map_err(failed)?;
+            clap_mangen::generate_to(command, dir).map_err(failed)?;
+            tracing::info!("wrote man pages to {}", dir);
+            Ok(())
+        }
+        None => clap_mangen::Man::new(command).render(&mut io::stdout()).map_err(|error| Error::output("failed to write the man page", error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _manpage() {
+        let command = clap::Command::new("transcript").subcommand(clap::Command::new("convert").about("Convert a subtitle file"));
+        let dir = std::env::temp_dir().join(format!("transcript-manpage-{}", std::process::id()));
+        manpage(command, Some(&dir.to_string_lossy())).unwrap();
+        let page = fs::read_to_string(dir.join("transcript-convert.1")).unwrap();
+        assert!(page.contains("Convert a subtitle file"), "{}", page);
+        assert!(dir.join("transcript.1").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use transcript::error::Error;
+use transcript::output::Format;
+
+// defaults for the flags people pass on every run; anything given on the command line wins
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub model: Option<String>,
+    pub language: Option<String>,
+    pub formats: Option<Vec<Format>>,
+    pub output_dir: Option<String>,
+    pub threads: Option<usize>,
+}
+
+// $XDG_CONFIG_HOME/transcript/config.toml, falling back to ~/.config
+pub fn default_path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("transcript").join("config.toml"))
+}
+
+impl Config {
+    pub fn parse(contents: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(contents).map_err(|error| error.to_string())?;
+        if let Some(language) = &config.language {
+            transcript::decode::parse_language(language)?;
+        }
+        // clap checks --threads, a config's is only checked here
+        if config.threads == Some(0) {
+            return Err("threads has to be at least 1".to_string());
+        }
+        Ok(config)
+    }
+
+    // a missing default config is fine, one named with --config has to be there
+    pub fn load(path: Option<&str>) -> Result<Config, Error> {
+        let (path, required) = match path {
+            Some(path) => (PathBuf::from(path), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound && !required => return Ok(Config::default()),
+            Err(error) => return Err(Error::input(format!("failed to read config {}", path.display()), error)),
+        };
+        Config::parse(&contents).map_err(|error| Error::input(format!("invalid config {}", path.display()), error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _parse() {
+        let config = Config::parse("model = \"models/ggml-base.bin\"\nformats = [\"srt\", \"vtt\"]\nthreads = 4\n").unwrap();
+        assert_eq!(config.model.as_deref(), Some("models/ggml-base.bin"));
+        assert_eq!(config.formats, Some(vec![Format::Srt, Format::Vtt]));
+        assert_eq!(config.threads, Some(4));
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+        assert!(Config::parse("language = \"xx\"").is_err());
+        assert!(Config::parse("threads = 0").is_err());
+        assert!(Config::parse("modle = \"typo.bin\"").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+use transcript::error::Error;
+use transcript::output::{self, parse, Format};
+use transcript::Transcript;
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Subtitle file to convert: srt, vtt, sbv or the json transcript
+    input: String,
+
+    /// Format to write it in
+    #[arg(long, value_enum)]
+    to: Format,
+
+    /// Output path without an extension; without one the converted file is printed to stdout
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Replace an output file that already exists instead of refusing to run
+    #[arg(long)]
+    overwrite: bool,
+
+    /// Language the transcript is in, for json's language and a {lang} in --name-template
+    #[arg(short, long, default_value = "en")]
+    language: String,
+
+    #[command(flatten)]
+    output_options: output::Options,
+}
+
+// read back as a transcript and written the way transcribe writes one, so the output flags such
+// as --shift and the subtitle layout ones apply as well
+pub fn run(args: Args) -> Result<(), Error> {
+    let mut transcript = parse::read(&args.input)?;
+    transcript.shift(args.output_options.shift);
+    write(&transcript, args.to, args.output.as_deref(), args.overwrite, &args.language, args.output_options)
+}
+
+// printed without an output path
+pub fn write(transcript: &Transcript, format: Format, output_path_raw: Option<&str>, overwrite: bool, language: &str, mut output_options: output::Options) -> Result<(), Error> {
+    output_options.language = Some(language.to_string());
+    output_options.keep_existing = !overwrite;
+    let Some(output_path_raw) = output_path_raw else {
+        print!("{}", format.render(transcript, &output_options));
+        return Ok(());
+    };
+    if !overwrite {
+        if let Some(path) = output::existing(output_path_raw, format, &output_options, language).first() {
+            return Err(Error::Output(format!("{} already exists, pass --overwrite to replace it", path)));
+        }
+    }
+    output::write_outputs(transcript, output_path_raw, &[format], &output_options, language)
+}
@@ -0,0 +1,239 @@
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{self, PathBuf};
+use std::process;
+use std::sync::mpsc;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use transcript::cancel::CancellationToken;
+use transcript::error::{self, Error};
+use transcript::{decode, models, Event, Segment, Transcriber, Transcript};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Model file or name, as for transcribe; it's loaded once and kept for every client
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Socket to listen on, defaults to $XDG_RUNTIME_DIR/transcript.sock
+    #[arg(long, value_name = "PATH")]
+    socket: Option<PathBuf>,
+
+    // only what goes into loading the model applies, --device, --gpu-device and --dtw; the rest
+    // comes with each client's request
+    #[command(flatten)]
+    decode_options: decode::Options,
+}
+
+// what a client asks for, a line of json followed by the samples as little endian f32s
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Request {
+    Detect { samples: usize },
+    Transcribe { options: Box<decode::Options>, samples: usize },
+}
+
+// what the daemon answers, a line of json each
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Reply {
+    Detected { language: String, probability: f32 },
+    Progress(u8),
+    Segment(Segment),
+    Done(Transcript),
+    Failed(Error),
+}
+
+// per user, so the daemons of two users never meet
+pub fn default_socket() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("transcript.sock"),
+        None => env::temp_dir().join(format!("transcript-{}.sock", env::var("USER").unwrap_or_default())),
+    }
+}
+
+fn send(writer: &mut impl Write, reply: &Reply) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, reply)?;
+    writer.write_all(b"\n")
+}
+
+fn serve(transcriber: &Transcriber, stream: UnixStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let request: Request = serde_json::from_str(&line)?;
+    let count = match &request {
+        Request::Detect { samples } | Request::Transcribe { samples, .. } => *samples,
+    };
+    let mut bytes = vec![0u8; count * 4];
+    reader.read_exact(&mut bytes)?;
+    let samples: Vec<f32> = bytes.chunks_exact(4).map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).collect();
+
+    match request {
+        Request::Detect { .. } => {
+            let reply = match transcriber.detect_language(&samples) {
+                Ok((language, probability)) => Reply::Detected { language: language.to_string(), probability },
+                Err(error) => Reply::Failed(error),
+            };
+            send(&mut writer, &reply)
+        }
+        Request::Transcribe { options, .. } => {
+            let token = CancellationToken::default();
+            // the client shuts its end when it's interrupted, which stops the decode with what it has
+            let watched = token.clone();
+            thread::spawn(move || {
+                let _ = reader.read(&mut [0u8; 1]);
+                watched.cancel();
+            });
+            let (events, received) = mpsc::channel();
+            let transcriber = transcriber.clone().options(*options).cancel_with(token).events(events);
+            let decode = thread::spawn(move || transcriber.transcribe(&samples));
+            for event in received {
+                let reply = match event {
+                    Event::Progress(percent) => Reply::Progress(percent),
+                    Event::SegmentDecoded(segment) => Reply::Segment(segment),
+                    _ => continue,
+                };
+                send(&mut writer, &reply)?;
+            }
+            let reply = match decode.join().expect("decode panicked") {
+                Ok(transcript) => Reply::Done(transcript),
+                Err(error) => Reply::Failed(error),
+            };
+            send(&mut writer, &reply)
+        }
+    }
+}
+
+pub fn run(args: Args) -> Result<(), Error> {
+    let model = match args.model {
+        Some(model) => model,
+        None => models::discover()?,
+    };
+    let socket = args.socket.unwrap_or_else(default_socket);
+    if UnixStream::connect(&socket).is_ok() {
+        return Err(Error::Input(format!("a daemon is already listening on {}", socket.display())));
+    }
+    // left behind by a daemon that didn't get to clean up
+    let _ = fs::remove_file(&socket);
+
+    tracing::info!("loading model {}", model);
+    let transcriber = Transcriber::new(model).options(args.decode_options);
+    transcriber.load()?;
+    let listener = UnixListener::bind(&socket).map_err(|error| Error::input(format!("failed to listen on {}", socket.display()), error))?;
+    let bound = socket.clone();
+    let installed = ctrlc::set_handler(move || {
+        let _ = fs::remove_file(&bound);
+        process::exit(error::INTERRUPTED as i32);
+    });
+    if let Err(error) = installed {
+        tracing::warn!("failed to install the ctrl-c handler: {}", error);
+    }
+    tracing::info!("listening on {}", socket.display());
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let transcriber = transcriber.clone();
+                thread::spawn(move || {
+                    if let Err(error) = serve(&transcriber, stream) {
+                        tracing::warn!("a client failed: {}", error);
+                    }
+                });
+            }
+            Err(error) => tracing::warn!("failed to accept a client: {}", error),
+        }
+    }
+    Ok(())
+}
+
+// transcribe's side of the socket
+pub struct Client {
+    socket: PathBuf,
+}
+
+impl Client {
+    pub fn new(socket: Option<&str>) -> Client {
+        Client { socket: socket.map_or_else(default_socket, PathBuf::from) }
+    }
+
+    fn request(&self, request: &Request, samples: &[f32]) -> Result<(UnixStream, BufReader<UnixStream>), Error> {
+        let mut stream = UnixStream::connect(&self.socket).map_err(|error| Error::model(format!("no daemon on {}, start one with transcript daemon", self.socket.display()), error))?;
+        let mut bytes = serde_json::to_vec(request).expect("failed to serialize request");
+        bytes.push(b'\n');
+        bytes.extend(samples.iter().flat_map(|sample| sample.to_le_bytes()));
+        stream.write_all(&bytes).map_err(|error| Error::inference("failed to send the audio to the daemon", error))?;
+        let reader = BufReader::new(stream.try_clone().map_err(|error| Error::inference("failed to read from the daemon", error))?);
+        Ok((stream, reader))
+    }
+
+    fn reply(reader: &mut BufReader<UnixStream>) -> Result<Reply, Error> {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) => Err(Error::Inference("the daemon hung up".to_string())),
+            Ok(_) => serde_json::from_str(&line).map_err(|error| Error::inference("the daemon's answer is garbled", error)),
+            Err(error) => Err(Error::inference("failed to read from the daemon", error)),
+        }
+    }
+
+    // language detection only looks at the first 30s
+    pub fn detect_language(&self, samples: &[f32]) -> Result<(String, f32), Error> {
+        let samples = &samples[..samples.len().min(30 * transcript::audio::SAMPLE_RATE as usize)];
+        let (_stream, mut reader) = self.request(&Request::Detect { samples: samples.len() }, samples)?;
+        match Client::reply(&mut reader)? {
+            Reply::Detected { language, probability } => Ok((language, probability)),
+            Reply::Failed(error) => Err(error),
+            reply => Err(Error::Inference(format!("the daemon answered {:?} to a language detection", reply))),
+        }
+    }
+
+    pub fn transcribe(&self, options: &decode::Options, samples: &[f32], segment: impl Fn(&Segment), progress: impl Fn(u8)) -> Result<Transcript, Error> {
+        // the daemon reads the files named in the options from its own working directory
+        let mut options = options.clone();
+        for file in [&mut options.prompt_file, &mut options.glossary, &mut options.grammar].into_iter().flatten() {
+            if let Ok(absolute) = path::absolute(&*file) {
+                *file = absolute.to_string_lossy().into_owned();
+            }
+        }
+        let request = Request::Transcribe { options: Box::new(options.clone()), samples: samples.len() };
+        let (stream, mut reader) = self.request(&request, samples)?;
+        let mut interrupted = false;
+        loop {
+            if options.cancel.is_cancelled() && !interrupted {
+                let _ = stream.shutdown(Shutdown::Write);
+                interrupted = true;
+            }
+            match Client::reply(&mut reader)? {
+                Reply::Progress(percent) => progress(percent),
+                Reply::Segment(decoded) => segment(&decoded),
+                Reply::Done(transcript) => return Ok(transcript),
+                Reply::Failed(error) => return Err(error),
+                reply => return Err(Error::Inference(format!("the daemon answered {:?} to a transcription", reply))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::ExitCode;
+
+    #[test]
+    fn _reply() {
+        let mut line = Vec::new();
+        send(&mut line, &Reply::Failed(Error::Input("failed to decode a.wav".to_string()))).unwrap();
+        assert_eq!(line, b"{\"failed\":{\"Input\":\"failed to decode a.wav\"}}\n");
+        let Reply::Failed(error) = serde_json::from_slice(&line).unwrap() else { panic!("expected a failure") };
+        assert_eq!(error.exit_code(), ExitCode::from(2));
+
+        let request = Request::Transcribe { options: Box::default(), samples: 16000 };
+        let Request::Transcribe { options, samples } = serde_json::from_str(&serde_json::to_string(&request).unwrap()).unwrap() else { panic!("expected a transcription") };
+        assert_eq!((options.window_overlap, samples), (5, 16000));
+    }
+}
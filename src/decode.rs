@@ -0,0 +1,728 @@
+use std::ops::Range;
+
+use clap::{Args, Command, FromArgMatches, ValueEnum};
+use serde::{Deserialize, Serialize};
+use whisper_rs::{DtwMode, DtwModelPreset, DtwParameters, FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperError, WhisperState};
+
+use crate::cancel::CancellationToken;
+use crate::device::Device;
+use crate::error::Error;
+use crate::glossary::Glossary;
+use crate::grammar::{Constraint, Grammar};
+use crate::transcript::{Segment, Transcript};
+use crate::vad::{self, Speech};
+
+// whisper.cpp aborts the process on 0
+pub fn parse_threads(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(0) | Err(_) => Err("expected a thread count of at least 1".to_string()),
+        Ok(threads) => Ok(threads),
+    }
+}
+
+pub fn parse_language(value: &str) -> Result<String, String> {
+    if value == "auto" {
+        return Ok(value.to_string());
+    }
+    match whisper_rs::get_lang_id(value) {
+        Some(_) => Ok(value.to_string()),
+        None => Err(format!("unknown language {value}")),
+    }
+}
+
+// start-end, each a time as --offset takes; in ms
+pub fn parse_range(value: &str) -> Result<Range<u64>, String> {
+    let (start, end) = value.split_once('-').ok_or_else(|| format!("expected a range like 00:00-00:30, got {}", value))?;
+    let (start, end) = (crate::audio::parse_time(start.trim())?, crate::audio::parse_time(end.trim())?);
+    if end <= start {
+        return Err(format!("{} ends before it starts", value));
+    }
+    Ok(start..end)
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    Greedy,
+    Beam,
+}
+
+// the alignment heads whisper.cpp ships for each official model
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dtw {
+    #[value(name = "tiny.en")]
+    TinyEn,
+    Tiny,
+    #[value(name = "base.en")]
+    BaseEn,
+    Base,
+    #[value(name = "small.en")]
+    SmallEn,
+    Small,
+    #[value(name = "medium.en")]
+    MediumEn,
+    Medium,
+    #[value(name = "large-v1")]
+    LargeV1,
+    #[value(name = "large-v2")]
+    LargeV2,
+    #[value(name = "large-v3")]
+    LargeV3,
+}
+
+impl Dtw {
+    fn preset(&self) -> DtwModelPreset {
+        match self {
+            Dtw::TinyEn => DtwModelPreset::TinyEn,
+            Dtw::Tiny => DtwModelPreset::Tiny,
+            Dtw::BaseEn => DtwModelPreset::BaseEn,
+            Dtw::Base => DtwModelPreset::Base,
+            Dtw::SmallEn => DtwModelPreset::SmallEn,
+            Dtw::Small => DtwModelPreset::Small,
+            Dtw::MediumEn => DtwModelPreset::MediumEn,
+            Dtw::Medium => DtwModelPreset::Medium,
+            Dtw::LargeV1 => DtwModelPreset::LargeV1,
+            Dtw::LargeV2 => DtwModelPreset::LargeV2,
+            Dtw::LargeV3 => DtwModelPreset::LargeV3,
+        }
+    }
+}
+
+// serialized to hand a decode to the daemon
+#[derive(Args, Serialize, Deserialize, Debug, Clone)]
+#[group(id = "decode_options")]
+pub struct Options {
+    /// Spoken language as a whisper language code or auto, defaults to en
+    #[arg(short, long, value_parser = parse_language)]
+    pub language: Option<String>,
+
+    /// Translate the speech to English instead of transcribing it
+    #[arg(long)]
+    pub translate: bool,
+
+    #[arg(long, value_enum, default_value = "greedy")]
+    pub strategy: Strategy,
+
+    /// Beams kept alive by the beam strategy
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(i32).range(1..))]
+    pub beam_size: i32,
+
+    /// Candidates sampled by the greedy strategy
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(i32).range(1..))]
+    pub best_of: i32,
+
+    /// Worker threads, defaults to the available parallelism
+    #[arg(short, long, value_parser = parse_threads)]
+    pub threads: Option<usize>,
+
+    /// Text that seeds the decoder, e.g. names and jargon it should spell right
+    #[arg(long, conflicts_with = "prompt_file")]
+    pub initial_prompt: Option<String>,
+
+    /// Read the initial prompt from a file
+    #[arg(long)]
+    pub prompt_file: Option<String>,
+
+    /// File of names and terms, one per line, added to the prompt
+    #[arg(long, value_name = "FILE")]
+    pub glossary: Option<String>,
+
+    /// Also respell near misses of glossary terms in the transcript
+    #[arg(long, requires = "glossary")]
+    pub glossary_correct: bool,
+
+    /// Sampling temperature of the first attempt
+    #[arg(long, default_value_t = 0.0, help_heading = "Temperature fallback")]
+    pub temperature: f32,
+
+    /// Temperature added on every retry, 0 disables the fallback
+    #[arg(long, default_value_t = 0.2, help_heading = "Temperature fallback")]
+    pub temperature_increment: f32,
+
+    /// Retry a window whose token entropy is above this
+    #[arg(long, default_value_t = 2.4, help_heading = "Temperature fallback")]
+    pub entropy_threshold: f32,
+
+    /// Retry when the average token log probability is below this
+    #[arg(long, default_value_t = -1.0, allow_negative_numbers = true, help_heading = "Temperature fallback")]
+    pub logprob_threshold: f32,
+
+    /// Treat a segment as silence when its no-speech probability is above this and its log
+    /// probability below --logprob-threshold
+    #[arg(long, default_value_t = 0.6, help_heading = "Temperature fallback")]
+    pub no_speech_threshold: f32,
+
+    /// Work out each segment's no-speech probability and compression ratio and flag likely hallucinations
+    #[arg(long, help_heading = "Hallucinations")]
+    pub flag_hallucinations: bool,
+
+    /// Leave flagged segments out of the transcript, implies --flag-hallucinations
+    #[arg(long, help_heading = "Hallucinations")]
+    pub drop_hallucinations: bool,
+
+    /// Flag a segment whose text compresses better than this, i.e. repeats itself
+    #[arg(long, default_value_t = 2.4, help_heading = "Hallucinations")]
+    pub compression_ratio_threshold: f32,
+
+    /// Longest segment in characters, 0 leaves segments as whisper produces them
+    #[arg(long, default_value_t = 0)]
+    pub max_len: i32,
+
+    /// Break long segments between words rather than between tokens
+    #[arg(long)]
+    pub split_on_word: bool,
+
+    /// Time every word, shown in json, vtt and ctm output
+    #[arg(long)]
+    pub word_timestamps: bool,
+
+    /// Latest in a window, in seconds, that its first segment can start; lower it when the first
+    /// cue comes in seconds after the speech does, 0 lifts the limit
+    #[arg(long, default_value_t = 1.0, value_name = "SECS", help_heading = "Timestamps")]
+    pub max_initial_ts: f32,
+
+    /// With word times, the probability a token's own timestamp needs to be used, a token below
+    /// it is timed from its neighbours
+    #[arg(long, default_value_t = 0.01, value_name = "PROBABILITY", help_heading = "Timestamps")]
+    pub timestamp_threshold: f32,
+
+    /// Probability all timestamps together need for a token to be timed by one
+    #[arg(long, default_value_t = 0.01, value_name = "PROBABILITY", help_heading = "Timestamps")]
+    pub timestamp_sum_threshold: f32,
+
+    /// Align word times with DTW using the heads of this model, implies --word-timestamps
+    #[arg(long, value_enum)]
+    pub dtw: Option<Dtw>,
+
+    /// Backend to run the model on, see --list-devices for what this build has
+    #[arg(long, value_enum, default_value = "auto")]
+    pub device: Device,
+
+    /// Which gpu to use when there are several
+    #[arg(long, default_value_t = 0, value_name = "INDEX")]
+    pub gpu_device: i32,
+
+    /// Mark speaker turns, needs a tinydiarize model such as small.en-tdrz
+    #[arg(long)]
+    pub tdrz: bool,
+
+    /// Keep bracketed non-speech like [MUSIC] or (applause) out of the text
+    #[arg(long)]
+    pub suppress_non_speech: bool,
+
+    /// Decode every window on its own instead of conditioning on the previous text; whisper.cpp
+    /// feeds the initial prompt through the same context, so there can't be one
+    #[arg(long, conflicts_with_all = ["initial_prompt", "prompt_file", "glossary"])]
+    pub no_context: bool,
+
+    /// Transcribe in overlapping windows of this many seconds, which keeps memory flat on
+    /// recordings hours long and has segments come out window by window
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u32).range(1..), help_heading = "Long audio")]
+    pub window: Option<u32>,
+
+    /// Seconds each window shares with the next, they're stitched together halfway through
+    #[arg(long, default_value_t = 5, value_name = "SECS", help_heading = "Long audio")]
+    pub window_overlap: u32,
+
+    /// Cut the audio at silences into this many chunks and decode them at once, each on its own
+    /// whisper state with a share of the threads; without a number, a chunk for every 4 threads.
+    /// Chunks are decoded without the text before them
+    #[arg(long, value_name = "CHUNKS", num_args = 0..=1, default_missing_value = "0", conflicts_with = "window", help_heading = "Long audio")]
+    pub parallel: Option<usize>,
+
+    /// Leave out stretches of silence before decoding, found by how loud the audio is; music
+    /// counts as speech
+    #[arg(long, help_heading = "Silence")]
+    pub vad: bool,
+
+    /// Audio quieter than this many dBFS is silence
+    #[arg(long, default_value_t = vad::THRESHOLD_DB, value_name = "DB", allow_negative_numbers = true, help_heading = "Silence")]
+    pub vad_threshold: f32,
+
+    /// Only silences at least this long are left out
+    #[arg(long, default_value_t = vad::MIN_SILENCE_SECS, value_name = "SECS", help_heading = "Silence")]
+    pub vad_min_silence: f32,
+
+    /// Speech kept either side of a silence that's left out
+    #[arg(long, default_value_t = vad::PAD_SECS, value_name = "SECS", help_heading = "Silence")]
+    pub vad_pad: f32,
+
+    /// Leave these stretches of the input out of the decode altogether, e.g. intro music or ads,
+    /// as 00:00-00:30,01:05:00-01:07:30; no cues come from them
+    #[arg(long, value_name = "RANGES", value_delimiter = ',', value_parser = parse_range, help_heading = "Silence")]
+    pub skip_ranges: Vec<Range<u64>>,
+
+    // the end of the previous window's text, set between windows
+    #[arg(skip)]
+    pub context: Option<String>,
+
+    // cancelling stops the decode, which returns whatever segments it finished
+    #[arg(skip)]
+    #[serde(skip)]
+    pub cancel: CancellationToken,
+
+    /// GBNF grammar the transcription has to follow
+    #[arg(long, value_name = "FILE", help_heading = "Grammar")]
+    pub grammar: Option<String>,
+
+    /// Rule of the grammar that matches the whole text
+    #[arg(long, default_value = "root", help_heading = "Grammar")]
+    pub grammar_rule: String,
+
+    /// Logit penalty for tokens the grammar doesn't allow
+    #[arg(long, default_value_t = 100.0, help_heading = "Grammar")]
+    pub grammar_penalty: f32,
+}
+
+// how far a windowed decode has got: the windows done and the transcript stitched from them
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Part {
+    pub windows: usize,
+    pub segments: Vec<Segment>,
+}
+
+// about as much of the previous window's text as fits in whisper's prompt
+const CONTEXT_WORDS: usize = 100;
+
+// --parallel doesn't cut chunks shorter than this, a state's setup would outweigh the decode
+const MIN_CHUNK_SECS: usize = 60;
+
+// whisper stops getting faster from more threads at about this many
+const THREADS_PER_CHUNK: usize = 4;
+
+// the encoder's input, the length of audio one no-speech figure covers
+const NO_SPEECH_WINDOW_MS: i64 = 30_000;
+
+// the clap defaults, as for output::Options
+impl Default for Options {
+    fn default() -> Options {
+        let matches = Options::augment_args(Command::new("options")).get_matches_from(["options"]);
+        Options::from_arg_matches(&matches).expect("option defaults are invalid")
+    }
+}
+
+impl Options {
+    pub fn params(&self) -> Result<FullParams<'_, '_>, Error> {
+        let strategy = match self.strategy {
+            Strategy::Greedy => SamplingStrategy::Greedy { best_of: self.best_of },
+            // a patience of -1 leaves whisper's default in place
+            Strategy::Beam => SamplingStrategy::BeamSearch { beam_size: self.beam_size, patience: -1.0 },
+        };
+        let mut params = FullParams::new(strategy);
+        // leaving the language unset keeps whisper's own default of en
+        if let Some(language) = &self.language {
+            params.set_language(Some(language));
+        }
+        params.set_translate(self.translate);
+        params.set_n_threads(self.threads() as i32);
+        if let Some(prompt) = self.prompt()? {
+            params.set_initial_prompt(&prompt);
+        }
+        params.set_temperature(self.temperature);
+        params.set_temperature_inc(self.temperature_increment);
+        params.set_entropy_thold(self.entropy_threshold);
+        params.set_logprob_thold(self.logprob_threshold);
+        params.set_no_speech_thold(self.no_speech_threshold);
+        // whisper cuts segments to length using token timestamps
+        if self.max_len > 0 || self.word_timestamps || self.dtw.is_some() {
+            params.set_token_timestamps(true);
+        }
+        params.set_max_len(self.max_len);
+        params.set_max_initial_ts(self.max_initial_ts);
+        params.set_thold_pt(self.timestamp_threshold);
+        params.set_thold_ptsum(self.timestamp_sum_threshold);
+        params.set_split_on_word(self.split_on_word);
+        params.set_tdrz_enable(self.tdrz);
+        params.set_suppress_non_speech_tokens(self.suppress_non_speech);
+        // no_context only drops text left over from an earlier run, within a run the
+        // previous windows are fed back through the text context
+        if self.no_context {
+            params.set_n_max_text_ctx(0);
+        }
+        Ok(params)
+    }
+
+    // with --vad, where the speech is; whatever streams segments out of a decode maps their times
+    // back through it, the transcript comes back with them mapped already
+    pub fn speech(&self, samples: &[f32]) -> Option<Speech> {
+        let speech = self.vad.then(|| Speech::detect(samples, self.vad_threshold, self.vad_min_silence * 1000.0, self.vad_pad * 1000.0));
+        if self.skip_ranges.is_empty() {
+            return speech;
+        }
+        let rate = crate::audio::SAMPLE_RATE as u64;
+        let skipped: Vec<Range<usize>> = self.skip_ranges.iter().map(|range| (range.start * rate / 1000) as usize..(range.end * rate / 1000) as usize).collect();
+        Some(speech.unwrap_or_else(|| Speech::whole(samples.len())).without(&skipped))
+    }
+
+    // --skip-ranges as times in audio that starts start_ms into what they were given for
+    pub fn skip_ranges_from(&self, start_ms: u64) -> Vec<Range<u64>> {
+        self.skip_ranges.iter().map(|range| range.start.saturating_sub(start_ms)..range.end.saturating_sub(start_ms)).filter(|range| !range.is_empty()).collect()
+    }
+
+    pub fn transcribe<F>(&self, ctx: &WhisperContext, samples: &[f32], configure: F) -> Result<Transcript, Error>
+    where
+        F: Fn(&mut FullParams),
+    {
+        let Some(speech) = self.speech(samples) else {
+            return self.decode(ctx, samples, configure);
+        };
+        let total_ms = (samples.len() * 1000 / crate::audio::SAMPLE_RATE as usize) as i64;
+        let left_out = if self.skip_ranges.is_empty() { "silence" } else { "silence and skipped ranges" };
+        tracing::info!("decoding {}s of speech, leaving out {}s of {}", speech.kept_ms() / 1000, (total_ms - speech.kept_ms()) / 1000, left_out);
+        if speech.is_empty() {
+            return Ok(Transcript { segments: Vec::new() });
+        }
+        let mut transcript = self.decode(ctx, &speech.gather(samples), configure)?;
+        speech.restore(&mut transcript);
+        Ok(transcript)
+    }
+
+    // whisper already falls back window by window, this retries the whole decode
+    // when the result as a whole is still below the logprob threshold
+    fn decode<F>(&self, ctx: &WhisperContext, samples: &[f32], configure: F) -> Result<Transcript, Error>
+    where
+        F: Fn(&mut FullParams),
+    {
+        let constraint = match &self.grammar {
+            Some(path) => Some(Constraint::new(Grammar::load(path, &self.grammar_rule)?, ctx, self.grammar_penalty)),
+            None => None,
+        };
+        let mut state = ctx.create_state().map_err(|error| Error::inference("failed to create state", error))?;
+        let temperatures = self.temperatures();
+        let mut attempt = 0;
+        let mut previous: Option<Transcript> = None;
+        loop {
+            let mut params = self.params()?;
+            params.set_temperature(temperatures[attempt]);
+            if let Some(constraint) = &constraint {
+                constraint.apply(&mut params);
+            }
+            configure(&mut params);
+            self.cancel.apply(&mut params);
+            let decoded = state.full(params, samples);
+            // an aborted decode still has the segments it finished, though a retry cut short has
+            // less than the whole attempt before it
+            if self.cancel.is_cancelled() {
+                return match previous {
+                    Some(previous) => Ok(previous),
+                    None => Transcript::from_state(ctx, &mut state),
+                };
+            }
+            decoded.map_err(|error| Error::inference("failed to run model", error))?;
+
+            let mut transcript = Transcript::from_state(ctx, &mut state)?;
+            let poor = transcript.mean_logprob().is_some_and(|logprob| logprob < self.logprob_threshold);
+            if !poor || attempt + 1 == temperatures.len() {
+                if self.flag_hallucinations || self.drop_hallucinations {
+                    self.flag(ctx, &mut state, samples, &mut transcript)?;
+                }
+                if let Some(path) = self.glossary.as_deref().filter(|_| self.glossary_correct) {
+                    Glossary::load(path)?.correct(&mut transcript);
+                }
+                return Ok(transcript);
+            }
+            attempt += 1;
+            tracing::info!("low confidence decode, retrying at temperature {:.1}", temperatures[attempt]);
+            previous = Some(transcript);
+        }
+    }
+
+    // a window at a time, each prompted with the text before it, so whisper only ever holds one
+    // window; carries on after the windows already in part. started gets each window's range in
+    // ms before it's decoded, finished the part so far after every whole window along with the
+    // segments the window made final, those before the seam with the next one
+    #[allow(clippy::too_many_arguments)]
+    pub fn transcribe_windows<F>(
+        &self,
+        ctx: &WhisperContext,
+        window: u32,
+        samples: &[f32],
+        part: Part,
+        configure: F,
+        started: impl Fn(Range<i64>),
+        mut finished: impl FnMut(&Part, &[Segment]) -> Result<(), Error>,
+    ) -> Result<Transcript, Error>
+    where
+        F: Fn(&mut FullParams),
+    {
+        let rate = crate::audio::SAMPLE_RATE as usize;
+        let overlap = self.window_overlap as usize * rate;
+        let ranges = windows(samples.len(), window as usize * rate, overlap);
+        let ms = |sample: usize| (sample * 1000 / rate) as i64;
+        let seam = |range: &Range<usize>| ms(range.start + overlap / 2);
+
+        let mut options = self.clone();
+        let done = part.windows;
+        let mut transcript = Transcript { segments: part.segments };
+        // a resumed decode already reported everything before the seam it stopped at
+        let mut reported = match done {
+            0 => 0,
+            _ => transcript.segments.iter().take_while(|segment| segment.middle_ms() < ranges.get(done).map_or(i64::MAX, seam)).count(),
+        };
+        for (i, range) in ranges.iter().enumerate().skip(done) {
+            // prompted with what was said before the window starts, not in the overlap
+            if i > 0 && !options.no_context {
+                let before: Vec<&str> = transcript.segments.iter().filter(|segment| segment.end_ms <= ms(range.start)).flat_map(|segment| segment.text.split_whitespace()).collect();
+                let words = &before[before.len().saturating_sub(CONTEXT_WORDS)..];
+                options.context = (!words.is_empty()).then(|| words.join(" "));
+            }
+            started(ms(range.start)..ms(range.end));
+            options.skip_ranges = self.skip_ranges_from(ms(range.start) as u64);
+            let mut part = options.transcribe(ctx, &samples[range.clone()], &configure)?;
+            part.shift(ms(range.start));
+            transcript.stitch(part, if i == 0 { i64::MIN } else { seam(range) });
+            // a window cut short goes in the transcript but isn't reported as finished
+            if options.cancel.is_cancelled() {
+                break;
+            }
+
+            let next_seam = ranges.get(i + 1).map_or(i64::MAX, seam);
+            let first = reported;
+            reported += transcript.segments[reported..].iter().take_while(|segment| segment.middle_ms() < next_seam).count();
+            let part = Part { windows: i + 1, segments: transcript.segments.clone() };
+            finished(&part, &transcript.segments[first..reported])?;
+        }
+        Ok(transcript)
+    }
+
+    // --parallel's chunks, a single one when the audio isn't long enough to be worth cutting
+    pub fn chunks(&self, samples: &[f32]) -> Vec<Range<usize>> {
+        let wanted = match self.parallel {
+            None => 1,
+            Some(0) => self.threads() / THREADS_PER_CHUNK,
+            Some(chunks) => chunks,
+        };
+        let count = wanted.min(samples.len() / (MIN_CHUNK_SECS * crate::audio::SAMPLE_RATE as usize)).max(1);
+        if count == 1 {
+            return std::iter::once(0..samples.len()).collect();
+        }
+        Speech::detect(samples, self.vad_threshold, vad::SPLIT_SILENCE_MS, 0.0).split(samples.len(), count)
+    }
+
+    // every chunk decoded on a thread and a state of its own, and the transcripts put back in
+    // order; finished gets the ms of audio done so far as each chunk is
+    pub fn transcribe_parallel(&self, ctx: &WhisperContext, samples: &[f32], chunks: &[Range<usize>], mut finished: impl FnMut(i64)) -> Result<Transcript, Error> {
+        let rate = crate::audio::SAMPLE_RATE as usize;
+        let ms = |sample: usize| (sample * 1000 / rate) as i64;
+        let mut options = self.clone();
+        options.threads = Some((self.threads() / chunks.len()).max(1));
+        tracing::info!("decoding {} chunks at once, {} threads each", chunks.len(), options.threads());
+        let mut transcripts: Vec<Option<Result<Transcript, Error>>> = chunks.iter().map(|_| None).collect();
+        std::thread::scope(|scope| {
+            let (sender, results) = std::sync::mpsc::channel();
+            for (i, range) in chunks.iter().enumerate() {
+                let sender = sender.clone();
+                let options = Options { skip_ranges: options.skip_ranges_from(ms(range.start) as u64), ..options.clone() };
+                scope.spawn(move || {
+                    let transcript = options.transcribe(ctx, &samples[range.clone()], |_| {}).map(|mut transcript| {
+                        transcript.shift(ms(range.start));
+                        transcript
+                    });
+                    let _ = sender.send((i, transcript));
+                });
+            }
+            drop(sender);
+            let mut done_ms = 0;
+            for (i, transcript) in results {
+                done_ms += ms(chunks[i].len());
+                finished(done_ms);
+                transcripts[i] = Some(transcript);
+            }
+        });
+        let mut transcript = Transcript { segments: Vec::new() };
+        for part in transcripts {
+            transcript.segments.extend(part.expect("every chunk sends its transcript")?.segments);
+        }
+        Ok(transcript)
+    }
+
+    // openai whisper's rules: silence when no-speech is likely and the text unlikely,
+    // a loop when the text compresses too well
+    fn flag(&self, ctx: &WhisperContext, state: &mut WhisperState, samples: &[f32], transcript: &mut Transcript) -> Result<(), Error> {
+        let threads = self.threads();
+        state.pcm_to_mel(samples, threads).map_err(|error| Error::inference("failed to compute spectrogram", error))?;
+        // an encode per 30s window as whisper decodes them, not per segment; the segments come in
+        // order, so the last window's figure is the only one worth keeping
+        let mut scored: Option<(i64, f32)> = None;
+        for segment in &mut transcript.segments {
+            let window_ms = no_speech_window(segment.start_ms);
+            let no_speech = match scored {
+                Some((scored_ms, no_speech)) if scored_ms == window_ms => no_speech,
+                _ => no_speech_probability(ctx, state, window_ms, threads)?,
+            };
+            scored = Some((window_ms, no_speech));
+            let compression_ratio = segment.compression_ratio();
+            let silent = no_speech > self.no_speech_threshold && segment.mean_logprob().is_some_and(|logprob| logprob < self.logprob_threshold);
+            segment.hallucination = silent || compression_ratio > self.compression_ratio_threshold;
+            segment.no_speech_probability = Some(no_speech);
+            segment.compression_ratio = Some(compression_ratio);
+        }
+        if self.drop_hallucinations {
+            transcript.segments.retain(|segment| !segment.hallucination);
+        }
+        Ok(())
+    }
+
+    pub fn context_params(&self) -> Result<WhisperContextParameters<'static>, Error> {
+        let mut context_params = WhisperContextParameters::default();
+        self.device.apply(&mut context_params, self.gpu_device)?;
+        if let Some(dtw) = self.dtw {
+            context_params.dtw_parameters(DtwParameters { mode: DtwMode::ModelPreset { model_preset: dtw.preset() }, ..DtwParameters::default() });
+        }
+        Ok(context_params)
+    }
+
+    pub fn prompt(&self) -> Result<Option<String>, Error> {
+        let prompt = match &self.prompt_file {
+            Some(path) => Some(std::fs::read_to_string(path).map_err(|error| Error::input(format!("failed to read prompt file {}", path), error))?.trim().to_string()),
+            None => self.initial_prompt.clone(),
+        };
+        let glossary = match self.glossary.as_deref() {
+            Some(path) => Some(Glossary::load(path)?.prompt()),
+            None => None,
+        };
+        // the previous window's text goes last, nearest the audio it runs on into
+        let parts: Vec<String> = [prompt, glossary, self.context.clone()].into_iter().flatten().collect();
+        Ok((!parts.is_empty()).then(|| parts.join(" ")))
+    }
+
+    // what the transcript ends up in, for naming outputs
+    pub fn output_language(&self) -> &str {
+        if self.translate {
+            "en"
+        } else {
+            self.language.as_deref().unwrap_or("en")
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads.unwrap_or_else(default_threads)
+    }
+
+    // what a poor decode is retried at, in whole steps of the increment up to 1.0, since adding
+    // them up in f32 overshoots it
+    fn temperatures(&self) -> Vec<f32> {
+        let steps = match self.temperature_increment {
+            increment if increment > 0.0 => ((1.0 - self.temperature) / increment + 1e-4).max(0.0) as usize,
+            _ => 0,
+        };
+        (0..=steps).map(|step| self.temperature + step as f32 * self.temperature_increment).collect()
+    }
+}
+
+// sample ranges window long, each starting overlap before the last one ends and the last running
+// to the end of the audio
+pub fn windows(length: usize, window: usize, overlap: usize) -> Vec<Range<usize>> {
+    assert!(overlap < window, "windows have to be longer than their overlap");
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + window).min(length);
+        ranges.push(start..end);
+        if end == length {
+            return ranges;
+        }
+        start = end - overlap;
+    }
+}
+
+pub fn default_threads() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+// a second of silence through the model once, so what a backend sets up on first use (gpu
+// kernels, compute buffers) is done before the first file rather than during it
+pub fn warm_up(ctx: &WhisperContext, threads: usize) -> Result<(), Error> {
+    let failed = |error: WhisperError| Error::inference("failed to warm up the model", error);
+    let mut state = ctx.create_state().map_err(failed)?;
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_n_threads(threads as i32);
+    params.set_single_segment(true);
+    state.full(params, &vec![0.0; crate::audio::SAMPLE_RATE as usize]).map_err(failed)?;
+    Ok(())
+}
+
+// the start of the 30s window a segment starting at start_ms is scored in
+fn no_speech_window(start_ms: i64) -> i64 {
+    start_ms.max(0) / NO_SPEECH_WINDOW_MS * NO_SPEECH_WINDOW_MS
+}
+
+// the no-speech token's probability straight after start-of-transcript for the 30s window from
+// start_ms, the figure whisper.cpp uses internally but doesn't report; expects pcm_to_mel first
+fn no_speech_probability(ctx: &WhisperContext, state: &mut WhisperState, start_ms: i64, threads: usize) -> Result<f32, Error> {
+    let failed = |error: WhisperError| Error::inference("failed to score no-speech", error);
+    // mel frames are 10ms apart
+    let frame = (start_ms.max(0) / 10) as usize;
+    state.encode(frame, threads).map_err(failed)?;
+    state.decode(&[ctx.token_sot()], 0, threads).map_err(failed)?;
+    let logits = state.get_logits().map_err(failed)?;
+    let max = logits.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    let total: f32 = logits.iter().map(|logit| (logit - max).exp()).sum();
+    Ok((logits[ctx.token_nosp() as usize] - max).exp() / total)
+}
+
+pub fn detect_language(ctx: &WhisperContext, samples: &[f32], threads: usize) -> Result<(&'static str, f32), Error> {
+    if !ctx.is_multilingual() {
+        return Err(Error::Model("language detection needs a multilingual model".to_string()));
+    }
+    let failed = |error: WhisperError| Error::inference("failed to detect language", error);
+    let mut state = ctx.create_state().map_err(failed)?;
+    state.pcm_to_mel(samples, threads).map_err(failed)?;
+    let (id, probabilities) = state.lang_detect(0, threads).map_err(failed)?;
+    let language = whisper_rs::get_lang_str(id).ok_or_else(|| Error::Inference(format!("detected an unknown language id {}", id)))?;
+    Ok((language, probabilities[id as usize]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _temperatures() {
+        let temperatures = |options: Options| options.temperatures().iter().map(|temperature| format!("{:.1}", temperature)).collect::<Vec<_>>();
+        assert_eq!(temperatures(Options::default()), ["0.0", "0.2", "0.4", "0.6", "0.8", "1.0"]);
+        assert_eq!(temperatures(Options { temperature: 0.5, temperature_increment: 0.0, ..Options::default() }), ["0.5"]);
+    }
+
+    #[test]
+    fn _no_speech_window() {
+        assert_eq!(no_speech_window(-200), 0);
+        assert_eq!(no_speech_window(29_999), 0);
+        assert_eq!(no_speech_window(61_500), 60_000);
+    }
+
+    #[test]
+    fn _windows() {
+        assert_eq!(windows(100, 30, 5), vec![0..30, 25..55, 50..80, 75..100]);
+        assert_eq!(windows(30, 30, 5), vec![0..30]);
+        assert_eq!(windows(10, 30, 5), vec![0..10]);
+    }
+
+    #[test]
+    fn _candidates() {
+        let parse = |args: &[&str]| Options::augment_args(Command::new("options")).try_get_matches_from([&["options"], args].concat());
+        assert!(parse(&["--strategy", "beam", "--beam-size", "0"]).is_err());
+        assert!(parse(&["--best-of", "0"]).is_err());
+        assert!(parse(&["--threads", "0"]).is_err());
+        assert_eq!(Options::from_arg_matches(&parse(&["--beam-size", "2"]).unwrap()).unwrap().beam_size, 2);
+    }
+
+    #[test]
+    fn _no_context() {
+        let parse = |args: &[&str]| Options::augment_args(Command::new("options")).try_get_matches_from([&["options", "--no-context"], args].concat());
+        assert!(parse(&[]).is_ok());
+        for prompt in [["--initial-prompt", "Kubernetes"], ["--prompt-file", "prompt.txt"], ["--glossary", "terms.txt"]] {
+            assert!(parse(&prompt).is_err(), "{:?}", prompt);
+        }
+    }
+
+    #[test]
+    fn _parse_range() {
+        assert_eq!(parse_range("00:00-00:30"), Ok(0..30_000));
+        assert_eq!(parse_range("01:05:00-01:07:30"), Ok(3_900_000..4_050_000));
+        assert!(parse_range("5m").is_err());
+        assert!(parse_range("90-30").is_err());
+
+        let options = Options { skip_ranges: vec![0..30_000, 60_000..90_000], ..Options::default() };
+        assert_eq!(options.skip_ranges_from(45_000), vec![15_000..45_000]);
+    }
+}
@@ -0,0 +1,71 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use whisper_rs::WhisperContextParameters;
+
+use crate::error::Error;
+
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Device {
+    /// The gpu backend the binary was built with, otherwise the cpu
+    Auto,
+    Cpu,
+    Cuda,
+    Metal,
+    /// Core ML runs the encoder on the Neural Engine, from a <model>-encoder.mlmodelc beside the model
+    Coreml,
+    Openvino,
+}
+
+impl Device {
+    // backends are compiled into whisper.cpp, so all a run can do is pick among those built in
+    fn built_in(&self) -> Result<(), &'static str> {
+        match self {
+            Device::Auto | Device::Cpu => Ok(()),
+            Device::Cuda if cfg!(feature = "cuda") => Ok(()),
+            Device::Cuda => Err("isn't built in, rebuild with --features cuda"),
+            Device::Metal if cfg!(feature = "metal") => Ok(()),
+            Device::Metal => Err("isn't built in, rebuild with --features metal"),
+            Device::Coreml if cfg!(feature = "coreml") => Ok(()),
+            Device::Coreml => Err("isn't built in, rebuild with --features coreml"),
+            Device::Openvino => Err("isn't supported by the whisper.cpp bindings yet"),
+        }
+    }
+
+    fn name(&self) -> String {
+        self.to_possible_value().expect("devices have names").get_name().to_string()
+    }
+
+    pub fn apply(&self, context_params: &mut WhisperContextParameters, gpu: i32) -> Result<(), Error> {
+        self.built_in().map_err(|reason| Error::Model(format!("device {} {}", self.name(), reason)))?;
+        // core ml only takes over the encoder, the decoder still runs wherever auto would put it
+        context_params.use_gpu = match self {
+            Device::Cpu => false,
+            Device::Cuda | Device::Metal => true,
+            _ => cfg!(any(feature = "cuda", feature = "metal")),
+        };
+        context_params.gpu_device = gpu;
+        Ok(())
+    }
+}
+
+// what --list-devices prints, one backend a line
+pub fn report() -> String {
+    let mut lines: Vec<String> = Device::value_variants()
+        .iter()
+        .filter(|device| **device != Device::Auto)
+        .map(|device| match device.built_in() {
+            Ok(()) => format!("{:<10}available", device.name()),
+            Err(reason) => format!("{:<10}{}", device.name(), reason),
+        })
+        .collect();
+    let auto = if cfg!(feature = "cuda") {
+        "cuda"
+    } else if cfg!(feature = "metal") {
+        "metal"
+    } else {
+        "cpu"
+    };
+    lines.push(format!("{:<10}{}", "auto", auto));
+    lines.push(format!("\nwhisper.cpp: {}", whisper_rs::print_system_info().trim()));
+    lines.join("\n")
+}
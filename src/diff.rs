@@ -0,0 +1,123 @@
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use transcript::compare::{self, Edit};
+use transcript::error::Error;
+use transcript::output::{parse, vtt};
+use transcript::transcript::Word;
+
+use crate::live;
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Transcript to compare from: srt, vtt, sbv or the json transcript
+    a: String,
+
+    /// Transcript to compare it to, in any of the same formats
+    b: String,
+
+    /// Compare words as they're written, case and punctuation too
+    #[arg(long)]
+    strict: bool,
+
+    /// Print the changes as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+// a run of words that changed between the two, and when, over the words on both sides
+#[derive(Serialize, Debug, PartialEq)]
+struct Change {
+    start_ms: i64,
+    end_ms: i64,
+    removed: String,
+    added: String,
+}
+
+// the words of a left out or changed in b, each run of them a change; words that are the same
+// in both split the runs
+fn changes(a: &[Word], b: &[Word], strict: bool) -> Vec<Change> {
+    let compared = |words: &[Word]| words.iter().map(|word| compare::words(&word.text, strict).join(" ")).collect::<Vec<String>>();
+    let mut changes = Vec::new();
+    let (mut removed, mut added): (Vec<&Word>, Vec<&Word>) = (Vec::new(), Vec::new());
+    let mut flush = |removed: &mut Vec<&Word>, added: &mut Vec<&Word>| {
+        let words = || removed.iter().chain(added.iter());
+        if let (Some(start_ms), Some(end_ms)) = (words().map(|word| word.start_ms).min(), words().map(|word| word.end_ms).max()) {
+            let text = |words: &[&Word]| words.iter().map(|word| word.text.as_str()).collect::<Vec<_>>().join(" ");
+            changes.push(Change { start_ms, end_ms, removed: text(removed), added: text(added) });
+        }
+        removed.clear();
+        added.clear();
+    };
+    for edit in compare::edits(&compared(a), &compared(b)) {
+        match edit {
+            Edit::Same(..) => flush(&mut removed, &mut added),
+            Edit::Changed(i, j) => {
+                removed.push(&a[i]);
+                added.push(&b[j]);
+            }
+            Edit::Removed(i) => removed.push(&a[i]),
+            Edit::Added(j) => added.push(&b[j]),
+        }
+    }
+    flush(&mut removed, &mut added);
+    changes
+}
+
+// [-removed-]{+added+}, in red and green on a terminal
+fn render(change: &Change, color: bool) -> String {
+    let paint = |text: &str, marks: (&str, &str), code: &str| match color {
+        true => format!("\x1b[{}m{}{}{}\x1b[0m", code, marks.0, text, marks.1),
+        false => format!("{}{}{}", marks.0, text, marks.1),
+    };
+    let mut line = format!("{} ", vtt::timestamp(change.start_ms));
+    if !change.removed.is_empty() {
+        line.push_str(&paint(&change.removed, ("[-", "-]"), "31"));
+    }
+    if !change.added.is_empty() {
+        line.push_str(&paint(&change.added, ("{+", "+}"), "32"));
+    }
+    line
+}
+
+// fails when the two differ, as diff does
+pub fn run(args: &Args) -> Result<ExitCode, Error> {
+    let (a, b) = (parse::read(&args.a)?, parse::read(&args.b)?);
+    let changes = changes(&a.timed_words(), &b.timed_words(), args.strict);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&changes).expect("failed to serialize changes"));
+    } else {
+        let color = live::colors();
+        for change in &changes {
+            println!("{}", render(change, color));
+        }
+    }
+    Ok(if changes.is_empty() { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<Word> {
+        text.split_whitespace().enumerate().map(|(i, text)| Word { start_ms: i as i64 * 1000, end_ms: i as i64 * 1000 + 800, text: text.to_string(), probability: 0.0 }).collect()
+    }
+
+    #[test]
+    fn _changes() {
+        let changes = changes(&words("Hello there, my old friend."), &words("hello their my friend. Bye"), false);
+        assert_eq!(
+            changes,
+            vec![
+                Change { start_ms: 1000, end_ms: 1800, removed: "there,".to_string(), added: "their".to_string() },
+                Change { start_ms: 3000, end_ms: 3800, removed: "old".to_string(), added: String::new() },
+                Change { start_ms: 4000, end_ms: 4800, removed: String::new(), added: "Bye".to_string() },
+            ]
+        );
+        assert_eq!(render(&changes[0], false), "00:00:01.000 [-there,-]{+their+}");
+        assert_eq!(render(&changes[2], true), "00:00:04.000 \x1b[32m{+Bye+}\x1b[0m");
+        assert!(super::changes(&words("Same words."), &words("same words"), false).is_empty());
+        assert_eq!(super::changes(&words("Same words."), &words("same words"), true).len(), 1);
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use id3::frame::{Lyrics, SynchronisedLyrics, SynchronisedLyricsType, TimestampFormat};
+use id3::{Tag, TagLike, Version};
+
+use transcript::error::Error;
+use transcript::Transcript;
+
+// --embed: the transcript written into the audio file's own tags, for players that show lyrics,
+// so there's no sidecar file to keep beside it
+pub fn embed(path: &str, transcript: &Transcript, language: &str) -> Result<(), Error> {
+    let extension = Path::new(path).extension().and_then(|extension| extension.to_str()).unwrap_or("").to_ascii_lowercase();
+    match extension.as_str() {
+        "mp3" => mp3(path, transcript, language),
+        "m4a" | "m4b" | "mp4" => mp4(path, transcript),
+        _ => {
+            tracing::warn!("{}: --embed only knows mp3 and m4a tags, left it untouched", path);
+            Ok(())
+        }
+    }
+}
+
+// one line a segment
+fn text(transcript: &Transcript) -> String {
+    transcript.segments.iter().map(|segment| segment.text.trim()).filter(|text| !text.is_empty()).collect::<Vec<_>>().join("\n")
+}
+
+// id3 and the video containers want iso 639-2, whisper gives 639-1
+pub fn language_code(language: &str) -> String {
+    isolang::Language::from_639_1(language).map_or_else(|| "und".to_string(), |language| language.to_639_3().to_string())
+}
+
+// USLT for the whole text and SYLT for it line by line, replacing those of an earlier run
+fn mp3(path: &str, transcript: &Transcript, language: &str) -> Result<(), Error> {
+    let mut tag = match Tag::read_from_path(path) {
+        Ok(tag) => tag,
+        Err(id3::Error { kind: id3::ErrorKind::NoTag, .. }) => Tag::new(),
+        Err(error) => return Err(Error::output(format!("failed to read the id3 tag of {}", path), error)),
+    };
+    let lang = language_code(language);
+    tag.remove("USLT");
+    tag.remove("SYLT");
+    tag.add_frame(Lyrics { lang: lang.clone(), description: String::new(), text: text(transcript) });
+    tag.add_frame(SynchronisedLyrics {
+        lang,
+        timestamp_format: TimestampFormat::Ms,
+        content_type: SynchronisedLyricsType::Lyrics,
+        description: String::new(),
+        content: transcript.segments.iter().map(|segment| (segment.start_ms.max(0) as u32, segment.text.trim().to_string())).collect(),
+    });
+    tag.write_to_path(path, Version::Id3v24).map_err(|error| Error::output(format!("failed to write the id3 tag of {}", path), error))
+}
+
+// mp4 has no synced lyrics atom, so it's the plain text in ©lyr
+fn mp4(path: &str, transcript: &Transcript) -> Result<(), Error> {
+    let mut tag = mp4ameta::Tag::read_from_path(path).map_err(|error| Error::output(format!("failed to read the tags of {}", path), error))?;
+    tag.set_lyrics(text(transcript));
+    tag.write_to_path(path).map_err(|error| Error::output(format!("failed to write the tags of {}", path), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transcript::Segment;
+
+    #[test]
+    fn _embed() {
+        assert_eq!(language_code("de"), "deu");
+        assert_eq!(language_code("auto"), "und");
+
+        let dir = std::env::temp_dir().join(format!("transcript-embed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.mp3");
+        std::fs::write(&path, [0xff, 0xfb, 0x90, 0x00]).unwrap();
+        let path = path.to_str().unwrap();
+        let transcript = Transcript { segments: vec![Segment::new(0, 1500, " Hello."), Segment::new(1500, 3000, " Bye.")] };
+        embed(path, &transcript, "en").unwrap();
+        embed(path, &transcript, "en").unwrap();
+        let tag = Tag::read_from_path(path).unwrap();
+        let lyrics: Vec<&Lyrics> = tag.lyrics().collect();
+        assert_eq!(lyrics.len(), 1);
+        assert_eq!((lyrics[0].lang.as_str(), lyrics[0].text.as_str()), ("eng", "Hello.\nBye."));
+        let synced: Vec<&SynchronisedLyrics> = tag.synchronised_lyrics().collect();
+        assert_eq!(synced[0].content, vec![(0, "Hello.".to_string()), (1500, "Bye.".to_string())]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
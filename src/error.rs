@@ -0,0 +1,56 @@
+use std::fmt::Display;
+use std::process::ExitCode;
+
+use serde::{Deserialize, Serialize};
+
+// the exit code shells give a process killed by SIGINT
+pub const INTERRUPTED: u8 = 130;
+
+// each kind has its own exit code so scripts can tell failures apart; 1 is left for a batch
+// where some files failed; serialized so the daemon's failures come out as the client's own
+#[derive(Debug, thiserror::Error, Serialize, Deserialize)]
+pub enum Error {
+    /// Missing or unreadable audio, or a bad file describing the job (manifest, glossary, ...)
+    #[error("{0}")]
+    Input(String),
+    /// A model that's missing or won't load
+    #[error("{0}")]
+    Model(String),
+    /// whisper failing partway through a decode
+    #[error("{0}")]
+    Inference(String),
+    /// An output that can't be written
+    #[error("{0}")]
+    Output(String),
+    /// Ctrl-C, after whatever was decoded has been written
+    #[error("{0}")]
+    Interrupted(String),
+}
+
+impl Error {
+    pub fn input(context: impl Display, error: impl Display) -> Error {
+        Error::Input(format!("{}: {}", context, error))
+    }
+
+    pub fn model(context: impl Display, error: impl Display) -> Error {
+        Error::Model(format!("{}: {}", context, error))
+    }
+
+    pub fn inference(context: impl Display, error: impl Display) -> Error {
+        Error::Inference(format!("{}: {}", context, error))
+    }
+
+    pub fn output(context: impl Display, error: impl Display) -> Error {
+        Error::Output(format!("{}: {}", context, error))
+    }
+
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(match self {
+            Error::Input(_) => 2,
+            Error::Model(_) => 3,
+            Error::Inference(_) => 4,
+            Error::Output(_) => 5,
+            Error::Interrupted(_) => INTERRUPTED,
+        })
+    }
+}
@@ -0,0 +1,187 @@
+use std::fs;
+
+use serde::Serialize;
+
+use transcript::compare::{self, Edit};
+use transcript::error::Error;
+use transcript::glossary::levenshtein;
+use transcript::output::parse;
+use transcript::{audio, decode, models, Transcriber};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Transcript to score: plain text, srt, vtt, sbv or the json transcript
+    #[arg(long, value_name = "FILE", required_unless_present = "input")]
+    hypothesis: Option<String>,
+
+    /// The correct transcript, in any of the same formats
+    #[arg(long, value_name = "FILE")]
+    reference: String,
+
+    /// Transcribe this audio and score that instead of --hypothesis
+    #[arg(short, long, conflicts_with = "hypothesis")]
+    input: Option<String>,
+
+    /// Model file or name for --input, as for transcribe
+    #[arg(short, long, requires = "input")]
+    model: Option<String>,
+
+    /// Compare words as they're written, case and punctuation too
+    #[arg(long)]
+    strict: bool,
+
+    /// Show the words lined up, [-left out-] and {+added+}
+    #[arg(long)]
+    diff: bool,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+
+    #[command(flatten)]
+    decode_options: decode::Options,
+
+    #[command(flatten)]
+    audio_options: audio::Options,
+}
+
+// a word of the reference and what the hypothesis has in its place, either missing when the
+// other has nothing there
+#[derive(Serialize, Debug, PartialEq)]
+struct Aligned {
+    reference: Option<String>,
+    hypothesis: Option<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Report {
+    reference_words: usize,
+    hypothesis_words: usize,
+    substitutions: usize,
+    deletions: usize,
+    insertions: usize,
+    word_error_rate: f64,
+    reference_characters: usize,
+    character_edits: usize,
+    character_error_rate: f64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    alignment: Vec<Aligned>,
+}
+
+// errors over the reference's length; with no reference, anything heard is all wrong
+fn rate(errors: usize, length: usize) -> f64 {
+    match length {
+        0 => f64::from(u8::from(errors > 0)),
+        _ => errors as f64 / length as f64,
+    }
+}
+
+impl Report {
+    // characters are counted in the words as compared, a space between each
+    fn new(reference: &[String], hypothesis: &[String], diff: bool) -> Report {
+        let edits = compare::edits(reference, hypothesis);
+        let count = |wanted: fn(&Edit) -> bool| edits.iter().filter(|edit| wanted(edit)).count();
+        let (substitutions, deletions, insertions) = (count(|edit| matches!(edit, Edit::Changed(..))), count(|edit| matches!(edit, Edit::Removed(_))), count(|edit| matches!(edit, Edit::Added(_))));
+        let characters = |words: &[String]| words.join(" ").chars().collect::<Vec<char>>();
+        let (reference_characters, hypothesis_characters) = (characters(reference), characters(hypothesis));
+        let character_edits = levenshtein(&reference_characters, &hypothesis_characters);
+        let alignment = match diff {
+            true => edits
+                .iter()
+                .map(|edit| match *edit {
+                    Edit::Same(i, j) | Edit::Changed(i, j) => Aligned { reference: Some(reference[i].clone()), hypothesis: Some(hypothesis[j].clone()) },
+                    Edit::Removed(i) => Aligned { reference: Some(reference[i].clone()), hypothesis: None },
+                    Edit::Added(j) => Aligned { reference: None, hypothesis: Some(hypothesis[j].clone()) },
+                })
+                .collect(),
+            false => Vec::new(),
+        };
+        Report {
+            reference_words: reference.len(),
+            hypothesis_words: hypothesis.len(),
+            substitutions,
+            deletions,
+            insertions,
+            word_error_rate: rate(substitutions + deletions + insertions, reference.len()),
+            reference_characters: reference_characters.len(),
+            character_edits,
+            character_error_rate: rate(character_edits, reference_characters.len()),
+            alignment,
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut lines = vec![
+            format!("wer  {:.2}% ({} substituted, {} deleted, {} inserted of {} words)", self.word_error_rate * 100.0, self.substitutions, self.deletions, self.insertions, self.reference_words),
+            format!("cer  {:.2}% ({} edits of {} characters)", self.character_error_rate * 100.0, self.character_edits, self.reference_characters),
+        ];
+        if !self.alignment.is_empty() {
+            let words: Vec<String> = self
+                .alignment
+                .iter()
+                .map(|aligned| match (&aligned.reference, &aligned.hypothesis) {
+                    (Some(reference), Some(hypothesis)) if reference == hypothesis => reference.clone(),
+                    (Some(reference), Some(hypothesis)) => format!("[-{}-]{{+{}+}}", reference, hypothesis),
+                    (Some(reference), None) => format!("[-{}-]", reference),
+                    (None, Some(hypothesis)) => format!("{{+{}+}}", hypothesis),
+                    (None, None) => String::new(),
+                })
+                .collect();
+            lines.push(String::new());
+            lines.push(words.join(" "));
+        }
+        lines.join("\n")
+    }
+}
+
+// a subtitle or json transcript's segments run together, anything else read as plain text
+fn text(path: &str) -> Result<String, Error> {
+    if parse::format(path).is_some() {
+        let transcript = parse::read(path)?;
+        return Ok(transcript.segments.iter().map(|segment| segment.text.as_str()).collect::<Vec<_>>().join(" "));
+    }
+    fs::read_to_string(path).map_err(|error| Error::input(format!("failed to read {}", path), error))
+}
+
+pub fn run(args: Args) -> Result<(), Error> {
+    let reference = compare::words(&text(&args.reference)?, args.strict);
+    let hypothesis = match (&args.hypothesis, &args.input) {
+        (Some(hypothesis), _) => text(hypothesis)?,
+        (None, Some(input)) => {
+            let model = match args.model.clone() {
+                Some(model) => model,
+                None => models::discover()?,
+            };
+            let transcript = Transcriber::new(model).options(args.decode_options).audio_options(args.audio_options).transcribe_file(input)?;
+            transcript.segments.iter().map(|segment| segment.text.as_str()).collect::<Vec<_>>().join(" ")
+        }
+        (None, None) => unreachable!("clap requires --hypothesis or --input"),
+    };
+    let report = Report::new(&reference, &compare::words(&hypothesis, args.strict), args.diff);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report).expect("failed to serialize report"));
+    } else {
+        println!("{}", report.render());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _new() {
+        let reference = compare::words("The cat sat on the mat.", false);
+        let report = Report::new(&reference, &compare::words("the cat sat in the hat mat", false), true);
+        assert_eq!((report.substitutions, report.deletions, report.insertions), (1, 0, 1));
+        assert_eq!(report.word_error_rate, 2.0 / 6.0);
+        assert_eq!((report.reference_characters, report.character_edits), (22, 5));
+        assert_eq!(report.render(), "wer  33.33% (1 substituted, 0 deleted, 1 inserted of 6 words)\ncer  22.73% (5 edits of 22 characters)\n\nthe cat sat [-on-]{+in+} the {+hat+} mat");
+
+        let perfect = Report::new(&reference, &reference, false);
+        assert_eq!((perfect.word_error_rate, perfect.character_error_rate), (0.0, 0.0));
+        assert!(perfect.alignment.is_empty());
+        assert_eq!(rate(3, 0), 1.0);
+    }
+}
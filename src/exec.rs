@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::process::Command;
+
+use clap::ValueEnum;
+
+use transcript::error::Error;
+use transcript::output::Format;
+
+use crate::batch;
+use crate::transcribe::Finished;
+
+// names a format's output by its --formats name, {srt}, {json} and so on
+fn name(format: Format) -> String {
+    format.to_possible_value().expect("formats have names").get_name().to_string()
+}
+
+// the {name}s in template, in order
+fn placeholders(template: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        match rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-')) {
+            Some(end) if end > 0 && rest[end..].starts_with('}') => {
+                found.push(&rest[..end]);
+                rest = &rest[end + 1..];
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+// every placeholder has to be something each file will have, so a typo fails before the batch starts
+pub fn check(template: &str, formats: &[Format]) -> Result<(), Error> {
+    for placeholder in placeholders(template) {
+        if !["input", "output", "lang"].contains(&placeholder) && !formats.iter().any(|format| name(*format) == placeholder) {
+            return Err(Error::Input(format!("--exec uses {{{}}}, which is none of input, output, lang or a format in --formats", placeholder)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(not(unix))]
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\\\""))
+}
+
+// the template with every placeholder swapped for its value, quoted for the shell
+fn substitute(template: &str, values: &HashMap<String, String>) -> String {
+    let mut command = String::new();
+    let mut rest = template;
+    for placeholder in placeholders(template) {
+        let marker = format!("{{{}}}", placeholder);
+        let start = rest.find(&marker).expect("placeholders come from the template");
+        command.push_str(&rest[..start]);
+        command.push_str(&quote(&values[placeholder]));
+        rest = &rest[start + marker.len()..];
+    }
+    command.push_str(rest);
+    command
+}
+
+fn values(job: &batch::Job, finished: &Finished, formats: &[Format]) -> HashMap<String, String> {
+    let mut values: HashMap<String, String> = formats.iter().zip(&finished.outputs).map(|(format, path)| (name(*format), path.clone())).collect();
+    values.insert("input".to_string(), job.input.clone());
+    values.insert("output".to_string(), job.output.clone().expect("--exec needs outputs"));
+    values.insert("lang".to_string(), finished.language.clone());
+    values
+}
+
+// runs through the shell so pipes and && work; a command that fails fails the file
+pub fn run(template: &str, job: &batch::Job, finished: &Finished, formats: &[Format]) -> Result<(), Error> {
+    let command = substitute(template, &values(job, finished, formats));
+    tracing::debug!("running {}", command);
+    let mut shell = if cfg!(unix) {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    } else {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    };
+    let status = shell.arg(&command).status().map_err(|error| Error::output(format!("failed to run --exec for {}", job.input), error))?;
+    if !status.success() {
+        return Err(Error::Output(format!("--exec for {} failed with {}", job.input, status)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn _substitute() {
+        let job = batch::Job { input: "in/it's.wav".to_string(), output: Some("out/it's".to_string()), language: None, translate: None };
+        let finished = Finished { audio_ms: 1000, segments: 1, language: "en".to_string(), outputs: vec!["out/it's.srt".to_string(), "out/it's.text.txt".to_string()] };
+        let values = values(&job, &finished, &[Format::Srt, Format::Text]);
+        assert_eq!(substitute("mux {input} {srt} > {output}.{lang}.mkv", &values), r#"mux 'in/it'\''s.wav' 'out/it'\''s.srt' > 'out/it'\''s'.'en'.mkv"#);
+        assert_eq!(substitute("cat {text} | awk '{print $1}'", &values), r#"cat 'out/it'\''s.text.txt' | awk '{print $1}'"#);
+
+        assert!(check("upload {srt} {input}", &[Format::Srt]).is_ok());
+        assert!(check("upload {vtt}", &[Format::Srt]).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use std::ops::Range;
+
+use crate::error::Error;
+use crate::transcript::Transcript;
+
+// what --remove-fillers takes out without a list of its own
+pub const DEFAULT: &[&str] = &["um", "umm", "uh", "uhh", "uh-huh", "er", "erm", "ah", "hm", "hmm", "mm", "mhm", "you know"];
+
+fn is_edge(c: char) -> bool {
+    !c.is_alphanumeric()
+}
+
+fn core(word: &str) -> String {
+    word.trim_matches(is_edge).to_lowercase()
+}
+
+fn ends_sentence(word: &str) -> bool {
+    word.trim_end_matches(['"', '\'', ')']).ends_with(['.', '?', '!'])
+}
+
+// disfluencies and filler phrases, matched a whole word at a time whatever their case
+pub struct Fillers {
+    phrases: Vec<Vec<String>>,
+}
+
+impl Fillers {
+    // one filler a line, # starts a comment
+    pub fn parse(contents: &str) -> Fillers {
+        Fillers::new(contents.lines().map(|line| line.split('#').next().unwrap_or("").trim()).filter(|line| !line.is_empty()))
+    }
+
+    pub fn load(path: &str) -> Result<Fillers, Error> {
+        let contents = std::fs::read_to_string(path).map_err(|error| Error::input(format!("failed to read filler list {}", path), error))?;
+        Ok(Fillers::parse(&contents))
+    }
+
+    // the longest phrases are tried first, so "you know" goes before a lone "you" could
+    pub fn new<'a>(fillers: impl IntoIterator<Item = &'a str>) -> Fillers {
+        let mut phrases: Vec<Vec<String>> = fillers.into_iter().map(|filler| filler.split_whitespace().map(core).collect()).filter(|phrase: &Vec<String>| !phrase.is_empty()).collect();
+        phrases.sort_by_key(|phrase| std::cmp::Reverse(phrase.len()));
+        Fillers { phrases }
+    }
+
+    // where the fillers are among words, by index
+    fn spans(&self, cores: &[String]) -> Vec<Range<usize>> {
+        let mut spans = Vec::new();
+        let mut i = 0;
+        while i < cores.len() {
+            match self.phrases.iter().find(|phrase| cores[i..].starts_with(phrase)) {
+                Some(phrase) => {
+                    spans.push(i..i + phrase.len());
+                    i += phrase.len();
+                }
+                None => i += 1,
+            }
+        }
+        spans
+    }
+
+    // the text without its fillers: a comma after one goes with it, a full stop moves to the word
+    // before, and the word after one that started a sentence takes over its capital
+    pub fn remove_text(&self, text: &str) -> String {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let spans = self.spans(&words.iter().map(|word| core(word)).collect::<Vec<_>>());
+        let mut kept: Vec<String> = Vec::new();
+        let mut capitalize = false;
+        let mut at = 0;
+        for span in spans.iter().chain(std::iter::once(&(words.len()..words.len()))) {
+            for word in &words[at..span.start] {
+                let mut word = word.to_string();
+                if std::mem::take(&mut capitalize) {
+                    word = word.chars().enumerate().map(|(i, c)| if i == 0 { c.to_uppercase().next().unwrap_or(c) } else { c }).collect();
+                }
+                kept.push(word);
+            }
+            if span.is_empty() {
+                break;
+            }
+            let last = words[span.end - 1];
+            let trailing = &last[last.trim_end_matches(is_edge).len()..];
+            match kept.last_mut() {
+                Some(before) if ends_sentence(trailing) && !before.ends_with(|c: char| c.is_ascii_punctuation()) => before.push_str(trailing),
+                _ => {}
+            }
+            let starts_sentence = kept.last().is_none_or(|before| ends_sentence(before));
+            capitalize = starts_sentence && words[span.start].starts_with(char::is_uppercase);
+            at = span.end;
+        }
+        if kept.is_empty() {
+            return String::new();
+        }
+        format!(" {}", kept.join(" "))
+    }
+
+    // segments keep their times, words their own; a segment that was nothing but fillers goes
+    pub fn apply(&self, transcript: &mut Transcript) {
+        for segment in &mut transcript.segments {
+            segment.text = self.remove_text(&segment.text);
+            let spans = self.spans(&segment.words.iter().map(|word| core(&word.text)).collect::<Vec<_>>());
+            for span in spans.into_iter().rev() {
+                segment.words.drain(span);
+            }
+        }
+        transcript.segments.retain(|segment| !segment.text.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _remove_text() {
+        let fillers = Fillers::new(DEFAULT.iter().copied());
+        assert_eq!(fillers.remove_text(" Um, I think, you know, it works. Uh, yes."), " I think, it works. Yes.");
+        assert_eq!(fillers.remove_text(" So we went there um."), " So we went there.");
+        assert_eq!(fillers.remove_text(" You know what? Umbrellas."), " What? Umbrellas.");
+        assert_eq!(Fillers::parse("basically # a habit\n").remove_text(" It's basically done"), " It's done");
+
+        let mut transcript = Transcript { segments: vec![Segment::new(0, 500, " Hmm."), Segment::new(500, 2000, " Right, uh, yes.")] };
+        fillers.apply(&mut transcript);
+        assert_eq!(transcript.segments, vec![Segment::new(500, 2000, " Right, yes.")]);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::error::Error;
+use crate::transcript::Transcript;
+
+pub struct Glossary {
+    terms: Vec<String>,
+}
+
+pub fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn is_edge(c: char) -> bool {
+    !c.is_alphanumeric()
+}
+
+impl Glossary {
+    // one term per line, # starts a comment
+    pub fn parse(contents: &str) -> Glossary {
+        let terms = contents.lines().map(|line| line.split('#').next().unwrap_or("").trim()).filter(|line| !line.is_empty()).map(String::from).collect();
+        Glossary { terms }
+    }
+
+    pub fn load(path: &str) -> Result<Glossary, Error> {
+        let contents = std::fs::read_to_string(path).map_err(|error| Error::input(format!("failed to read glossary {}", path), error))?;
+        Ok(Glossary::parse(&contents))
+    }
+
+    // whisper picks up spellings from the prompt, so the terms go in as a plain list
+    pub fn prompt(&self) -> String {
+        format!("{}.", self.terms.join(", "))
+    }
+
+    // a near miss is within one edit for every four characters of the term
+    fn closest(&self, candidate: &str) -> Option<&str> {
+        let lowered: Vec<char> = candidate.to_lowercase().chars().collect();
+        if lowered.len() < 4 {
+            return None;
+        }
+        self.terms
+            .iter()
+            .filter(|term| term.split_whitespace().count() == candidate.split_whitespace().count())
+            .map(|term| (term, levenshtein(&lowered, &term.to_lowercase().chars().collect::<Vec<_>>())))
+            .filter(|(term, distance)| *distance <= term.chars().count() / 4)
+            .min_by_key(|(_, distance)| *distance)
+            .map(|(term, _)| term.as_str())
+    }
+
+    // replaces runs of words that nearly spell a term, leaving surrounding punctuation alone
+    pub fn correct_text(&self, text: &str) -> String {
+        let mut words: Vec<String> = text.split(' ').map(String::from).collect();
+        let longest = self.terms.iter().map(|term| term.split_whitespace().count()).max().unwrap_or(0);
+        let mut i = 0;
+        while i < words.len() {
+            for length in (1..=longest.min(words.len() - i)).rev() {
+                let window = words[i..i + length].join(" ");
+                let core = window.trim_matches(is_edge);
+                if core.is_empty() || core.split_whitespace().count() != length {
+                    continue;
+                }
+                if let Some(term) = self.closest(core) {
+                    let start = window.find(core).expect("core is part of the window");
+                    let corrected = format!("{}{}{}", &window[..start], term, &window[start + core.len()..]);
+                    words.splice(i..i + length, [corrected]);
+                    break;
+                }
+            }
+            i += 1;
+        }
+        words.join(" ")
+    }
+
+    pub fn correct(&self, transcript: &mut Transcript) {
+        for segment in &mut transcript.segments {
+            segment.text = self.correct_text(&segment.text);
+            for word in &mut segment.words {
+                word.text = self.correct_text(&word.text);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _correct_text() {
+        let glossary = Glossary::parse("Kubernetes\nAlex Maras # the author\nwhisper.cpp\n");
+        assert_eq!(glossary.prompt(), "Kubernetes, Alex Maras, whisper.cpp.");
+        assert_eq!(glossary.correct_text(" We run kubernetis, said Alex Marras."), " We run Kubernetes, said Alex Maras.");
+        assert_eq!(glossary.correct_text(" Whisper CPP is fast"), " Whisper CPP is fast");
+        assert_eq!(glossary.correct_text(" the cat"), " the cat");
+    }
+}
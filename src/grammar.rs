@@ -0,0 +1,435 @@
+use std::collections::{HashMap, HashSet};
+use std::os::raw::{c_int, c_void};
+
+use whisper_rs::{FullParams, WhisperContext};
+use whisper_rs_sys::{whisper_context, whisper_state, whisper_token_data};
+
+use crate::error::Error;
+
+// whisper-rs hands whisper.cpp's grammar_rules a flat element array where it expects an array of
+// rules, so the grammar is enforced here instead, through the logits filter callback
+
+#[derive(Debug, Clone, PartialEq)]
+enum Element {
+    // inclusive ranges, matching anything outside them when negated
+    Chars { ranges: Vec<(char, char)>, negated: bool },
+    Rule(usize),
+}
+
+impl Element {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Element::Chars { ranges, negated } => ranges.iter().any(|(low, high)| (*low..=*high).contains(&c)) != *negated,
+            Element::Rule(_) => false,
+        }
+    }
+}
+
+// every rule is a list of alternatives, every alternative a sequence of elements
+type Rules = Vec<Vec<Vec<Element>>>;
+
+// where matching stands in a sequence: rule, alternative, element
+type Position = (usize, usize, usize);
+
+// the innermost rule is last
+type Stack = Vec<Position>;
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    rules: Rules,
+    names: HashMap<String, usize>,
+    defined: HashSet<usize>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    // whitespace and comments, newlines too when inside brackets
+    fn skip(&mut self, newlines: bool) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.pos += 1;
+                }
+            } else if c == ' ' || c == '\t' || c == '\r' || (newlines && c == '\n') {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn name(&mut self) -> Option<String> {
+        let mut name = String::new();
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_') {
+            name.push(c);
+            self.pos += 1;
+        }
+        Some(name).filter(|name| !name.is_empty())
+    }
+
+    fn rule_id(&mut self, name: String) -> usize {
+        if let Some(id) = self.names.get(&name) {
+            return *id;
+        }
+        self.rules.push(Vec::new());
+        self.names.insert(name, self.rules.len() - 1);
+        self.rules.len() - 1
+    }
+
+    fn new_rule(&mut self, alternatives: Vec<Vec<Element>>) -> usize {
+        self.rules.push(alternatives);
+        self.defined.insert(self.rules.len() - 1);
+        self.rules.len() - 1
+    }
+
+    fn escaped(&mut self) -> Result<char, String> {
+        let c = self.peek().ok_or("unexpected end of grammar")?;
+        self.pos += 1;
+        if c != '\\' {
+            return Ok(c);
+        }
+        let c = self.peek().ok_or("unexpected end of grammar")?;
+        self.pos += 1;
+        let hex = |parser: &mut Parser, digits: usize| -> Result<char, String> {
+            let text: String = parser.chars.iter().skip(parser.pos).take(digits).collect();
+            parser.pos += digits;
+            u32::from_str_radix(&text, 16).ok().and_then(char::from_u32).ok_or(format!("bad escape \\{c}{text}"))
+        };
+        match c {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            'x' => hex(self, 2),
+            'u' => hex(self, 4),
+            'U' => hex(self, 8),
+            _ => Ok(c),
+        }
+    }
+
+    fn alternatives(&mut self, nested: bool) -> Result<Vec<Vec<Element>>, String> {
+        let mut alternatives = vec![self.sequence(nested)?];
+        loop {
+            // an alternative may continue on the next line
+            let end = self.pos;
+            self.skip(true);
+            if self.peek() != Some('|') {
+                self.pos = end;
+                return Ok(alternatives);
+            }
+            self.pos += 1;
+            self.skip(true);
+            alternatives.push(self.sequence(nested)?);
+        }
+    }
+
+    fn sequence(&mut self, nested: bool) -> Result<Vec<Element>, String> {
+        let mut sequence = Vec::new();
+        loop {
+            self.skip(nested);
+            let element = match self.peek() {
+                Some('"') => {
+                    self.pos += 1;
+                    let mut literal = Vec::new();
+                    while self.peek() != Some('"') {
+                        let c = self.escaped()?;
+                        literal.push(Element::Chars { ranges: vec![(c, c)], negated: false });
+                    }
+                    self.pos += 1;
+                    // a repeated literal repeats as a whole
+                    if !matches!(self.peek(), Some('*' | '+' | '?')) {
+                        sequence.extend(literal);
+                        continue;
+                    }
+                    Element::Rule(self.new_rule(vec![literal]))
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    let negated = self.peek() == Some('^');
+                    if negated {
+                        self.pos += 1;
+                    }
+                    let mut ranges = Vec::new();
+                    while self.peek() != Some(']') {
+                        let low = self.escaped()?;
+                        let high = if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                            self.pos += 1;
+                            self.escaped()?
+                        } else {
+                            low
+                        };
+                        ranges.push((low, high));
+                    }
+                    self.pos += 1;
+                    Element::Chars { ranges, negated }
+                }
+                Some('.') => {
+                    self.pos += 1;
+                    Element::Chars { ranges: vec![], negated: true }
+                }
+                Some('(') => {
+                    self.pos += 1;
+                    self.skip(true);
+                    let alternatives = self.alternatives(true)?;
+                    self.skip(true);
+                    if self.peek() != Some(')') {
+                        return Err(format!("expected ) at character {}", self.pos));
+                    }
+                    self.pos += 1;
+                    Element::Rule(self.new_rule(alternatives))
+                }
+                Some(c) if c.is_ascii_alphanumeric() => {
+                    let name = self.name().expect("a name starts here");
+                    Element::Rule(self.rule_id(name))
+                }
+                _ => return Ok(sequence),
+            };
+            // repetition becomes a helper rule: x* is r ::= x r | empty, x+ is x x*, x? is r ::= x | empty
+            match self.peek() {
+                Some('*') => {
+                    self.pos += 1;
+                    let repeat = self.new_rule(Vec::new());
+                    self.rules[repeat] = vec![vec![element, Element::Rule(repeat)], vec![]];
+                    sequence.push(Element::Rule(repeat));
+                }
+                Some('+') => {
+                    self.pos += 1;
+                    let repeat = self.new_rule(Vec::new());
+                    self.rules[repeat] = vec![vec![element.clone(), Element::Rule(repeat)], vec![]];
+                    sequence.push(element);
+                    sequence.push(Element::Rule(repeat));
+                }
+                Some('?') => {
+                    self.pos += 1;
+                    sequence.push(Element::Rule(self.new_rule(vec![vec![element], vec![]])));
+                }
+                _ => sequence.push(element),
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Grammar {
+    rules: Rules,
+    start: usize,
+}
+
+impl Grammar {
+    // GBNF as used by llama.cpp and whisper.cpp: literals, [classes], rule references, groups and * + ?
+    pub fn parse(source: &str, start_rule: &str) -> Result<Grammar, String> {
+        let mut parser = Parser { chars: source.chars().collect(), pos: 0, rules: Vec::new(), names: HashMap::new(), defined: HashSet::new() };
+        loop {
+            parser.skip(true);
+            if parser.peek().is_none() {
+                break;
+            }
+            let name = parser.name().ok_or(format!("expected a rule name at character {}", parser.pos))?;
+            parser.skip(false);
+            if !parser.chars[parser.pos..].starts_with(&[':', ':', '=']) {
+                return Err(format!("expected ::= after {name}"));
+            }
+            parser.pos += 3;
+            parser.skip(true);
+            let id = parser.rule_id(name.clone());
+            parser.rules[id] = parser.alternatives(false)?;
+            parser.defined.insert(id);
+        }
+
+        if let Some((name, _)) = parser.names.iter().find(|(_, id)| !parser.defined.contains(id)) {
+            return Err(format!("rule {name} is used but never defined"));
+        }
+        let start = *parser.names.get(start_rule).ok_or(format!("grammar has no {start_rule} rule"))?;
+        Ok(Grammar { rules: parser.rules, start })
+    }
+
+    // every way of reaching the next character, an empty stack meaning the grammar is complete
+    fn expand(&self, mut stack: Stack, stacks: &mut HashSet<Stack>) {
+        let Some(&(rule, alternative, element)) = stack.last() else {
+            stacks.insert(stack);
+            return;
+        };
+        let sequence = &self.rules[rule][alternative];
+        if element == sequence.len() {
+            stack.pop();
+            return self.expand(stack, stacks);
+        }
+        // deep stacks only come from left recursion, which never consumes anything
+        if stack.len() > 256 {
+            return;
+        }
+        match &sequence[element] {
+            Element::Chars { .. } => {
+                stacks.insert(stack);
+            }
+            Element::Rule(next) => {
+                stack.last_mut().expect("stack has a top").2 += 1;
+                for i in 0..self.rules[*next].len() {
+                    let mut nested = stack.clone();
+                    nested.push((*next, i, 0));
+                    self.expand(nested, stacks);
+                }
+            }
+        }
+    }
+
+    fn initial(&self) -> HashSet<Stack> {
+        let mut stacks = HashSet::new();
+        for i in 0..self.rules[self.start].len() {
+            self.expand(vec![(self.start, i, 0)], &mut stacks);
+        }
+        stacks
+    }
+
+    fn advance(&self, stacks: &HashSet<Stack>, c: char) -> HashSet<Stack> {
+        let mut next = HashSet::new();
+        for stack in stacks {
+            let Some(&(rule, alternative, element)) = stack.last() else {
+                continue;
+            };
+            if self.rules[rule][alternative][element].matches(c) {
+                let mut stack = stack.clone();
+                stack.last_mut().expect("stack has a top").2 += 1;
+                self.expand(stack, &mut next);
+            }
+        }
+        next
+    }
+
+    pub fn load(path: &str, start_rule: &str) -> Result<Grammar, Error> {
+        let source = std::fs::read_to_string(path).map_err(|error| Error::input(format!("failed to read grammar {}", path), error))?;
+        Grammar::parse(&source, start_rule).map_err(|error| Error::input(format!("invalid grammar {}", path), error))
+    }
+
+    #[cfg(test)]
+    fn accepts(&self, text: &str) -> bool {
+        let stacks = text.chars().fold(self.initial(), |stacks, c| self.advance(&stacks, c));
+        stacks.iter().any(|stack| stack.is_empty())
+    }
+}
+
+#[derive(Default)]
+struct Node {
+    children: Vec<(char, usize)>,
+    tokens: Vec<usize>,
+}
+
+pub struct Constraint {
+    grammar: Grammar,
+    texts: Vec<String>,
+    // the vocabulary as a prefix tree, so a dead end rules out every token below it at once
+    trie: Vec<Node>,
+    eot: usize,
+    n_vocab: usize,
+    penalty: f32,
+}
+
+impl Constraint {
+    pub fn new(grammar: Grammar, ctx: &WhisperContext, penalty: f32) -> Constraint {
+        let eot = ctx.token_eot() as usize;
+        let texts: Vec<String> = (0..eot as i32).map(|id| ctx.token_to_cstr(id).map(|text| text.to_string_lossy().into_owned()).unwrap_or_default()).collect();
+        let mut trie = vec![Node::default()];
+        for (id, text) in texts.iter().enumerate() {
+            let mut node = 0;
+            for c in text.chars() {
+                node = match trie[node].children.iter().find(|(child, _)| *child == c) {
+                    Some((_, next)) => *next,
+                    None => {
+                        trie.push(Node::default());
+                        let next = trie.len() - 1;
+                        trie[node].children.push((c, next));
+                        next
+                    }
+                };
+            }
+            trie[node].tokens.push(id);
+        }
+        Constraint { grammar, texts, trie, eot, n_vocab: ctx.n_vocab() as usize, penalty }
+    }
+
+    fn allow(&self, node: usize, stacks: &HashSet<Stack>, allowed: &mut [bool]) {
+        // the empty token doesn't move the grammar along, so it can't help either
+        if node != 0 {
+            for token in &self.trie[node].tokens {
+                allowed[*token] = true;
+            }
+        }
+        for (c, child) in &self.trie[node].children {
+            let next = self.grammar.advance(stacks, *c);
+            if !next.is_empty() {
+                self.allow(*child, &next, allowed);
+            }
+        }
+    }
+
+    // penalises every text token the grammar can't take next, and the end of text until it's complete
+    fn filter(&self, tokens: &[usize], logits: &mut [f32]) {
+        let mut stacks = self.grammar.initial();
+        for token in tokens.iter().filter(|token| **token < self.eot) {
+            for c in self.texts[*token].chars() {
+                stacks = self.grammar.advance(&stacks, c);
+            }
+        }
+        // once the text has left the grammar there is nothing left to steer
+        if stacks.is_empty() {
+            return;
+        }
+
+        let mut allowed = vec![false; self.eot];
+        self.allow(0, &stacks, &mut allowed);
+        for (token, allowed) in allowed.iter().enumerate() {
+            if !allowed {
+                logits[token] -= self.penalty;
+            }
+        }
+        if !stacks.iter().any(|stack| stack.is_empty()) {
+            logits[self.eot] -= self.penalty;
+        }
+    }
+
+    pub fn apply(&self, params: &mut FullParams) {
+        // the constraint outlives the params, which only live for one decode
+        unsafe {
+            params.set_filter_logits_callback(Some(filter_logits));
+            params.set_filter_logits_callback_user_data(self as *const Constraint as *mut c_void);
+        }
+    }
+}
+
+unsafe extern "C" fn filter_logits(
+    _ctx: *mut whisper_context,
+    _state: *mut whisper_state,
+    tokens: *const whisper_token_data,
+    n_tokens: c_int,
+    logits: *mut f32,
+    user_data: *mut c_void,
+) {
+    let constraint = &*(user_data as *const Constraint);
+    let tokens: Vec<usize> = if n_tokens > 0 { std::slice::from_raw_parts(tokens, n_tokens as usize).iter().map(|token| token.id as usize).collect() } else { Vec::new() };
+    let logits = std::slice::from_raw_parts_mut(logits, constraint.n_vocab);
+    constraint.filter(&tokens, logits);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _accepts() {
+        let source = "# a command\nroot ::= \" turn \" (\"on\" | \"off\") \" the \" device\ndevice ::= [a-z]+ \"s\"?\n";
+        let grammar = Grammar::parse(source, "root").unwrap();
+        assert!(grammar.accepts(" turn on the light"));
+        assert!(grammar.accepts(" turn off the lights"));
+        assert!(!grammar.accepts(" turn up the light"));
+        assert!(!grammar.accepts(" turn on the"));
+        let repeated = Grammar::parse("root ::= \"ab\"+\n   | \"c\"", "root").unwrap();
+        assert!(repeated.accepts("abab"));
+        assert!(repeated.accepts("c"));
+        assert!(!repeated.accepts("aba"));
+        assert!(Grammar::parse("root ::= missing", "root").is_err());
+        assert!(Grammar::parse("main ::= \"a\"", "root").is_err());
+    }
+}
@@ -0,0 +1,19 @@
+use std::process;
+
+use transcript::cancel::CancellationToken;
+use transcript::error;
+
+// the first ctrl-c cancels the token, so the decode stops at the next chance and the run writes
+// what it has; a second one quits straight away
+pub fn install(token: CancellationToken) {
+    let installed = ctrlc::set_handler(move || {
+        if token.is_cancelled() {
+            process::exit(error::INTERRUPTED as i32);
+        }
+        token.cancel();
+        tracing::warn!("stopping, the segments decoded so far will be written; ctrl-c again to quit without them");
+    });
+    if let Err(error) = installed {
+        tracing::warn!("failed to install the ctrl-c handler: {}", error);
+    }
+}
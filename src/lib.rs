@@ -0,0 +1,39 @@
+//! Speech to text with whisper.cpp, the library behind the `transcript` command: audio decoding
+//! and resampling, transcription, and the subtitle and transcript formats.
+//!
+//! ```no_run
+//! use transcript::output::{self, Format};
+//! use transcript::Transcriber;
+//!
+//! let transcriber = Transcriber::new("base").language("de");
+//! let transcript = transcriber.transcribe_file("interview.mp3")?;
+//! print!("{}", Format::Srt.render(&transcript, &output::Options::default()));
+//! # Ok::<(), transcript::Error>(())
+//! ```
+
+pub mod alignment;
+pub mod audio;
+pub mod cancel;
+pub mod compare;
+pub mod decode;
+pub mod device;
+pub mod error;
+pub mod fillers;
+pub mod glossary;
+pub mod grammar;
+pub mod models;
+pub mod output;
+pub mod postprocess;
+pub mod redact;
+pub mod refine;
+pub mod replace;
+pub mod storage;
+pub mod store;
+pub mod transcriber;
+pub mod transcript;
+pub mod vad;
+
+pub use crate::cancel::CancellationToken;
+pub use crate::error::Error;
+pub use crate::transcriber::{Event, Transcriber};
+pub use crate::transcript::{Segment, Transcript};
@@ -0,0 +1,31 @@
+use std::process::ExitCode;
+
+use transcript::error::Error;
+use transcript::output::{layout, parse};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Subtitle files to check: srt, vtt, sbv or the json transcript
+    #[arg(required = true)]
+    files: Vec<String>,
+
+    #[command(flatten)]
+    layout_options: layout::Options,
+}
+
+// every problem on a line of its own, failing when there's any
+pub fn run(args: &Args) -> Result<ExitCode, Error> {
+    let mut found = 0;
+    for file in &args.files {
+        let problems = layout::lint(&parse::read(file)?, &args.layout_options);
+        for problem in &problems {
+            println!("{}: {}", file, problem);
+        }
+        found += problems.len();
+    }
+    if found > 0 {
+        tracing::warn!("found {} problems", found);
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
+}
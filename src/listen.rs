@@ -0,0 +1,136 @@
+use std::io::{self, IsTerminal};
+use std::sync::mpsc::{self, Sender};
+use std::time::Duration;
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample};
+
+use transcript::audio::{self, resample};
+use transcript::cancel::CancellationToken;
+use transcript::error::Error;
+use transcript::{decode, models, Transcriber};
+
+use crate::interrupt;
+use crate::live::{self, Live, Printer};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Model file or name, as for transcribe
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Input to capture, by (part of) its name; defaults to the system's default input
+    #[arg(long, value_name = "NAME")]
+    input_device: Option<String>,
+
+    /// List the inputs that can be captured and exit
+    #[arg(long)]
+    list_inputs: bool,
+
+    #[command(flatten)]
+    decode_options: decode::Options,
+}
+
+fn failed(context: &str) -> impl Fn(cpal::Error) -> Error + '_ {
+    move |error| Error::input(context, error)
+}
+
+fn name(device: &cpal::Device) -> String {
+    device.description().map_or_else(|_| "unknown".to_string(), |description| description.name().to_string())
+}
+
+fn input(args: &Args) -> Result<cpal::Device, Error> {
+    let host = cpal::default_host();
+    match &args.input_device {
+        Some(wanted) => host
+            .input_devices()
+            .map_err(failed("failed to list the inputs"))?
+            .find(|device| name(device).to_lowercase().contains(&wanted.to_lowercase()))
+            .ok_or_else(|| Error::Input(format!("no input named like {}, --list-inputs shows them", wanted))),
+        None => host.default_input_device().ok_or_else(|| Error::Input("there's no default input to capture".to_string())),
+    }
+}
+
+// every callback's frames mixed down to mono and handed to the decoding loop
+fn capture<T>(device: &cpal::Device, config: cpal::StreamConfig, sender: Sender<Vec<f32>>) -> Result<cpal::Stream, Error>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels as usize;
+    let on_data = move |data: &[T], _: &cpal::InputCallbackInfo| {
+        let mono = data.chunks_exact(channels).map(|frame| frame.iter().map(|sample| f32::from_sample_(*sample)).sum::<f32>() / channels as f32).collect();
+        let _ = sender.send(mono);
+    };
+    let on_error = |error: cpal::Error| tracing::warn!("capture: {}", error);
+    device.build_input_stream(config, on_data, on_error, None).map_err(failed("failed to start capturing"))
+}
+
+pub fn run(args: Args) -> Result<(), Error> {
+    let host = cpal::default_host();
+    if args.list_inputs {
+        for device in host.input_devices().map_err(failed("failed to list the inputs"))? {
+            println!("{}", name(&device));
+        }
+        return Ok(());
+    }
+    let model = match args.model.clone() {
+        Some(model) => model,
+        None => models::discover()?,
+    };
+    let device = input(&args)?;
+    let config = device.default_input_config().map_err(failed("failed to read the input's format"))?;
+    let format = config.sample_format();
+    let config: cpal::StreamConfig = config.into();
+    let rate = config.sample_rate;
+
+    // ctrl-c ends the capture, not the decode of what came before it
+    let stop = CancellationToken::default();
+    interrupt::install(stop.clone());
+    let transcriber = Transcriber::new(model).options(args.decode_options);
+    transcriber.load()?;
+
+    let (sender, captured) = mpsc::channel();
+    let stream = match format {
+        SampleFormat::F32 => capture::<f32>(&device, config, sender),
+        SampleFormat::I16 => capture::<i16>(&device, config, sender),
+        SampleFormat::I32 => capture::<i32>(&device, config, sender),
+        SampleFormat::U16 => capture::<u16>(&device, config, sender),
+        SampleFormat::U8 => capture::<u8>(&device, config, sender),
+        other => Err(Error::Input(format!("inputs giving {} samples aren't supported", other))),
+    }?;
+    stream.play().map_err(failed("failed to start capturing"))?;
+    tracing::info!("listening to {}, ctrl-c to stop", name(&device));
+
+    let mut resampler = (rate != audio::SAMPLE_RATE).then(|| resample::Stream::new(rate, audio::SAMPLE_RATE, 1));
+    let mut resampled = vec![Vec::new()];
+    let mut live = Live::default();
+    let printer = Printer { interim: io::stdout().is_terminal(), jsonl: false, color: live::colors() };
+    let transcribe = |samples: &[f32]| transcriber.transcribe(samples);
+    while !stop.is_cancelled() {
+        // whatever piled up during the last decode is taken in one go
+        let Ok(first) = captured.recv_timeout(Duration::from_millis(250)) else { continue };
+        for chunk in std::iter::once(first).chain(captured.try_iter()) {
+            match &mut resampler {
+                Some(resampler) => {
+                    resampler.push(&[chunk], &mut resampled);
+                    live.extend(&resampled[0]);
+                    resampled[0].clear();
+                }
+                None => live.extend(&chunk),
+            }
+        }
+        if live.due(false) {
+            for update in live.decode(transcribe, false)? {
+                printer.show(&update);
+            }
+        }
+    }
+    drop(stream);
+    if live.due(true) {
+        for update in live.decode(transcribe, true)? {
+            printer.show(&update);
+        }
+    }
+    Ok(())
+}
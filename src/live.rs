@@ -0,0 +1,212 @@
+use std::env;
+use std::io::{self, IsTerminal, Write};
+
+use serde::Serialize;
+
+use transcript::error::Error;
+use transcript::{audio, output, Segment, Transcript};
+
+// the audio is decoded again whenever this much more has come in
+const STEP_MS: usize = 1000;
+// once the audio held back reaches this, everything but its last segment is final; whisper
+// decodes 30s at a time
+const FINALIZE_MS: usize = 20000;
+// the most that's ever held back, the oldest audio goes first past it; a stretch with no
+// segments in it would otherwise never be let go of
+const HELD_MS: usize = 30000;
+
+// what goes back over the socket: partial segments are replaced by the next message, final ones
+// are never sent again
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Update {
+    Partial { segments: Vec<Segment> },
+    Final { segments: Vec<Segment> },
+    Error { message: String },
+}
+
+// the live audio not yet final, as 16kHz mono comes in a chunk at a time
+#[derive(Default)]
+pub struct Live {
+    samples: Vec<f32>,
+    // an odd byte left over from the last chunk
+    leftover: Option<u8>,
+    // where samples starts in the stream, in ms
+    offset_ms: i64,
+    decoded: usize,
+    // the partial segments the last decode came to
+    previous: Vec<Segment>,
+}
+
+// the leading segments that came out the same as in the last decode, bar the last one, whose
+// end may yet move with the audio after it
+fn settled(segments: &[Segment], previous: &[Segment]) -> usize {
+    segments.iter().take(segments.len().saturating_sub(1)).zip(previous).take_while(|(segment, previous)| segment.start_ms == previous.start_ms && segment.text.trim() == previous.text.trim()).count()
+}
+
+fn samples_to_ms(samples: usize) -> usize {
+    samples * 1000 / audio::SAMPLE_RATE as usize
+}
+
+impl Live {
+    pub fn push(&mut self, pcm: &[u8]) {
+        let mut bytes: Vec<u8> = self.leftover.take().into_iter().collect();
+        bytes.extend_from_slice(pcm);
+        let mut pairs = bytes.chunks_exact(2);
+        let samples: Vec<f32> = pairs.by_ref().map(|pair| i16::from_le_bytes([pair[0], pair[1]]) as f32 / 32768.0).collect();
+        self.leftover = pairs.remainder().first().copied();
+        self.extend(&samples);
+    }
+
+    // samples already at 16kHz, from a capture as much as from pcm bytes
+    pub fn extend(&mut self, samples: &[f32]) {
+        self.samples.extend_from_slice(samples);
+    }
+
+    // a step's worth has come in since the last decode, or the stream is over
+    pub fn due(&self, end: bool) -> bool {
+        if end {
+            !self.samples.is_empty()
+        } else {
+            samples_to_ms(self.samples.len() - self.decoded) >= STEP_MS
+        }
+    }
+
+    // decodes what's held back and works out which of it is final, which is what's come out the
+    // same twice in a row or what's been held back too long; the audio of final segments is let
+    // go of, so the next decode starts where they end
+    pub fn decode(&mut self, transcribe: impl Fn(&[f32]) -> Result<Transcript, Error>, end: bool) -> Result<Vec<Update>, Error> {
+        self.decoded = self.samples.len();
+        let mut transcript = transcribe(&self.samples)?;
+        transcript.shift(self.offset_ms);
+        let finals = if end {
+            transcript.segments.len()
+        } else if samples_to_ms(self.samples.len()) >= FINALIZE_MS {
+            transcript.segments.len().saturating_sub(1)
+        } else {
+            settled(&transcript.segments, &self.previous)
+        };
+        let partials = transcript.segments.split_off(finals);
+        let mut updates = Vec::new();
+        if let Some(last) = transcript.segments.last() {
+            let keep_from = ((last.end_ms - self.offset_ms).max(0) as usize * audio::SAMPLE_RATE as usize / 1000).min(self.samples.len());
+            self.samples.drain(..keep_from);
+            self.decoded = self.samples.len();
+            self.offset_ms = last.end_ms.max(self.offset_ms);
+            updates.push(Update::Final { segments: transcript.segments });
+        }
+        if samples_to_ms(self.samples.len()) > HELD_MS {
+            let dropped_ms = samples_to_ms(self.samples.len()) - HELD_MS;
+            self.samples.drain(..dropped_ms * audio::SAMPLE_RATE as usize / 1000);
+            self.decoded = self.samples.len();
+            self.offset_ms += dropped_ms as i64;
+        }
+        if !end {
+            self.previous = partials.clone();
+            updates.push(Update::Partial { segments: partials });
+        } else {
+            self.samples.clear();
+            self.decoded = 0;
+            self.previous.clear();
+        }
+        Ok(updates)
+    }
+}
+
+// finals are printed for good, as timestamped lines or jsonl; on a terminal the partial guess sits
+// on the line below them and is redrawn as it changes
+pub struct Printer {
+    pub interim: bool,
+    pub jsonl: bool,
+    // finals coloured by confidence
+    pub color: bool,
+}
+
+// colours only for a person at a terminal, and not when NO_COLOR asks for none
+pub fn colors() -> bool {
+    io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+impl Printer {
+    fn clear(&self) {
+        if self.interim {
+            print!("\r\x1b[2K");
+        }
+    }
+
+    pub fn show(&self, update: &Update) {
+        match update {
+            Update::Final { segments } => {
+                self.clear();
+                for segment in segments {
+                    if self.jsonl {
+                        println!("{}", serde_json::to_string(segment).expect("failed to serialize segment"));
+                    } else if self.color {
+                        println!("{}", output::txt::colored_line(segment));
+                    } else {
+                        println!("{}", output::txt::line(segment));
+                    }
+                }
+            }
+            Update::Partial { segments } if self.interim => {
+                self.clear();
+                print!("{}", segments.iter().map(|segment| segment.text.trim()).collect::<Vec<_>>().join(" "));
+            }
+            Update::Partial { .. } => {}
+            Update::Error { message } => tracing::error!("{}", message),
+        }
+        let _ = io::stdout().flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a segment a second, as long as the audio
+    fn seconds(samples: &[f32]) -> Result<Transcript, Error> {
+        let seconds = samples_to_ms(samples.len()) as i64 / 1000;
+        Ok(Transcript { segments: (0..seconds).map(|second| Segment::new(second * 1000, second * 1000 + 1000, &format!(" {}", second))).collect() })
+    }
+
+    fn pcm(ms: usize) -> Vec<u8> {
+        vec![0u8; ms * audio::SAMPLE_RATE as usize / 1000 * 2]
+    }
+
+    #[test]
+    fn _live() {
+        let mut live = Live::default();
+        live.push(&pcm(500));
+        assert!(!live.due(false));
+        live.push(&pcm(500));
+        assert!(live.due(false));
+        assert_eq!(live.decode(seconds, false).unwrap(), vec![Update::Partial { segments: vec![Segment::new(0, 1000, " 0")] }]);
+
+        live.push(&pcm(20000));
+        let updates = live.decode(seconds, false).unwrap();
+        let Update::Final { segments } = &updates[0] else { panic!("expected final segments") };
+        assert_eq!((segments.len(), segments[19].end_ms), (20, 20000));
+        assert_eq!(updates[1], Update::Partial { segments: vec![Segment::new(20000, 21000, " 20")] });
+
+        // an odd byte waits for its pair
+        live.push(&[0u8; 3]);
+        live.push(&[0u8; 1]);
+        assert_eq!(live.samples.len(), 16002);
+        assert_eq!(live.decode(seconds, true).unwrap(), vec![Update::Final { segments: vec![Segment::new(20000, 21000, " 0")] }]);
+        assert!(!live.due(true));
+
+        // segments that come out the same twice are final without waiting for the rest
+        live.push(&pcm(2000));
+        assert_eq!(live.decode(seconds, false).unwrap(), vec![Update::Partial { segments: vec![Segment::new(21000, 22000, " 0"), Segment::new(22000, 23000, " 1")] }]);
+        live.push(&pcm(1000));
+        let updates = live.decode(seconds, false).unwrap();
+        assert_eq!(updates[0], Update::Final { segments: vec![Segment::new(21000, 22000, " 0"), Segment::new(22000, 23000, " 1")] });
+        assert_eq!(updates[1], Update::Partial { segments: vec![Segment::new(23000, 24000, " 2")] });
+
+        // silence that never makes a segment isn't held on to forever
+        let mut live = Live::default();
+        live.push(&pcm(40000));
+        assert_eq!(live.decode(|_| Ok(Transcript { segments: Vec::new() }), false).unwrap(), vec![Update::Partial { segments: Vec::new() }]);
+        assert_eq!((samples_to_ms(live.samples.len()), live.offset_ms), (30000, 10000));
+    }
+}
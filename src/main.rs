@@ -1,113 +1,299 @@
-use whisper_rs::{WhisperContext, FullParams, SamplingStrategy};
-use std::{path::Path, cmp};
-use std::fs::File;
-use std::io::Write;
-use hound::{SampleFormat, WavReader};
-use clap::Parser;
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, IsTerminal};
+use std::process::ExitCode;
+use std::time::Instant;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use tracing::Level;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt;
+use tracing_subscriber::prelude::*;
+use whisper_rs::WhisperContextParameters;
+
+mod align;
+mod apply_edits;
+mod batch;
+mod bench;
+mod cache;
+mod checkpoint;
+mod comparison;
+mod completions;
+mod config;
+mod convert;
+mod exec;
+#[cfg(unix)]
+mod daemon;
+mod diff;
+mod embed;
+mod eval;
+mod interrupt;
+#[cfg(feature = "listen")]
+mod listen;
+mod lint;
+mod live;
+mod merge;
+mod meta;
+mod mux;
+mod plan;
+mod progress;
+mod quotes;
+mod remote;
+mod retime;
+mod search;
+mod serve;
+mod speech;
+mod stats;
+mod transcribe;
+#[cfg(feature = "tui")]
+mod tui;
+mod watch;
+mod webhook;
+
+use transcript::{audio, decode, models, transcriber, Error};
+
+const EXIT_CODES: &str = "Exit codes:
+  0  success
+  1  some inputs of a batch failed, lint found problems or diff found changes
+  2  bad input (missing or unreadable audio, manifest, glossary, ...)
+  3  model missing or failed to load
+  4  inference failed
+  5  output could not be written
+  130  interrupted with ctrl-c, after writing what was decoded";
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    #[arg(short, long)]
-    model: String,
+#[command(author, version, about, long_about = None, after_help = EXIT_CODES)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
 
-    #[arg(short, long)]
-    input: String,
+    /// More logging, -vv for everything including whisper.cpp's debug output
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
 
-    #[arg(short, long)]
-    output: String,
-}
+    /// Only log errors, hiding progress bars and whisper.cpp's output
+    #[arg(short, long, conflicts_with = "verbose", global = true)]
+    quiet: bool,
 
-fn write_to_file(path: &Path, lines: Vec<String>) {
-    let mut file = File::create(path).expect("Could not create file");
-    for line in lines {
-        file.write_all(line.as_bytes()).expect("Could not write to file");
-    }
 }
 
-fn parse_wav_file(path: &Path) -> Vec<i16> {
-    let reader = WavReader::open(path).expect("failed to read file");
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Transcribe audio files, what a bare `transcript <flags>` does too
+    #[command(after_help = EXIT_CODES)]
+    Transcribe(Box<transcribe::Args>),
 
-    if reader.spec().channels != 1 {
-        panic!("expected mono audio file");
-    }
-    if reader.spec().sample_format != SampleFormat::Int {
-        panic!("expected integer sample format");
-    }
-    if reader.spec().sample_rate != 16000 {
-        panic!("expected 16KHz sample rate");
-    }
-    if reader.spec().bits_per_sample != 16 {
-        panic!("expected 16 bits per sample");
-    }
+    /// Print the spoken language and its probability without transcribing
+    Detect {
+        /// Model file or name, as for transcribe
+        #[arg(short, long)]
+        model: Option<String>,
 
-    reader
-        .into_samples::<i16>()
-        .map(|x| x.expect("sample"))
-        .collect::<Vec<_>>()
-}
+        #[arg(short, long)]
+        input: String,
 
-fn segment_time_to_srt_time_string(time: i64) -> String {
-    let positive_time = cmp::max(0, time) * 10;
-    let ms = positive_time % 1000;
-    let seconds = (positive_time / 1000) % 60;
-    let minutes = (positive_time / 1000 / 60) % 60;
-    let hours = positive_time / 1000 / 60 / 60;
-    format!("{:02}:{:02}:{:02},{:03}", hours, minutes, seconds, ms)
-}
+        /// Worker threads, defaults to the available parallelism
+        #[arg(short, long, value_parser = decode::parse_threads)]
+        threads: Option<usize>,
 
-fn main() {
-    let args = Args::parse();
-    
-    let audio_file_path_raw = &args.input;
-    let model_path_raw = &args.model;
-    let output_path_raw = &args.output;
+        #[command(flatten)]
+        audio_options: audio::Options,
+    },
 
-    let audio_file_path = Path::new(audio_file_path_raw);
-    if !audio_file_path.exists() {
-        panic!("audio file doesn't exist");
-    }
-    let model_path = Path::new(model_path_raw);
-    if !model_path.exists() {
-        panic!("model does not exist");
-    }
+    /// Report how much of the audio is speech and how long its pauses are, as --vad measures them
+    Speech(Box<speech::Args>),
+
+    /// Time a known script against its audio, writing the script's lines as cues
+    Align(Box<align::Args>),
+
+    /// Convert a subtitle file to another format, laid out as transcribe would
+    Convert(Box<convert::Args>),
+
+    /// Shift and stretch the cue times of a subtitle file, to fix drift against the video
+    Retime(Box<retime::Args>),
+
+    /// Transcribe a video and put the subtitles into it as a track of their own, with ffmpeg
+    Mux(Box<mux::Args>),
+
+    /// Write a transcript's outputs again with a person's corrections to its segments' text,
+    /// keeping their times, without decoding again
+    ApplyEdits(Box<apply_edits::Args>),
+
+    /// Join subtitle files of audio transcribed in parts, each moved to where its part starts
+    Merge(Box<merge::Args>),
+
+    /// Show the words that changed between two transcripts, and when
+    Diff(Box<diff::Args>),
+
+    /// Score a transcript against a correct one by word and character error rate
+    Eval(Box<eval::Args>),
+
+    /// Time loading a model and decoding with it at several thread counts
+    Bench(Box<bench::Args>),
 
-    let audio_data = parse_wav_file(audio_file_path);
-    let ingested_wav = whisper_rs::convert_integer_to_float_audio(&audio_data);
+    /// Check subtitle files against reading speed, line length and cue timing limits
+    Lint(Box<lint::Args>),
 
-    println!("{}", &model_path.to_string_lossy());
-    let ctx = WhisperContext::new(&model_path.to_string_lossy()).expect("Failed to load model");
+    /// Pull the segments that mention something out of a transcript, with those around them and
+    /// their times, ready to paste
+    Quotes(Box<quotes::Args>),
 
-    let params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    /// Search the transcripts that transcribe --store kept, showing each match in its context
+    Search(Box<search::Args>),
 
-    let mut state = ctx.create_state().expect("failed to create state");
-    state.full(params, &ingested_wav).expect("failed to run model");
+    /// Transcribe audio files as they're dropped into a directory, moving each out once it's done
+    Watch(Box<watch::Args>),
 
-    // fetch the results
-    let num_segments = state.full_n_segments().expect("failed to get number of segments");
+    /// Caption the microphone live, printing segments as they're decoded
+    #[cfg(feature = "listen")]
+    Listen(Box<listen::Args>),
 
-    println!("{}", num_segments);
-    
-    let mut srt_sequences: Vec<String> = Vec::new();
-    let mut timestamped_lines: Vec<String> = Vec::new();
+    /// Transcribe audio files in a full screen view of each file's progress and transcript,
+    /// which can be scrolled and searched as it comes in
+    #[cfg(feature = "tui")]
+    Tui(Box<tui::Args>),
 
-    for i in 0..num_segments {
-        let segment = state.full_get_segment_text(i).expect("failed to get segment");
-        let start_timestamp = state.full_get_segment_t0(i).expect("failed to get segment start timestamp");
-        let end_timestamp = state.full_get_segment_t1(i).expect("failed to get segment end timestamp");
+    /// Serve an OpenAI-compatible transcription API over HTTP, keeping the model loaded
+    Serve(Box<serve::Args>),
 
-        let srt_start_timestamp = segment_time_to_srt_time_string(start_timestamp);
-        let srt_end_timestamp = segment_time_to_srt_time_string(end_timestamp);
-        let srt_formatted: String = format!("{}\n{srt_start_timestamp} --> {srt_end_timestamp}\n{segment}\n\n", i+1);
-        srt_sequences.push(srt_formatted);
+    /// Keep a model loaded for `transcribe --via-daemon`, listening on a unix socket
+    #[cfg(unix)]
+    Daemon(Box<daemon::Args>),
 
-        let timestamped: String = format!("[{} - {}]: {}", start_timestamp, end_timestamp, segment);
-        println!("{}", timestamped);
-        timestamped_lines.push(format!("{}\n", timestamped));
+    /// Download, list and remove models from Hugging Face
+    Models {
+        #[command(subcommand)]
+        command: models::Command,
+    },
+
+    /// Print the completion script for a shell, e.g. `transcript completions bash >
+    /// /usr/share/bash-completion/completions/transcript`
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+
+    /// Print the man page, or write one for every subcommand as well into a directory
+    Manpage {
+        /// Directory for transcript.1 and a transcript-<subcommand>.1 for each subcommand
+        #[arg(long, value_name = "DIR")]
+        dir: Option<String>,
+    },
+}
+
+fn detect(model: Option<String>, audio_file_path_raw: &str, threads: usize, audio_options: &audio::Options) -> Result<(), Error> {
+    let model_path_raw = match model {
+        Some(model) => model,
+        None => models::discover()?,
+    };
+    let ingested_wav = transcribe::load_audio(audio_file_path_raw, audio_options)?;
+    let ctx = transcriber::load_model(&model_path_raw, WhisperContextParameters::default())?;
+    let (language, probability) = decode::detect_language(&ctx, &ingested_wav, threads)?;
+    println!("{}\t{:.4}", language, probability);
+    Ok(())
+}
+
+// our own messages at info, whisper.cpp's model loading chatter only from -v, everything at -vv
+fn init_logging(verbose: u8, quiet: bool) {
+    let targets = match (quiet, verbose) {
+        (true, _) => Targets::new().with_default(Level::ERROR),
+        (false, 0) => Targets::new().with_target("transcript", Level::INFO).with_default(Level::WARN),
+        (false, 1) => Targets::new().with_target("transcript", Level::DEBUG).with_target("whisper_rs", Level::INFO).with_default(Level::WARN),
+        (false, _) => Targets::new().with_default(Level::TRACE),
+    };
+    tracing_subscriber::registry().with(fmt::layer().with_writer(io::stderr).with_ansi(io::stderr().is_terminal()).without_time().with_target(verbose > 1)).with(targets).init();
+    whisper_rs::install_whisper_tracing_trampoline();
+}
+
+// every flag can also be set through a TRANSCRIPT_ variable named after it, --output-dir through
+// TRANSCRIPT_OUTPUT_DIR and so on; clap already prefers the command line over the environment
+fn with_env(arg: clap::Arg) -> clap::Arg {
+    let name = format!("TRANSCRIPT_{}", arg.get_id().as_str().to_uppercase());
+    arg.env(name)
+}
+
+fn command() -> clap::Command {
+    let mut command = Cli::command().mut_args(with_env);
+    let names: Vec<String> = command.get_subcommands().map(|subcommand| subcommand.get_name().to_string()).collect();
+    for name in names {
+        command = command.mut_subcommand(name, |subcommand| subcommand.mut_args(with_env));
     }
+    command
+}
 
-    write_to_file(Path::new(&format!("{}.txt", output_path_raw)), timestamped_lines);
-    write_to_file(Path::new(&format!("{}.srt", output_path_raw)), srt_sequences);
+// -v, -vv and -q can come before the subcommand
+fn is_logging_flag(arg: &str) -> bool {
+    match arg.strip_prefix('-') {
+        Some(flags) if !flags.starts_with('-') => !flags.is_empty() && flags.chars().all(|flag| flag == 'v' || flag == 'q'),
+        _ => arg == "--verbose" || arg == "--quiet",
+    }
+}
+
+// the transcribe flags used to sit on the top level, so anything that doesn't start with a
+// subcommand (or ask for help) is still a transcribe
+fn with_default_subcommand(command: &clap::Command, mut args: Vec<OsString>) -> Vec<OsString> {
+    let explicit = match args.iter().skip(1).filter_map(|arg| arg.to_str()).find(|arg| !is_logging_flag(arg)) {
+        Some("help" | "-h" | "--help" | "-V" | "--version") => true,
+        Some(name) => command.find_subcommand(name).is_some(),
+        None => false,
+    };
+    if !explicit {
+        args.insert(1, OsString::from("transcribe"));
+    }
+    args
+}
+
+fn main() -> ExitCode {
+    let started = Instant::now();
+    let command = command();
+    let matches = command.clone().get_matches_from(with_default_subcommand(&command, env::args_os().collect()));
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    // anything logged would be drawn over the screen
+    #[cfg(feature = "tui")]
+    let quiet = cli.quiet || matches!(cli.command, Command::Tui(_));
+    #[cfg(not(feature = "tui"))]
+    let quiet = cli.quiet;
+    init_logging(cli.verbose, quiet);
+    let result = match cli.command {
+        Command::Transcribe(args) => {
+            let matches = matches.subcommand_matches("transcribe").expect("transcribe was matched");
+            transcribe::run(*args, matches, started)
+        }
+        Command::Watch(args) => {
+            let matches = matches.subcommand_matches("watch").expect("watch was matched");
+            watch::run(*args, matches)
+        }
+        Command::Detect { model, input, threads, audio_options } => detect(model, &input, threads.unwrap_or_else(decode::default_threads), &audio_options).map(|()| ExitCode::SUCCESS),
+        Command::Speech(args) => speech::run(&args).map(|()| ExitCode::SUCCESS),
+        Command::Align(args) => align::run(*args).map(|()| ExitCode::SUCCESS),
+        Command::Convert(args) => convert::run(*args).map(|()| ExitCode::SUCCESS),
+        Command::Retime(args) => retime::run(*args).map(|()| ExitCode::SUCCESS),
+        Command::Quotes(args) => quotes::run(&args).map(|()| ExitCode::SUCCESS),
+        Command::Search(args) => search::run(&args).map(|()| ExitCode::SUCCESS),
+        Command::Mux(args) => mux::run(*args).map(|()| ExitCode::SUCCESS),
+        Command::ApplyEdits(args) => apply_edits::run(*args).map(|()| ExitCode::SUCCESS),
+        Command::Merge(args) => merge::run(*args).map(|()| ExitCode::SUCCESS),
+        Command::Diff(args) => diff::run(&args),
+        Command::Eval(args) => eval::run(*args).map(|()| ExitCode::SUCCESS),
+        Command::Bench(args) => bench::run(*args).map(|()| ExitCode::SUCCESS),
+        Command::Lint(args) => lint::run(&args),
+        #[cfg(feature = "listen")]
+        Command::Listen(args) => listen::run(*args).map(|()| ExitCode::SUCCESS),
+        #[cfg(feature = "tui")]
+        Command::Tui(args) => tui::run(*args).map(|()| ExitCode::SUCCESS),
+        Command::Serve(args) => serve::run(*args).map(|()| ExitCode::SUCCESS),
+        #[cfg(unix)]
+        Command::Daemon(args) => daemon::run(*args).map(|()| ExitCode::SUCCESS),
+        Command::Models { command } => models::run(&command).map(|()| ExitCode::SUCCESS),
+        Command::Completions { shell } => completions::completions(command, shell).map(|()| ExitCode::SUCCESS),
+        Command::Manpage { dir } => completions::manpage(command, dir.as_deref()).map(|()| ExitCode::SUCCESS),
+    };
+    match result {
+        Ok(code) => code,
+        Err(error) => {
+            tracing::error!("{}", error);
+            error.exit_code()
+        }
+    }
 }
 
 #[cfg(test)]
@@ -115,10 +301,22 @@ mod tests {
     use super::*;
 
     #[test]
-    fn _segment_time_to_srt_time_string() {
-        assert_eq!(segment_time_to_srt_time_string(1999), "00:00:19,990");
-        assert_eq!(segment_time_to_srt_time_string(838850), "02:19:48,500");
-        assert_eq!(segment_time_to_srt_time_string(5602555), "15:33:45,550");
-        assert_eq!(segment_time_to_srt_time_string(-4550), "00:00:00,000");
+    fn _command() {
+        command().debug_assert();
+    }
+
+    #[test]
+    fn _with_default_subcommand() {
+        let command = command();
+        let args = |args: &[&str]| -> Vec<String> {
+            with_default_subcommand(&command, args.iter().map(OsString::from).collect()).into_iter().map(|arg| arg.into_string().unwrap()).collect()
+        };
+        assert_eq!(args(&["transcript", "-m", "base.bin", "-i", "a.wav"]), ["transcript", "transcribe", "-m", "base.bin", "-i", "a.wav"]);
+        assert_eq!(args(&["transcript", "detect", "-m", "base.bin"]), ["transcript", "detect", "-m", "base.bin"]);
+        assert_eq!(args(&["transcript", "transcribe", "-i", "a.wav"]), ["transcript", "transcribe", "-i", "a.wav"]);
+        assert_eq!(args(&["transcript", "-vv", "detect"]), ["transcript", "-vv", "detect"]);
+        assert_eq!(args(&["transcript", "-v", "-i", "a.wav"]), ["transcript", "transcribe", "-v", "-i", "a.wav"]);
+        assert_eq!(args(&["transcript", "--help"]), ["transcript", "--help"]);
+        assert_eq!(args(&["transcript"]), ["transcript", "transcribe"]);
     }
 }
@@ -0,0 +1,86 @@
+use transcript::error::Error;
+use transcript::output::{self, parse, Format};
+use transcript::{audio, Transcript};
+
+use crate::convert;
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Files to join, in order, each with where its audio starts in the whole as part2.srt@00:45:00;
+    /// a part without a time follows on from the end of the one before it
+    #[arg(required = true, value_name = "FILE[@TIME]", value_parser = parse_part)]
+    parts: Vec<Part>,
+
+    /// Format to write them in, the first part's own by default
+    #[arg(long, value_enum)]
+    to: Option<Format>,
+
+    /// Output path without an extension; without one the merged file is printed to stdout
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Replace an output file that already exists instead of refusing to run
+    #[arg(long)]
+    overwrite: bool,
+
+    /// Language the transcript is in, as for convert
+    #[arg(short, long, default_value = "en")]
+    language: String,
+
+    #[command(flatten)]
+    output_options: output::Options,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Part {
+    path: String,
+    // ms
+    offset: Option<u64>,
+}
+
+// the time is after the last @, so a path with an @ of its own still works when given one
+fn parse_part(value: &str) -> Result<Part, String> {
+    match value.rsplit_once('@') {
+        Some((path, offset)) if parse::format(path).is_some() => Ok(Part { path: path.to_string(), offset: Some(audio::parse_time(offset)?) }),
+        _ => Ok(Part { path: value.to_string(), offset: None }),
+    }
+}
+
+// every part's cues moved along by its offset and put one after another; the formats number the
+// cues as they write them
+fn merge(parts: Vec<(Transcript, Option<u64>)>) -> Transcript {
+    let mut merged = Transcript { segments: Vec::new() };
+    for (mut transcript, offset) in parts {
+        let offset_ms = offset.map_or_else(|| merged.segments.iter().map(|segment| segment.end_ms).max().unwrap_or(0), |offset| offset as i64);
+        transcript.shift(offset_ms);
+        merged.segments.extend(transcript.segments);
+    }
+    merged.segments.sort_by_key(|segment| segment.start_ms);
+    merged
+}
+
+pub fn run(args: Args) -> Result<(), Error> {
+    let parts = args.parts.iter().map(|part| Ok((parse::read(&part.path)?, part.offset))).collect::<Result<Vec<_>, Error>>()?;
+    let mut merged = merge(parts);
+    merged.shift(args.output_options.shift);
+    let format = args.to.or_else(|| parse::format(&args.parts[0].path)).expect("the first part was read, so its format is known");
+    convert::write(&merged, format, args.output.as_deref(), args.overwrite, &args.language, args.output_options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transcript::Segment;
+
+    #[test]
+    fn _merge() {
+        assert_eq!(parse_part("part2.srt@00:45:00"), Ok(Part { path: "part2.srt".to_string(), offset: Some(2_700_000) }));
+        assert_eq!(parse_part("me@home.vtt"), Ok(Part { path: "me@home.vtt".to_string(), offset: None }));
+        assert!(parse_part("part2.srt@soon").is_err());
+
+        let part = |text: &str| Transcript { segments: vec![Segment::new(0, 1000, text), Segment::new(2000, 3000, text)] };
+        let merged = merge(vec![(part(" one"), Some(0)), (part(" two"), Some(60_000)), (part(" three"), None)]);
+        let times: Vec<(i64, &str)> = merged.segments.iter().map(|segment| (segment.start_ms, segment.text.as_str())).collect();
+        assert_eq!(times, vec![(0, " one"), (2000, " one"), (60_000, " two"), (62_000, " two"), (63_000, " three"), (65_000, " three")]);
+    }
+}
@@ -0,0 +1,69 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use transcript::error::Error;
+use transcript::{models, output, storage};
+
+// --meta: how a transcript was made, written beside its outputs for anyone who has to make it
+// again or account for it
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Meta {
+    pub input: String,
+    pub input_sha256: Option<String>,
+    pub duration_ms: u64,
+    pub model: Option<String>,
+    pub model_sha256: Option<String>,
+    // the language spoken, as given or detected, even when it was translated
+    pub language: String,
+    // the command line
+    pub parameters: Vec<String>,
+    pub version: String,
+    pub wall_seconds: f64,
+}
+
+// a local file's, none for stdin or a url
+pub fn sha256(path: &str) -> Option<String> {
+    if storage::is_remote(path) || !Path::new(path).is_file() {
+        return None;
+    }
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path).ok()?, &mut hasher).ok()?;
+    Some(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+// a run has the one model, hashed the first time it's asked for however many files there are
+pub fn model_sha256(model: &str) -> Option<String> {
+    static HASH: OnceLock<Option<String>> = OnceLock::new();
+    HASH.get_or_init(|| sha256(&models::resolve(model).to_string_lossy())).clone()
+}
+
+// as <output>.meta.json
+pub fn write(output_path_raw: &str, meta: &Meta) -> Result<(), Error> {
+    let path = format!("{}.meta.json", output_path_raw);
+    let contents = serde_json::to_string_pretty(meta).expect("failed to serialize metadata");
+    if storage::is_remote(&path) {
+        storage::put(&path, contents)
+    } else {
+        output::write_to_file(Path::new(&path), &contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _sha256() {
+        let path = std::env::temp_dir().join(format!("transcript-meta-test-{}.txt", std::process::id()));
+        std::fs::write(&path, "abc").unwrap();
+        assert_eq!(sha256(path.to_str().unwrap()).as_deref(), Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(sha256(path.to_str().unwrap()), None);
+        assert_eq!(sha256("-"), None);
+    }
+}
@@ -0,0 +1,225 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek};
+use std::path::Path;
+
+// "ggml" read as a little endian u32
+const GGML_MAGIC: u32 = 0x6767_6d6c;
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+// whisper.cpp folds the quantization version into ftype as version * 1000 + ftype
+const QUANTIZATION_VERSION_FACTOR: i32 = 1000;
+// multilingual models add the language tokens on top of gpt-2's vocabulary
+const MULTILINGUAL_VOCAB: i32 = 51865;
+
+#[derive(Debug, PartialEq)]
+pub struct Info {
+    pub vocab: i32,
+    pub audio_state: i32,
+    pub audio_layers: i32,
+    pub text_layers: i32,
+    pub mels: i32,
+    pub ftype: i32,
+    pub tensors: usize,
+    pub size: u64,
+}
+
+impl Info {
+    pub fn multilingual(&self) -> bool {
+        self.vocab >= MULTILINGUAL_VOCAB
+    }
+
+    // the openai size the layer counts match
+    pub fn kind(&self) -> String {
+        let size = match (self.audio_layers, self.text_layers) {
+            (4, _) => "tiny",
+            (6, _) => "base",
+            (12, _) => "small",
+            (24, _) => "medium",
+            (32, 4) => "large-v3-turbo",
+            (32, _) if self.mels == 128 => "large-v3",
+            (32, _) => "large",
+            _ => return format!("unknown ({} audio layers, {} text layers)", self.audio_layers, self.text_layers),
+        };
+        if self.multilingual() {
+            size.to_string()
+        } else {
+            format!("{}.en", size)
+        }
+    }
+
+    pub fn quantization(&self) -> String {
+        match self.ftype % QUANTIZATION_VERSION_FACTOR {
+            0 => "f32".to_string(),
+            1 => "f16".to_string(),
+            2 => "q4_0".to_string(),
+            3 => "q4_1".to_string(),
+            7 => "q8_0".to_string(),
+            8 => "q5_0".to_string(),
+            9 => "q5_1".to_string(),
+            10 => "q2_k".to_string(),
+            11 => "q3_k".to_string(),
+            12 => "q4_k".to_string(),
+            13 => "q5_k".to_string(),
+            14 => "q6_k".to_string(),
+            ftype => format!("unknown ({})", ftype),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let language = if self.multilingual() { "multilingual" } else { "English only" };
+        format!(
+            "format        ggml\ntype          {} ({})\nquantization  {}\nvocabulary    {} tokens\nmel bands     {}\nlayers        {} audio, {} text\nwidth         {}\ntensors       {}\nsize          {} MB",
+            self.kind(),
+            language,
+            self.quantization(),
+            self.vocab,
+            self.mels,
+            self.audio_layers,
+            self.text_layers,
+            self.audio_state,
+            self.tensors,
+            self.size / 1_000_000
+        )
+    }
+}
+
+// bytes taken by count elements of a ggml tensor type, quantized types go in blocks
+fn data_size(ttype: i32, count: u64) -> Option<u64> {
+    let (block, bytes) = match ttype {
+        0 => (1, 4),
+        1 => (1, 2),
+        2 => (32, 18),
+        3 => (32, 20),
+        6 => (32, 22),
+        7 => (32, 24),
+        8 => (32, 34),
+        9 => (32, 36),
+        10 => (256, 84),
+        11 => (256, 110),
+        12 => (256, 144),
+        13 => (256, 176),
+        14 => (256, 210),
+        15 => (256, 292),
+        _ => return None,
+    };
+    Some(count.div_ceil(block) * bytes)
+}
+
+fn i32_from(reader: &mut impl Read) -> io::Result<i32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(i32::from_le_bytes(bytes))
+}
+
+fn truncated(error: io::Error) -> String {
+    if error.kind() == io::ErrorKind::UnexpectedEof {
+        "the file is truncated, download it again".to_string()
+    } else {
+        error.to_string()
+    }
+}
+
+// walks the whole layout whisper.cpp loads, skipping over the tensor data, so a short file shows
+// up here rather than as a failure partway through loading
+pub fn parse<R: Read + Seek>(reader: &mut R, size: u64) -> Result<Info, String> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic).map_err(truncated)?;
+    if &magic == GGUF_MAGIC {
+        return Err("this is a gguf file, whisper.cpp loads ggml .bin models".to_string());
+    }
+    if u32::from_le_bytes(magic) != GGML_MAGIC {
+        return Err("not a ggml model, the file doesn't start with the ggml magic".to_string());
+    }
+    let mut hparams = [0i32; 11];
+    for value in &mut hparams {
+        *value = i32_from(reader).map_err(truncated)?;
+    }
+    let [vocab, _audio_ctx, audio_state, _audio_head, audio_layers, _text_ctx, _text_state, _text_head, text_layers, mels, ftype] = hparams;
+
+    let filter_mels = i32_from(reader).map_err(truncated)?;
+    let filter_fft = i32_from(reader).map_err(truncated)?;
+    skip(reader, 4 * filter_mels.max(0) as i64 * filter_fft.max(0) as i64, size)?;
+
+    let words = i32_from(reader).map_err(truncated)?;
+    for _ in 0..words {
+        let length = i32_from(reader).map_err(truncated)?;
+        skip(reader, length.max(0) as i64, size)?;
+    }
+
+    let mut tensors = 0;
+    loop {
+        let mut header = [0u8; 4];
+        match reader.read(&mut header).map_err(truncated)? {
+            0 => break,
+            4 => {}
+            read => reader.read_exact(&mut header[read..]).map_err(truncated)?,
+        }
+        let dimensions = i32::from_le_bytes(header);
+        let name_length = i32_from(reader).map_err(truncated)?;
+        let ttype = i32_from(reader).map_err(truncated)?;
+        if !(1..=4).contains(&dimensions) {
+            return Err(format!("tensor {} has {} dimensions, the file is corrupt", tensors, dimensions));
+        }
+        let mut count: u64 = 1;
+        for _ in 0..dimensions {
+            count *= i32_from(reader).map_err(truncated)?.max(0) as u64;
+        }
+        let mut name = vec![0u8; name_length.max(0) as usize];
+        reader.read_exact(&mut name).map_err(truncated)?;
+        let bytes = data_size(ttype, count).ok_or_else(|| format!("tensor {} has unknown type {}", String::from_utf8_lossy(&name), ttype))?;
+        skip(reader, bytes as i64, size)?;
+        tensors += 1;
+    }
+    Ok(Info { vocab, audio_state, audio_layers, text_layers, mels, ftype, tensors, size })
+}
+
+// seeking past the end succeeds, so the position is checked against the file size instead
+fn skip<R: Read + Seek>(reader: &mut R, bytes: i64, size: u64) -> Result<(), String> {
+    let position = reader.seek(io::SeekFrom::Current(bytes)).map_err(truncated)?;
+    if position > size {
+        return Err(truncated(io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(())
+}
+
+pub fn read(path: &Path) -> Result<Info, String> {
+    let file = File::open(path).map_err(|error| error.to_string())?;
+    let size = file.metadata().map_err(|error| error.to_string())?.len();
+    parse(&mut BufReader::new(file), size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn model(vocab: i32, tensor_type: i32, elements: i32) -> Vec<u8> {
+        let mut bytes = GGML_MAGIC.to_le_bytes().to_vec();
+        for value in [vocab, 1500, 384, 6, 4, 448, 384, 6, 4, 80, 1001, 2, 3] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend([0u8; 24]);
+        bytes.extend(1i32.to_le_bytes());
+        bytes.extend(2i32.to_le_bytes());
+        bytes.extend(b"hi");
+        for value in [1, 1, tensor_type, elements] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.push(b'w');
+        bytes.extend(vec![0u8; data_size(tensor_type, elements as u64).unwrap() as usize]);
+        bytes
+    }
+
+    #[test]
+    fn _parse() {
+        let bytes = model(51865, 1, 10);
+        let info = parse(&mut Cursor::new(&bytes), bytes.len() as u64).unwrap();
+        assert_eq!((info.kind(), info.quantization(), info.tensors), ("tiny".to_string(), "f16".to_string(), 1));
+
+        let bytes = model(51864, 8, 64);
+        let info = parse(&mut Cursor::new(&bytes), bytes.len() as u64).unwrap();
+        assert_eq!(info.kind(), "tiny.en");
+        assert!(parse(&mut Cursor::new(&bytes[..bytes.len() - 1]), bytes.len() as u64 - 1).unwrap_err().contains("truncated"));
+        assert!(parse(&mut Cursor::new(b"GGUF\x03\0\0\0"), 8).unwrap_err().contains("gguf"));
+        assert!(parse(&mut Cursor::new(b"RIFF"), 4).unwrap_err().contains("magic"));
+    }
+}
@@ -0,0 +1,277 @@
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Subcommand;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::audio::http;
+use crate::error::Error;
+
+pub mod ggml;
+
+// whisper.cpp's own conversions, every model is ggml-<name>.bin at the top of the repo
+const REPOSITORY: &str = "ggerganov/whisper.cpp";
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Download models by name (tiny, base.en, large-v3, ...) into the cache
+    Pull {
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    /// Show the models found in $TRANSCRIPT_MODEL_DIR, the cache and ./models
+    List {
+        /// List the models that can be pulled instead
+        #[arg(long)]
+        available: bool,
+    },
+    /// Delete downloaded models
+    Rm {
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    /// Check a model file and print its type, quantization and vocabulary
+    Info {
+        /// Model file or name
+        model: String,
+    },
+}
+
+// one file in the repository listing, lfs files carry their sha256 as the oid
+#[derive(Deserialize, Debug)]
+struct Entry {
+    path: String,
+    lfs: Option<Lfs>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Lfs {
+    oid: String,
+    size: u64,
+}
+
+// HF_ENDPOINT points at a mirror, as it does for the huggingface tooling
+fn endpoint() -> String {
+    env::var("HF_ENDPOINT").ok().filter(|endpoint| !endpoint.is_empty()).unwrap_or_else(|| "https://huggingface.co".to_string()).trim_end_matches('/').to_string()
+}
+
+// $XDG_CACHE_HOME/transcript/models, falling back to ~/.cache
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+    };
+    Some(base.join("transcript").join("models"))
+}
+
+fn file_name(name: &str) -> String {
+    format!("ggml-{}.bin", name)
+}
+
+fn name(file_name: &str) -> Option<&str> {
+    file_name.strip_prefix("ggml-")?.strip_suffix(".bin").filter(|name| !name.is_empty())
+}
+
+// a bare name like base.en rather than a path
+fn is_name(model: &str) -> bool {
+    !model.is_empty() && !model.contains(['/', '\\']) && !model.ends_with(".bin")
+}
+
+// names people reach for that whisper.cpp's files don't use
+fn alias(name: &str) -> &str {
+    match name {
+        "large" => "large-v3",
+        "turbo" => "large-v3-turbo",
+        name => name,
+    }
+}
+
+// where models given by name are looked for, in order
+pub fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = env::var_os("TRANSCRIPT_MODEL_DIR").filter(|dir| !dir.is_empty()).map(PathBuf::from).into_iter().collect();
+    dirs.extend(cache_dir());
+    dirs.push(PathBuf::from("./models"));
+    dirs
+}
+
+fn search_dirs_list() -> String {
+    search_dirs().iter().map(|dir| dir.display().to_string()).collect::<Vec<_>>().join(", ")
+}
+
+// paths are taken as they are, a name is looked for as ggml-<name>.bin or <name>.bin
+pub fn resolve(model: &str) -> PathBuf {
+    if Path::new(model).exists() || !is_name(model) {
+        return PathBuf::from(model);
+    }
+    let name = alias(model);
+    search_dirs()
+        .into_iter()
+        .flat_map(|dir| [dir.join(file_name(name)), dir.join(format!("{}.bin", name))])
+        .find(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(model))
+}
+
+// what load failures suggest when the model was given by name
+pub fn hint(model: &str) -> Option<String> {
+    is_name(model).then(|| format!("it isn't in {} either, `transcript models pull {}` downloads it", search_dirs_list(), alias(model)))
+}
+
+// without a --model, the one model in the search directories
+pub fn discover() -> Result<String, Error> {
+    let mut found: Vec<Installed> = Vec::new();
+    for dir in search_dirs() {
+        for model in installed(&dir)? {
+            if !found.iter().any(|other| other.name == model.name) {
+                found.push(model);
+            }
+        }
+    }
+    match found.as_slice() {
+        [] => Err(Error::Model(format!("no --model given and none found in {}, `transcript models pull base.en` downloads one", search_dirs_list()))),
+        [model] => {
+            tracing::debug!("using {}, the only model found", model.path.display());
+            Ok(model.path.display().to_string())
+        }
+        _ => {
+            let names: Vec<&str> = found.iter().map(|model| model.name.as_str()).collect();
+            Err(Error::Model(format!("no --model given and several found ({}), pick one", names.join(", "))))
+        }
+    }
+}
+
+fn cache() -> Result<PathBuf, Error> {
+    cache_dir().ok_or_else(|| Error::Model("no cache directory, HOME isn't set".to_string()))
+}
+
+fn available() -> Result<Vec<(String, Lfs)>, Error> {
+    let url = format!("{}/api/models/{}/tree/main", endpoint(), REPOSITORY);
+    let failed = |error: &dyn std::fmt::Display| Error::model(format!("failed to list {}", url), error);
+    let listing = ureq::get(&url).call().map_err(|error| failed(&http::describe(error)))?.into_string().map_err(|error| failed(&error))?;
+    let entries: Vec<Entry> = serde_json::from_str(&listing).map_err(|error| failed(&error))?;
+    Ok(entries.into_iter().filter_map(|entry| Some((name(&entry.path)?.to_string(), entry.lfs?))).collect())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        write!(hex, "{:02x}", byte).expect("writing to a string can't fail");
+        hex
+    })
+}
+
+// downloaded beside the destination and only renamed into place once the checksum matches
+fn pull(name: &str, models: &[(String, Lfs)], dir: &Path) -> Result<PathBuf, Error> {
+    let (_, lfs) = models.iter().find(|(model, _)| model == name).ok_or_else(|| {
+        let names: Vec<&str> = models.iter().map(|(model, _)| model.as_str()).collect();
+        Error::Model(format!("no model named {}, pick one of {}", name, names.join(", ")))
+    })?;
+    let path = dir.join(file_name(name));
+    let partial = dir.join(format!(".{}.part", file_name(name)));
+    let url = format!("{}/{}/resolve/main/{}", endpoint(), REPOSITORY, file_name(name));
+    let mut hasher = Sha256::new();
+    http::save(&url, &partial, &file_name(name), |chunk| hasher.update(chunk)).map_err(|error| Error::model(format!("failed to download {}", url), error))?;
+    let checksum = hex(&hasher.finalize());
+    if checksum != lfs.oid {
+        let _ = fs::remove_file(&partial);
+        return Err(Error::Model(format!("{} is corrupt, its sha256 is {} rather than {}", url, checksum, lfs.oid)));
+    }
+    fs::rename(&partial, &path).map_err(|error| Error::model(format!("could not write {}", path.display()), error))?;
+    Ok(path)
+}
+
+struct Installed {
+    name: String,
+    path: PathBuf,
+    size: u64,
+}
+
+fn installed(dir: &Path) -> Result<Vec<Installed>, Error> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(Error::model(format!("failed to read {}", dir.display()), error)),
+    };
+    let mut models: Vec<Installed> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            // the same files resolve finds, ggml-<name>.bin or <name>.bin
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let name = name(&file_name).or_else(|| file_name.strip_suffix(".bin")).filter(|name| !name.is_empty() && !name.starts_with('.'))?.to_string();
+            Some(Installed { name, path: entry.path(), size: entry.metadata().ok()?.len() })
+        })
+        .collect();
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(models)
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{} MB", bytes / 1_000_000)
+}
+
+// the model file checked over before whisper.cpp gets it, so a bad download fails with a reason
+pub fn inspect(model: &str) -> Result<ggml::Info, Error> {
+    let path = resolve(model);
+    if !path.exists() {
+        return Err(Error::Model(match hint(model) {
+            Some(hint) => format!("model {} does not exist, {}", model, hint),
+            None => format!("model {} does not exist", model),
+        }));
+    }
+    ggml::read(&path).map_err(|error| Error::model(format!("model {} can't be loaded", path.display()), error))
+}
+
+pub fn run(command: &Command) -> Result<(), Error> {
+    match command {
+        Command::Pull { names } => {
+            let dir = cache()?;
+            let models = available()?;
+            fs::create_dir_all(&dir).map_err(|error| Error::model(format!("failed to create {}", dir.display()), error))?;
+            for name in names {
+                tracing::info!("pulling {}", name);
+                let path = pull(alias(name), &models, &dir)?;
+                println!("{}", path.display());
+            }
+        }
+        Command::List { available: false } => {
+            for dir in search_dirs() {
+                for model in installed(&dir)? {
+                    println!("{}\t{}\t{}", model.name, megabytes(model.size), model.path.display());
+                }
+            }
+        }
+        Command::List { available: true } => {
+            for (name, lfs) in available()? {
+                println!("{}\t{}", name, megabytes(lfs.size));
+            }
+        }
+        Command::Rm { names } => {
+            let dir = cache()?;
+            for name in names {
+                let path = dir.join(file_name(alias(name)));
+                fs::remove_file(&path).map_err(|error| Error::model(format!("failed to remove {}", name), error))?;
+            }
+        }
+        Command::Info { model } => println!("{}", inspect(model)?.render()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _name() {
+        assert_eq!(name(&file_name("base.en")), Some("base.en"));
+        assert_eq!(name("ggml-large-v3-q5_0.bin"), Some("large-v3-q5_0"));
+        assert_eq!(name("for-tests-ggml-tiny.bin"), None);
+        assert_eq!(name("ggml-.bin"), None);
+        assert!(is_name("large-v3"));
+        assert!(!is_name("models/ggml-base.bin"));
+        assert!(!is_name("ggml-base.bin"));
+        assert_eq!(alias("large"), "large-v3");
+        assert_eq!(alias("base.en"), "base.en");
+    }
+}
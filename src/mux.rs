@@ -0,0 +1,169 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use clap::ValueEnum;
+
+use transcript::error::Error;
+use transcript::output::{self, Format};
+use transcript::{audio, decode, models, Transcriber};
+
+use crate::{embed, interrupt};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Model file or name, as for transcribe
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Video to transcribe and put the subtitles into
+    #[arg(long)]
+    video: String,
+
+    /// Video to write, with every stream of the input and the subtitles as its first subtitle
+    /// track; defaults to <video>.subtitled beside it, in the same container
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Subtitle format of the new track; mp4 and mov get it as mov_text and webm as webvtt
+    #[arg(long, value_enum, default_value_t = Subtitles::Srt)]
+    subtitles: Subtitles,
+
+    /// Replace the output video if it already exists instead of refusing to run
+    #[arg(long)]
+    overwrite: bool,
+
+    #[command(flatten)]
+    decode_options: decode::Options,
+
+    #[command(flatten)]
+    audio_options: audio::Options,
+
+    #[command(flatten)]
+    output_options: output::Options,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Subtitles {
+    Srt,
+    Ass,
+    Vtt,
+}
+
+impl Subtitles {
+    fn format(self) -> Format {
+        match self {
+            Subtitles::Srt => Format::Srt,
+            Subtitles::Ass => Format::Ass,
+            Subtitles::Vtt => Format::Vtt,
+        }
+    }
+}
+
+fn extension(path: &str) -> String {
+    Path::new(path).extension().and_then(|extension| extension.to_str()).unwrap_or("").to_ascii_lowercase()
+}
+
+// the input's name with .subtitled before its extension
+fn default_output(video: &str) -> String {
+    let path = Path::new(video);
+    let stem = path.file_stem().map_or_else(|| video.to_string(), |stem| stem.to_string_lossy().into_owned());
+    let name = match path.extension() {
+        Some(extension) => format!("{}.subtitled.{}", stem, extension.to_string_lossy()),
+        None => format!("{}.subtitled", stem),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+// what the container can hold the new track as, the others' tracks copied as they are
+fn codec(output: &str) -> &'static str {
+    match extension(output).as_str() {
+        "mp4" | "m4v" | "mov" => "mov_text",
+        "webm" => "webvtt",
+        _ => "copy",
+    }
+}
+
+// the new track goes first among the subtitles, so it's s:0 whatever the input already has
+fn ffmpeg_args(video: &str, subtitles: &Path, output: &str, language: &str) -> Vec<String> {
+    let mut args: Vec<String> = ["-nostdin", "-loglevel", "error", "-y", "-i", video, "-i"].iter().map(|arg| arg.to_string()).collect();
+    args.push(subtitles.to_string_lossy().into_owned());
+    for map in ["0:v?", "0:a?", "1:0", "0:s?", "0:d?", "0:t?"] {
+        args.extend(["-map".to_string(), map.to_string()]);
+    }
+    let language = format!("language={}", embed::language_code(language));
+    let new = ["-c", "copy", "-c:s:0", codec(output), "-metadata:s:s:0", &language, "-disposition:s:0", "default", output];
+    args.extend(new.iter().map(|arg| arg.to_string()));
+    args
+}
+
+fn mux(video: &str, subtitles: &Path, output: &str, language: &str) -> Result<(), Error> {
+    let status = Command::new("ffmpeg")
+        .args(ffmpeg_args(video, subtitles, output, language))
+        .stdout(Stdio::null())
+        .status()
+        .map_err(|error| Error::output("failed to run ffmpeg, which mux needs", error))?;
+    if !status.success() {
+        return Err(Error::Output(format!("ffmpeg could not put the subtitles into {}", output)));
+    }
+    Ok(())
+}
+
+// transcribed as transcribe would, laid out as one of the subtitle formats and handed to ffmpeg
+// with the video, whose streams are copied rather than encoded again
+pub fn run(args: Args) -> Result<(), Error> {
+    let output = args.output.clone().unwrap_or_else(|| default_output(&args.video));
+    if Path::new(&output) == Path::new(&args.video) {
+        return Err(Error::Output(format!("{} is the input video, give another --output", output)));
+    }
+    if !args.overwrite && Path::new(&output).exists() {
+        return Err(Error::Output(format!("{} already exists, pass --overwrite to replace it", output)));
+    }
+    // found out before the decode rather than after it
+    if !audio::ffmpeg::available() {
+        return Err(Error::Output("mux needs ffmpeg, which isn't on the PATH".to_string()));
+    }
+    let model = match args.model {
+        Some(model) => model,
+        None => models::discover()?,
+    };
+    let mut transcriber = Transcriber::new(model).options(args.decode_options).audio_options(args.audio_options);
+    interrupt::install(transcriber.cancellation_token());
+    let samples = transcriber.load_audio(&args.video)?;
+    if transcriber.decode_options().language.as_deref() == Some("auto") {
+        let (language, probability) = transcriber.detect_language(&samples)?;
+        tracing::info!("detected language {} ({:.2})", language, probability);
+        transcriber = transcriber.language(language);
+    }
+    let language = transcriber.decode_options().output_language().to_string();
+    let mut transcript = transcriber.transcribe(&samples)?;
+    if transcriber.cancellation_token().is_cancelled() {
+        return Err(Error::Interrupted(format!("interrupted, {} was left as it was", args.video)));
+    }
+    transcript.shift(args.output_options.shift);
+
+    let output_options = output::Options { language: Some(language.clone()), ..args.output_options };
+    let format = args.subtitles.format();
+    let subtitles = env::temp_dir().join(format!("transcript-mux-{}.{}", std::process::id(), format.extension()));
+    fs::write(&subtitles, format.render(&transcript, &output_options)).map_err(|error| Error::output("failed to write the subtitles for ffmpeg", error))?;
+    tracing::info!("muxing {} subtitles in {} into {}", format.extension(), language, output);
+    let muxed = mux(&args.video, &subtitles, &output, &language);
+    let _ = fs::remove_file(&subtitles);
+    muxed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _ffmpeg_args() {
+        assert_eq!(default_output("talks/film.mkv"), "talks/film.subtitled.mkv");
+        let args = ffmpeg_args("film.mkv", Path::new("/tmp/subs.srt"), "out.mp4", "de");
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        assert_eq!(args[..7], ["-nostdin", "-loglevel", "error", "-y", "-i", "film.mkv", "-i"]);
+        assert_eq!(args[args.len() - 9..], ["-c", "copy", "-c:s:0", "mov_text", "-metadata:s:s:0", "language=deu", "-disposition:s:0", "default", "out.mp4"]);
+        assert_eq!(codec("out.MKV"), "copy");
+    }
+}
@@ -0,0 +1,96 @@
+use std::cmp;
+
+use super::Options;
+use crate::transcript::Transcript;
+
+pub fn parse_color(value: &str) -> Result<u32, String> {
+    let hex = value.trim_start_matches('#');
+    if hex.len() != 6 {
+        return Err(format!("expected a RRGGBB colour, got {value}"));
+    }
+    u32::from_str_radix(hex, 16).map_err(|_| format!("expected a RRGGBB colour, got {value}"))
+}
+
+// ASS stores colours as &HAABBGGRR with alpha 00 being opaque
+fn color(rgb: u32) -> String {
+    let r = (rgb >> 16) & 0xFF;
+    let g = (rgb >> 8) & 0xFF;
+    let b = rgb & 0xFF;
+    format!("&H00{:02X}{:02X}{:02X}", b, g, r)
+}
+
+pub fn timestamp(ms: i64) -> String {
+    let centis = cmp::max(0, ms) / 10;
+    let hundredths = centis % 100;
+    let seconds = (centis / 100) % 60;
+    let minutes = (centis / 100 / 60) % 60;
+    let hours = centis / 100 / 60 / 60;
+    format!("{}:{:02}:{:02}.{:02}", hours, minutes, seconds, hundredths)
+}
+
+pub fn header(options: &Options) -> String {
+    let mut header = String::new();
+    header.push_str("[Script Info]\n");
+    header.push_str("ScriptType: v4.00+\n");
+    header.push_str("PlayResX: 384\n");
+    header.push_str("PlayResY: 288\n");
+    header.push_str("WrapStyle: 0\n");
+    header.push_str("ScaledBorderAndShadow: yes\n\n");
+
+    header.push_str("[V4+ Styles]\n");
+    header.push_str("Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n");
+    header.push_str(&format!(
+        "Style: Default,{},{},{},{},{},{},0,0,0,0,100,100,0,0,1,{},0,2,10,10,10,1\n\n",
+        options.ass_font,
+        options.ass_font_size,
+        color(options.ass_primary_color),
+        color(options.ass_secondary_color),
+        color(options.ass_outline_color),
+        color(options.ass_back_color),
+        options.ass_outline,
+    ));
+
+    header.push_str("[Events]\n");
+    header.push_str("Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n");
+    header
+}
+
+pub fn dialogue(start_ms: i64, end_ms: i64, text: &str) -> String {
+    format!("Dialogue: 0,{},{},Default,,0,0,0,,{}\n", timestamp(start_ms), timestamp(end_ms), text)
+}
+
+// braces open and close override tags, so text with them in is escaped to show them as they are
+pub fn escape(text: &str) -> String {
+    text.trim().replace('{', "\\{").replace('}', "\\}").replace('\n', "\\N")
+}
+
+pub fn render(transcript: &Transcript, options: &Options) -> String {
+    let mut ass = header(options);
+    for segment in &transcript.segments {
+        ass.push_str(&dialogue(segment.start_ms, segment.end_ms, &escape(&super::display_text(segment))));
+    }
+    ass
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _timestamp() {
+        assert_eq!(timestamp(19990), "0:00:19.99");
+        assert_eq!(timestamp(8388500), "2:19:48.50");
+        assert_eq!(timestamp(-100), "0:00:00.00");
+    }
+
+    #[test]
+    fn _escape() {
+        assert_eq!(escape(" {sic} two\nlines "), "\\{sic\\} two\\Nlines");
+    }
+
+    #[test]
+    fn _color() {
+        assert_eq!(parse_color("#FF8000").map(color), Ok("&H000080FF".to_string()));
+        assert!(parse_color("FFF").is_err());
+    }
+}
@@ -0,0 +1,29 @@
+use crate::transcript::Transcript;
+
+fn seconds(ms: i64) -> String {
+    format!("{:.6}", ms as f64 / 1000.0)
+}
+
+// label tracks are tab separated and can't contain line breaks
+pub fn render(transcript: &Transcript) -> String {
+    transcript
+        .segments
+        .iter()
+        .map(|segment| {
+            let text = super::display_text(segment).trim().replace(['\t', '\n'], " ");
+            format!("{}\t{}\t{}\n", seconds(segment.start_ms), seconds(segment.end_ms), text)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _render() {
+        let transcript = Transcript { segments: vec![Segment::new(1250, 2000, " Hello\tthere")] };
+        assert_eq!(render(&transcript), "1.250000\t2.000000\tHello there\n");
+    }
+}
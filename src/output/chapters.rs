@@ -0,0 +1,152 @@
+use clap::{Args, FromArgMatches};
+
+use crate::audio;
+use crate::transcript::Transcript;
+
+#[derive(Args, Debug, Clone)]
+#[group(id = "chapter_options")]
+pub struct Options {
+    /// Silence that can end a chapter, as 3s or 1500ms
+    #[arg(long, default_value = "3s", value_name = "TIME", value_parser = audio::parse_time, help_heading = "Chapters")]
+    pub chapter_pause: u64,
+
+    /// Shortest a chapter can be; a pause before then doesn't end it
+    #[arg(long, default_value = "1m", value_name = "TIME", value_parser = audio::parse_time, help_heading = "Chapters")]
+    pub chapter_min_duration: u64,
+
+    /// End a chapter at the next cue once it has run this long, pause or not
+    #[arg(long, value_name = "TIME", value_parser = audio::parse_time, help_heading = "Chapters")]
+    pub chapter_max_duration: Option<u64>,
+
+    /// End a chapter at the next pause once it has this many cues, however short it is
+    #[arg(long, value_name = "CUES", value_parser = clap::value_parser!(u32).range(1..), help_heading = "Chapters")]
+    pub chapter_max_cues: Option<u32>,
+}
+
+// the clap defaults, as for output::Options
+impl Default for Options {
+    fn default() -> Options {
+        let matches = Options::augment_args(clap::Command::new("options")).get_matches_from(["options"]);
+        Options::from_arg_matches(&matches).expect("option defaults are invalid")
+    }
+}
+
+// titles are the start of the chapter's first sentence, cut short at a word
+const TITLE_CHARS: usize = 60;
+
+#[derive(Debug, PartialEq)]
+pub struct Chapter {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub title: String,
+}
+
+fn title(text: &str) -> String {
+    let text = text.trim();
+    let sentence = text.find(['.', '?', '!']).map_or(text, |end| &text[..end]).trim();
+    if sentence.chars().count() <= TITLE_CHARS {
+        return sentence.to_string();
+    }
+    let cut: String = sentence.chars().take(TITLE_CHARS).collect();
+    let cut = cut.rfind(' ').map_or(cut.as_str(), |space| &cut[..space]).trim_end_matches([',', ';', ':']);
+    format!("{}…", cut)
+}
+
+// a chapter ends at a long enough pause once it's long enough, or sooner at a pause once it has
+// --chapter-max-cues cues, and at the next cue however it's going once it passes
+// --chapter-max-duration. The first starts at zero as players want, each runs to the next one's
+// start and the last to the end of the speech
+pub fn chapters(transcript: &Transcript, options: &Options) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+    let mut cues = 0;
+    let mut previous_end = 0;
+    for segment in transcript.segments.iter().filter(|segment| !segment.text.trim().is_empty()) {
+        let new_chapter = match chapters.last() {
+            None => true,
+            Some(chapter) => {
+                let length = (segment.start_ms - chapter.start_ms).max(0) as u64;
+                let pause = segment.start_ms - previous_end >= options.chapter_pause as i64;
+                let crowded = options.chapter_max_cues.is_some_and(|max| cues >= max);
+                (pause && (length >= options.chapter_min_duration || crowded)) || options.chapter_max_duration.is_some_and(|max| length >= max)
+            }
+        };
+        if new_chapter {
+            if let Some(chapter) = chapters.last_mut() {
+                chapter.end_ms = segment.start_ms;
+            }
+            let start_ms = if chapters.is_empty() { 0 } else { segment.start_ms };
+            chapters.push(Chapter { start_ms, end_ms: segment.end_ms, title: title(&super::display_text(segment)) });
+            cues = 0;
+        }
+        cues += 1;
+        previous_end = segment.end_ms;
+        if let Some(chapter) = chapters.last_mut() {
+            chapter.end_ms = segment.end_ms;
+        }
+    }
+    chapters
+}
+
+// as youtube reads them from a description: m:ss, or h:mm:ss past the hour
+fn timestamp(ms: i64) -> String {
+    let seconds = ms.max(0) / 1000;
+    match seconds / 3600 {
+        0 => format!("{}:{:02}", seconds / 60, seconds % 60),
+        hours => format!("{}:{:02}:{:02}", hours, (seconds / 60) % 60, seconds % 60),
+    }
+}
+
+pub fn youtube(transcript: &Transcript, options: &Options) -> String {
+    chapters(transcript, options).iter().map(|chapter| format!("{} {}\n", timestamp(chapter.start_ms), chapter.title)).collect()
+}
+
+// ffmpeg metadata values have = ; # \ and newlines escaped with a backslash
+fn escape_ffmetadata(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// for ffmpeg -i input -i chapters.ffmetadata -map_metadata 1 -codec copy output
+pub fn ffmetadata(transcript: &Transcript, options: &Options) -> String {
+    let mut metadata = String::from(";FFMETADATA1\n");
+    for chapter in chapters(transcript, options) {
+        metadata.push_str(&format!("\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n", chapter.start_ms.max(0), chapter.end_ms.max(0), escape_ffmetadata(&chapter.title)));
+    }
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _chapters() {
+        let transcript = Transcript {
+            segments: vec![
+                Segment::new(2000, 40_000, " Welcome to the show. Today, budgets."),
+                Segment::new(45_000, 70_000, " First the numbers."),
+                Segment::new(75_000, 80_000, " Now; the weather = rain #2."),
+                Segment::new(80_500, 81_000, " "),
+                Segment::new(3_700_000, 3_701_000, " And a very long closing remark that goes on and on about nothing in particular"),
+            ],
+        };
+        let options = Options::default();
+        let starts = |options: &Options| chapters(&transcript, options).iter().map(|chapter| chapter.start_ms).collect::<Vec<_>>();
+        assert_eq!(starts(&options), vec![0, 75_000, 3_700_000]);
+        assert_eq!(starts(&Options { chapter_max_cues: Some(1), ..Options::default() }), vec![0, 45_000, 75_000, 3_700_000]);
+        assert_eq!(starts(&Options { chapter_pause: 10_000_000, chapter_max_duration: Some(40_000), ..Options::default() }), vec![0, 45_000, 3_700_000]);
+
+        assert_eq!(youtube(&transcript, &options), "0:00 Welcome to the show\n1:15 Now; the weather = rain #2\n1:01:40 And a very long closing remark that goes on and on about…\n");
+        assert_eq!(
+            ffmetadata(&Transcript { segments: transcript.segments[2..3].to_vec() }, &options),
+            ";FFMETADATA1\n\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=80000\ntitle=Now\\; the weather \\= rain \\#2\n"
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use super::{json, Options};
+use crate::error::Error;
+use crate::transcript::Transcript;
+
+// --format-cmd: a formatter of the user's own, run through the shell with the transcript as the
+// json format writes it on its stdin; what it prints is the output, as is, so it can be binary
+
+// named after the program, ./to_anki.py being written as <output>.to_anki
+pub fn extension(command: &str) -> String {
+    let program = command.split_whitespace().next().unwrap_or(command);
+    Path::new(program).file_stem().map_or_else(|| program.to_string(), |stem| stem.to_string_lossy().into_owned())
+}
+
+pub fn render(command: &str, transcript: &Transcript, options: &Options) -> Result<Vec<u8>, Error> {
+    let mut shell = if cfg!(unix) {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    } else {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    };
+    tracing::debug!("running {}", command);
+    let mut child = shell.arg(command).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().map_err(|error| Error::output(format!("failed to run --format-cmd {}", command), error))?;
+    // written from a thread of its own, so a formatter that prints as it reads doesn't fill its
+    // stdout while we're still filling its stdin
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = json::render(transcript, options);
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output().map_err(|error| Error::output(format!("failed to run --format-cmd {}", command), error))?;
+    // one that doesn't read all of its input is fine, so a broken pipe isn't an error
+    let _ = writer.join();
+    if !output.status.success() {
+        return Err(Error::Output(format!("--format-cmd {} failed with {}", command, output.status)));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _extension() {
+        assert_eq!(extension("./formatters/to_anki.py --deck Spanish"), "to_anki");
+        assert_eq!(extension("wc"), "wc");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn _render() {
+        let transcript = Transcript { segments: vec![Segment::new(0, 1000, " Hello.")] };
+        let output = render("grep -c start_ms", &transcript, &Options::default()).unwrap();
+        assert_eq!(output, b"1\n");
+        assert!(render("exit 3", &transcript, &Options::default()).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use super::Options;
+use crate::transcript::{Segment, Transcript};
+
+// real word times when whisper produced them, otherwise the segment's duration
+// spread over its words in proportion to their length
+fn words(segment: &Segment) -> Vec<(i64, i64, &str, Option<f32>)> {
+    if !segment.words.is_empty() {
+        return segment.words.iter().map(|word| (word.start_ms, word.end_ms, word.text.as_str(), Some(word.probability))).collect();
+    }
+
+    let words: Vec<&str> = segment.text.split_whitespace().collect();
+    let total_chars: usize = words.iter().map(|word| word.chars().count()).sum();
+    if total_chars == 0 {
+        return Vec::new();
+    }
+
+    let duration = segment.end_ms - segment.start_ms;
+    let mut chars_before = 0;
+    words
+        .into_iter()
+        .map(|word| {
+            let chars = word.chars().count();
+            let start = segment.start_ms + duration * chars_before as i64 / total_chars as i64;
+            chars_before += chars;
+            let end = segment.start_ms + duration * chars_before as i64 / total_chars as i64;
+            (start, end, word, segment.confidence())
+        })
+        .collect()
+}
+
+fn seconds(ms: i64) -> String {
+    format!("{:.3}", ms as f64 / 1000.0)
+}
+
+pub fn render(transcript: &Transcript, options: &Options) -> String {
+    let conversation = options.ctm_conversation_id.as_deref().unwrap_or("transcript");
+    let mut ctm = String::new();
+    for segment in &transcript.segments {
+        for (start, end, word, confidence) in words(segment) {
+            ctm.push_str(&format!("{} A {} {} {}", conversation, seconds(start), seconds(end - start), word));
+            if let Some(confidence) = confidence {
+                ctm.push_str(&format!(" {:.4}", confidence));
+            }
+            ctm.push('\n');
+        }
+    }
+    ctm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Word;
+
+    #[test]
+    fn _render() {
+        let transcript = Transcript { segments: vec![Segment::new(1000, 2000, " ab abc")] };
+        let options = Options { ctm_conversation_id: Some("call1".to_string()), ..Options::default() };
+        // 5 characters over 1000ms, so the first word gets 2/5 of the time
+        assert_eq!(render(&transcript, &options), "call1 A 1.000 0.400 ab\ncall1 A 1.400 0.600 abc\n");
+
+        let word = Word { start_ms: 1200, end_ms: 1500, text: "ab".to_string(), probability: 0.5 };
+        let transcript = Transcript { segments: vec![Segment { words: vec![word], ..Segment::new(1000, 2000, " ab") }] };
+        assert_eq!(render(&transcript, &options), "call1 A 1.200 0.300 ab 0.5000\n");
+    }
+}
@@ -0,0 +1,60 @@
+use csv::WriterBuilder;
+
+use super::Options;
+use crate::transcript::Transcript;
+
+pub fn render(transcript: &Transcript, delimiter: u8, options: &Options) -> String {
+    let mut writer = WriterBuilder::new().delimiter(delimiter).from_writer(Vec::new());
+
+    let speakers = transcript.segments.iter().any(|segment| segment.speaker.is_some());
+    let mut header = vec!["start_ms", "end_ms", "text"];
+    if speakers {
+        header.push("speaker");
+    }
+    if options.with_confidence {
+        header.extend(["confidence", "avg_logprob"]);
+    }
+    writer.write_record(&header).expect("failed to write header");
+
+    for segment in &transcript.segments {
+        // the speaker column already names the speaker
+        let text = if speakers { segment.text.clone() } else { super::display_text(segment) };
+        let mut record = vec![segment.start_ms.to_string(), segment.end_ms.to_string(), text.trim().to_string()];
+        if speakers {
+            record.push(segment.speaker.clone().unwrap_or_default());
+        }
+        if options.with_confidence {
+            record.push(segment.confidence().map(|c| format!("{:.4}", c)).unwrap_or_default());
+            record.push(segment.mean_logprob().map(|logprob| format!("{:.4}", logprob)).unwrap_or_default());
+        }
+        writer.write_record(&record).expect("failed to write record");
+    }
+
+    String::from_utf8(writer.into_inner().expect("failed to flush records")).expect("records are not utf-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::{Segment, Token};
+
+    #[test]
+    fn _render() {
+        let mut segment = Segment::new(0, 1500, " Hello, world");
+        segment.tokens = vec![
+            Token::new(" Hello", 0.5),
+            Token::new(", world", 1.0),
+        ];
+        let transcript = Transcript { segments: vec![segment] };
+        let options = Options { with_confidence: true, ..Options::default() };
+
+        assert_eq!(
+            render(&transcript, b',', &options),
+            "start_ms,end_ms,text,confidence,avg_logprob\n0,1500,\"Hello, world\",0.7500,-0.3466\n"
+        );
+        assert_eq!(
+            render(&transcript, b'\t', &options),
+            "start_ms\tend_ms\ttext\tconfidence\tavg_logprob\n0\t1500\tHello, world\t0.7500\t-0.3466\n"
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::html::{absolute, percent_encode};
+use super::{escape_xml, Options};
+use crate::transcript::Transcript;
+
+// seconds since the epoch as the xsd:dateTime the schema wants for the document's DATE, in utc;
+// the days to a civil date as Howard Hinnant works them out
+fn date(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let time = seconds % 86_400;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+fn mime_type(path: &str) -> &'static str {
+    match Path::new(path).extension().map(|extension| extension.to_string_lossy().to_lowercase()).as_deref() {
+        Some("wav") => "audio/x-wav",
+        Some("mp3") => "audio/mpeg",
+        Some("mp4" | "m4v" | "mov") => "video/mp4",
+        Some("mpg" | "mpeg") => "video/mpeg",
+        Some(_) | None => "audio/*",
+    }
+}
+
+// the media ELAN opens beside the tiers: --html-audio as given, or the input as a file url
+fn media_url(options: &Options) -> Option<String> {
+    if let Some(audio) = &options.html_audio {
+        return Some(audio.clone());
+    }
+    let input = options.input.as_ref()?;
+    if input.contains("://") {
+        return Some(input.clone());
+    }
+    Some(format!("file://{}", percent_encode(&absolute(Path::new(input)).to_string_lossy())))
+}
+
+// a speaker's name and the start, end and text of each of their annotations
+type Tier<'a> = (&'a str, Vec<(i64, i64, &'a str)>);
+
+// a tier per speaker in order of appearance, or a single one without speakers; annotations on a
+// tier can't overlap, so each starts no earlier than the one before it ends
+fn tiers(transcript: &Transcript) -> Vec<Tier<'_>> {
+    let mut tiers: Vec<Tier> = Vec::new();
+    for segment in &transcript.segments {
+        let name = segment.speaker.as_deref().unwrap_or("transcript");
+        let index = match tiers.iter().position(|(tier, _)| *tier == name) {
+            Some(index) => index,
+            None => {
+                tiers.push((name, Vec::new()));
+                tiers.len() - 1
+            }
+        };
+        let annotations = &mut tiers[index].1;
+        let start = annotations.last().map_or(segment.start_ms, |(_, end, _)| segment.start_ms.max(*end)).max(0);
+        if segment.end_ms > start {
+            annotations.push((start, segment.end_ms, segment.text.trim()));
+        }
+    }
+    if tiers.is_empty() {
+        tiers.push(("transcript", Vec::new()));
+    }
+    tiers
+}
+
+pub fn render(transcript: &Transcript, options: &Options) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    let mut eaf = String::new();
+    eaf.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    eaf.push_str(&format!(
+        "<ANNOTATION_DOCUMENT AUTHOR=\"\" DATE=\"{}\" FORMAT=\"3.0\" VERSION=\"3.0\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xsi:noNamespaceSchemaLocation=\"http://www.mpi.nl/tools/elan/EAFv3.0.xsd\">\n",
+        date(now)
+    ));
+    eaf.push_str("    <HEADER MEDIA_FILE=\"\" TIME_UNITS=\"milliseconds\">\n");
+    if let Some(media) = media_url(options) {
+        eaf.push_str(&format!("        <MEDIA_DESCRIPTOR MEDIA_URL=\"{}\" MIME_TYPE=\"{}\"/>\n", escape_xml(&media), mime_type(&media)));
+    }
+    eaf.push_str("    </HEADER>\n");
+
+    let tiers = tiers(transcript);
+    // every annotation has two time slots of its own, numbered through the tiers
+    eaf.push_str("    <TIME_ORDER>\n");
+    let mut slot = 0;
+    for (start, end, _) in tiers.iter().flat_map(|(_, annotations)| annotations) {
+        for ms in [start, end] {
+            slot += 1;
+            eaf.push_str(&format!("        <TIME_SLOT TIME_SLOT_ID=\"ts{}\" TIME_VALUE=\"{}\"/>\n", slot, ms));
+        }
+    }
+    eaf.push_str("    </TIME_ORDER>\n");
+    let mut annotation = 0;
+    for (name, annotations) in &tiers {
+        let participant = if *name == "transcript" { String::new() } else { format!(" PARTICIPANT=\"{}\"", escape_xml(name)) };
+        eaf.push_str(&format!("    <TIER LINGUISTIC_TYPE_REF=\"default-lt\" TIER_ID=\"{}\"{}>\n", escape_xml(name), participant));
+        for (_, _, text) in annotations {
+            annotation += 1;
+            eaf.push_str("        <ANNOTATION>\n");
+            eaf.push_str(&format!("            <ALIGNABLE_ANNOTATION ANNOTATION_ID=\"a{}\" TIME_SLOT_REF1=\"ts{}\" TIME_SLOT_REF2=\"ts{}\">\n", annotation, annotation * 2 - 1, annotation * 2));
+            eaf.push_str(&format!("                <ANNOTATION_VALUE>{}</ANNOTATION_VALUE>\n", escape_xml(text)));
+            eaf.push_str("            </ALIGNABLE_ANNOTATION>\n        </ANNOTATION>\n");
+        }
+        eaf.push_str("    </TIER>\n");
+    }
+    eaf.push_str("    <LINGUISTIC_TYPE GRAPHIC_REFERENCES=\"false\" LINGUISTIC_TYPE_ID=\"default-lt\" TIME_ALIGNABLE=\"true\"/>\n");
+    eaf.push_str("</ANNOTATION_DOCUMENT>\n");
+    eaf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _render() {
+        assert_eq!(date(0), "1970-01-01T00:00:00Z");
+        assert_eq!(date(1_709_210_096), "2024-02-29T12:34:56Z");
+
+        let segment = |start_ms, end_ms, text: &str, speaker: &str| Segment { speaker: Some(speaker.to_string()), ..Segment::new(start_ms, end_ms, text) };
+        let transcript = Transcript { segments: vec![segment(0, 1500, " Fish & chips?", "Ann"), segment(1200, 2000, " Yes.", "Bob"), segment(1400, 3000, " Two.", "Ann")] };
+        let options = Options { input: Some("/data/my talk#1.wav".to_string()), ..Options::default() };
+        let eaf = render(&transcript, &options);
+        assert!(eaf.contains("<MEDIA_DESCRIPTOR MEDIA_URL=\"file:///data/my%20talk%231.wav\" MIME_TYPE=\"audio/x-wav\"/>"), "{}", eaf);
+        let options = Options { html_audio: Some("talk.wav".to_string()), ..options };
+        assert_eq!(media_url(&options).as_deref(), Some("talk.wav"));
+        assert!(eaf.contains("<TIME_SLOT TIME_SLOT_ID=\"ts3\" TIME_VALUE=\"1500\"/>\n        <TIME_SLOT TIME_SLOT_ID=\"ts4\" TIME_VALUE=\"3000\"/>"), "{}", eaf);
+        assert!(eaf.contains("<TIER LINGUISTIC_TYPE_REF=\"default-lt\" TIER_ID=\"Ann\" PARTICIPANT=\"Ann\">\n        <ANNOTATION>\n            <ALIGNABLE_ANNOTATION ANNOTATION_ID=\"a1\" TIME_SLOT_REF1=\"ts1\" TIME_SLOT_REF2=\"ts2\">\n                <ANNOTATION_VALUE>Fish &amp; chips?</ANNOTATION_VALUE>"), "{}", eaf);
+        assert!(eaf.contains("ANNOTATION_ID=\"a3\" TIME_SLOT_REF1=\"ts5\" TIME_SLOT_REF2=\"ts6\">\n                <ANNOTATION_VALUE>Yes.</ANNOTATION_VALUE>"), "{}", eaf);
+    }
+}
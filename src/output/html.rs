@@ -0,0 +1,107 @@
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+use super::{display_text, escape_xml, Options};
+use crate::storage;
+use crate::transcript::Transcript;
+
+const TEMPLATE: &str = include_str!("templates/transcript.html");
+
+fn seconds(ms: i64) -> String {
+    format!("{:.3}", ms as f64 / 1000.0)
+}
+
+// absolute, with . and .. worked out without looking at the filesystem, since the output needn't
+// exist yet
+pub(super) fn absolute(path: &Path) -> PathBuf {
+    let joined = env::current_dir().map_or_else(|_| path.to_path_buf(), |dir| dir.join(path));
+    let mut absolute = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            component => absolute.push(component),
+        }
+    }
+    absolute
+}
+
+// the path to `to` from the directory `from`
+fn relative(from: &Path, to: &Path) -> PathBuf {
+    let (from, to) = (absolute(from), absolute(to));
+    let common = from.components().zip(to.components()).take_while(|(a, b)| a == b).count();
+    let mut relative: PathBuf = from.components().skip(common).map(|_| "..").collect();
+    relative.extend(to.components().skip(common));
+    relative
+}
+
+// a path as it goes in a url, everything but the unreserved characters and / escaped
+pub(super) fn percent_encode(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => (byte as char).to_string(),
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+// --html-audio as given, or the input as the page finds it from the directory it's written to
+fn audio_src(options: &Options) -> String {
+    if let Some(audio) = &options.html_audio {
+        return audio.clone();
+    }
+    let Some(input) = &options.input else { return String::new() };
+    if input.contains("://") {
+        return input.clone();
+    }
+    let path = match options.output_path.as_deref().filter(|output| !storage::is_remote(output)) {
+        Some(output) => relative(Path::new(output).parent().unwrap_or(Path::new("")), Path::new(input)),
+        None => PathBuf::from(input),
+    };
+    percent_encode(&path.to_string_lossy())
+}
+
+pub fn render(transcript: &Transcript, options: &Options) -> String {
+    let title = options.html_audio.clone().or_else(|| options.input.clone()).unwrap_or_default();
+    let audio = audio_src(options);
+    let segments = transcript
+        .segments
+        .iter()
+        .map(|segment| {
+            format!(
+                "<span class=\"segment\" data-start=\"{}\" data-end=\"{}\">{}</span>",
+                seconds(segment.start_ms),
+                seconds(segment.end_ms),
+                escape_xml(display_text(segment).trim())
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    TEMPLATE
+        .replace("{{title}}", &escape_xml(&title))
+        .replace("{{audio}}", &escape_xml(&audio))
+        .replace("{{segments}}", &segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _render() {
+        let transcript = Transcript { segments: vec![Segment::new(1250, 2000, " Hi")] };
+        let options = Options { html_audio: Some("talk.wav".to_string()), ..Options::default() };
+        let html = render(&transcript, &options);
+        assert!(html.contains("<audio id=\"audio\" controls src=\"talk.wav\"></audio>"));
+        assert!(html.contains("<span class=\"segment\" data-start=\"1.250\" data-end=\"2.000\">Hi</span>"));
+
+        let options = Options { input: Some("rec/my talk #1.wav".to_string()), output_path: Some("out/html/talk".to_string()), ..Options::default() };
+        assert_eq!(audio_src(&options), "../../rec/my%20talk%20%231.wav");
+        assert_eq!(audio_src(&Options { output_path: Some("talk".to_string()), ..options }), "rec/my%20talk%20%231.wav");
+        assert_eq!(relative(Path::new("/data/out"), Path::new("/data/talk.wav")), Path::new("../talk.wav"));
+    }
+}
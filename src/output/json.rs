@@ -0,0 +1,57 @@
+use serde_json::{json, Value};
+
+use super::Options;
+use crate::transcript::{Segment, Transcript};
+
+// every segment's tokens in full for --dump-tokens, with what a given token wasn't decoded with
+// left null
+fn dump_tokens(segment: &Segment) -> Value {
+    let tokens = segment.tokens.iter().map(|token| json!({ "id": token.id, "text": token.text, "probability": token.probability, "start_ms": token.start_ms, "end_ms": token.end_ms }));
+    Value::Array(tokens.collect())
+}
+
+// every segment with its mean token log probability alongside, worked out from its tokens, and
+// the language once it's known
+pub fn render(transcript: &Transcript, options: &Options) -> String {
+    let mut value = serde_json::to_value(transcript).expect("failed to serialize transcript");
+    if let (Value::Object(json), Some(language)) = (&mut value, &options.language) {
+        json.insert("language".to_string(), Value::from(language.as_str()));
+    }
+    if let Some(Value::Array(segments)) = value.get_mut("segments") {
+        for (json, segment) in segments.iter_mut().zip(&transcript.segments) {
+            let Value::Object(json) = json else { continue };
+            if let Some(logprob) = segment.mean_logprob() {
+                json.insert("avg_logprob".to_string(), Value::from(logprob));
+            }
+            if options.dump_tokens && !segment.tokens.is_empty() {
+                json.insert("tokens".to_string(), dump_tokens(segment));
+            }
+        }
+    }
+    serde_json::to_string_pretty(&value).expect("failed to serialize transcript")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Token;
+
+    #[test]
+    fn _render() {
+        let segment = Segment { tokens: vec![Token::new(" Hi", 0.5)], low_confidence: true, ..Segment::new(0, 1000, " Hi") };
+        let transcript = Transcript { segments: vec![segment, Segment::new(1000, 2000, " there")] };
+        let options = Options { language: Some("de".to_string()), ..Options::default() };
+        let value: Value = serde_json::from_str(&render(&transcript, &options)).unwrap();
+        assert_eq!(value["language"], "de");
+        assert_eq!(value["segments"][0]["avg_logprob"].as_f64().map(|logprob| (logprob * 1000.0).round()), Some(-693.0));
+        assert_eq!(value["segments"][0]["low_confidence"], Value::Bool(true));
+        assert!(value["segments"][1].get("avg_logprob").is_none());
+        assert_eq!(value["segments"][0]["tokens"][0], serde_json::json!({ "text": " Hi", "probability": 0.5 }));
+
+        let token = Token { id: Some(2425), start_ms: Some(0), end_ms: Some(400), ..Token::new(" Hi", 0.5) };
+        let transcript = Transcript { segments: vec![Segment { tokens: vec![token, Token::new("!", 1.0)], ..Segment::new(0, 1000, " Hi!") }] };
+        let options = Options { dump_tokens: true, ..Options::default() };
+        let value: Value = serde_json::from_str(&render(&transcript, &options)).unwrap();
+        assert_eq!(value["segments"][0]["tokens"], json!([{ "id": 2425, "text": " Hi", "probability": 0.5, "start_ms": 0, "end_ms": 400 }, { "id": null, "text": "!", "probability": 1.0, "start_ms": null, "end_ms": null }]));
+    }
+}
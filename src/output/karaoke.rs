@@ -0,0 +1,48 @@
+use super::{ass, display_text, Options};
+use crate::transcript::{Segment, Transcript};
+
+fn centis(ms: i64) -> i64 {
+    ms.max(0) / 10
+}
+
+// every word gets a \k tag lasting until the next word starts, so the gaps highlight nothing
+fn line(segment: &Segment) -> String {
+    // escape trims, and the prefix keeps its space before the first word
+    let prefix = super::speaker_prefix(segment);
+    let mut text = if prefix.is_empty() { String::new() } else { format!("{} ", ass::escape(&prefix)) };
+    let mut cursor = segment.start_ms;
+    for word in &segment.words {
+        if word.start_ms > cursor {
+            text.push_str(&format!("{{\\k{}}}", centis(word.start_ms - cursor)));
+        }
+        let start = word.start_ms.max(cursor);
+        text.push_str(&format!("{{\\k{}}}{} ", centis(word.end_ms - start), ass::escape(&word.text)));
+        cursor = word.end_ms.max(start);
+    }
+    text.trim_end().to_string()
+}
+
+pub fn render(transcript: &Transcript, options: &Options) -> String {
+    let mut karaoke = ass::header(options);
+    for segment in &transcript.segments {
+        // without word times there is nothing to highlight, so show the plain cue
+        let text = if segment.words.is_empty() { ass::escape(&display_text(segment)) } else { line(segment) };
+        karaoke.push_str(&ass::dialogue(segment.start_ms, segment.end_ms, &text));
+    }
+    karaoke
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Word;
+
+    #[test]
+    fn _line() {
+        let word = |start_ms: i64, end_ms: i64, text: &str| Word { start_ms, end_ms, text: text.to_string(), probability: 1.0 };
+        let segment = Segment { words: vec![word(1200, 1500, "Hello,"), word(1500, 2100, "world")], ..Segment::new(1000, 2500, " Hello, world") };
+        assert_eq!(line(&segment), "{\\k20}{\\k30}Hello, {\\k60}world");
+        let segment = Segment { speaker: Some("{Ann}".to_string()), ..segment };
+        assert_eq!(line(&segment), "\\{Ann\\}: {\\k20}{\\k30}Hello, {\\k60}world");
+    }
+}
@@ -0,0 +1,440 @@
+use std::fmt;
+
+use clap::{Args, FromArgMatches};
+
+use crate::audio;
+use crate::transcript::{Segment, Transcript};
+
+use super::{display_text, speaker_prefix, vtt};
+
+#[derive(Args, Debug, Clone)]
+#[group(id = "layout_options")]
+pub struct Options {
+    /// Wrap subtitle text into lines of at most this many characters, breaking at spaces
+    /// and splitting a segment into several cues when it needs more than --max-lines
+    #[arg(long, value_name = "CHARS", value_parser = clap::value_parser!(u16).range(1..), help_heading = "Subtitle layout")]
+    pub max_line_chars: Option<u16>,
+
+    /// Lines per cue when wrapping with --max-line-chars
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u16).range(1..), help_heading = "Subtitle layout")]
+    pub max_lines: u16,
+
+    /// Join subtitle cues shorter than this to the next one, as 1.5s or 800ms
+    #[arg(long, value_name = "TIME", value_parser = audio::parse_time, help_heading = "Subtitle layout")]
+    pub min_duration: Option<u64>,
+
+    /// Split subtitle cues longer than this at the word closest to an even split
+    #[arg(long, value_name = "TIME", value_parser = audio::parse_time, help_heading = "Subtitle layout")]
+    pub max_duration: Option<u64>,
+
+    /// End every subtitle cue at least this long before the next one starts
+    #[arg(long, value_name = "TIME", value_parser = audio::parse_time, help_heading = "Subtitle layout")]
+    pub min_gap: Option<u64>,
+
+    /// Lengthen subtitle cues that read faster than this many characters per second into the
+    /// gap after them
+    #[arg(long, value_name = "CPS", value_parser = parse_cps, help_heading = "Subtitle layout")]
+    pub max_cps: Option<f64>,
+
+    /// Frame rate of the video the subtitles go with, as 25, 23.976 or 24000/1001; cue times are
+    /// moved to the start of the frame they fall in, rounded up to the millisecond so a player
+    /// never puts them a frame early
+    #[arg(long, value_name = "FPS", value_parser = parse_frame_rate, help_heading = "Subtitle layout")]
+    pub frame_rate: Option<f64>,
+
+    /// Move cue times to the nearest frame boundary of --frame-rate instead, so a cue can start
+    /// on the frame after the one it falls in
+    #[arg(long, requires = "frame_rate", help_heading = "Subtitle layout")]
+    pub snap_to_frames: bool,
+}
+
+// the clap defaults, as for output::Options
+impl Default for Options {
+    fn default() -> Options {
+        let matches = Options::augment_args(clap::Command::new("options")).get_matches_from(["options"]);
+        Options::from_arg_matches(&matches).expect("option defaults are invalid")
+    }
+}
+
+fn parse_cps(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(cps) if cps > 0.0 && cps.is_finite() => Ok(cps),
+        _ => Err(format!("expected characters per second above 0, got {}", value)),
+    }
+}
+
+// frames per second, as a number or a fraction as ffprobe gives it
+fn parse_frame_rate(value: &str) -> Result<f64, String> {
+    let invalid = || format!("expected frames per second such as 25, 23.976 or 24000/1001, got {}", value);
+    let number = |text: &str| text.trim().parse::<f64>().ok().filter(|number| *number > 0.0 && number.is_finite()).ok_or_else(invalid);
+    match value.split_once('/') {
+        Some((frames, seconds)) => Ok(number(frames)? / number(seconds)?),
+        None => number(value),
+    }
+}
+
+// the words on each line, each line as long as fits in max_chars and a word longer than that on a
+// line of its own; first is how much of the first line is taken already
+fn fill(words: &[&str], max_chars: usize, first: usize) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let (mut count, mut length) = (0, first);
+    for word in words {
+        let extra = if count == 0 { word.chars().count() } else { word.chars().count() + 1 };
+        if count > 0 && length + extra > max_chars {
+            lines.push(count);
+            (count, length) = (0, 0);
+            length += word.chars().count();
+        } else {
+            length += extra;
+        }
+        count += 1;
+    }
+    if count > 0 {
+        lines.push(count);
+    }
+    lines
+}
+
+fn width(words: &[&str]) -> usize {
+    words.iter().map(|word| word.chars().count()).sum::<usize>() + words.len().saturating_sub(1)
+}
+
+// two lines are broken where they come out closest in length, the bottom one the longer on a
+// tie, as captioning guidelines ask; more lines stay as they were filled
+fn balance<'a>(words: &[&'a str], lines: &[usize], max_chars: usize, first: usize) -> Vec<Vec<&'a str>> {
+    if lines.len() == 2 {
+        let best = (1..words.len())
+            .filter(|split| first + width(&words[..*split]) <= max_chars && width(&words[*split..]) <= max_chars)
+            .min_by_key(|split| ((first + width(&words[..*split])).abs_diff(width(&words[*split..])), first + width(&words[..*split]) > width(&words[*split..])));
+        if let Some(split) = best {
+            return vec![words[..split].to_vec(), words[split..].to_vec()];
+        }
+    }
+    let mut rest = words;
+    lines.iter().map(|count| {
+        let (line, after) = rest.split_at(*count);
+        rest = after;
+        line.to_vec()
+    }).collect()
+}
+
+// where a cue starting at word index should start: at that word when every word has a time,
+// otherwise as far into the segment as the text before it
+fn boundary_ms(segment: &Segment, words: &[&str], index: usize) -> i64 {
+    if segment.words.len() == words.len() {
+        return segment.words[index].start_ms.clamp(segment.start_ms, segment.end_ms);
+    }
+    let total = width(words).max(1) as i64;
+    segment.start_ms + (segment.end_ms - segment.start_ms) * (width(&words[..index]) as i64 + 1) / total
+}
+
+// the words from..to of a segment as a segment of their own, timed as boundary_ms has it; only
+// the first starts a speaker turn
+pub(crate) fn piece(segment: &Segment, words: &[&str], from: usize, to: usize) -> Segment {
+    let timed = segment.words.len() == words.len();
+    let mut piece = Segment {
+        text: format!(" {}", words[from..to].join(" ")),
+        tokens: Vec::new(),
+        words: if timed { segment.words[from..to].to_vec() } else { Vec::new() },
+        ..segment.clone()
+    };
+    piece.start_ms = if from == 0 { segment.start_ms } else { boundary_ms(segment, words, from) };
+    piece.end_ms = if to == words.len() { segment.end_ms } else { boundary_ms(segment, words, to) };
+    piece.speaker_turn &= from == 0;
+    piece
+}
+
+// a segment longer than max_ms cut into as few pieces as keep under it, each cut at the word
+// boundary closest to where an even split would put it
+fn split(segment: &Segment, max_ms: i64) -> Vec<Segment> {
+    let words: Vec<&str> = segment.text.split_whitespace().collect();
+    let duration = segment.end_ms - segment.start_ms;
+    let pieces = ((duration + max_ms - 1) / max_ms).clamp(1, words.len().max(1) as i64) as usize;
+    if pieces == 1 {
+        return vec![segment.clone()];
+    }
+    let mut cuts = vec![0];
+    for k in 1..pieces {
+        let target = segment.start_ms + duration * k as i64 / pieces as i64;
+        let after = cuts[cuts.len() - 1] + 1;
+        let cut = (after..=words.len() - (pieces - k)).min_by_key(|index| (boundary_ms(segment, &words, *index) - target).abs()).unwrap_or(after);
+        cuts.push(cut);
+    }
+    cuts.push(words.len());
+    cuts.windows(2).map(|cut| piece(segment, &words, cut[0], cut[1])).collect()
+}
+
+pub(crate) fn join(mut first: Segment, second: Segment) -> Segment {
+    first.end_ms = second.end_ms;
+    first.text.push_str(&second.text);
+    first.tokens.extend(second.tokens);
+    first.words.extend(second.words);
+    first.hallucination &= second.hallucination;
+    first.low_confidence |= second.low_confidence;
+    first
+}
+
+// cues are cut down to max_ms and those shorter than min_ms joined to the one after them (the last
+// to the one before), as long as that's the same speaker and doesn't make a cue longer than max_ms
+pub fn retime(transcript: &Transcript, min_ms: Option<i64>, max_ms: Option<i64>) -> Transcript {
+    let max_ms = max_ms.filter(|max_ms| *max_ms > 0);
+    let fits = |first: &Segment, second: &Segment| first.speaker == second.speaker && max_ms.is_none_or(|max_ms| second.end_ms - first.start_ms <= max_ms);
+    let mut segments: Vec<Segment> = Vec::new();
+    for segment in &transcript.segments {
+        let pieces = match max_ms {
+            Some(max_ms) if segment.end_ms - segment.start_ms > max_ms => split(segment, max_ms),
+            _ => vec![segment.clone()],
+        };
+        for piece in pieces {
+            match segments.pop() {
+                Some(last) if min_ms.is_some_and(|min_ms| last.end_ms - last.start_ms < min_ms) && fits(&last, &piece) => segments.push(join(last, piece)),
+                Some(last) => segments.extend([last, piece]),
+                None => segments.push(piece),
+            }
+        }
+    }
+    if let (Some(min_ms), [.., before, last]) = (min_ms, segments.as_slice()) {
+        if last.end_ms - last.start_ms < min_ms && fits(before, last) {
+            let last = segments.pop().expect("there are two segments");
+            let before = segments.pop().expect("there are two segments");
+            segments.push(join(before, last));
+        }
+    }
+    Transcript { segments }
+}
+
+// every cue ends at least gap_ms before the next one starts, ending earlier if it has to but never
+// before it starts
+pub fn space(transcript: &mut Transcript, gap_ms: i64) {
+    for i in 1..transcript.segments.len() {
+        let next_start = transcript.segments[i].start_ms;
+        let cue = &mut transcript.segments[i - 1];
+        if next_start - cue.end_ms < gap_ms {
+            cue.end_ms = (next_start - gap_ms).max(cue.start_ms);
+        }
+    }
+}
+
+// every cue time on a frame of the video: the frame it falls in, or the nearest boundary with
+// snap, written as the first whole millisecond of that frame; a cue keeps at least one frame
+pub fn frames(transcript: &mut Transcript, fps: f64, snap: bool) {
+    // a time that's a frame's own start, once rounded, still falls in that frame
+    let frame = |ms: i64| {
+        let frames = ms as f64 * fps / 1000.0;
+        if snap { frames.round() } else { (frames + 1e-6).floor() }
+    };
+    let time = |frame: f64| (frame * 1000.0 / fps - 1e-6).ceil() as i64;
+    for cue in &mut transcript.segments {
+        let start = frame(cue.start_ms);
+        let end = frame(cue.end_ms).max(start + 1.0);
+        (cue.start_ms, cue.end_ms) = (time(start), time(end));
+    }
+}
+
+// what a reading speed is measured on: the cue as it's shown, label and all, without its line breaks
+fn characters(segment: &Segment) -> usize {
+    display_text(segment).trim().chars().filter(|c| *c != '\n').count()
+}
+
+pub fn cps(segment: &Segment) -> f64 {
+    characters(segment) as f64 * 1000.0 / (segment.end_ms - segment.start_ms).max(1) as f64
+}
+
+// cues reading faster than max_cps are lengthened until they don't, as far as the next cue (less
+// gap_ms) allows
+pub fn slow_down(transcript: &mut Transcript, max_cps: f64, gap_ms: i64) {
+    for i in 0..transcript.segments.len() {
+        let limit = transcript.segments.get(i + 1).map_or(i64::MAX, |next| next.start_ms - gap_ms);
+        let cue = &mut transcript.segments[i];
+        if cps(cue) > max_cps {
+            let needed_ms = (characters(cue) as f64 * 1000.0 / max_cps).ceil() as i64;
+            cue.end_ms = (cue.start_ms + needed_ms).min(limit).max(cue.end_ms);
+        }
+    }
+}
+
+// how the subtitle formats' cues are laid out: their durations first, then their lines, which
+// may split them further, their reading speed and last the gaps between them; none when no option
+// asks for any of it
+pub fn apply(transcript: &Transcript, options: &Options) -> Option<Transcript> {
+    let (min_ms, max_ms) = (options.min_duration.map(|ms| ms as i64), options.max_duration.map(|ms| ms as i64));
+    if min_ms.is_none() && max_ms.is_none() && options.max_line_chars.is_none() && options.min_gap.is_none() && options.max_cps.is_none() && options.frame_rate.is_none() {
+        return None;
+    }
+    let mut cues = retime(transcript, min_ms, max_ms);
+    if let Some(max_chars) = options.max_line_chars {
+        cues = wrap(&cues, max_chars as usize, options.max_lines as usize);
+    }
+    let gap_ms = options.min_gap.unwrap_or(0) as i64;
+    if let Some(max_cps) = options.max_cps {
+        slow_down(&mut cues, max_cps, gap_ms);
+    }
+    if options.min_gap.is_some() {
+        space(&mut cues, gap_ms);
+    }
+    if let Some(fps) = options.frame_rate {
+        frames(&mut cues, fps, options.snap_to_frames);
+    }
+    Some(cues)
+}
+
+// a cue breaking one of the layout limits
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    // counting from 1, as srt numbers them
+    pub cue: usize,
+    pub start_ms: i64,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cue {} at {} {}", self.cue, vtt::timestamp(self.start_ms), self.message)
+    }
+}
+
+// every limit the options set checked against the cues as they are, --max-lines whether or not
+// --max-line-chars is given
+pub fn lint(transcript: &Transcript, options: &Options) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (i, cue) in transcript.segments.iter().enumerate() {
+        let mut problem = |message: String| problems.push(Problem { cue: i + 1, start_ms: cue.start_ms, message });
+        let duration_ms = cue.end_ms - cue.start_ms;
+        if let Some(max_cps) = options.max_cps.filter(|max_cps| cps(cue) > *max_cps) {
+            problem(format!("reads at {:.1} characters per second, over {}", cps(cue), max_cps));
+        }
+        let text = display_text(cue);
+        let lines: Vec<&str> = text.trim().lines().collect();
+        if lines.len() > options.max_lines as usize {
+            problem(format!("has {} lines, over {}", lines.len(), options.max_lines));
+        }
+        if let Some(max_chars) = options.max_line_chars {
+            for line in lines.iter().filter(|line| line.chars().count() > max_chars as usize) {
+                problem(format!("has a line of {} characters, over {}: {}", line.chars().count(), max_chars, line));
+            }
+        }
+        if let Some(min_ms) = options.min_duration.filter(|min_ms| duration_ms < *min_ms as i64) {
+            problem(format!("lasts {}ms, under {}ms", duration_ms, min_ms));
+        }
+        if let Some(max_ms) = options.max_duration.filter(|max_ms| duration_ms > *max_ms as i64) {
+            problem(format!("lasts {}ms, over {}ms", duration_ms, max_ms));
+        }
+        let gap_ms = transcript.segments.get(i + 1).map(|next| next.start_ms - cue.end_ms);
+        if let (Some(min_gap), Some(gap_ms)) = (options.min_gap, gap_ms) {
+            if gap_ms < min_gap as i64 {
+                problem(format!("ends {}ms before the next cue, under {}ms", gap_ms, min_gap));
+            }
+        }
+    }
+    problems
+}
+
+// re-wraps every segment's text at word boundaries into lines of at most max_chars, and splits a
+// segment that needs more than max_lines lines into several cues, timed from its words when it has
+// them; only the first cue of a segment keeps its speaker label
+pub fn wrap(transcript: &Transcript, max_chars: usize, max_lines: usize) -> Transcript {
+    let mut segments = Vec::new();
+    for segment in &transcript.segments {
+        let words: Vec<&str> = segment.text.split_whitespace().collect();
+        if words.is_empty() {
+            segments.push(segment.clone());
+            continue;
+        }
+        let prefix = speaker_prefix(segment).chars().count();
+        let lines = fill(&words, max_chars, prefix);
+        let mut start = 0;
+        for (cue, group) in lines.chunks(max_lines).enumerate() {
+            let count: usize = group.iter().sum();
+            let first = if cue == 0 { prefix } else { 0 };
+            let text = balance(&words[start..start + count], group, max_chars, first).iter().map(|line| line.join(" ")).collect::<Vec<_>>().join("\n");
+            let end = start + count;
+            let wrapped = piece(segment, &words, start, end);
+            segments.push(Segment { text, words: Vec::new(), speaker: wrapped.speaker.filter(|_| cue == 0), ..wrapped });
+            start = end;
+        }
+    }
+    Transcript { segments }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Word;
+
+    #[test]
+    fn _retime() {
+        let word = |start_ms: i64, text: &str| Word { start_ms, end_ms: start_ms + 400, text: format!(" {}", text), probability: 1.0 };
+        let long = Segment {
+            words: vec![word(2000, "one"), word(5500, "two"), word(9800, "three"), word(12500, "four")],
+            ..Segment::new(2000, 14000, " one two three four")
+        };
+        let transcript = Transcript { segments: vec![Segment::new(0, 500, " Hi."), Segment::new(600, 2000, " How are you?"), long, Segment::new(14000, 14300, " Bye.")] };
+        let mut cues = retime(&transcript, Some(1000), Some(5000));
+        let shown = |cues: &Transcript| cues.segments.iter().map(|cue| (cue.start_ms, cue.end_ms, cue.text.clone())).collect::<Vec<_>>();
+        assert_eq!(shown(&cues), [(0, 2000, " Hi. How are you?".to_string()), (2000, 5500, " one".to_string()), (5500, 9800, " two".to_string()), (9800, 14300, " three four Bye.".to_string())]);
+        assert_eq!(cues.segments[3].words.len(), 2);
+
+        // different speakers aren't joined
+        let mut turns = transcript.clone();
+        turns.segments[0].speaker = Some("Alice".to_string());
+        assert_eq!(retime(&turns, Some(1000), None).segments.len(), 3);
+
+        space(&mut cues, 100);
+        assert_eq!(cues.segments.iter().map(|cue| cue.end_ms).collect::<Vec<_>>(), [1900, 5400, 9700, 14300]);
+    }
+
+    #[test]
+    fn _frames() {
+        assert_eq!(parse_frame_rate("24000/1001").map(|fps| (fps * 1000.0).round()), Ok(23976.0));
+        assert!(parse_frame_rate("25/0").is_err());
+
+        let mut cues = Transcript { segments: vec![Segment::new(1030, 2010, " One."), Segment::new(2030, 2040, " Two.")] };
+        frames(&mut cues, 25.0, false);
+        assert_eq!(cues.segments.iter().map(|cue| (cue.start_ms, cue.end_ms)).collect::<Vec<_>>(), [(1000, 2000), (2000, 2040)]);
+
+        // frame 1 of 23.976 starts 41.7ms in, so it's written as 42 rather than the 41 of frame 0
+        let mut cues = Transcript { segments: vec![Segment::new(30, 1020, " One.")] };
+        frames(&mut cues, 24000.0 / 1001.0, true);
+        assert_eq!((cues.segments[0].start_ms, cues.segments[0].end_ms), (42, 1001));
+    }
+
+    #[test]
+    fn _lint() {
+        // 35 characters over a second, with room for 2s more before the next cue
+        let mut transcript = Transcript { segments: vec![Segment::new(0, 1000, " Far too much to read in one second."), Segment::new(4000, 6000, " Fine.\nAnd a line\ntoo many.")] };
+        let options = Options { max_cps: Some(17.0), ..Options::default() };
+        let problems = lint(&transcript, &options);
+        assert_eq!(problems.iter().map(|problem| problem.to_string()).collect::<Vec<_>>(), ["cue 1 at 00:00:00.000 reads at 35.0 characters per second, over 17", "cue 2 at 00:00:04.000 has 3 lines, over 2"]);
+
+        slow_down(&mut transcript, 17.0, 500);
+        assert_eq!(transcript.segments[0].end_ms, 2059);
+        slow_down(&mut transcript, 8.0, 500);
+        assert_eq!(transcript.segments[0].end_ms, 3500);
+    }
+
+    #[test]
+    fn _wrap() {
+        let short = Transcript { segments: vec![Segment::new(0, 1000, " Fine.")] };
+        assert_eq!(wrap(&short, 42, 2).segments[0].text, "Fine.");
+
+        // balanced rather than filled, which would leave "the lazy dog" on its own
+        let fox = Transcript { segments: vec![Segment::new(0, 3000, " The quick brown fox jumps over the lazy dog")] };
+        assert_eq!(wrap(&fox, 32, 2).segments[0].text, "The quick brown fox\njumps over the lazy dog");
+
+        // too long for one cue, split where the text runs out, proportionally
+        let long = Transcript { segments: vec![Segment::new(0, 8000, " one two three four five six seven eight nine ten eleven")] };
+        let cues = wrap(&long, 12, 2).segments;
+        assert_eq!(cues.iter().map(|cue| cue.text.as_str()).collect::<Vec<_>>(), ["one two\nthree four", "five six\nseven eight", "nine ten\neleven"]);
+        assert_eq!((cues[0].start_ms, cues[0].end_ms, cues[1].end_ms, cues[2].end_ms), (0, 2763, 5818, 8000));
+
+        // word times are used when there's one per word, and a speaker label takes up room
+        let word = |start_ms: i64, text: &str| Word { start_ms, end_ms: start_ms + 400, text: format!(" {}", text), probability: 1.0 };
+        let timed = Segment {
+            speaker: Some("Alice".to_string()),
+            words: vec![word(0, "alpha"), word(500, "beta"), word(1000, "gamma"), word(4000, "delta")],
+            ..Segment::new(0, 4500, " alpha beta gamma delta")
+        };
+        let cues = wrap(&Transcript { segments: vec![timed] }, 14, 1).segments;
+        assert_eq!(cues.iter().map(|cue| (cue.text.as_str(), cue.start_ms, cue.end_ms, cue.speaker.is_some())).collect::<Vec<_>>(), [("alpha", 0, 500, true), ("beta gamma", 500, 4000, false), ("delta", 4000, 4500, false)]);
+    }
+}
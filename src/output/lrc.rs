@@ -0,0 +1,31 @@
+use std::cmp;
+
+use crate::transcript::Transcript;
+
+// minutes are not wrapped into hours, players read them as a running total
+pub fn timestamp(ms: i64) -> String {
+    let centis = cmp::max(0, ms) / 10;
+    let hundredths = centis % 100;
+    let seconds = (centis / 100) % 60;
+    let minutes = centis / 100 / 60;
+    format!("[{:02}:{:02}.{:02}]", minutes, seconds, hundredths)
+}
+
+pub fn render(transcript: &Transcript) -> String {
+    transcript
+        .segments
+        .iter()
+        .map(|segment| format!("{}{}\n", timestamp(segment.start_ms), super::display_text(segment).trim()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _timestamp() {
+        assert_eq!(timestamp(19990), "[00:19.99]");
+        assert_eq!(timestamp(8388500), "[139:48.50]");
+    }
+}
@@ -0,0 +1,68 @@
+use super::Options;
+use crate::transcript::Transcript;
+
+fn anchor(ms: i64) -> String {
+    let seconds = ms / 1000;
+    format!("**[{:02}:{:02}:{:02}]**", seconds / 3600, (seconds / 60) % 60, seconds % 60)
+}
+
+pub fn render(transcript: &Transcript, options: &Options) -> String {
+    let pause_ms = (options.paragraph_pause * 1000.0) as i64;
+    let interval_ms = options.markdown_anchor_interval.map(|minutes| minutes as i64 * 60 * 1000);
+    let mut next_anchor_ms = 0;
+    let mut markdown = String::new();
+    let mut previous_end: Option<i64> = None;
+
+    for segment in &transcript.segments {
+        // prose only names the speaker where they take over
+        let text = if segment.speaker_turn || previous_end.is_none() { super::display_text(segment) } else { segment.text.clone() };
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        let anchor_due = interval_ms.filter(|_| segment.start_ms >= next_anchor_ms);
+        match previous_end {
+            Some(end) if anchor_due.is_some() || segment.speaker_turn || segment.start_ms - end >= pause_ms => markdown.push_str("\n\n"),
+            Some(_) => markdown.push(' '),
+            None => {}
+        }
+        if let Some(interval) = anchor_due {
+            markdown.push_str(&anchor(segment.start_ms));
+            markdown.push(' ');
+            while next_anchor_ms <= segment.start_ms {
+                next_anchor_ms += interval;
+            }
+        }
+        markdown.push_str(text);
+        previous_end = Some(segment.end_ms);
+    }
+
+    if !markdown.is_empty() {
+        markdown.push('\n');
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _render() {
+        let transcript = Transcript {
+            segments: vec![
+                Segment::new(0, 1000, " Welcome."),
+                Segment::new(1000, 2000, " Today we talk."),
+                Segment::new(61000, 62000, " A minute in."),
+                Segment::new(62000, 63000, " Still going."),
+            ],
+        };
+        let options = Options { markdown_anchor_interval: Some(1), ..Options::default() };
+        assert_eq!(
+            render(&transcript, &options),
+            "**[00:00:00]** Welcome. Today we talk.\n\n**[00:01:01]** A minute in. Still going.\n"
+        );
+    }
+}
@@ -0,0 +1,585 @@
+use std::cmp;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+
+use clap::{Args, Command, FromArgMatches, ValueEnum};
+use serde::Deserialize;
+
+use crate::audio;
+use crate::error::Error;
+use crate::storage;
+use crate::transcript::{Segment, Transcript};
+
+pub mod ass;
+pub mod audacity;
+pub mod command;
+pub mod chapters;
+pub mod ctm;
+pub mod delimited;
+pub mod eaf;
+pub mod html;
+pub mod json;
+pub mod karaoke;
+pub mod layout;
+pub mod lrc;
+pub mod markdown;
+pub mod parse;
+pub mod podcast;
+pub mod sami;
+pub mod sbv;
+pub mod speaking;
+pub mod srt;
+pub mod template;
+pub mod text;
+pub mod textgrid;
+pub mod ttml;
+pub mod txt;
+pub mod vtt;
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Txt,
+    Srt,
+    Vtt,
+    Json,
+    Text,
+    Csv,
+    Tsv,
+    Ass,
+    Lrc,
+    Ttml,
+    Audacity,
+    Sbv,
+    /// SAMI captions, for older Windows and embedded players
+    Sami,
+    Html,
+    Markdown,
+    Ctm,
+    Textgrid,
+    /// ELAN annotation document, a tier per speaker
+    Eaf,
+    /// Podcasting 2.0 transcript JSON
+    Podcast,
+    /// ASS subtitles highlighting each word as it is spoken
+    Karaoke,
+    /// Chapter list to paste into a YouTube description
+    Chapters,
+    /// Chapters as an ffmpeg metadata file
+    Ffmetadata,
+    /// Words per minute, talk time per speaker and how long the pauses are, as a table
+    Speaking,
+    /// The same speaking statistics as JSON
+    #[serde(rename = "speaking-json")]
+    SpeakingJson,
+}
+
+// what subtitle files are written in; some hardware players and older editors only read them as
+// utf-16 or with a byte order mark
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    #[value(name = "utf-8")]
+    Utf8,
+    #[value(name = "utf-16le")]
+    Utf16le,
+}
+
+#[derive(Args, Debug, Clone)]
+#[group(id = "output_options")]
+pub struct Options {
+    /// Silence in seconds that starts a new paragraph in prose and markdown output
+    #[arg(long, default_value_t = 2.0)]
+    pub paragraph_pause: f64,
+
+    /// Add confidence (mean token probability) and avg_logprob columns to csv/tsv output
+    #[arg(long)]
+    pub with_confidence: bool,
+
+    /// Insert a timestamp anchor into markdown output every N minutes
+    #[arg(long, value_name = "MINUTES", value_parser = clap::value_parser!(u64).range(1..))]
+    pub markdown_anchor_interval: Option<u64>,
+
+    /// Audio source for the html player and the media an eaf opens with, used as given; defaults
+    /// to the input file, found from where the outputs are written
+    #[arg(long)]
+    pub html_audio: Option<String>,
+
+    // the input the transcript is of, what the html player and an eaf point at without --html-audio
+    #[arg(skip)]
+    pub input: Option<String>,
+
+    // the output path being written, filled in by write_outputs so a format can point at files
+    // relative to where it's written
+    #[arg(skip)]
+    pub output_path: Option<String>,
+
+    /// Number the first srt cue this, e.g. 0 for software that counts from zero or on from where
+    /// another file's cues stop
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub start_index: u64,
+
+    /// Write the txt's timestamps as this locale does, e.g. de-DE, or en_US.UTF-8 as $LANG has
+    /// it: hh:mm:ss with its decimal separator, and 12 or 24 hour times of day with --time-of-day
+    #[arg(long, value_parser = txt::parse_locale)]
+    pub locale: Option<txt::Locale>,
+
+    /// The time of day the recording started, as 14:30 or 14:30:15, so the txt's timestamps are
+    /// the times of day things were said
+    #[arg(long, value_name = "HH:MM", value_parser = txt::parse_time_of_day, conflicts_with = "spoken_timestamps")]
+    pub time_of_day: Option<u64>,
+
+    /// Spell the txt's timestamps out, as 1 hour 5 minutes 3 seconds in --locale's language
+    /// where it's known, which screen readers read better than 01:05:03
+    #[arg(long)]
+    pub spoken_timestamps: bool,
+
+    /// Also write every speaker's segments on their own, as <output>.<speaker>, e.g.
+    /// interview.alice.txt; speakers come from --split-channels, named with --speakers
+    #[arg(long)]
+    pub split_by_speaker: bool,
+
+    /// Give every json token its vocabulary id and start and end ms as well as its text and
+    /// probability, for looking into what whisper decoded; special tokens aren't kept
+    #[arg(long)]
+    pub dump_tokens: bool,
+
+    /// Conversation id written on every ctm line, defaults to the input file stem
+    #[arg(long)]
+    pub ctm_conversation_id: Option<String>,
+
+    // the transcript's language, filled in once it's known, for the formats that record it
+    #[arg(skip)]
+    pub language: Option<String>,
+
+    // set by the commands that only replace outputs with --overwrite: the per-speaker files,
+    // which aren't named until the speakers are known, stop the write rather than replace a file
+    #[arg(skip)]
+    pub keep_existing: bool,
+
+    // what else a run writes beside each output, as the part of the name after <output>., e.g.
+    // meta.json; write_outputs doesn't write these, they're only checked and planned with the rest
+    #[arg(skip)]
+    pub beside: Vec<String>,
+
+    /// Move every timestamp by this much, as +00:01:23.500 or -5s, e.g. to line the
+    /// subtitles of an excerpt up with the programme it was cut from
+    #[arg(long, value_name = "TIME", default_value = "0", value_parser = parse_shift, allow_hyphen_values = true)]
+    pub shift: i64,
+
+    /// Also write the transcript through this minijinja template, as <output>.<its name less its
+    /// last extension>, e.g. cards.csv.j2 as <output>.cards.csv; it loops over segments
+    #[arg(long = "template", value_name = "FILE")]
+    pub templates: Vec<String>,
+
+    /// Also pipe the transcript, as json, through this shell command and write what it prints as
+    /// <output>.<program name>, e.g. ./to_anki.py as <output>.to_anki
+    #[arg(long = "format-cmd", value_name = "COMMAND")]
+    pub format_commands: Vec<String>,
+
+    /// Output file name, from {stem} (the output name), {lang} and {ext}
+    #[arg(long, default_value = "{stem}.{ext}", value_parser = parse_name_template)]
+    pub name_template: String,
+
+    /// Start subtitle files with a byte order mark, which utf-16le files nearly always want
+    #[arg(long)]
+    pub bom: bool,
+
+    /// End the lines of subtitle files with CRLF rather than LF
+    #[arg(long)]
+    pub crlf: bool,
+
+    /// Character encoding of subtitle files
+    #[arg(long, value_enum, default_value = "utf-8")]
+    pub encoding: Encoding,
+
+    #[command(flatten)]
+    pub layout: layout::Options,
+
+    #[command(flatten)]
+    pub chapters: chapters::Options,
+
+    #[arg(long, default_value = "Arial", help_heading = "ASS style")]
+    pub ass_font: String,
+
+    #[arg(long, default_value_t = 20, help_heading = "ASS style")]
+    pub ass_font_size: u32,
+
+    /// Text colour as RRGGBB
+    #[arg(long, default_value = "FFFFFF", value_parser = ass::parse_color, help_heading = "ASS style")]
+    pub ass_primary_color: u32,
+
+    /// Karaoke fill colour as RRGGBB
+    #[arg(long, default_value = "FF0000", value_parser = ass::parse_color, help_heading = "ASS style")]
+    pub ass_secondary_color: u32,
+
+    /// Outline colour as RRGGBB
+    #[arg(long, default_value = "000000", value_parser = ass::parse_color, help_heading = "ASS style")]
+    pub ass_outline_color: u32,
+
+    /// Shadow/box colour as RRGGBB
+    #[arg(long, default_value = "000000", value_parser = ass::parse_color, help_heading = "ASS style")]
+    pub ass_back_color: u32,
+
+    #[arg(long, default_value_t = 2.0, help_heading = "ASS style")]
+    pub ass_outline: f32,
+}
+
+// the clap defaults, so library-style callers and tests don't have to spell every option out
+impl Default for Options {
+    fn default() -> Options {
+        let matches = Options::augment_args(Command::new("options")).get_matches_from(["options"]);
+        Options::from_arg_matches(&matches).expect("option defaults are invalid")
+    }
+}
+
+// a time as --offset takes it, with an optional sign; in ms
+fn parse_shift(value: &str) -> Result<i64, String> {
+    let (sign, time) = match value.split_at(value.find(|c: char| c != '+' && c != '-').unwrap_or(0)) {
+        ("-", time) => (-1, time),
+        ("+" | "", time) => (1, time),
+        _ => return Err(format!("expected a time like +00:01:23.500 or -5s, got {}", value)),
+    };
+    Ok(sign * audio::parse_time(time)? as i64)
+}
+
+// every format needs its own file
+fn parse_name_template(value: &str) -> Result<String, String> {
+    if value.contains("{ext}") {
+        Ok(value.to_string())
+    } else {
+        Err("name template needs {ext}".to_string())
+    }
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Txt => "txt",
+            Format::Srt => "srt",
+            Format::Vtt => "vtt",
+            Format::Json => "json",
+            // plain .txt is already taken by the timestamped lines
+            Format::Text => "text.txt",
+            Format::Csv => "csv",
+            Format::Tsv => "tsv",
+            Format::Ass => "ass",
+            Format::Lrc => "lrc",
+            Format::Ttml => "ttml",
+            Format::Audacity => "labels.txt",
+            Format::Sbv => "sbv",
+            Format::Sami => "smi",
+            Format::Html => "html",
+            Format::Markdown => "md",
+            Format::Ctm => "ctm",
+            Format::Textgrid => "TextGrid",
+            Format::Eaf => "eaf",
+            Format::Podcast => "podcast.json",
+            Format::Karaoke => "karaoke.ass",
+            Format::Chapters => "chapters.txt",
+            Format::Ffmetadata => "ffmetadata",
+            Format::Speaking => "speaking.txt",
+            Format::SpeakingJson => "speaking.json",
+        }
+    }
+
+    pub fn render(&self, transcript: &Transcript, options: &Options) -> String {
+        // the subtitle formats show their segments as cues, so that's where cues are laid out
+        let laid_out = matches!(self, Format::Srt | Format::Vtt | Format::Ass | Format::Ttml | Format::Sbv | Format::Sami).then(|| layout::apply(transcript, &options.layout)).flatten();
+        let transcript = laid_out.as_ref().unwrap_or(transcript);
+        match self {
+            Format::Txt => txt::render(transcript, options),
+            Format::Srt => srt::render(transcript, options),
+            Format::Vtt => vtt::render(transcript),
+            Format::Json => json::render(transcript, options),
+            Format::Text => text::render(transcript, options),
+            Format::Csv => delimited::render(transcript, b',', options),
+            Format::Tsv => delimited::render(transcript, b'\t', options),
+            Format::Ass => ass::render(transcript, options),
+            Format::Lrc => lrc::render(transcript),
+            Format::Ttml => ttml::render(transcript, options),
+            Format::Audacity => audacity::render(transcript),
+            Format::Sbv => sbv::render(transcript),
+            Format::Sami => sami::render(transcript, options),
+            Format::Html => html::render(transcript, options),
+            Format::Markdown => markdown::render(transcript, options),
+            Format::Ctm => ctm::render(transcript, options),
+            Format::Textgrid => textgrid::render(transcript),
+            Format::Eaf => eaf::render(transcript, options),
+            Format::Podcast => podcast::render(transcript),
+            Format::Karaoke => karaoke::render(transcript, options),
+            Format::Chapters => chapters::youtube(transcript, &options.chapters),
+            Format::Ffmetadata => chapters::ffmetadata(transcript, &options.chapters),
+            Format::Speaking => speaking::Summary::new(transcript).render(),
+            Format::SpeakingJson => serde_json::to_string_pretty(&speaking::Summary::new(transcript)).expect("failed to serialize speaking statistics"),
+        }
+    }
+}
+
+// a file's bytes; --bom, --crlf and --encoding are for the players subtitles are loaded into, so
+// the other formats are always utf-8 as they were rendered
+pub fn encode(format: Format, contents: String, options: &Options) -> Vec<u8> {
+    if !matches!(format, Format::Srt | Format::Vtt | Format::Ass | Format::Ttml | Format::Sbv | Format::Sami | Format::Lrc | Format::Karaoke) {
+        return contents.into_bytes();
+    }
+    let contents = if options.crlf { contents.replace("\r\n", "\n").replace('\n', "\r\n") } else { contents };
+    let contents = if options.bom { format!("\u{feff}{}", contents) } else { contents };
+    match options.encoding {
+        Encoding::Utf8 => contents.into_bytes(),
+        Encoding::Utf16le => contents.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+    }
+}
+
+// hh:mm:ss followed by milliseconds, negative times clamp to zero
+fn clock(ms: i64, decimal_separator: char) -> String {
+    let positive_time = cmp::max(0, ms);
+    let millis = positive_time % 1000;
+    let seconds = (positive_time / 1000) % 60;
+    let minutes = (positive_time / 1000 / 60) % 60;
+    let hours = positive_time / 1000 / 60 / 60;
+    format!("{:02}:{:02}:{:02}{}{:03}", hours, minutes, seconds, decimal_separator, millis)
+}
+
+// known speakers label their segment, anonymous speaker changes get the ">> " subtitles use
+fn speaker_prefix(segment: &Segment) -> String {
+    match &segment.speaker {
+        Some(speaker) => format!("{}: ", speaker),
+        None if segment.speaker_turn => ">> ".to_string(),
+        None => String::new(),
+    }
+}
+
+fn display_text(segment: &Segment) -> String {
+    let prefix = speaker_prefix(segment);
+    if prefix.is_empty() {
+        segment.text.clone()
+    } else {
+        prefix + segment.text.trim_start()
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// written beside the destination and renamed over it, so an interrupted run never leaves a
+// truncated file behind
+pub fn write_to_file(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), Error> {
+    let failed = |error: io::Error| Error::output(format!("could not write {}", path.display()), error);
+    let name = path.file_name().ok_or_else(|| Error::Output(format!("output path {} needs a file name", path.display())))?.to_string_lossy();
+    let temporary = path.with_file_name(format!(".{}.{}.tmp", name, process::id()));
+    let mut file = File::create(&temporary).map_err(failed)?;
+    file.write_all(contents.as_ref()).map_err(failed)?;
+    file.sync_all().map_err(failed)?;
+    fs::rename(&temporary, path).map_err(failed)
+}
+
+// the template names the file in the output path's directory; split on / so uris work too
+pub fn path(output_path_raw: &str, format: Format, options: &Options, language: &str) -> String {
+    named(output_path_raw, format.extension(), options, language)
+}
+
+fn named(output_path_raw: &str, extension: &str, options: &Options, language: &str) -> String {
+    let (directory, stem) = match output_path_raw.rsplit_once('/') {
+        Some((directory, stem)) => (Some(directory), stem),
+        None => (None, output_path_raw),
+    };
+    let name = options.name_template.replace("{stem}", stem).replace("{lang}", language).replace("{ext}", extension);
+    match directory {
+        Some(directory) => format!("{}/{}", directory, name),
+        None => name,
+    }
+}
+
+// the files a format's output is at; with auto in place of the language and {lang} in the
+// template, the language isn't known until the audio's been heard, so it's whichever language's
+// files are there
+pub fn existing(output_path_raw: &str, format: Format, options: &Options, language: &str) -> Vec<String> {
+    let exact = path(output_path_raw, format, options, language);
+    if language != "auto" || !options.name_template.contains("{lang}") || storage::is_remote(output_path_raw) {
+        return if Path::new(&exact).exists() { vec![exact] } else { Vec::new() };
+    }
+    let pattern = path(output_path_raw, format, options, "{lang}");
+    let Some((before, after)) = pattern.split_once("{lang}") else { return Vec::new() };
+    let directory = Path::new(&pattern).parent().filter(|directory| !directory.as_os_str().is_empty());
+    let Ok(entries) = fs::read_dir(directory.unwrap_or(Path::new("."))) else { return Vec::new() };
+    let mut found: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match directory {
+            Some(directory) => directory.join(entry.file_name()).to_string_lossy().into_owned(),
+            None => entry.file_name().to_string_lossy().into_owned(),
+        })
+        .filter(|candidate| {
+            let language = candidate.strip_prefix(before).and_then(|rest| rest.strip_suffix(after));
+            language.is_some_and(|language| !language.is_empty() && language.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+// a speaker's name as it goes in a file name, lowercase with anything but letters and digits a -
+fn speaker_slug(speaker: &str) -> String {
+    let slug: String = speaker.chars().map(|c| if c.is_alphanumeric() { c } else { '-' }).flat_map(char::to_lowercase).collect();
+    let slug = slug.split('-').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("-");
+    if slug.is_empty() {
+        "speaker".to_string()
+    } else {
+        slug
+    }
+}
+
+// --split-by-speaker: every speaker's segments in order of who spoke first, without the name on
+// each line since it's in the file's; speakers whose names come to the same slug share a file
+fn by_speaker(transcript: &Transcript) -> Vec<(String, Transcript)> {
+    let mut speakers: Vec<(String, Transcript)> = Vec::new();
+    for segment in &transcript.segments {
+        let Some(speaker) = &segment.speaker else { continue };
+        let slug = speaker_slug(speaker);
+        let segment = Segment { speaker: None, speaker_turn: false, ..segment.clone() };
+        match speakers.iter_mut().find(|(name, _)| *name == slug) {
+            Some((_, transcript)) => transcript.segments.push(segment),
+            None => speakers.push((slug, Transcript { segments: vec![segment] })),
+        }
+    }
+    speakers
+}
+
+// every file write_outputs writes for one output path, the templates' and commands' as well as
+// the formats'
+pub fn paths(output_path_raw: &str, formats: &[Format], options: &Options, language: &str) -> Vec<String> {
+    formats.iter().map(|format| path(output_path_raw, *format, options, language)).chain(extra_paths(output_path_raw, options, language)).collect()
+}
+
+// where each --format-cmd's and --template's output goes, then what's written beside them
+pub fn extra_paths(output_path_raw: &str, options: &Options, language: &str) -> Vec<String> {
+    let commands = options.format_commands.iter().map(|command| named(output_path_raw, &command::extension(command), options, language));
+    let templates = options.templates.iter().map(|template| named(output_path_raw, &template::extension(template), options, language));
+    let beside = options.beside.iter().map(|name| format!("{}.{}", output_path_raw, name));
+    commands.chain(templates).chain(beside).collect()
+}
+
+pub fn write_outputs(transcript: &Transcript, output_path_raw: &str, formats: &[Format], options: &Options, language: &str) -> Result<(), Error> {
+    if options.split_by_speaker {
+        let speakers = by_speaker(transcript);
+        if speakers.is_empty() {
+            tracing::warn!("--split-by-speaker: the transcript has no speakers to split it by");
+        }
+        // the run's own files beside the outputs are only beside the whole transcript's
+        let options = Options { split_by_speaker: false, beside: Vec::new(), ..options.clone() };
+        // all checked before any is written, so a clash doesn't leave some speakers' files new
+        if options.keep_existing {
+            let speaker_paths = speakers.iter().flat_map(|(speaker, _)| paths(&format!("{}.{}", output_path_raw, speaker), formats, &options, language));
+            if let Some(path) = speaker_paths.filter(|path| !storage::is_remote(path)).find(|path| Path::new(path).exists()) {
+                return Err(Error::Output(format!("{} already exists, pass --overwrite to replace it", path)));
+            }
+        }
+        for (speaker, transcript) in &speakers {
+            write_outputs(transcript, &format!("{}.{}", output_path_raw, speaker), formats, &options, language)?;
+        }
+    }
+    let options = &Options { output_path: Some(output_path_raw.to_string()), ..options.clone() };
+    let templates = options.templates.iter().map(|path| template::Template::load(path)).collect::<Result<Vec<_>, Error>>()?;
+    for format in formats {
+        let path = path(output_path_raw, *format, options, language);
+        let contents = encode(*format, format.render(transcript, options), options);
+        if storage::is_remote(&path) {
+            storage::put(&path, contents)?;
+        } else {
+            write_to_file(Path::new(&path), contents)?;
+        }
+    }
+    for command in &options.format_commands {
+        let path = named(output_path_raw, &command::extension(command), options, language);
+        let contents = command::render(command, transcript, options)?;
+        if storage::is_remote(&path) {
+            storage::put(&path, contents)?;
+        } else {
+            write_to_file(Path::new(&path), contents)?;
+        }
+    }
+    for template in &templates {
+        let path = named(output_path_raw, template.extension(), options, language);
+        let contents = template.render(transcript, options)?;
+        if storage::is_remote(&path) {
+            storage::put(&path, contents)?;
+        } else {
+            write_to_file(Path::new(&path), contents)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _by_speaker() {
+        let segment = |start_ms, speaker: Option<&str>| Segment { speaker: speaker.map(String::from), speaker_turn: true, ..Segment::new(start_ms, start_ms + 500, " Hi.") };
+        let transcript = Transcript { segments: vec![segment(0, Some("Dr. Alice Smith")), segment(500, Some("SPEAKER_1")), segment(1000, None), segment(1500, Some("dr alice smith"))] };
+        let speakers = by_speaker(&transcript);
+        assert_eq!(speakers.iter().map(|(name, transcript)| (name.as_str(), transcript.segments.len())).collect::<Vec<_>>(), vec![("dr-alice-smith", 2), ("speaker-1", 1)]);
+        assert!(speakers[0].1.segments.iter().all(|segment| segment.speaker.is_none() && !segment.speaker_turn));
+        assert_eq!(speaker_slug("--"), "speaker");
+
+        let dir = std::env::temp_dir().join(format!("transcript-by-speaker-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("talk").to_string_lossy().into_owned();
+        fs::write(format!("{}.speaker-1.txt", output), "kept").unwrap();
+        let options = Options { split_by_speaker: true, keep_existing: true, ..Options::default() };
+        assert!(write_outputs(&transcript, &output, &[Format::Txt], &options, "en").is_err());
+        assert!(!Path::new(&format!("{}.dr-alice-smith.txt", output)).exists());
+        write_outputs(&transcript, &output, &[Format::Txt], &Options { keep_existing: false, ..options }, "en").unwrap();
+        assert_ne!(fs::read_to_string(format!("{}.speaker-1.txt", output)).unwrap(), "kept");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn _markdown_anchor_interval() {
+        let parse = |interval: &str| Options::augment_args(Command::new("options")).try_get_matches_from(["options", "--markdown-anchor-interval", interval]);
+        assert!(parse("0").is_err());
+        assert_eq!(Options::from_arg_matches(&parse("5").unwrap()).unwrap().markdown_anchor_interval, Some(5));
+    }
+
+    #[test]
+    fn _parse_shift() {
+        assert_eq!(parse_shift("+00:01:23.500"), Ok(83_500));
+        assert_eq!(parse_shift("-5s"), Ok(-5_000));
+        assert_eq!(parse_shift("90"), Ok(90_000));
+        assert!(parse_shift("+-5").is_err());
+        assert!(parse_shift("--5").is_err());
+    }
+
+    #[test]
+    fn _encode() {
+        let options = Options { bom: true, crlf: true, encoding: Encoding::Utf16le, ..Options::default() };
+        assert_eq!(encode(Format::Srt, "é\n".to_string(), &options), [0xff, 0xfe, 0xe9, 0x00, b'\r', 0x00, b'\n', 0x00]);
+        assert_eq!(encode(Format::Vtt, "a\r\nb\n".to_string(), &Options { crlf: true, ..Options::default() }), b"a\r\nb\r\n");
+        assert_eq!(encode(Format::Json, "{}\n".to_string(), &options), b"{}\n");
+    }
+
+    #[test]
+    fn _path() {
+        let options = Options { name_template: "{stem}.{lang}.{ext}".to_string(), ..Options::default() };
+        assert_eq!(path("out/episode", Format::Vtt, &options, "de"), "out/episode.de.vtt");
+        assert_eq!(path("s3://bucket/episode", Format::Srt, &options, "en"), "s3://bucket/episode.en.srt");
+
+        let dir = std::env::temp_dir().join(format!("transcript-existing-test-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["one.de.srt", "one.en.vtt", "one.srt", "two.en.srt"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let one = format!("{}/one", dir.display());
+        assert_eq!(existing(&one, Format::Srt, &options, "auto"), [format!("{}.de.srt", one)]);
+        assert_eq!(existing(&one, Format::Srt, &options, "en"), Vec::<String>::new());
+        assert_eq!(existing(&one, Format::Srt, &Options::default(), "auto"), [format!("{}.srt", one)]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(path("episode", Format::Text, &Options::default(), "en"), "episode.text.txt");
+    }
+}
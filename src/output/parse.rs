@@ -0,0 +1,123 @@
+use std::fs;
+use std::path::Path;
+
+use crate::audio;
+use crate::error::Error;
+use crate::transcript::{Segment, Transcript};
+
+use super::Format;
+
+// a cue's time as srt (with a comma), vtt (hours optional) or sbv write it; in ms
+fn time(value: &str) -> Result<i64, String> {
+    audio::parse_time(&value.trim().replace(',', ".")).map(|ms| ms as i64)
+}
+
+// markup such as <i> and vtt's word timestamps left out, vtt's escapes undone and the spaces they
+// leave behind collapsed
+fn plain(line: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in line.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text.replace("&lt;", "<").replace("&gt;", ">").replace("&nbsp;", " ").replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// blocks of lines between blank ones, each a cue when it has a line of times; vtt's header, notes
+// and styles have none; text lines are kept as the cue's lines, with the leading space whisper's
+// segments start with
+fn cues(contents: &str, times: impl Fn(&str) -> Option<(&str, &str)>) -> Result<Transcript, String> {
+    let contents = contents.replace("\r\n", "\n");
+    let mut segments = Vec::new();
+    for block in contents.split("\n\n").map(str::trim).filter(|block| !block.is_empty()) {
+        let lines: Vec<&str> = block.lines().collect();
+        let Some(at) = lines.iter().position(|line| times(line).is_some()) else { continue };
+        let (start, end) = times(lines[at]).expect("the line has times");
+        let text = lines[at + 1..].iter().map(|line| plain(line)).collect::<Vec<_>>().join("\n");
+        let invalid = |error: String| format!("cue {:?}: {}", lines[at], error);
+        segments.push(Segment::new(time(start).map_err(invalid)?, time(end).map_err(invalid)?, &format!(" {}", text.trim())));
+    }
+    Ok(Transcript { segments })
+}
+
+// srt and vtt put "start --> end" on a line, vtt perhaps followed by cue settings
+fn arrow(line: &str) -> Option<(&str, &str)> {
+    let (start, rest) = line.split_once("-->")?;
+    Some((start, rest.split_whitespace().next()?))
+}
+
+// sbv puts "start,end" on the line, neither with a comma of its own
+fn comma(line: &str) -> Option<(&str, &str)> {
+    line.split_once(',').filter(|(start, end)| start.contains(':') && end.contains(':'))
+}
+
+// a subtitle file back as a transcript, one segment per cue; json as the json format writes it
+pub fn parse(contents: &str, format: Format) -> Result<Transcript, String> {
+    match format {
+        Format::Srt | Format::Vtt => cues(contents, arrow),
+        Format::Sbv => cues(contents, comma),
+        Format::Json => serde_json::from_str(contents).map_err(|error| error.to_string()),
+        other => Err(format!("{} files can't be read back, only srt, vtt, sbv and json", other.extension())),
+    }
+}
+
+// the format a file can be read back in, told by its extension
+pub fn format(path: &str) -> Option<Format> {
+    let extension = Path::new(path).extension().map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "srt" => Some(Format::Srt),
+        "vtt" => Some(Format::Vtt),
+        "sbv" => Some(Format::Sbv),
+        "json" => Some(Format::Json),
+        _ => None,
+    }
+}
+
+// utf-8, with or without a byte order mark, or utf-16le with one, as --encoding and --bom write them
+fn text(bytes: &[u8]) -> Result<String, String> {
+    match bytes {
+        [0xff, 0xfe, rest @ ..] if rest.len() % 2 == 0 => String::from_utf16(&rest.chunks(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect::<Vec<u16>>()).map_err(|error| error.to_string()),
+        [0xef, 0xbb, 0xbf, rest @ ..] | rest => String::from_utf8(rest.to_vec()).map_err(|error| error.to_string()),
+    }
+}
+
+pub fn read(path: &str) -> Result<Transcript, Error> {
+    let format = format(path).ok_or_else(|| Error::Input(format!("can't tell what {} is, expected an .srt, .vtt, .sbv or .json file", path)))?;
+    let bytes = fs::read(path).map_err(|error| Error::input(format!("failed to read {}", path), error))?;
+    let contents = text(&bytes).map_err(|error| Error::input(format!("failed to read {}", path), error))?;
+    parse(&contents, format).map_err(|error| Error::input(format!("failed to read {}", path), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _parse() {
+        let srt = "1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>Hello</i> there,\r\nfriend.\r\n\r\n2\r\n00:01:00,000 --> 00:01:01,000\r\nBye.\r\n";
+        let transcript = parse(srt, Format::Srt).unwrap();
+        assert_eq!(transcript.segments, vec![Segment::new(1000, 2500, " Hello there,\nfriend."), Segment::new(60_000, 61_000, " Bye.")]);
+
+        let vtt = "WEBVTT\n\nNOTE a comment\n\nintro\n00:01.000 --> 00:02.000 align:start\nFish <00:00:01.500> &amp; chips\n";
+        assert_eq!(parse(vtt, Format::Vtt).unwrap().segments, vec![Segment::new(1000, 2000, " Fish & chips")]);
+
+        let sbv = "0:00:01.000,0:00:02.000\nHi, you.\n";
+        assert_eq!(parse(sbv, Format::Sbv).unwrap().segments, vec![Segment::new(1000, 2000, " Hi, you.")]);
+
+        assert!(parse("1\n00:00:01,000 --> soon\nHi.\n", Format::Srt).is_err());
+        assert!(parse("", Format::Lrc).is_err());
+    }
+
+    #[test]
+    fn _text() {
+        assert_eq!(text(b"\xef\xbb\xbfHi"), Ok("Hi".to_string()));
+        assert_eq!(text(&[0xff, 0xfe, b'H', 0, b'i', 0]), Ok("Hi".to_string()));
+        assert!(text(&[0xff, 0xfe, b'H']).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use serde::Serialize;
+
+use crate::transcript::Transcript;
+
+#[derive(Serialize)]
+struct PodcastTranscript<'a> {
+    version: &'static str,
+    segments: Vec<PodcastSegment<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PodcastSegment<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speaker: Option<&'a str>,
+    start_time: f64,
+    end_time: f64,
+    body: &'a str,
+}
+
+pub fn render(transcript: &Transcript) -> String {
+    let podcast = PodcastTranscript {
+        version: "1.0.0",
+        segments: transcript
+            .segments
+            .iter()
+            .map(|segment| PodcastSegment {
+                speaker: segment.speaker.as_deref(),
+                start_time: segment.start_ms as f64 / 1000.0,
+                end_time: segment.end_ms as f64 / 1000.0,
+                body: segment.text.trim(),
+            })
+            .collect(),
+    };
+    serde_json::to_string_pretty(&podcast).expect("failed to serialize transcript")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _render() {
+        let transcript = Transcript { segments: vec![Segment::new(500, 1250, " Hello")] };
+        let value: serde_json::Value = serde_json::from_str(&render(&transcript)).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "version": "1.0.0",
+                "segments": [{ "startTime": 0.5, "endTime": 1.25, "body": "Hello" }]
+            })
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use super::{display_text, escape_xml, Options};
+use crate::transcript::Transcript;
+
+// the caption class SAMI players pick a language by, ENCC for en
+fn class(language: &str) -> String {
+    format!("{}CC", language.to_uppercase())
+}
+
+// html 4 has no &apos;, and the players that read SAMI are that old
+fn escape(text: &str) -> String {
+    escape_xml(text).replace("&apos;", "'")
+}
+
+// each cue a SYNC at its start, cleared with a blank one at its end unless the next cue starts
+// there anyway
+pub fn render(transcript: &Transcript, options: &Options) -> String {
+    let language = options.language.as_deref().filter(|language| *language != "auto").unwrap_or("en");
+    let class = class(language);
+    let mut sami = String::new();
+    sami.push_str("<SAMI>\n<HEAD>\n<SAMIParam>\n  Metrics {time:ms;}\n  Spec {MSFT:1.0;}\n</SAMIParam>\n");
+    sami.push_str("<STYLE TYPE=\"text/css\">\n<!--\nP { font-family: Arial; font-weight: normal; color: white; background-color: black; text-align: center; }\n");
+    sami.push_str(&format!(".{} {{ name: {}; lang: {}; SAMIType: CC; }}\n-->\n</STYLE>\n</HEAD>\n<BODY>\n", class, language, language));
+    for (i, segment) in transcript.segments.iter().enumerate() {
+        let text = escape(display_text(segment).trim()).replace('\n', "<BR>");
+        sami.push_str(&format!("<SYNC Start={}><P Class={}>{}</P></SYNC>\n", segment.start_ms.max(0), class, text));
+        if transcript.segments.get(i + 1).is_none_or(|next| next.start_ms > segment.end_ms) {
+            sami.push_str(&format!("<SYNC Start={}><P Class={}>&nbsp;</P></SYNC>\n", segment.end_ms.max(0), class));
+        }
+    }
+    sami.push_str("</BODY>\n</SAMI>\n");
+    sami
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _render() {
+        let transcript = Transcript { segments: vec![Segment::new(0, 1500, " Fish & <chips>"), Segment::new(1500, 3000, " It's\nlate."), Segment::new(4000, 5000, " Bye.")] };
+        let options = Options { language: Some("de".to_string()), ..Options::default() };
+        let sami = render(&transcript, &options);
+        assert!(sami.contains(".DECC { name: de; lang: de; SAMIType: CC; }"), "{}", sami);
+        assert!(sami.ends_with(
+            "<BODY>\n<SYNC Start=0><P Class=DECC>Fish &amp; &lt;chips&gt;</P></SYNC>\n<SYNC Start=1500><P Class=DECC>It's<BR>late.</P></SYNC>\n<SYNC Start=3000><P Class=DECC>&nbsp;</P></SYNC>\n<SYNC Start=4000><P Class=DECC>Bye.</P></SYNC>\n<SYNC Start=5000><P Class=DECC>&nbsp;</P></SYNC>\n</BODY>\n</SAMI>\n"
+        ), "{}", sami);
+    }
+}
@@ -0,0 +1,33 @@
+use std::cmp;
+
+use crate::transcript::Transcript;
+
+pub fn timestamp(ms: i64) -> String {
+    let positive_time = cmp::max(0, ms);
+    let millis = positive_time % 1000;
+    let seconds = (positive_time / 1000) % 60;
+    let minutes = (positive_time / 1000 / 60) % 60;
+    let hours = positive_time / 1000 / 60 / 60;
+    format!("{}:{:02}:{:02}.{:03}", hours, minutes, seconds, millis)
+}
+
+pub fn render(transcript: &Transcript) -> String {
+    let mut sbv = String::new();
+    for segment in &transcript.segments {
+        let start = timestamp(segment.start_ms);
+        let end = timestamp(segment.end_ms);
+        sbv.push_str(&format!("{start},{end}\n{}\n\n", super::display_text(segment).trim()));
+    }
+    sbv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _timestamp() {
+        assert_eq!(timestamp(19990), "0:00:19.990");
+        assert_eq!(timestamp(56025550), "15:33:45.550");
+    }
+}
@@ -0,0 +1,177 @@
+use serde::Serialize;
+
+use crate::transcript::Transcript;
+
+// pauses are counted into these, in seconds; the last takes everything from 5s up
+const BUCKETS: [f64; 4] = [0.5, 1.0, 2.0, 5.0];
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Speaker {
+    pub name: String,
+    pub talk_seconds: f64,
+    // of everyone's talk time
+    pub share: f64,
+    pub words: usize,
+    pub words_per_minute: f64,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Bucket {
+    pub from_seconds: f64,
+    pub to_seconds: Option<f64>,
+    pub count: usize,
+}
+
+// the silences between one segment and the next, overlapping segments having none
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Pauses {
+    pub count: usize,
+    pub total_seconds: f64,
+    pub mean_seconds: f64,
+    pub median_seconds: f64,
+    pub longest_seconds: f64,
+    pub buckets: Vec<Bucket>,
+}
+
+// how the transcript was spoken: words per minute count only the time someone was talking
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Summary {
+    pub words: usize,
+    pub segments: usize,
+    pub duration_seconds: f64,
+    pub talk_seconds: f64,
+    pub words_per_minute: f64,
+    pub average_segment_seconds: f64,
+    pub average_segment_words: f64,
+    // by talk time, only when the segments have speakers
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub speakers: Vec<Speaker>,
+    pub pauses: Pauses,
+}
+
+fn per_minute(words: usize, seconds: f64) -> f64 {
+    if seconds > 0.0 { words as f64 * 60.0 / seconds } else { 0.0 }
+}
+
+impl Summary {
+    pub fn new(transcript: &Transcript) -> Summary {
+        let segments: Vec<_> = transcript.segments.iter().filter(|segment| !segment.text.trim().is_empty()).collect();
+        let seconds = |ms: i64| ms.max(0) as f64 / 1000.0;
+        let words = |text: &str| text.split_whitespace().count();
+        let total_words = segments.iter().map(|segment| words(&segment.text)).sum();
+        let talk_seconds: f64 = segments.iter().map(|segment| seconds(segment.end_ms - segment.start_ms)).sum();
+
+        let mut speakers: Vec<Speaker> = Vec::new();
+        for segment in &segments {
+            let Some(name) = &segment.speaker else { continue };
+            let index = speakers.iter().position(|speaker| &speaker.name == name).unwrap_or_else(|| {
+                speakers.push(Speaker { name: name.clone(), talk_seconds: 0.0, share: 0.0, words: 0, words_per_minute: 0.0 });
+                speakers.len() - 1
+            });
+            speakers[index].talk_seconds += seconds(segment.end_ms - segment.start_ms);
+            speakers[index].words += words(&segment.text);
+        }
+        for speaker in &mut speakers {
+            speaker.share = if talk_seconds > 0.0 { speaker.talk_seconds / talk_seconds } else { 0.0 };
+            speaker.words_per_minute = per_minute(speaker.words, speaker.talk_seconds);
+        }
+        speakers.sort_by(|a, b| b.talk_seconds.total_cmp(&a.talk_seconds));
+
+        let mut gaps: Vec<f64> = segments.windows(2).map(|pair| seconds(pair[1].start_ms - pair[0].end_ms)).filter(|gap| *gap > 0.0).collect();
+        gaps.sort_by(f64::total_cmp);
+        let total_pause: f64 = gaps.iter().sum();
+        let median = match gaps.len() {
+            0 => 0.0,
+            n if n % 2 == 1 => gaps[n / 2],
+            n => (gaps[n / 2 - 1] + gaps[n / 2]) / 2.0,
+        };
+        let buckets = (0..=BUCKETS.len())
+            .map(|i| {
+                let from_seconds = if i == 0 { 0.0 } else { BUCKETS[i - 1] };
+                let to_seconds = BUCKETS.get(i).copied();
+                let count = gaps.iter().filter(|gap| **gap >= from_seconds && to_seconds.is_none_or(|to| **gap < to)).count();
+                Bucket { from_seconds, to_seconds, count }
+            })
+            .collect();
+
+        let count = segments.len().max(1) as f64;
+        Summary {
+            words: total_words,
+            segments: segments.len(),
+            duration_seconds: segments.first().zip(segments.last()).map_or(0.0, |(first, last)| seconds(last.end_ms - first.start_ms)),
+            talk_seconds,
+            words_per_minute: per_minute(total_words, talk_seconds),
+            average_segment_seconds: talk_seconds / count,
+            average_segment_words: total_words as f64 / count,
+            speakers,
+            pauses: Pauses {
+                count: gaps.len(),
+                total_seconds: total_pause,
+                mean_seconds: if gaps.is_empty() { 0.0 } else { total_pause / gaps.len() as f64 },
+                median_seconds: median,
+                longest_seconds: gaps.last().copied().unwrap_or(0.0),
+                buckets,
+            },
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut lines = vec![
+            format!("words             {}", self.words),
+            format!("segments          {}", self.segments),
+            format!("duration          {:.1}s", self.duration_seconds),
+            format!("talk time         {:.1}s", self.talk_seconds),
+            format!("words per minute  {:.1}", self.words_per_minute),
+            format!("average segment   {:.1}s, {:.1} words", self.average_segment_seconds, self.average_segment_words),
+            format!("pauses            {}, {:.1}s in all", self.pauses.count, self.pauses.total_seconds),
+            format!("  mean            {:.2}s", self.pauses.mean_seconds),
+            format!("  median          {:.2}s", self.pauses.median_seconds),
+            format!("  longest         {:.2}s", self.pauses.longest_seconds),
+        ];
+        for bucket in &self.pauses.buckets {
+            let range = match bucket.to_seconds {
+                Some(to) => format!("{}s to {}s", bucket.from_seconds, to),
+                None => format!("{}s and more", bucket.from_seconds),
+            };
+            lines.push(format!("  {:<16}{}", range, bucket.count));
+        }
+        if !self.speakers.is_empty() {
+            let width = self.speakers.iter().map(|speaker| speaker.name.chars().count()).max().unwrap_or(0).max("speaker".len());
+            lines.push(String::new());
+            lines.push(format!("{:<width$}  talk time  share  words    wpm", "speaker"));
+            for speaker in &self.speakers {
+                lines.push(format!("{:<width$}  {:>8.1}s  {:>4.0}%  {:>5}  {:>5.1}", speaker.name, speaker.talk_seconds, speaker.share * 100.0, speaker.words, speaker.words_per_minute));
+            }
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _new() {
+        let segment = |start_ms, end_ms, text: &str, speaker: &str| Segment { speaker: Some(speaker.to_string()), ..Segment::new(start_ms, end_ms, text) };
+        let transcript = Transcript {
+            segments: vec![
+                segment(0, 3000, " One two three four five six.", "Ann"),
+                segment(3500, 4500, " Seven eight.", "Bob"),
+                segment(4300, 6000, " Nine ten eleven.", "Ann"),
+                Segment::new(6000, 6100, " "),
+                segment(12_000, 15_000, " Twelve.", "Bob"),
+            ],
+        };
+        let summary = Summary::new(&transcript);
+        assert_eq!((summary.words, summary.segments, summary.duration_seconds), (12, 4, 15.0));
+        assert!((summary.talk_seconds - 8.7).abs() < 1e-9);
+        assert_eq!(summary.pauses.count, 2);
+        assert_eq!((summary.pauses.median_seconds, summary.pauses.longest_seconds), (3.25, 6.0));
+        assert_eq!(summary.pauses.buckets.iter().map(|bucket| bucket.count).collect::<Vec<_>>(), vec![0, 1, 0, 0, 1]);
+        assert_eq!(summary.speakers.iter().map(|speaker| (speaker.name.as_str(), speaker.words)).collect::<Vec<_>>(), vec![("Ann", 9), ("Bob", 3)]);
+        assert_eq!(summary.speakers[1].words_per_minute, 45.0);
+        assert!(summary.render().contains("words per minute  82.8\n"));
+    }
+}
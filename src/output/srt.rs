@@ -0,0 +1,38 @@
+use super::{display_text, Options};
+use crate::transcript::Transcript;
+
+pub fn timestamp(ms: i64) -> String {
+    super::clock(ms, ',')
+}
+
+pub fn render(transcript: &Transcript, options: &Options) -> String {
+    let mut srt = String::new();
+    for (i, segment) in (options.start_index..).zip(&transcript.segments) {
+        let start = timestamp(segment.start_ms);
+        let end = timestamp(segment.end_ms);
+        srt.push_str(&format!("{}\n{start} --> {end}\n{}\n\n", i, display_text(segment)));
+    }
+    srt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _timestamp() {
+        assert_eq!(timestamp(19990), "00:00:19,990");
+        assert_eq!(timestamp(8388500), "02:19:48,500");
+        assert_eq!(timestamp(56025550), "15:33:45,550");
+        assert_eq!(timestamp(-45500), "00:00:00,000");
+    }
+
+    #[test]
+    fn _render() {
+        let turn = Segment { speaker_turn: true, ..Segment::new(1000, 2000, " Hi.") };
+        let transcript = Transcript { segments: vec![Segment::new(0, 1000, " Hello."), turn] };
+        assert_eq!(render(&transcript, &Options::default()), "1\n00:00:00,000 --> 00:00:01,000\n Hello.\n\n2\n00:00:01,000 --> 00:00:02,000\n>> Hi.\n\n");
+        assert!(render(&transcript, &Options { start_index: 0, ..Options::default() }).starts_with("0\n"));
+    }
+}
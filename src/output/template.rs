@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::Path;
+
+use minijinja::value::{Serde, Value};
+use minijinja::{context, Environment};
+use serde_json::json;
+
+use super::{clock, Options};
+use crate::error::Error;
+use crate::transcript::Transcript;
+
+// --template: a format of the user's own, a minijinja template rendered once per file with the
+// transcript's segments to loop over
+pub struct Template {
+    environment: Environment<'static>,
+    name: String,
+}
+
+// ms as 00:01:02.500, or with another separator such as srt's comma
+fn timestamp(ms: i64, separator: Option<String>) -> String {
+    clock(ms, separator.and_then(|separator| separator.chars().next()).unwrap_or('.'))
+}
+
+// the extension a template's output is written with: its own file name without the last
+// extension, so anki.csv.j2 is written as <output>.anki.csv
+fn strip_extension(name: &str) -> &str {
+    name.rsplit_once('.').map_or(name, |(stem, _)| stem)
+}
+
+// as above from the template's path, for naming its output without loading it
+pub fn extension(path: &str) -> String {
+    let name = Path::new(path).file_name().map_or_else(|| path.to_string(), |name| name.to_string_lossy().into_owned());
+    strip_extension(&name).to_string()
+}
+
+impl Template {
+    // compiled as it's loaded, so a mistake in it turns up before anything is decoded
+    pub fn load(path: &str) -> Result<Template, Error> {
+        let source = fs::read_to_string(path).map_err(|error| Error::input(format!("failed to read template {}", path), error))?;
+        let name = Path::new(path).file_name().map_or_else(|| path.to_string(), |name| name.to_string_lossy().into_owned());
+        let mut environment = Environment::new();
+        environment.add_filter("timestamp", timestamp);
+        environment.add_filter("seconds", |ms: i64| ms as f64 / 1000.0);
+        environment.add_template_owned(name.clone(), source).map_err(|error| Error::input(format!("template {} is invalid", path), error))?;
+        Ok(Template { environment, name })
+    }
+
+    pub fn extension(&self) -> &str {
+        strip_extension(&self.name)
+    }
+
+    // segments has each segment as the json format has it, with its number, its trimmed text and
+    // its confidence; the name decides the escaping, html for .html and .xml, json for .json
+    pub fn render(&self, transcript: &Transcript, options: &Options) -> Result<String, Error> {
+        let segments: Vec<serde_json::Value> = transcript
+            .segments
+            .iter()
+            .enumerate()
+            .map(|(i, segment)| {
+                let mut value = serde_json::to_value(segment).expect("failed to serialize segment");
+                value["index"] = json!(i + 1);
+                value["text"] = json!(super::display_text(segment).trim());
+                value["confidence"] = json!(segment.confidence());
+                value
+            })
+            .collect();
+        let text = transcript.segments.iter().map(|segment| segment.text.trim()).filter(|text| !text.is_empty()).collect::<Vec<_>>().join(" ");
+        let context = context! { segments => Value::from(Serde(&segments)), text, language => options.language.clone() };
+        let template = self.environment.get_template(&self.name).expect("the template was added as it was loaded");
+        template.render(context).map_err(|error| Error::input(format!("failed to render template {}", self.name), error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _render() {
+        let path = std::env::temp_dir().join(format!("transcript-template-test-{}.cards.xml.j2", std::process::id()));
+        fs::write(&path, "{% for segment in segments %}<card n=\"{{ segment.index }}\" at=\"{{ segment.start_ms | timestamp(',') }}\">{{ segment.text }}</card>\n{% endfor %}{{ language }}").unwrap();
+        let template = Template::load(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(template.extension().ends_with(".cards.xml"));
+
+        let transcript = Transcript { segments: vec![Segment::new(1500, 2000, " Fish & chips.")] };
+        let options = Options { language: Some("en".to_string()), ..Options::default() };
+        assert_eq!(template.render(&transcript, &options).unwrap(), "<card n=\"1\" at=\"00:00:01,500\">Fish &amp; chips.</card>\nen");
+    }
+}
@@ -0,0 +1,48 @@
+use super::Options;
+use crate::transcript::Transcript;
+
+pub fn render(transcript: &Transcript, options: &Options) -> String {
+    let pause_ms = (options.paragraph_pause * 1000.0) as i64;
+    let mut prose = String::new();
+    let mut previous_end: Option<i64> = None;
+
+    for segment in &transcript.segments {
+        // prose only names the speaker where they take over
+        let text = if segment.speaker_turn || previous_end.is_none() { super::display_text(segment) } else { segment.text.clone() };
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        match previous_end {
+            Some(end) if segment.speaker_turn || segment.start_ms - end >= pause_ms => prose.push_str("\n\n"),
+            Some(_) => prose.push(' '),
+            None => {}
+        }
+        prose.push_str(text);
+        previous_end = Some(segment.end_ms);
+    }
+
+    if !prose.is_empty() {
+        prose.push('\n');
+    }
+    prose
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _render() {
+        let transcript = Transcript {
+            segments: vec![
+                Segment::new(0, 1000, " Hello there."),
+                Segment::new(1200, 2000, " How are you?"),
+                Segment::new(5000, 6000, " Fine."),
+            ],
+        };
+        let options = Options::default();
+        assert_eq!(render(&transcript, &options), "Hello there. How are you?\n\nFine.\n");
+    }
+}
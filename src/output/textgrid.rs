@@ -0,0 +1,91 @@
+use std::cmp;
+
+use crate::transcript::{Segment, Transcript};
+
+fn seconds(ms: i64) -> String {
+    format!("{}", ms as f64 / 1000.0)
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+// interval tiers have to cover the whole file without overlaps, so gaps become empty intervals
+fn intervals(segments: &[&Segment], xmax: i64) -> Vec<(i64, i64, String)> {
+    let mut intervals = Vec::new();
+    let mut cursor = 0;
+    for segment in segments {
+        let start = cmp::max(cursor, segment.start_ms);
+        let end = cmp::min(segment.end_ms, xmax);
+        if end <= start {
+            continue;
+        }
+        if start > cursor {
+            intervals.push((cursor, start, String::new()));
+        }
+        intervals.push((start, end, segment.text.trim().to_string()));
+        cursor = end;
+    }
+    if cursor < xmax || intervals.is_empty() {
+        intervals.push((cursor, xmax, String::new()));
+    }
+    intervals
+}
+
+pub fn render(transcript: &Transcript) -> String {
+    let xmax = transcript.segments.iter().map(|segment| segment.end_ms).max().unwrap_or(0).max(0);
+    // a tier per speaker in order of appearance, or a single one without speakers
+    let mut tiers: Vec<(&str, Vec<&Segment>)> = Vec::new();
+    for segment in &transcript.segments {
+        let name = segment.speaker.as_deref().unwrap_or("transcript");
+        match tiers.iter_mut().find(|(tier, _)| *tier == name) {
+            Some((_, segments)) => segments.push(segment),
+            None => tiers.push((name, vec![segment])),
+        }
+    }
+    if tiers.is_empty() {
+        tiers.push(("transcript", Vec::new()));
+    }
+
+    let mut grid = String::new();
+    grid.push_str("File type = \"ooTextFile\"\nObject class = \"TextGrid\"\n\n");
+    grid.push_str(&format!("xmin = 0\nxmax = {}\ntiers? <exists>\nsize = {}\nitem []:\n", seconds(xmax), tiers.len()));
+
+    for (tier_index, (name, segments)) in tiers.iter().enumerate() {
+        let intervals = intervals(segments, xmax);
+        grid.push_str(&format!("    item [{}]:\n", tier_index + 1));
+        grid.push_str("        class = \"IntervalTier\"\n");
+        grid.push_str(&format!("        name = {}\n", quote(name)));
+        grid.push_str(&format!("        xmin = 0\n        xmax = {}\n", seconds(xmax)));
+        grid.push_str(&format!("        intervals: size = {}\n", intervals.len()));
+        for (i, (start, end, text)) in intervals.iter().enumerate() {
+            grid.push_str(&format!("        intervals [{}]:\n", i + 1));
+            grid.push_str(&format!("            xmin = {}\n", seconds(*start)));
+            grid.push_str(&format!("            xmax = {}\n", seconds(*end)));
+            grid.push_str(&format!("            text = {}\n", quote(text)));
+        }
+    }
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _intervals() {
+        let first = Segment::new(500, 1500, " Hello");
+        let second = Segment::new(1400, 2000, " \"there\"");
+        let intervals = intervals(&[&first, &second], 2500);
+        assert_eq!(
+            intervals,
+            vec![
+                (0, 500, String::new()),
+                (500, 1500, "Hello".to_string()),
+                (1500, 2000, "\"there\"".to_string()),
+                (2000, 2500, String::new()),
+            ]
+        );
+        assert_eq!(quote("\"there\""), "\"\"\"there\"\"\"");
+    }
+}
@@ -0,0 +1,36 @@
+use super::{display_text, escape_xml, vtt, Options};
+use crate::transcript::Transcript;
+
+// an empty xml:lang is ttml's way of saying the language isn't known
+pub fn render(transcript: &Transcript, options: &Options) -> String {
+    let language = options.language.as_deref().filter(|language| *language != "auto").unwrap_or("");
+    let mut ttml = String::new();
+    ttml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    ttml.push_str(&format!("<tt xmlns=\"http://www.w3.org/ns/ttml\" xml:lang=\"{}\">\n", escape_xml(language)));
+    ttml.push_str("  <body>\n    <div>\n");
+    for segment in &transcript.segments {
+        ttml.push_str(&format!(
+            "      <p begin=\"{}\" end=\"{}\">{}</p>\n",
+            vtt::timestamp(segment.start_ms),
+            vtt::timestamp(segment.end_ms),
+            escape_xml(display_text(segment).trim()).replace('\n', "<br/>")
+        ));
+    }
+    ttml.push_str("    </div>\n  </body>\n</tt>\n");
+    ttml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _render() {
+        let transcript = Transcript { segments: vec![Segment::new(0, 1500, " Fish & <chips>")] };
+        assert!(render(&transcript, &Options::default()).contains("<p begin=\"00:00:00.000\" end=\"00:00:01.500\">Fish &amp; &lt;chips&gt;</p>"));
+        assert!(render(&transcript, &Options::default()).contains("xml:lang=\"\""));
+        let options = Options { language: Some("de".to_string()), ..Options::default() };
+        assert!(render(&transcript, &options).contains("<tt xmlns=\"http://www.w3.org/ns/ttml\" xml:lang=\"de\">"));
+    }
+}
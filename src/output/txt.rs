@@ -0,0 +1,187 @@
+use super::{clock, Options};
+use crate::transcript::{Segment, Transcript};
+
+// languages that write a decimal comma
+const DECIMAL_COMMA: &[&str] = &["cs", "da", "de", "el", "es", "fi", "fr", "hu", "id", "it", "nb", "nl", "nn", "no", "pl", "pt", "ro", "ru", "sk", "sv", "tr", "uk", "vi"];
+
+// regions that tell the time in 12 hours
+const TWELVE_HOUR: &[&str] = &["AU", "BD", "CA", "EG", "IN", "NZ", "PH", "PK", "SA", "US"];
+
+// hour, hours, minute, minutes, second, seconds; english for any language not here
+const UNITS: &[(&str, [&str; 6])] = &[
+    ("de", ["Stunde", "Stunden", "Minute", "Minuten", "Sekunde", "Sekunden"]),
+    ("es", ["hora", "horas", "minuto", "minutos", "segundo", "segundos"]),
+    ("fr", ["heure", "heures", "minute", "minutes", "seconde", "secondes"]),
+    ("it", ["ora", "ore", "minuto", "minuti", "secondo", "secondi"]),
+    ("nl", ["uur", "uur", "minuut", "minuten", "seconde", "seconden"]),
+    ("pt", ["hora", "horas", "minuto", "minutos", "segundo", "segundos"]),
+];
+const ENGLISH_UNITS: [&str; 6] = ["hour", "hours", "minute", "minutes", "second", "seconds"];
+
+// --locale: a language and maybe a region, as much of a locale as the txt's timestamps need
+#[derive(Debug, Clone, PartialEq)]
+pub struct Locale {
+    language: String,
+    region: Option<String>,
+}
+
+// de, de-DE, or de_DE.UTF-8 as $LANG has it
+pub fn parse_locale(value: &str) -> Result<Locale, String> {
+    let invalid = || format!("expected a locale like de-DE or en_US.UTF-8, got {}", value);
+    let tag = value.split(['.', '@']).next().unwrap_or_default();
+    let mut parts = tag.split(['-', '_']);
+    let language = parts.next().filter(|language| (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_alphabetic())).ok_or_else(invalid)?;
+    let region = parts.next().filter(|region| region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()));
+    Ok(Locale { language: language.to_lowercase(), region: region.map(str::to_uppercase) })
+}
+
+// 14:30 or 14:30:15; in ms since midnight
+pub fn parse_time_of_day(value: &str) -> Result<u64, String> {
+    let invalid = || format!("expected a time of day like 14:30 or 14:30:15, got {}", value);
+    let parts: Vec<u64> = value.split(':').map(|part| part.parse().map_err(|_| invalid())).collect::<Result<_, _>>()?;
+    match parts[..] {
+        [hours, minutes] if hours < 24 && minutes < 60 => Ok((hours * 3600 + minutes * 60) * 1000),
+        [hours, minutes, seconds] if hours < 24 && minutes < 60 && seconds < 60 => Ok((hours * 3600 + minutes * 60 + seconds) * 1000),
+        _ => Err(invalid()),
+    }
+}
+
+impl Locale {
+    fn decimal_separator(&self) -> char {
+        if DECIMAL_COMMA.contains(&self.language.as_str()) {
+            ','
+        } else {
+            '.'
+        }
+    }
+
+    // plain en is en-US
+    fn twelve_hour(&self) -> bool {
+        match &self.region {
+            Some(region) => TWELVE_HOUR.contains(&region.as_str()),
+            None => self.language == "en",
+        }
+    }
+
+    fn units(&self) -> [&'static str; 6] {
+        UNITS.iter().find(|(language, _)| *language == self.language).map_or(ENGLISH_UNITS, |(_, units)| *units)
+    }
+}
+
+// 1 hour 5 minutes 3 seconds, leaving out what's 0
+fn spoken(ms: i64, units: [&str; 6]) -> String {
+    let seconds = ms.max(0) / 1000;
+    let amounts = [(seconds / 3600, units[0], units[1]), (seconds / 60 % 60, units[2], units[3]), (seconds % 60, units[4], units[5])];
+    let parts: Vec<String> = amounts.iter().filter(|(amount, _, _)| *amount > 0).map(|(amount, one, many)| format!("{} {}", amount, if *amount == 1 { one } else { many })).collect();
+    if parts.is_empty() {
+        format!("0 {}", units[5])
+    } else {
+        parts.join(" ")
+    }
+}
+
+// hh:mm:ss with the locale's decimal separator, or the time of day that far into the recording
+fn stamp(ms: i64, options: &Options) -> String {
+    let locale = options.locale.as_ref();
+    match options.time_of_day {
+        Some(start_ms) => {
+            let seconds = (start_ms as i64 + ms.max(0)) / 1000 % 86_400;
+            let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+            if locale.is_some_and(Locale::twelve_hour) {
+                let twelve = if hours % 12 == 0 { 12 } else { hours % 12 };
+                format!("{}:{:02}:{:02} {}", twelve, minutes, seconds, if hours < 12 { "AM" } else { "PM" })
+            } else {
+                format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+            }
+        }
+        None => clock(ms, locale.map_or('.', Locale::decimal_separator)),
+    }
+}
+
+// timestamps stay in whisper's 10ms ticks so existing .txt consumers keep working
+pub fn line(segment: &Segment) -> String {
+    format!("[{} - {}]: {}", segment.start_ms / 10, segment.end_ms / 10, super::display_text(segment))
+}
+
+// the txt's line, its timestamps written for people with --locale, --time-of-day or
+// --spoken-timestamps; a spoken line only says where it starts, the end would only be in the way
+fn localized_line(segment: &Segment, options: &Options) -> String {
+    if options.spoken_timestamps {
+        let units = options.locale.as_ref().map_or(ENGLISH_UNITS, Locale::units);
+        return format!("{}: {}", spoken(segment.start_ms, units), super::display_text(segment));
+    }
+    if options.locale.is_none() && options.time_of_day.is_none() {
+        return line(segment);
+    }
+    format!("[{} - {}]: {}", stamp(segment.start_ms, options), stamp(segment.end_ms, options), super::display_text(segment))
+}
+
+// ansi colours by probability: green from GOOD, yellow from FAIR, red below
+const GOOD: f32 = 0.8;
+const FAIR: f32 = 0.5;
+
+fn paint(text: &str, probability: Option<f32>) -> String {
+    match probability {
+        Some(probability) if probability >= GOOD => format!("\x1b[32m{}\x1b[0m", text),
+        Some(probability) if probability >= FAIR => format!("\x1b[33m{}\x1b[0m", text),
+        Some(_) => format!("\x1b[31m{}\x1b[0m", text),
+        None => text.to_string(),
+    }
+}
+
+// line for a terminal, every word coloured by its probability, or the whole text by the
+// segment's confidence without word times; a segment without tokens stays plain
+pub fn colored_line(segment: &Segment) -> String {
+    let text = if segment.words.is_empty() {
+        paint(&super::display_text(segment), segment.confidence())
+    } else {
+        super::speaker_prefix(segment) + &segment.words.iter().map(|word| paint(&word.text, Some(word.probability))).collect::<String>()
+    };
+    format!("[{} - {}]: {}", segment.start_ms / 10, segment.end_ms / 10, text)
+}
+
+pub fn colored(transcript: &Transcript) -> String {
+    transcript.segments.iter().map(|segment| format!("{}\n", colored_line(segment))).collect()
+}
+
+pub fn render(transcript: &Transcript, options: &Options) -> String {
+    transcript
+        .segments
+        .iter()
+        .map(|segment| format!("{}\n", localized_line(segment, options)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::{Token, Word};
+
+    #[test]
+    fn _colored_line() {
+        let word = |text: &str, probability: f32| Word { start_ms: 0, end_ms: 0, text: text.to_string(), probability };
+        let segment = Segment { words: vec![word(" Sure", 0.9), word(" thing", 0.6), word(" mate", 0.2)], ..Segment::new(0, 1500, " Sure thing mate") };
+        assert_eq!(colored_line(&segment), "[0 - 150]: \x1b[32m Sure\x1b[0m\x1b[33m thing\x1b[0m\x1b[31m mate\x1b[0m");
+        let segment = Segment { tokens: vec![Token::new(" Hm", 0.4)], ..Segment::new(0, 1000, " Hm") };
+        assert_eq!(colored_line(&segment), "[0 - 100]: \x1b[31m Hm\x1b[0m");
+        assert_eq!(colored_line(&Segment::new(0, 1000, " Plain")), line(&Segment::new(0, 1000, " Plain")));
+    }
+
+    #[test]
+    fn _localized_line() {
+        let segment = Segment::new(3_905_500, 3_907_250, " Hallo.");
+        let options = |locale: Option<&str>, time_of_day: Option<&str>, spoken_timestamps| Options {
+            locale: locale.map(|locale| parse_locale(locale).unwrap()),
+            time_of_day: time_of_day.map(|time| parse_time_of_day(time).unwrap()),
+            spoken_timestamps,
+            ..Options::default()
+        };
+        assert_eq!(localized_line(&segment, &options(None, None, false)), "[390550 - 390725]:  Hallo.");
+        assert_eq!(localized_line(&segment, &options(Some("de_DE.UTF-8"), None, false)), "[01:05:05,500 - 01:05:07,250]:  Hallo.");
+        assert_eq!(localized_line(&segment, &options(Some("en-US"), Some("12:30"), false)), "[1:35:05 PM - 1:35:07 PM]:  Hallo.");
+        assert_eq!(localized_line(&segment, &options(Some("en-GB"), Some("23:30"), false)), "[00:35:05 - 00:35:07]:  Hallo.");
+        assert_eq!(localized_line(&segment, &options(Some("de"), None, true)), "1 Stunde 5 Minuten 5 Sekunden:  Hallo.");
+        assert_eq!(spoken(400, ENGLISH_UNITS), "0 seconds");
+        assert!(parse_locale("x").is_err() && parse_time_of_day("24:00").is_err() && parse_time_of_day("14").is_err());
+    }
+}
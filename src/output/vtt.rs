@@ -0,0 +1,59 @@
+use crate::transcript::{Segment, Transcript};
+
+pub fn timestamp(ms: i64) -> String {
+    super::clock(ms, '.')
+}
+
+// cue text is markup, so these are the three that need escaping; a --> in the text can't end
+// the cue's timing line then either
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// with word times the cue carries a timestamp tag in front of every word after the first
+fn cue_text(segment: &Segment) -> String {
+    if segment.words.is_empty() {
+        return escape(&super::display_text(segment));
+    }
+    let mut text = escape(&super::speaker_prefix(segment));
+    for (i, word) in segment.words.iter().enumerate() {
+        if i > 0 {
+            text.push_str(&format!(" <{}>", timestamp(word.start_ms)));
+        }
+        text.push_str(&escape(&word.text));
+    }
+    text
+}
+
+pub fn render(transcript: &Transcript) -> String {
+    let mut vtt = String::from("WEBVTT\n\n");
+    for segment in &transcript.segments {
+        let start = timestamp(segment.start_ms);
+        let end = timestamp(segment.end_ms);
+        vtt.push_str(&format!("{start} --> {end}\n{}\n\n", cue_text(segment)));
+    }
+    vtt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Word;
+
+    #[test]
+    fn _timestamp() {
+        assert_eq!(timestamp(19990), "00:00:19.990");
+        assert_eq!(timestamp(-45500), "00:00:00.000");
+    }
+
+    #[test]
+    fn _cue_text() {
+        let word = |start_ms: i64, text: &str| Word { start_ms, end_ms: start_ms + 100, text: text.to_string(), probability: 1.0 };
+        let segment = Segment { words: vec![word(0, "Hello,"), word(1500, "world")], ..Segment::new(0, 2000, " Hello, world") };
+        assert_eq!(cue_text(&segment), "Hello, <00:00:01.500>world");
+        assert_eq!(cue_text(&Segment::new(0, 2000, " Hello")), " Hello");
+        assert_eq!(cue_text(&Segment::new(0, 2000, " Fish & <chips> --> here")), " Fish &amp; &lt;chips&gt; --&gt; here");
+        let segment = Segment { words: vec![word(0, "a<b"), word(1500, "-->")], ..Segment::new(0, 2000, " a<b -->") };
+        assert_eq!(cue_text(&segment), "a&lt;b <00:00:01.500>--&gt;");
+    }
+}
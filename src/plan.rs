@@ -0,0 +1,93 @@
+use std::fs::File;
+use std::path::Path;
+
+use transcript::audio::stream;
+use transcript::storage;
+
+use crate::batch;
+
+// --dry-run: what a run would do with one input, worked out without loading the model
+pub struct Entry {
+    input: String,
+    // why the input can't be decoded, as far as can be told without decoding it
+    problem: Option<String>,
+    skipped: bool,
+    // each output and whether it's already there
+    outputs: Vec<(String, bool)>,
+}
+
+impl Entry {
+    pub fn new(job: &batch::Job, outputs: Vec<String>, skipped: bool) -> Entry {
+        // urls, buckets and pipes are only read once the run opens them
+        let local = !storage::is_remote(&job.input) && !stream::is_stream(&job.input);
+        let problem = match local.then(|| File::open(&job.input)) {
+            Some(Err(error)) => Some(error.to_string()),
+            Some(Ok(_)) if Path::new(&job.input).is_dir() => Some("it's a directory".to_string()),
+            _ => None,
+        };
+        let outputs = outputs.into_iter().map(|output| (output.clone(), !storage::is_remote(&output) && Path::new(&output).exists())).collect();
+        Entry { input: job.input.clone(), problem, skipped, outputs }
+    }
+}
+
+// every input with the files it would write under it, then what would stop the run; anything
+// that would is a conflict, an existing output only when it's neither replaced nor skipped
+pub fn render(entries: &[Entry], overwrite: bool) -> (String, usize) {
+    let mut lines = Vec::new();
+    for entry in entries {
+        match (&entry.problem, entry.skipped) {
+            (Some(problem), _) => lines.push(format!("{}  can't be read: {}", entry.input, problem)),
+            (None, true) => lines.push(format!("{}  skipped, its outputs exist", entry.input)),
+            (None, false) => lines.push(entry.input.clone()),
+        }
+        if entry.skipped {
+            continue;
+        }
+        if entry.outputs.is_empty() {
+            lines.push("  stdout".to_string());
+        }
+        for (output, exists) in &entry.outputs {
+            let note = match (exists, overwrite) {
+                (true, true) => "  replaced",
+                (true, false) => "  exists",
+                (false, _) => "",
+            };
+            lines.push(format!("  {}{}", output, note));
+        }
+    }
+    let unreadable = entries.iter().filter(|entry| entry.problem.is_some()).count();
+    let skipped = entries.iter().filter(|entry| entry.skipped).count();
+    let written: Vec<&(String, bool)> = entries.iter().filter(|entry| !entry.skipped).flat_map(|entry| &entry.outputs).collect();
+    let existing = written.iter().filter(|(_, exists)| *exists).count();
+    lines.push(String::new());
+    lines.push(format!("{} inputs, {} skipped, {} unreadable; {} outputs, {} already there", entries.len(), skipped, unreadable, written.len(), existing));
+    let conflicts = unreadable + if overwrite { 0 } else { existing };
+    (lines.join("\n"), conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _render() {
+        let entry = |input: &str, problem: Option<&str>, skipped, outputs: &[(&str, bool)]| Entry {
+            input: input.to_string(),
+            problem: problem.map(String::from),
+            skipped,
+            outputs: outputs.iter().map(|(output, exists)| (output.to_string(), *exists)).collect(),
+        };
+        let entries = [
+            entry("a.wav", None, false, &[("out/a.txt", false), ("out/a.srt", true)]),
+            entry("b.wav", Some("No such file or directory (os error 2)"), false, &[("out/b.txt", false)]),
+            entry("c.wav", None, true, &[("out/c.txt", true)]),
+        ];
+        let (rendered, conflicts) = render(&entries, false);
+        assert_eq!(
+            rendered,
+            "a.wav\n  out/a.txt\n  out/a.srt  exists\nb.wav  can't be read: No such file or directory (os error 2)\n  out/b.txt\nc.wav  skipped, its outputs exist\n\n3 inputs, 1 skipped, 1 unreadable; 3 outputs, 1 already there"
+        );
+        assert_eq!(conflicts, 2);
+        assert_eq!(render(&entries, true).1, 1);
+    }
+}
@@ -0,0 +1,328 @@
+use clap::{Args, Command, FromArgMatches};
+
+use crate::audio;
+use crate::error::Error;
+use crate::fillers::{self, Fillers};
+use crate::glossary::levenshtein;
+use crate::output::layout::{join, piece};
+use crate::output::vtt;
+use crate::redact::{Redaction, Redactions};
+use crate::replace::Rules;
+use crate::transcript::{Segment, Transcript};
+
+// segments further apart than this aren't merged, whatever their length
+const MAX_MERGE_GAP_MS: i64 = 1500;
+
+// brackets whisper puts sound descriptions in, [BLANK_AUDIO], (laughs), [音楽] and the like
+const OPENING: &[char] = &['[', '(', '（', '【', '［'];
+const CLOSING: &[char] = &[']', ')', '）', '】', '］'];
+
+// this many alike segments in a row make a loop rather than someone repeating themselves
+const MIN_REPEATS: usize = 3;
+
+// words ending in a full stop that don't end a sentence
+const ABBREVIATIONS: &[&str] = &["mr.", "mrs.", "ms.", "dr.", "prof.", "st.", "vs.", "etc.", "e.g.", "i.e.", "no."];
+
+// passes over the finished transcript, before it's written in any format
+#[derive(Args, Debug, Clone)]
+#[group(id = "postprocess_options")]
+pub struct Options {
+    /// TOML file of [[rule]] find/replace regex pairs run over the text in order, e.g. to fix a
+    /// name whisper always gets wrong
+    #[arg(long, value_name = "FILE", help_heading = "Post-processing")]
+    pub replace: Option<String>,
+
+    /// Flag segments whose mean token probability is below this, from 0 to 1, as low_confidence
+    /// in the json output
+    #[arg(long, value_name = "RATIO", value_parser = parse_ratio, help_heading = "Post-processing")]
+    pub min_confidence: Option<f32>,
+
+    /// Leave segments below --min-confidence out of the transcript instead
+    #[arg(long, requires = "min_confidence", help_heading = "Post-processing")]
+    pub drop_low_confidence: bool,
+
+    /// Collapse runs of alike segments, whisper's loop when it gets stuck, to the first of them
+    #[arg(long, help_heading = "Post-processing")]
+    pub dedupe_repeats: bool,
+
+    /// How alike segments have to be for --dedupe-repeats, from 0 to 1 for the same text
+    #[arg(long, default_value_t = 0.9, value_name = "RATIO", value_parser = parse_ratio, requires = "dedupe_repeats", help_heading = "Post-processing")]
+    pub repeat_similarity: f32,
+
+    /// Take bracketed sound descriptions such as [BLANK_AUDIO], (laughs) and [音楽] out of the
+    /// text, dropping segments left with nothing else
+    #[arg(long, help_heading = "Post-processing")]
+    pub strip_annotations: bool,
+
+    /// Take ums, uhs, "you know" and the like out of the text, leaving the timings alone
+    #[arg(long, help_heading = "Post-processing")]
+    pub remove_fillers: bool,
+
+    /// File of fillers, one a line, for --remove-fillers to take out instead of its own list
+    #[arg(long, value_name = "FILE", requires = "remove_fillers", help_heading = "Post-processing")]
+    pub fillers: Option<String>,
+
+    /// File of words, one a line, masked as f*** in every output
+    #[arg(long, value_name = "FILE", help_heading = "Post-processing")]
+    pub redact: Option<String>,
+
+    /// Also write when each masked word was said to <output>.redactions.json
+    #[arg(long, requires = "redact", help_heading = "Post-processing")]
+    pub redact_index: bool,
+
+    /// Split segments where a sentence ends and merge tiny ones into a neighbour, for cues that
+    /// follow the sentences rather than whisper's windows
+    #[arg(long, help_heading = "Post-processing")]
+    pub resegment: bool,
+
+    /// With --resegment, segments shorter than this are merged when they have few words
+    #[arg(long, default_value = "1s", value_name = "TIME", value_parser = audio::parse_time, requires = "resegment", help_heading = "Post-processing")]
+    pub merge_under: u64,
+
+    /// With --resegment, the most words a segment merged for being short can have
+    #[arg(long, default_value_t = 3, value_name = "WORDS", requires = "resegment", help_heading = "Post-processing")]
+    pub merge_words: usize,
+}
+
+// the clap defaults, as for output::Options
+impl Default for Options {
+    fn default() -> Options {
+        let matches = Options::augment_args(Command::new("options")).get_matches_from(["options"]);
+        Options::from_arg_matches(&matches).expect("option defaults are invalid")
+    }
+}
+
+fn parse_ratio(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(ratio),
+        _ => Err(format!("expected a ratio from 0 to 1, got {}", value)),
+    }
+}
+
+// one minus the edit distance over the longer of the two, on the letters and digits alone
+fn similarity(a: &str, b: &str) -> f32 {
+    let normalized = |text: &str| text.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect::<Vec<char>>();
+    let (a, b) = (normalized(a), normalized(b));
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(&a, &b) as f32 / longest as f32
+}
+
+// runs of MIN_REPEATS or more segments alike enough to the first of them keep only that first,
+// with a warning for each run saying where the loop was
+pub fn dedupe_repeats(transcript: &mut Transcript, threshold: f32) {
+    let segments = std::mem::take(&mut transcript.segments);
+    let mut start = 0;
+    while start < segments.len() {
+        let first = &segments[start];
+        let run = segments[start..].iter().take_while(|segment| segment.speaker == first.speaker && similarity(&first.text, &segment.text) >= threshold).count();
+        if run >= MIN_REPEATS {
+            let (from, to) = (vtt::timestamp(first.start_ms), vtt::timestamp(segments[start + run - 1].end_ms));
+            tracing::warn!("collapsed {} repeats of \"{}\" from {} to {}, likely a loop", run, first.text.trim(), from, to);
+            transcript.segments.push(first.clone());
+        } else {
+            transcript.segments.extend_from_slice(&segments[start..start + run]);
+        }
+        start += run;
+    }
+}
+
+fn ends_sentence(word: &str) -> bool {
+    let word = word.trim_end_matches(['"', '\'', ')', '”', '’']);
+    word.ends_with(['.', '?', '!', '…']) && !ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+// a segment cut after every word that ends a sentence, when the next one starts a new one
+fn split_sentences(segment: &Segment) -> Vec<Segment> {
+    let words: Vec<&str> = segment.text.split_whitespace().collect();
+    let mut cuts = vec![0];
+    for i in 1..words.len() {
+        if ends_sentence(words[i - 1]) && !words[i].starts_with(char::is_lowercase) {
+            cuts.push(i);
+        }
+    }
+    if cuts.len() == 1 {
+        return vec![segment.clone()];
+    }
+    cuts.push(words.len());
+    cuts.windows(2).map(|cut| piece(segment, &words, cut[0], cut[1])).collect()
+}
+
+// the text outside brackets, depth carrying over from the text before; an unclosed bracket runs
+// to the end
+fn outside_brackets(text: &str, depth: &mut usize) -> String {
+    let mut kept = String::new();
+    for c in text.chars() {
+        if OPENING.contains(&c) {
+            *depth += 1;
+        } else if CLOSING.contains(&c) && *depth > 0 {
+            *depth -= 1;
+        } else if *depth == 0 {
+            kept.push(c);
+        }
+    }
+    kept
+}
+
+// words go when nothing of them is outside the brackets, the brackets being followed from one word
+// into the next
+pub fn strip_annotations(transcript: &mut Transcript) {
+    for segment in &mut transcript.segments {
+        let text = outside_brackets(&segment.text, &mut 0);
+        let words: Vec<&str> = text.split_whitespace().collect();
+        segment.text = if words.is_empty() { String::new() } else { format!(" {}", words.join(" ")) };
+        let mut depth = 0;
+        segment.words.retain_mut(|word| {
+            word.text = outside_brackets(&word.text, &mut depth);
+            !word.text.trim().is_empty()
+        });
+    }
+    transcript.segments.retain(|segment| !segment.text.is_empty());
+}
+
+impl Options {
+    // a segment without tokens has no confidence to be low
+    fn flag_low_confidence(&self, transcript: &mut Transcript, min_confidence: f32) {
+        for segment in &mut transcript.segments {
+            segment.low_confidence = segment.confidence().is_some_and(|confidence| confidence < min_confidence);
+        }
+        let low = transcript.segments.iter().filter(|segment| segment.low_confidence).count();
+        if low > 0 {
+            tracing::info!("{} of {} segments are below --min-confidence{}", low, transcript.segments.len(), if self.drop_low_confidence { ", leaving them out" } else { "" });
+        }
+        if self.drop_low_confidence {
+            transcript.segments.retain(|segment| !segment.low_confidence);
+        }
+    }
+
+    fn tiny(&self, segment: &Segment) -> bool {
+        segment.end_ms - segment.start_ms < self.merge_under as i64 && segment.text.split_whitespace().count() <= self.merge_words
+    }
+
+    // sentences first, then tiny segments (a sentence of its own or not) joined to the previous
+    // one while that hasn't ended its sentence, otherwise to the next; never across speakers
+    pub fn resegment(&self, transcript: &mut Transcript) {
+        let mut segments: Vec<Segment> = transcript.segments.iter().flat_map(split_sentences).collect();
+        let near = |first: &Segment, second: &Segment| first.speaker == second.speaker && second.start_ms - first.end_ms <= MAX_MERGE_GAP_MS;
+        let mut i = 0;
+        while i < segments.len() {
+            if !self.tiny(&segments[i]) {
+                i += 1;
+                continue;
+            }
+            let previous = i > 0 && near(&segments[i - 1], &segments[i]);
+            let next = i + 1 < segments.len() && near(&segments[i], &segments[i + 1]);
+            let open = previous && !segments[i - 1].text.split_whitespace().last().is_some_and(ends_sentence);
+            if previous && (open || !next) {
+                let segment = segments.remove(i);
+                segments[i - 1] = join(segments[i - 1].clone(), segment);
+            } else if next {
+                let segment = segments.remove(i);
+                segments[i] = join(segment, segments[i].clone());
+            } else {
+                i += 1;
+            }
+        }
+        transcript.segments = segments;
+    }
+
+    // confidence while every segment still has its tokens, then loops, annotations and
+    // replacements, so fillers, redactions and sentences are found in the text as it'll read;
+    // returns what was redacted
+    pub fn apply(&self, transcript: &mut Transcript) -> Result<Vec<Redaction>, Error> {
+        if let Some(min_confidence) = self.min_confidence {
+            self.flag_low_confidence(transcript, min_confidence);
+        }
+        if self.dedupe_repeats {
+            dedupe_repeats(transcript, self.repeat_similarity);
+        }
+        if self.strip_annotations {
+            strip_annotations(transcript);
+        }
+        if let Some(path) = &self.replace {
+            Rules::load(path)?.apply(transcript);
+        }
+        if self.remove_fillers {
+            match &self.fillers {
+                Some(path) => Fillers::load(path)?,
+                None => Fillers::new(fillers::DEFAULT.iter().copied()),
+            }
+            .apply(transcript);
+        }
+        let redactions = match &self.redact {
+            Some(path) => Redactions::load(path)?.apply(transcript),
+            None => Vec::new(),
+        };
+        if self.resegment {
+            self.resegment(transcript);
+        }
+        Ok(redactions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::{Token, Word};
+
+    #[test]
+    fn _flag_low_confidence() {
+        let segment = |probability: f32| Segment { tokens: vec![Token::new(" so", probability)], ..Segment::new(0, 1000, " so") };
+        let mut transcript = Transcript { segments: vec![segment(0.9), segment(0.3), Segment::new(0, 1000, " untokenized")] };
+        let options = Options { min_confidence: Some(0.5), ..Options::default() };
+        options.apply(&mut transcript).unwrap();
+        assert_eq!(transcript.segments.iter().map(|segment| segment.low_confidence).collect::<Vec<_>>(), [false, true, false]);
+
+        let options = Options { drop_low_confidence: true, ..options };
+        options.apply(&mut transcript).unwrap();
+        assert_eq!(transcript.segments.len(), 2);
+    }
+
+    #[test]
+    fn _dedupe_repeats() {
+        let segment = |start_ms: i64, text: &str| Segment::new(start_ms, start_ms + 1000, text);
+        let mut transcript = Transcript {
+            segments: vec![
+                segment(0, " Thanks for watching!"),
+                segment(1000, " Thanks for watching."),
+                segment(2000, " thanks for watching"),
+                segment(3000, " Thanks for watchin!"),
+                segment(4000, " No, no."),
+                segment(5000, " No, no."),
+                segment(6000, " Bye."),
+            ],
+        };
+        dedupe_repeats(&mut transcript, 0.9);
+        assert_eq!(transcript.segments.iter().map(|segment| (segment.start_ms, segment.text.as_str())).collect::<Vec<_>>(), [(0, " Thanks for watching!"), (4000, " No, no."), (5000, " No, no."), (6000, " Bye.")]);
+        assert!(similarity(" Hello there", " Goodbye") < 0.5);
+    }
+
+    #[test]
+    fn _strip_annotations() {
+        let word = |text: &str| Word { start_ms: 0, end_ms: 0, text: text.to_string(), probability: 1.0 };
+        let laughing = Segment { words: vec![word(" Yes"), word(" (laughs"), word(" loudly)"), word(" really.")], ..Segment::new(0, 2000, " Yes (laughs loudly) really.") };
+        let mut transcript = Transcript { segments: vec![Segment::new(0, 1000, " [BLANK_AUDIO]"), laughing, Segment::new(2000, 3000, " [音楽]"), Segment::new(3000, 4000, " （拍手）ありがとう")] };
+        strip_annotations(&mut transcript);
+        assert_eq!(transcript.segments.iter().map(|segment| segment.text.as_str()).collect::<Vec<_>>(), [" Yes really.", " ありがとう"]);
+        assert_eq!(transcript.segments[0].words.iter().map(|word| word.text.as_str()).collect::<Vec<_>>(), [" Yes", " really."]);
+    }
+
+    #[test]
+    fn _resegment() {
+        let options = Options { resegment: true, ..Options::default() };
+        let mut transcript = Transcript {
+            segments: vec![
+                Segment::new(0, 4000, " I met Dr. Jones. She was late. Very late"),
+                Segment::new(4000, 4500, " indeed."),
+                Segment::new(6000, 6400, " Right."),
+                Segment::new(6500, 9000, " So where were we?"),
+                Segment::new(20_000, 20_300, " Hm."),
+            ],
+        };
+        options.apply(&mut transcript).unwrap();
+        let shown: Vec<(i64, i64, &str)> = transcript.segments.iter().map(|segment| (segment.start_ms, segment.end_ms, segment.text.as_str())).collect();
+        assert_eq!(shown, [(0, 1700, " I met Dr. Jones."), (1700, 3100, " She was late."), (3100, 4500, " Very late indeed."), (6000, 9000, " Right. So where were we?"), (20_000, 20_300, " Hm.")]);
+    }
+}
@@ -0,0 +1,67 @@
+use std::cell::Cell;
+use std::ffi::{c_int, c_void};
+use std::time::Duration;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use whisper_rs::FullParams;
+use whisper_rs_sys::{whisper_context, whisper_state};
+
+// whisper reports whole percents, so the bar counts milliseconds of audio to make the eta a
+// straight projection of the realtime factor so far
+pub struct Progress {
+    bar: ProgressBar,
+    duration_ms: u64,
+    // the start and length of the window being decoded, all the audio unless it goes in windows
+    window: Cell<(u64, u64)>,
+}
+
+impl Progress {
+    pub fn new(bars: &MultiProgress, name: &str, duration_ms: u64) -> Progress {
+        let bar = bars.add(ProgressBar::new(duration_ms));
+        if !tracing::enabled!(tracing::Level::INFO) {
+            bar.set_draw_target(ProgressDrawTarget::hidden());
+        }
+        bar.set_style(ProgressStyle::with_template("{msg} [{bar:30}] {percent:>3}% {elapsed_precise} eta {eta_precise}").expect("progress template is invalid").progress_chars("=> "));
+        bar.set_message(format!("{} ({})", name, clock(duration_ms)));
+        bar.enable_steady_tick(Duration::from_secs(1));
+        Progress { bar, duration_ms, window: Cell::new((0, duration_ms)) }
+    }
+
+    pub fn window(&self, start_ms: u64, length_ms: u64) {
+        self.window.set((start_ms, length_ms));
+    }
+
+    // how far a decode the daemon runs has got as a whole
+    pub fn percent(&self, percent: u8) {
+        self.bar.set_position(self.duration_ms * percent.min(100) as u64 / 100);
+    }
+
+    pub fn apply(&self, params: &mut FullParams) {
+        // the progress outlives the params, which only live for one decode; a temperature
+        // retry starts the bar over, or the window when there are several
+        match self.window.get() {
+            (0, _) => self.bar.reset(),
+            (start_ms, _) => self.bar.set_position(start_ms),
+        }
+        unsafe {
+            params.set_progress_callback(Some(report));
+            params.set_progress_callback_user_data(self as *const Progress as *mut c_void);
+        }
+    }
+
+    pub fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+fn clock(ms: u64) -> String {
+    format!("{}:{:02}:{:02}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60)
+}
+
+// set_progress_callback_safe keeps a pointer to its closure from before it's boxed, so this
+// goes through the raw callback instead
+unsafe extern "C" fn report(_ctx: *mut whisper_context, _state: *mut whisper_state, percent: c_int, user_data: *mut c_void) {
+    let progress = &*(user_data as *const Progress);
+    let (start_ms, length_ms) = progress.window.get();
+    progress.bar.set_position((start_ms + length_ms * percent.clamp(0, 100) as u64 / 100).min(progress.duration_ms));
+}
@@ -0,0 +1,158 @@
+use serde::Serialize;
+
+use transcript::error::Error;
+use transcript::output::parse;
+use transcript::{Segment, Transcript};
+
+use crate::live;
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Transcript to pull quotes from: the json transcript, or srt, vtt or sbv
+    input: String,
+
+    /// Words or a phrase to look for, ignoring case; repeat it to find any of several
+    #[arg(long, required = true)]
+    query: Vec<String>,
+
+    /// Segments kept either side of each match
+    #[arg(long, default_value_t = 1, value_name = "SEGMENTS")]
+    context: usize,
+
+    /// Print the quotes as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Line {
+    start_ms: i64,
+    end_ms: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speaker: Option<String>,
+    text: String,
+    matched: bool,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Quote {
+    start_ms: i64,
+    end_ms: i64,
+    lines: Vec<Line>,
+}
+
+// the time a quote is cited by; interviews run long, so the hours are always there
+fn timecode(ms: i64) -> String {
+    let seconds = ms.max(0) / 1000;
+    format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+fn matches(segment: &Segment, queries: &[String]) -> bool {
+    let text = segment.text.to_lowercase();
+    queries.iter().any(|query| text.contains(&query.to_lowercase()))
+}
+
+// each match with the segments around it, and a match whose context runs into the next one's
+// makes a single quote with it
+fn quotes(transcript: &Transcript, queries: &[String], context: usize) -> Vec<Quote> {
+    let segments = &transcript.segments;
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+    for (i, _) in segments.iter().enumerate().filter(|(_, segment)| matches(segment, queries)) {
+        let (start, end) = (i.saturating_sub(context), (i + context).min(segments.len() - 1));
+        match spans.last_mut() {
+            Some((_, last)) if start <= *last + 1 => *last = end,
+            _ => spans.push((start, end)),
+        }
+    }
+    spans
+        .into_iter()
+        .map(|(start, end)| Quote {
+            start_ms: segments[start].start_ms,
+            end_ms: segments[end].end_ms,
+            lines: segments[start..=end]
+                .iter()
+                .map(|segment| Line { start_ms: segment.start_ms, end_ms: segment.end_ms, speaker: segment.speaker.clone(), text: segment.text.trim().to_string(), matched: matches(segment, queries) })
+                .collect(),
+        })
+        .collect()
+}
+
+// the query wherever it is in the text, between the marks; text whose lowercase isn't the same
+// length can't be searched by the lowercase's offsets, so it's left as it is
+fn mark(text: &str, queries: &[String], (open, close): (&str, &str)) -> String {
+    let lower = text.to_lowercase();
+    if open.is_empty() || lower.len() != text.len() {
+        return text.to_string();
+    }
+    let mut marked = vec![false; text.len()];
+    for query in queries.iter().map(|query| query.to_lowercase()).filter(|query| !query.is_empty()) {
+        for (at, _) in lower.match_indices(&query) {
+            marked[at..at + query.len()].fill(true);
+        }
+    }
+    let mut out = String::new();
+    for (at, c) in text.char_indices() {
+        if marked[at] && (at == 0 || !marked[at - 1]) {
+            out.push_str(open);
+        }
+        out.push(c);
+        if marked[at] && marked.get(at + c.len_utf8()) != Some(&true) {
+            out.push_str(close);
+        }
+    }
+    out
+}
+
+// the file and the quote's span to cite it by, then a line per segment as it can be pasted
+fn render(input: &str, quote: &Quote, queries: &[String], marks: (&str, &str)) -> String {
+    let mut lines = vec![format!("{} {} - {}", input, timecode(quote.start_ms), timecode(quote.end_ms))];
+    for line in &quote.lines {
+        let speaker = line.speaker.as_ref().map_or_else(String::new, |speaker| format!("{}: ", speaker));
+        let text = if line.matched { mark(&line.text, queries, marks) } else { line.text.clone() };
+        lines.push(format!("[{}] {}{}", timecode(line.start_ms), speaker, text));
+    }
+    lines.join("\n")
+}
+
+pub fn run(args: &Args) -> Result<(), Error> {
+    let transcript = parse::read(&args.input)?;
+    let quotes = quotes(&transcript, &args.query, args.context);
+    // the words that matched are in bold on a terminal, as search has them
+    let marks = if !args.json && live::colors() { ("\x1b[1m", "\x1b[0m") } else { ("", "") };
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&quotes).expect("failed to serialize quotes"));
+    } else if quotes.is_empty() {
+        tracing::info!("nothing in {} matched {}", args.input, args.query.join(", "));
+    } else {
+        println!("{}", quotes.iter().map(|quote| render(&args.input, quote, &args.query, marks)).collect::<Vec<_>>().join("\n\n"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _quotes() {
+        let segment = |start_ms, end_ms, text: &str| Segment { speaker: Some("Ann".to_string()), ..Segment::new(start_ms, end_ms, text) };
+        let transcript = Transcript {
+            segments: vec![
+                segment(0, 2000, " Hello."),
+                segment(2000, 4000, " The Budget was late."),
+                segment(4000, 6000, " We waited."),
+                segment(6000, 8000, " And waited."),
+                segment(3_599_000, 3_600_000, " So."),
+                segment(3_600_000, 3_602_000, " Then the budget came."),
+                segment(3_602_000, 3_605_000, " Bye."),
+            ],
+        };
+        let queries = vec!["budget".to_string()];
+        let quotes = quotes(&transcript, &queries, 1);
+        assert_eq!(quotes.iter().map(|quote| (quote.start_ms, quote.end_ms, quote.lines.len())).collect::<Vec<_>>(), vec![(0, 6000, 3), (3_599_000, 3_605_000, 3)]);
+        let quote = &quotes[0];
+        assert_eq!(render("talk.json", quote, &queries, ("*", "*")), "talk.json 00:00:00 - 00:00:06\n[00:00:00] Ann: Hello.\n[00:00:02] Ann: The *Budget* was late.\n[00:00:04] Ann: We waited.");
+        assert_eq!(quotes[1].lines[1].text, "Then the budget came.");
+        assert!(quotes[1].lines[1].matched && !quotes[1].lines[0].matched);
+    }
+}
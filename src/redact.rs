@@ -0,0 +1,101 @@
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::transcript::Transcript;
+
+// a word that was masked and when it was said, for --redact-index
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Redaction {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+}
+
+// words masked wherever they're said, matched whole and whatever their case
+pub struct Redactions {
+    words: Vec<String>,
+}
+
+fn is_edge(c: char) -> bool {
+    !c.is_alphanumeric()
+}
+
+// the first letter kept and the rest starred out
+fn mask(word: &str) -> String {
+    word.chars().enumerate().map(|(i, c)| if i == 0 { c } else { '*' }).collect()
+}
+
+impl Redactions {
+    // one word per line, # starts a comment
+    pub fn parse(contents: &str) -> Redactions {
+        let words = contents.lines().map(|line| line.split('#').next().unwrap_or("").trim().to_lowercase()).filter(|line| !line.is_empty()).collect();
+        Redactions { words }
+    }
+
+    pub fn load(path: &str) -> Result<Redactions, Error> {
+        let contents = std::fs::read_to_string(path).map_err(|error| Error::input(format!("failed to read redaction list {}", path), error))?;
+        Ok(Redactions::parse(&contents))
+    }
+
+    // the text with every listed word masked, and the masked words
+    pub fn redact_text(&self, text: &str) -> (String, Vec<String>) {
+        let mut masked = Vec::new();
+        let words: Vec<String> = text
+            .split(' ')
+            .map(|word| {
+                let core = word.trim_matches(is_edge);
+                if core.is_empty() || !self.words.contains(&core.to_lowercase()) {
+                    return word.to_string();
+                }
+                masked.push(mask(core));
+                word.replacen(core, &mask(core), 1)
+            })
+            .collect();
+        (words.join(" "), masked)
+    }
+
+    // timed by the words when there are word times, by the segment otherwise; tokens can split a
+    // word anywhere, so a segment with something masked loses its tokens rather than keep it there
+    pub fn apply(&self, transcript: &mut Transcript) -> Vec<Redaction> {
+        let mut redactions = Vec::new();
+        for segment in &mut transcript.segments {
+            let (text, masked) = self.redact_text(&segment.text);
+            if masked.is_empty() {
+                continue;
+            }
+            segment.text = text;
+            segment.tokens.clear();
+            let mut timed = Vec::new();
+            for word in &mut segment.words {
+                let (text, masked) = self.redact_text(&word.text);
+                word.text = text;
+                timed.extend(masked.into_iter().map(|text| Redaction { start_ms: word.start_ms, end_ms: word.end_ms, text }));
+            }
+            if timed.len() == masked.len() {
+                redactions.extend(timed);
+            } else {
+                redactions.extend(masked.into_iter().map(|text| Redaction { start_ms: segment.start_ms, end_ms: segment.end_ms, text }));
+            }
+        }
+        redactions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::{Segment, Word};
+
+    #[test]
+    fn _apply() {
+        let redactions = Redactions::parse("darn # mild\nHeck\n");
+        assert_eq!(redactions.redact_text(" Well, heck! Darnation, darn it."), (" Well, h***! Darnation, d*** it.".to_string(), vec!["h***".to_string(), "d***".to_string()]));
+
+        let word = |start_ms: i64, text: &str| Word { start_ms, end_ms: start_ms + 300, text: text.to_string(), probability: 1.0 };
+        let timed = Segment { words: vec![word(1000, " Oh"), word(1400, " heck.")], ..Segment::new(1000, 2000, " Oh heck.") };
+        let mut transcript = Transcript { segments: vec![timed, Segment::new(3000, 4000, " Darn."), Segment::new(4000, 5000, " Fine.")] };
+        let index = redactions.apply(&mut transcript);
+        assert_eq!(transcript.segments[0].words[1].text, " h***.");
+        assert_eq!(index, [Redaction { start_ms: 1400, end_ms: 1700, text: "h***".to_string() }, Redaction { start_ms: 3000, end_ms: 4000, text: "D***".to_string() }]);
+    }
+}
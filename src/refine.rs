@@ -0,0 +1,141 @@
+use std::ops::Range;
+
+use clap::{Args, FromArgMatches};
+use whisper_rs::WhisperContext;
+
+use crate::audio;
+use crate::decode;
+use crate::error::Error;
+use crate::transcriber::load_model;
+use crate::transcript::{Segment, Transcript};
+
+#[derive(Args, Debug, Clone)]
+#[group(id = "refine_options")]
+pub struct Options {
+    /// Decode the low confidence stretches again with this bigger model and splice its segments
+    /// in where it's surer, e.g. a tiny.en first pass refined with large-v3
+    #[arg(long, value_name = "MODEL", help_heading = "Refinement")]
+    pub refine_with: Option<String>,
+
+    /// Confidence below which a segment is decoded again
+    #[arg(long, default_value_t = 0.6, value_name = "CONFIDENCE", help_heading = "Refinement")]
+    pub refine_below: f32,
+
+    /// How far either side of a low confidence segment is decoded along with it, rounded out to
+    /// whole segments so none is cut in two
+    #[arg(long, default_value = "2s", value_name = "TIME", value_parser = audio::parse_time, help_heading = "Refinement")]
+    pub refine_padding: u64,
+}
+
+// the clap defaults, as for output::Options
+impl Default for Options {
+    fn default() -> Options {
+        let matches = Options::augment_args(clap::Command::new("options")).get_matches_from(["options"]);
+        Options::from_arg_matches(&matches).expect("option defaults are invalid")
+    }
+}
+
+// the mean probability of the segments' tokens, so a long segment counts for more than a short one
+fn confidence(segments: &[Segment]) -> Option<f32> {
+    let probabilities: Vec<f32> = segments.iter().flat_map(|segment| segment.tokens.iter().map(|token| token.probability)).collect();
+    (!probabilities.is_empty()).then(|| probabilities.iter().sum::<f32>() / probabilities.len() as f32)
+}
+
+// the stretches to decode again, in ms, each running from the start of its first segment to the
+// end of its last; stretches that would overlap are decoded as one
+pub fn ranges(transcript: &Transcript, options: &Options) -> Vec<Range<i64>> {
+    let padding = options.refine_padding as i64;
+    let mut ranges: Vec<Range<i64>> = Vec::new();
+    for segment in transcript.segments.iter().filter(|segment| segment.confidence().is_some_and(|confidence| confidence < options.refine_below)) {
+        let (from, to) = (segment.start_ms - padding, segment.end_ms + padding);
+        let covered = transcript.segments.iter().filter(|other| other.end_ms > from && other.start_ms < to);
+        let start = covered.clone().map(|other| other.start_ms).min().unwrap_or(segment.start_ms);
+        let end = covered.map(|other| other.end_ms).max().unwrap_or(segment.end_ms);
+        match ranges.last_mut() {
+            Some(last) if start <= last.end => last.end = last.end.max(end),
+            _ => ranges.push(start..end),
+        }
+    }
+    ranges
+}
+
+// the segments within range replaced with those of the second decode, when it's surer of them
+pub fn splice(transcript: &mut Transcript, range: Range<i64>, refined: Transcript) -> bool {
+    let first = transcript.segments.iter().position(|segment| segment.start_ms >= range.start).unwrap_or(transcript.segments.len());
+    let count = transcript.segments[first..].iter().take_while(|segment| segment.end_ms <= range.end).count();
+    let better = match (confidence(&transcript.segments[first..first + count]), confidence(&refined.segments)) {
+        (Some(before), Some(after)) => after > before,
+        (_, after) => after.is_some(),
+    };
+    if better {
+        transcript.segments.splice(first..first + count, refined.segments);
+    }
+    better
+}
+
+// --refine-with: the bigger model, loaded once for every file
+pub struct Refiner {
+    ctx: WhisperContext,
+    options: Options,
+}
+
+impl Refiner {
+    pub fn load(options: &Options, decode_options: &decode::Options) -> Result<Option<Refiner>, Error> {
+        let Some(model) = &options.refine_with else { return Ok(None) };
+        tracing::info!("loading model {} to refine with", model);
+        Ok(Some(Refiner { ctx: load_model(model, decode_options.context_params()?)?, options: options.clone() }))
+    }
+
+    // the samples are those the transcript was decoded from, its times counting from their start
+    pub fn refine(&self, decode_options: &decode::Options, samples: &[f32], transcript: &mut Transcript) -> Result<(), Error> {
+        let ranges = ranges(transcript, &self.options);
+        let rate = audio::SAMPLE_RATE as i64;
+        let (mut refined, mut refined_ms) = (0, 0);
+        // spliced from the end, so the ranges before still find their segments
+        for range in ranges.iter().rev() {
+            if decode_options.cancel.is_cancelled() {
+                break;
+            }
+            let samples = &samples[(range.start * rate / 1000).max(0) as usize..((range.end * rate / 1000) as usize).min(samples.len())];
+            let mut decoded = decode_options.transcribe(&self.ctx, samples, |_| {})?;
+            decoded.shift(range.start);
+            if splice(transcript, range.clone(), decoded) {
+                refined += 1;
+                refined_ms += range.end - range.start;
+            }
+        }
+        tracing::info!("refined {} of {} low confidence stretches, {}s of audio", refined, ranges.len(), refined_ms / 1000);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Token;
+
+    fn segment(start_ms: i64, end_ms: i64, text: &str, probability: f32) -> Segment {
+        Segment { tokens: vec![Token::new(text, probability)], ..Segment::new(start_ms, end_ms, text) }
+    }
+
+    #[test]
+    fn _ranges() {
+        let mut transcript = Transcript {
+            segments: vec![
+                segment(0, 4000, " One.", 0.9),
+                segment(4000, 6000, " Tu.", 0.3),
+                segment(6500, 9000, " Three.", 0.9),
+                segment(9000, 12_000, " For.", 0.4),
+                segment(20_000, 22_000, " Five.", 0.9),
+                segment(30_000, 32_000, " Sics.", 0.2),
+            ],
+        };
+        let options = Options { refine_padding: 1000, ..Options::default() };
+        assert_eq!(ranges(&transcript, &options), vec![0..12_000, 30_000..32_000]);
+
+        assert!(splice(&mut transcript, 30_000..32_000, Transcript { segments: vec![segment(30_100, 31_900, " Six.", 0.8)] }));
+        assert!(!splice(&mut transcript, 0..12_000, Transcript { segments: vec![segment(0, 12_000, " Won to free.", 0.1)] }));
+        let texts: Vec<&str> = transcript.segments.iter().map(|segment| segment.text.as_str()).collect();
+        assert_eq!(texts, vec![" One.", " Tu.", " Three.", " For.", " Five.", " Six."]);
+    }
+}
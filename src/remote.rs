@@ -0,0 +1,301 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use transcript::audio::{self, http};
+use transcript::error::Error;
+use transcript::transcript::{Token, Word};
+use transcript::{decode, Segment, Transcript};
+
+// an hour of audio can take a while on a busy server
+const TIMEOUT: Duration = Duration::from_secs(3600);
+
+const BOUNDARY: &str = "transcript-audio-boundary";
+
+// what whisper.cpp's server and openai's api answer with response_format=verbose_json, times in
+// seconds; the server puts the words in their segments, openai lists them all apart
+#[derive(Deserialize, Debug, PartialEq)]
+struct Reply {
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    detected_language_probability: Option<f32>,
+    #[serde(default)]
+    segments: Vec<ReplySegment>,
+    #[serde(default)]
+    words: Vec<ReplyWord>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct ReplySegment {
+    start: f64,
+    end: f64,
+    text: String,
+    #[serde(default)]
+    words: Vec<ReplyWord>,
+    #[serde(default)]
+    no_speech_prob: Option<f32>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct ReplyWord {
+    word: String,
+    start: f64,
+    end: f64,
+    #[serde(default)]
+    probability: Option<f32>,
+}
+
+fn ms(seconds: f64) -> i64 {
+    (seconds * 1000.0).round() as i64
+}
+
+// text as whisper gives it, after a space, which openai leaves out
+fn spaced(text: &str) -> String {
+    if text.starts_with(' ') { text.to_string() } else { format!(" {}", text) }
+}
+
+impl Reply {
+    // the words stand in for the tokens where they have probabilities, so the confidence colouring
+    // and filters still have something to go on
+    fn transcript(self, word_timestamps: bool) -> Transcript {
+        let apart = self.words;
+        let segments = self
+            .segments
+            .into_iter()
+            .map(|reply| {
+                let words: Vec<&ReplyWord> = if reply.words.is_empty() { apart.iter().filter(|word| word.start >= reply.start && word.start < reply.end).collect() } else { reply.words.iter().collect() };
+                let tokens = words.iter().filter_map(|word| Some(Token { start_ms: Some(ms(word.start)), end_ms: Some(ms(word.end)), ..Token::new(&word.word, word.probability?) })).collect();
+                let words = if word_timestamps { words.iter().map(|word| Word { start_ms: ms(word.start), end_ms: ms(word.end), text: spaced(&word.word), probability: word.probability.unwrap_or(1.0) }).collect() } else { Vec::new() };
+                Segment { tokens, words, no_speech_probability: reply.no_speech_prob, ..Segment::new(ms(reply.start), ms(reply.end), &spaced(&reply.text)) }
+            })
+            .collect();
+        Transcript { segments }
+    }
+
+    // openai names the language, "english", where whisper has its code
+    fn language(&self) -> Option<String> {
+        let language = self.language.as_deref()?;
+        whisper_rs::get_lang_id(language).and_then(whisper_rs::get_lang_str).map(str::to_string)
+    }
+}
+
+// 16 bit mono pcm at whisper's rate, as the server expects it
+fn wav(samples: &[f32]) -> Vec<u8> {
+    let data = samples.len() as u32 * 2;
+    let mut wav = Vec::with_capacity(44 + data as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&audio::SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(audio::SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data.to_le_bytes());
+    wav.extend(samples.iter().flat_map(|sample| ((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes()));
+    wav
+}
+
+fn form(fields: &[(&str, String)], samples: &[f32]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (name, value) in fields {
+        body.extend_from_slice(format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", BOUNDARY, name, value).as_bytes());
+    }
+    body.extend_from_slice(format!("--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"audio.wav\"\r\nContent-Type: audio/wav\r\n\r\n", BOUNDARY).as_bytes());
+    body.extend(wav(samples));
+    body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+    body
+}
+
+// what failing to get there at all is put down to, the model's not being available
+fn post(url: &str, what: &str, authorization: Option<&str>, fields: &[(&str, String)], samples: &[f32]) -> Result<Reply, Error> {
+    let mut request = ureq::post(url).timeout(TIMEOUT).set("Content-Type", &format!("multipart/form-data; boundary={}", BOUNDARY));
+    if let Some(authorization) = authorization {
+        request = request.set("Authorization", authorization);
+    }
+    let response = match request.send_bytes(&form(fields, samples)) {
+        Ok(response) => response,
+        Err(ureq::Error::Transport(transport)) => return Err(Error::model(format!("no {} at {}", what, url), http::describe(ureq::Error::Transport(transport)))),
+        Err(error) => return Err(Error::inference(format!("the {} at {} failed", what, url), http::describe(error))),
+    };
+    let body = response.into_string().map_err(|error| Error::inference(format!("failed to read from the {} at {}", what, url), error))?;
+    serde_json::from_str(&body).map_err(|error| Error::inference(format!("the {} at {} answered garbled json", what, url), error))
+}
+
+// --backend server: a whisper.cpp server, with whatever model it was started with, does the
+// decoding; the audio is loaded, and the transcript post-processed and written, here as ever
+pub struct Client {
+    url: String,
+}
+
+impl Client {
+    pub fn new(url: &str) -> Client {
+        Client { url: format!("{}/inference", url.trim_end_matches('/')) }
+    }
+
+    fn post(&self, fields: &[(&str, String)], samples: &[f32]) -> Result<Reply, Error> {
+        post(&self.url, "whisper.cpp server", None, fields, samples)
+    }
+
+    // language detection only looks at the first 30s
+    pub fn detect_language(&self, samples: &[f32]) -> Result<(String, f32), Error> {
+        let samples = &samples[..samples.len().min(30 * audio::SAMPLE_RATE as usize)];
+        let fields = [("language", "auto".to_string()), ("detect_language", "true".to_string()), ("response_format", "verbose_json".to_string())];
+        let reply = self.post(&fields, samples)?;
+        let language = reply.language().ok_or_else(|| Error::Inference(format!("the server at {} didn't say what language it heard", self.url)))?;
+        Ok((language, reply.detected_language_probability.unwrap_or(0.0)))
+    }
+
+    // the decode options the server takes a request at a time; the rest are its own, set when it
+    // was started
+    pub fn transcribe(&self, options: &decode::Options, samples: &[f32]) -> Result<Transcript, Error> {
+        let mut fields = vec![
+            ("response_format", "verbose_json".to_string()),
+            ("language", options.language.clone().unwrap_or_else(|| "en".to_string())),
+            ("translate", options.translate.to_string()),
+            ("temperature", options.temperature.to_string()),
+            ("temperature_inc", options.temperature_increment.to_string()),
+            ("beam_size", options.beam_size.to_string()),
+            ("best_of", options.best_of.to_string()),
+            ("entropy_thold", options.entropy_threshold.to_string()),
+            ("logprob_thold", options.logprob_threshold.to_string()),
+            ("no_speech_thold", options.no_speech_threshold.to_string()),
+            ("max_len", options.max_len.to_string()),
+            ("split_on_word", options.split_on_word.to_string()),
+            ("suppress_nst", options.suppress_non_speech.to_string()),
+            ("no_context", options.no_context.to_string()),
+        ];
+        if let Some(prompt) = options.prompt()? {
+            fields.push(("prompt", prompt));
+        }
+        Ok(self.post(&fields, samples)?.transcript(options.word_timestamps))
+    }
+}
+
+// the api takes files up to 25MB, so longer audio goes in parts of at most this, about 19MB as wav
+const PART_SECONDS: usize = 600;
+// a part ends at the quietest 100ms of its last 30s, so a word is seldom cut in two
+const SEAM_SECONDS: usize = 30;
+
+// where each part starts and ends, in samples
+fn parts(samples: &[f32]) -> Vec<(usize, usize)> {
+    let rate = audio::SAMPLE_RATE as usize;
+    let (part, seam, frame) = (PART_SECONDS * rate, SEAM_SECONDS * rate, rate / 10);
+    let mut parts = Vec::new();
+    let mut start = 0;
+    while samples.len() - start > part {
+        let search = start + part - seam;
+        let loudness = |at: usize| samples[at..at + frame].iter().map(|sample| sample * sample).sum::<f32>();
+        let quietest = (search..start + part - frame).step_by(frame).min_by(|a, b| loudness(*a).total_cmp(&loudness(*b))).unwrap_or(start + part);
+        parts.push((start, quietest));
+        start = quietest;
+    }
+    parts.push((start, samples.len()));
+    parts
+}
+
+// --backend openai: openai's hosted transcription api, or one that works the same way at
+// --openai-url, with the key in OPENAI_API_KEY
+pub struct OpenAi {
+    url: String,
+    model: String,
+    key: String,
+}
+
+impl OpenAi {
+    pub fn new(url: &str, model: &str) -> Result<OpenAi, Error> {
+        let key = std::env::var("OPENAI_API_KEY").map_err(|_| Error::Model("--backend openai needs an api key in OPENAI_API_KEY".to_string()))?;
+        Ok(OpenAi { url: url.trim_end_matches('/').to_string(), model: model.to_string(), key })
+    }
+
+    fn post(&self, endpoint: &str, fields: &[(&str, String)], samples: &[f32]) -> Result<Reply, Error> {
+        post(&format!("{}/audio/{}", self.url, endpoint), "transcription api", Some(&format!("Bearer {}", self.key)), fields, samples)
+    }
+
+    // the api has no detection of its own, the language a transcription of the first 30s comes
+    // back in is the one
+    pub fn detect_language(&self, samples: &[f32]) -> Result<(String, f32), Error> {
+        let samples = &samples[..samples.len().min(30 * audio::SAMPLE_RATE as usize)];
+        let fields = [("model", self.model.clone()), ("response_format", "verbose_json".to_string())];
+        let reply = self.post("transcriptions", &fields, samples)?;
+        let language = reply.language().ok_or_else(|| Error::Inference(format!("the transcription api at {} didn't say what language it heard", self.url)))?;
+        Ok((language, 1.0))
+    }
+
+    // the api takes the language, prompt and temperature, everything else about the decode is its own
+    pub fn transcribe(&self, options: &decode::Options, samples: &[f32]) -> Result<Transcript, Error> {
+        let endpoint = if options.translate { "translations" } else { "transcriptions" };
+        let mut fields = vec![("model", self.model.clone()), ("response_format", "verbose_json".to_string()), ("temperature", options.temperature.to_string()), ("timestamp_granularities[]", "segment".to_string())];
+        if options.word_timestamps {
+            fields.push(("timestamp_granularities[]", "word".to_string()));
+        }
+        if let Some(language) = options.language.as_ref().filter(|language| !options.translate && *language != "auto") {
+            fields.push(("language", language.clone()));
+        }
+        if let Some(prompt) = options.prompt()? {
+            fields.push(("prompt", prompt));
+        }
+        let mut transcript = Transcript { segments: Vec::new() };
+        for (start, end) in parts(samples) {
+            if options.cancel.is_cancelled() {
+                break;
+            }
+            let mut part = self.post(endpoint, &fields, &samples[start..end])?.transcript(options.word_timestamps);
+            part.shift((start as u64 * 1000 / audio::SAMPLE_RATE as u64) as i64);
+            transcript.segments.extend(part.segments);
+        }
+        Ok(transcript)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _transcript() {
+        let reply: Reply = serde_json::from_str(
+            r#"{"task": "transcribe", "language": "en", "duration": 2.0, "text": " Hi there.",
+                "segments": [{"id": 0, "text": " Hi there.", "start": 0.0, "end": 1.52, "tokens": [50364, 2421],
+                "words": [{"word": " Hi", "start": 0.0, "end": 0.5, "probability": 0.9}, {"word": " there.", "start": 0.5, "end": 1.52, "probability": 0.7}],
+                "no_speech_prob": 0.01}]}"#,
+        )
+        .unwrap();
+        let transcript = reply.transcript(false);
+        let segment = &transcript.segments[0];
+        assert_eq!((segment.start_ms, segment.end_ms, segment.text.as_str()), (0, 1520, " Hi there."));
+        assert_eq!(segment.confidence().map(|confidence| (confidence * 10.0).round()), Some(8.0));
+        assert!(segment.words.is_empty());
+
+        // openai's words come apart from the segments, without probabilities
+        let reply: Reply = serde_json::from_str(
+            r#"{"language": "english", "segments": [{"start": 0.0, "end": 1.0, "text": "Hi."}, {"start": 1.0, "end": 2.0, "text": "Bye."}],
+                "words": [{"word": "Hi", "start": 0.1, "end": 0.6}, {"word": "Bye", "start": 1.2, "end": 1.5}]}"#,
+        )
+        .unwrap();
+        assert_eq!(reply.language().as_deref(), Some("en"));
+        let transcript = reply.transcript(true);
+        assert_eq!(transcript.segments[1].words.iter().map(|word| (word.start_ms, word.text.as_str())).collect::<Vec<_>>(), [(1200, " Bye")]);
+        assert_eq!((transcript.segments[1].text.as_str(), transcript.segments[1].confidence()), (" Bye.", None));
+
+        let wav = wav(&[0.0, 1.0]);
+        assert_eq!((&wav[..4], wav.len(), &wav[44..]), (&b"RIFF"[..], 48, &[0, 0, 0xff, 0x7f][..]));
+    }
+
+    #[test]
+    fn _parts() {
+        let rate = audio::SAMPLE_RATE as usize;
+        assert_eq!(parts(&vec![0.5; 60 * rate]), [(0, 60 * rate)]);
+        // 25 minutes with a quiet moment 9m50s in
+        let mut samples = vec![0.5; 25 * 60 * rate];
+        samples[590 * rate..590 * rate + rate / 10].fill(0.0);
+        let parts = parts(&samples);
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], (0, 590 * rate));
+        assert_eq!((parts[1].0, parts[2].1), (590 * rate, 25 * 60 * rate));
+    }
+}
@@ -0,0 +1,83 @@
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::transcript::Transcript;
+
+#[derive(Deserialize)]
+struct Rule {
+    find: String,
+    replace: String,
+}
+
+#[derive(Deserialize)]
+struct File {
+    #[serde(default)]
+    rule: Vec<Rule>,
+}
+
+// find/replace pairs run one after another, each on what the ones before it left
+pub struct Rules {
+    rules: Vec<(Regex, String)>,
+}
+
+impl Rules {
+    // a toml file of [[rule]] tables, each a find regex and what to replace it with, $1 and
+    // ${name} standing for its groups
+    pub fn parse(contents: &str) -> Result<Rules, String> {
+        let file: File = toml::from_str(contents).map_err(|error| error.to_string())?;
+        let rules = file
+            .rule
+            .into_iter()
+            .map(|rule| Regex::new(&rule.find).map(|find| (find, rule.replace)).map_err(|error| format!("bad find pattern {:?}: {}", rule.find, error)))
+            .collect::<Result<_, _>>()?;
+        Ok(Rules { rules })
+    }
+
+    pub fn load(path: &str) -> Result<Rules, Error> {
+        let contents = std::fs::read_to_string(path).map_err(|error| Error::input(format!("failed to read replacement rules {}", path), error))?;
+        Rules::parse(&contents).map_err(|error| Error::input(format!("failed to read replacement rules {}", path), error))
+    }
+
+    pub fn replace_text(&self, text: &str) -> String {
+        self.rules.iter().fold(text.to_string(), |text, (find, replace)| find.replace_all(&text, replace.as_str()).into_owned())
+    }
+
+    // as for the glossary, words are replaced on their own, so a rule spanning several words only
+    // changes the segment's text
+    pub fn apply(&self, transcript: &mut Transcript) {
+        for segment in &mut transcript.segments {
+            segment.text = self.replace_text(&segment.text);
+            for word in &mut segment.words {
+                word.text = self.replace_text(&word.text);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _replace_text() {
+        let rules = Rules::parse(
+            r#"
+            [[rule]]
+            find = "(?i)\\bopen ?ai\\b"
+            replace = "OpenAI"
+
+            [[rule]]
+            find = "\\b(\\d+) percent\\b"
+            replace = "$1%"
+
+            [[rule]]
+            find = "OpenAI's"
+            replace = "the company's"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rules.replace_text(" open ai said 40 percent of openai's users"), " OpenAI said 40% of the company's users");
+        assert!(Rules::parse("[[rule]]\nfind = \"(\"\nreplace = \"\"\n").is_err());
+    }
+}
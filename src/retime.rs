@@ -0,0 +1,77 @@
+use transcript::error::Error;
+use transcript::output::{self, parse, Format};
+
+use crate::convert;
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Subtitle file to retime: srt, vtt, sbv or the json transcript
+    input: String,
+
+    /// Stretch every time by this factor before shifting it, as 1.001 or a frame rate ratio such
+    /// as 25/23.976
+    #[arg(long, default_value = "1", value_parser = parse_scale)]
+    scale: f64,
+
+    /// Format to write it in, the input's own by default
+    #[arg(long, value_enum)]
+    to: Option<Format>,
+
+    /// Output path without an extension; without one the retimed file is printed to stdout
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Replace an output file that already exists instead of refusing to run
+    #[arg(long)]
+    overwrite: bool,
+
+    /// Language the transcript is in, as for convert
+    #[arg(short, long, default_value = "en")]
+    language: String,
+
+    #[command(flatten)]
+    output_options: output::Options,
+}
+
+// a positive factor, or one number over another
+fn parse_scale(value: &str) -> Result<f64, String> {
+    let number = |part: &str| part.trim().parse::<f64>().map_err(|_| format!("{} isn't a number or a ratio such as 25/23.976", value));
+    let scale = match value.split_once('/') {
+        Some((over, under)) => number(over)? / number(under)?,
+        None => number(value)?,
+    };
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err(format!("{} must be more than 0", value));
+    }
+    Ok(scale)
+}
+
+// --scale, then --shift, so a cue at 10s with --scale 2 --shift 1s ends up at 21s
+pub fn run(args: Args) -> Result<(), Error> {
+    let mut transcript = parse::read(&args.input)?;
+    let (scale, shift) = (args.scale, args.output_options.shift);
+    transcript.map_times(|ms| (ms as f64 * scale).round() as i64 + shift);
+    let before = transcript.segments.len();
+    transcript.segments.retain(|segment| segment.end_ms > 0);
+    if transcript.segments.len() < before {
+        tracing::warn!("left out {} cues shifted to before the start", before - transcript.segments.len());
+    }
+    // one shifted across the start is cut there
+    transcript.map_times(|ms| ms.max(0));
+    let format = args.to.or_else(|| parse::format(&args.input)).expect("the input was read, so its format is known");
+    convert::write(&transcript, format, args.output.as_deref(), args.overwrite, &args.language, args.output_options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _parse_scale() {
+        assert_eq!(parse_scale("1.001"), Ok(1.001));
+        assert_eq!(parse_scale("25/20"), Ok(1.25));
+        assert!(parse_scale("0").is_err());
+        assert!(parse_scale("25/0").is_err());
+        assert!(parse_scale("fast").is_err());
+    }
+}
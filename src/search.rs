@@ -0,0 +1,67 @@
+use std::path::Path;
+
+use transcript::error::Error;
+use transcript::output::vtt;
+use transcript::store::{Hit, Store};
+
+use crate::live;
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Words to look for, all of them in the same segment; "in quotes" for a phrase, and OR, NOT
+    /// and budget* as SQLite's FTS5 has them
+    query: String,
+
+    /// Database that transcribe --store wrote
+    #[arg(long, value_name = "FILE")]
+    store: String,
+
+    /// Segments shown either side of each match
+    #[arg(long, default_value_t = 1, value_name = "SEGMENTS")]
+    context: usize,
+
+    /// Matches shown at most, the best first
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+
+    /// Print the matches as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+// the file and time, then the match between the segments around it
+fn render(hit: &Hit) -> String {
+    let mut lines = vec![format!("{} {}", hit.input, vtt::timestamp(hit.start_ms))];
+    lines.extend(hit.before.iter().map(|text| format!("  {}", text)));
+    lines.push(format!("> {}", hit.text));
+    lines.extend(hit.after.iter().map(|text| format!("  {}", text)));
+    lines.join("\n")
+}
+
+pub fn run(args: &Args) -> Result<(), Error> {
+    if !Path::new(&args.store).exists() {
+        return Err(Error::Input(format!("there's no store at {}, transcribe with --store to make one", args.store)));
+    }
+    // the words that matched are in bold on a terminal
+    let marks = if !args.json && live::colors() { ("\x1b[1m", "\x1b[0m") } else { ("", "") };
+    let hits = Store::open(&args.store)?.search(&args.query, args.context, args.limit, marks)?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&hits).expect("failed to serialize matches"));
+    } else if hits.is_empty() {
+        tracing::info!("nothing matched {}", args.query);
+    } else {
+        println!("{}", hits.iter().map(render).collect::<Vec<_>>().join("\n\n"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _render() {
+        let hit = Hit { input: "talk.wav".to_string(), start_ms: 61_500, end_ms: 63_000, text: "the budget meeting".to_string(), before: vec!["So.".to_string()], after: Vec::new() };
+        assert_eq!(render(&hit), "talk.wav 00:01:01.500\n  So.\n> the budget meeting");
+    }
+}
@@ -0,0 +1,204 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tiny_http::Request;
+
+use transcript::error::Error;
+use transcript::output;
+use transcript::Transcriber;
+
+use super::metrics::Metrics;
+use super::{read_form, transcribe_upload, upload_extension, Failure, Form};
+
+static IDS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+// job.json, in a directory of its own beside the upload until that's been transcribed
+#[derive(Serialize, Deserialize, Debug)]
+struct Job {
+    id: String,
+    status: Status,
+    form: Form,
+    upload: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+// $XDG_DATA_HOME/transcript/jobs, falling back to ~/.local/share
+pub fn default_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(base.join("transcript").join("jobs"))
+}
+
+// in time order, so the queue picks up after a restart in the order jobs came in
+fn new_id() -> String {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis());
+    format!("{:012x}{:04x}", millis, IDS.fetch_add(1, Ordering::Relaxed) & 0xffff)
+}
+
+// what GET /jobs/{id} answers with, the result as json when it is some
+fn render(job: &Job) -> String {
+    let result = match (&job.result, job.content_type.as_deref()) {
+        (Some(result), Some("application/json")) => serde_json::from_str(result).unwrap_or(serde_json::Value::Null),
+        (Some(result), _) => serde_json::Value::String(result.clone()),
+        (None, _) => serde_json::Value::Null,
+    };
+    serde_json::json!({ "id": job.id, "status": job.status, "result": result, "error": job.error }).to_string()
+}
+
+// queued uploads and their results, on disk so neither is lost to a restart
+pub struct Jobs {
+    dir: PathBuf,
+    queue: Sender<String>,
+}
+
+impl Jobs {
+    // jobs a restart caught queued or running are queued again
+    pub fn open(dir: PathBuf) -> Result<(Jobs, Receiver<String>), Error> {
+        let failed = |error: std::io::Error| Error::input(format!("failed to open the job queue {}", dir.display()), error);
+        fs::create_dir_all(&dir).map_err(failed)?;
+        let (queue, queued) = mpsc::channel();
+        let jobs = Jobs { dir: dir.clone(), queue };
+        let mut unfinished = Vec::new();
+        for entry in fs::read_dir(&dir).map_err(failed)? {
+            let id = entry.map_err(failed)?.file_name().to_string_lossy().into_owned();
+            match jobs.load(&id) {
+                Ok(job) if matches!(job.status, Status::Queued | Status::Running) => unfinished.push(id),
+                Ok(_) => {}
+                Err(error) => tracing::warn!("skipping job {}: {}", id, error.message),
+            }
+        }
+        unfinished.sort();
+        if !unfinished.is_empty() {
+            tracing::info!("queueing {} unfinished jobs from {}", unfinished.len(), dir.display());
+        }
+        for id in unfinished {
+            jobs.queue.send(id).expect("the job queue is open");
+        }
+        Ok((jobs, queued))
+    }
+
+    fn file(&self, id: &str) -> PathBuf {
+        self.dir.join(id).join("job.json")
+    }
+
+    fn load(&self, id: &str) -> Result<Job, Failure> {
+        // ids come from urls, so nothing that could step out of the directory
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Failure { status: 404, message: format!("no such job {}", id) });
+        }
+        let contents = fs::read_to_string(self.file(id)).map_err(|_| Failure { status: 404, message: format!("no such job {}", id) })?;
+        serde_json::from_str(&contents).map_err(|error| Failure { status: 500, message: format!("job {} is corrupt: {}", id, error) })
+    }
+
+    fn save(&self, job: &Job) -> Result<(), Error> {
+        output::write_to_file(&self.file(&job.id), serde_json::to_string(job).expect("failed to serialize job"))
+    }
+
+    // stores the upload and queues it, answering with the job's id straight away
+    pub fn submit(&self, request: &mut Request, max_upload: u64) -> Result<String, Failure> {
+        let (file, form) = read_form(request, max_upload)?;
+        let id = new_id();
+        let stored = |error: &dyn std::fmt::Display| Failure { status: 500, message: format!("failed to store the job: {}", error) };
+        fs::create_dir_all(self.dir.join(&id)).map_err(|error| stored(&error))?;
+        let upload = format!("upload{}", upload_extension(&file));
+        fs::write(self.dir.join(&id).join(&upload), &file.data).map_err(|error| stored(&error))?;
+        let job = Job { id: id.clone(), status: Status::Queued, form, upload, content_type: None, result: None, error: None };
+        self.save(&job).map_err(|error| stored(&error))?;
+        self.queue.send(id.clone()).expect("the job queue is open");
+        Ok(serde_json::json!({ "id": id, "status": job.status }).to_string())
+    }
+
+    pub fn status(&self, id: &str) -> Result<String, Failure> {
+        self.load(id).map(|job| render(&job))
+    }
+
+    // works through the queue a job at a time, for as long as the server runs
+    pub fn run(&self, queued: Receiver<String>, transcriber: &Transcriber, metrics: &Metrics) {
+        for id in queued {
+            let mut job = match self.load(&id) {
+                Ok(job) => job,
+                Err(failure) => {
+                    tracing::warn!("skipping job {}: {}", id, failure.message);
+                    continue;
+                }
+            };
+            job.status = Status::Running;
+            if let Err(error) = self.save(&job) {
+                tracing::warn!("{}", error);
+            }
+            tracing::info!("running job {}", id);
+            let upload = self.dir.join(&id).join(&job.upload);
+            match transcribe_upload(transcriber, &job.form, &upload, metrics) {
+                Ok((result, content_type)) => {
+                    job.status = Status::Done;
+                    job.result = Some(result);
+                    job.content_type = Some(content_type.to_string());
+                }
+                Err(failure) => {
+                    tracing::warn!("job {} failed: {}", id, failure.message);
+                    job.status = Status::Failed;
+                    job.error = Some(failure.message);
+                }
+            }
+            match self.save(&job) {
+                // the result stands in for the audio from here on
+                Ok(()) => {
+                    let _ = fs::remove_file(&upload);
+                }
+                Err(error) => tracing::warn!("{}", error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serve::ResponseFormat;
+
+    #[test]
+    fn _open() {
+        let dir = env::temp_dir().join(format!("transcript-jobs-test-{}", std::process::id()));
+        let form = || Form { language: None, prompt: None, temperature: None, words: false, response_format: ResponseFormat::Json };
+        {
+            let (jobs, _) = Jobs::open(dir.clone()).unwrap();
+            for (status, result) in [(Status::Done, Some(r#"{"text":"Hello."}"#)), (Status::Running, None), (Status::Queued, None)] {
+                let id = new_id();
+                fs::create_dir_all(dir.join(&id)).unwrap();
+                let content_type = result.map(|_| "application/json".to_string());
+                jobs.save(&Job { id, status, form: form(), upload: "upload.wav".to_string(), content_type, result: result.map(String::from), error: None }).unwrap();
+            }
+        }
+        let (jobs, queued) = Jobs::open(dir.clone()).unwrap();
+        let requeued: Vec<String> = queued.try_iter().collect();
+        assert_eq!(requeued.len(), 2);
+        assert_eq!(jobs.load(&requeued[0]).unwrap().status, Status::Running);
+
+        let mut ids: Vec<String> = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned()).collect();
+        ids.sort();
+        assert_eq!(jobs.status(&ids[0]).unwrap(), format!(r#"{{"error":null,"id":"{}","result":{{"text":"Hello."}},"status":"done"}}"#, ids[0]));
+        assert_eq!(jobs.status("../../etc").err().map(|failure| failure.status), Some(404));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
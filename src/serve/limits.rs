@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use tiny_http::Request;
+
+// compared byte by byte all the way through, so the time taken doesn't give away how much of a
+// guess was right
+fn same(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
+
+// with no keys every request is let in
+pub fn authorized(request: &Request, keys: &[String]) -> bool {
+    if keys.is_empty() {
+        return true;
+    }
+    let token = request.headers().iter().find(|header| header.field.equiv("Authorization")).and_then(|header| header.value.as_str().strip_prefix("Bearer ").map(str::trim));
+    token.is_some_and(|token| keys.iter().fold(false, |found, key| same(token.as_bytes(), key.as_bytes()) | found))
+}
+
+// the requests each client address has queued, being decoded or streaming
+pub struct Clients {
+    limit: Option<usize>,
+    in_flight: Mutex<HashMap<IpAddr, usize>>,
+}
+
+// a request counted against its client until it's dropped
+pub struct Admitted {
+    clients: Arc<Clients>,
+    address: IpAddr,
+}
+
+impl Drop for Admitted {
+    fn drop(&mut self) {
+        let mut in_flight = self.clients.in_flight.lock().expect("client counts poisoned");
+        if let Some(count) = in_flight.get_mut(&self.address) {
+            *count -= 1;
+            if *count == 0 {
+                in_flight.remove(&self.address);
+            }
+        }
+    }
+}
+
+impl Clients {
+    pub fn new(limit: Option<usize>) -> Clients {
+        Clients { limit, in_flight: Mutex::new(HashMap::new()) }
+    }
+
+    // none once the client has as many requests going as it's allowed
+    pub fn admit(self: &Arc<Self>, address: IpAddr) -> Option<Admitted> {
+        let mut in_flight = self.in_flight.lock().expect("client counts poisoned");
+        let count = in_flight.entry(address).or_default();
+        if self.limit.is_some_and(|limit| *count >= limit) {
+            return None;
+        }
+        *count += 1;
+        Some(Admitted { clients: self.clone(), address })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _admit() {
+        let clients = Arc::new(Clients::new(Some(2)));
+        let one: IpAddr = "10.0.0.1".parse().unwrap();
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+        let first = clients.admit(one).unwrap();
+        let _second = clients.admit(one).unwrap();
+        assert!(clients.admit(one).is_none());
+        assert!(clients.admit(other).is_some());
+        drop(first);
+        assert!(clients.admit(one).is_some());
+
+        assert!(same(b"secret", b"secret"));
+        assert!(!same(b"secret", b"secreT"));
+        assert!(!same(b"secret", b"secre"));
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// upper bounds of the latency buckets in seconds, from a short clip to an hour long recording
+const LATENCY_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+// processing time over audio time, below 1 is faster than realtime
+const REALTIME_BUCKETS: &[f64] = &[0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 5.0];
+
+struct Histogram {
+    bounds: &'static [f64],
+    // per bucket, not yet cumulative
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Histogram {
+        Histogram { bounds, counts: vec![0; bounds.len()], sum: 0.0, count: 0 }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if let Some(bucket) = self.bounds.iter().position(|bound| value <= *bound) {
+            self.counts[bucket] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} histogram", name, help, name);
+        let mut cumulative = 0;
+        for (bound, count) in self.bounds.iter().zip(&self.counts) {
+            cumulative += count;
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+        }
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}\n{}_sum {}\n{}_count {}", name, self.count, name, self.sum, name, self.count);
+    }
+}
+
+struct Recorded {
+    // by endpoint and status code
+    requests: BTreeMap<(String, u16), u64>,
+    audio_seconds: f64,
+    latency: Histogram,
+    realtime: Histogram,
+}
+
+// what /metrics reports, in the prometheus text format
+pub struct Metrics {
+    recorded: Mutex<Recorded>,
+    // requests waiting for a worker, and being decoded by one
+    queued: AtomicI64,
+    active: AtomicI64,
+}
+
+impl Default for Metrics {
+    fn default() -> Metrics {
+        let recorded = Recorded { requests: BTreeMap::new(), audio_seconds: 0.0, latency: Histogram::new(LATENCY_BUCKETS), realtime: Histogram::new(REALTIME_BUCKETS) };
+        Metrics { recorded: Mutex::new(recorded), queued: AtomicI64::new(0), active: AtomicI64::new(0) }
+    }
+}
+
+impl Metrics {
+    fn recorded(&self) -> std::sync::MutexGuard<'_, Recorded> {
+        self.recorded.lock().expect("metrics poisoned")
+    }
+
+    pub fn request(&self, endpoint: &str, status: u16) {
+        *self.recorded().requests.entry((endpoint.to_string(), status)).or_default() += 1;
+    }
+
+    // a decode of audio_ms of audio that took inference
+    pub fn decoded(&self, audio_ms: u64, inference: Duration) {
+        let mut recorded = self.recorded();
+        let audio_seconds = audio_ms as f64 / 1000.0;
+        recorded.audio_seconds += audio_seconds;
+        recorded.latency.observe(inference.as_secs_f64());
+        if audio_seconds > 0.0 {
+            recorded.realtime.observe(inference.as_secs_f64() / audio_seconds);
+        }
+    }
+
+    pub fn queue(&self) {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // a worker took a request off the queue
+    pub fn start(&self) {
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        self.active.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn finish(&self) {
+        self.active.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn render(&self) -> String {
+        let recorded = self.recorded();
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP transcript_requests_total Requests answered, by endpoint and status code\n# TYPE transcript_requests_total counter");
+        for ((endpoint, status), count) in &recorded.requests {
+            let _ = writeln!(out, "transcript_requests_total{{endpoint=\"{}\",status=\"{}\"}} {}", endpoint, status, count);
+        }
+        let _ = writeln!(out, "# HELP transcript_audio_seconds_total Seconds of audio transcribed\n# TYPE transcript_audio_seconds_total counter\ntranscript_audio_seconds_total {}", recorded.audio_seconds);
+        recorded.latency.render(&mut out, "transcript_inference_seconds", "Time spent decoding a request's audio");
+        recorded.realtime.render(&mut out, "transcript_realtime_factor", "Decoding time over audio duration, below 1 is faster than realtime");
+        let _ = writeln!(out, "# HELP transcript_queue_depth Requests waiting for a worker\n# TYPE transcript_queue_depth gauge\ntranscript_queue_depth {}", self.queued.load(Ordering::Relaxed));
+        let _ = writeln!(out, "# HELP transcript_requests_active Requests being decoded\n# TYPE transcript_requests_active gauge\ntranscript_requests_active {}", self.active.load(Ordering::Relaxed));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _render() {
+        let metrics = Metrics::default();
+        metrics.request("/v1/audio/transcriptions", 200);
+        metrics.request("/v1/audio/transcriptions", 200);
+        metrics.request("/v1/audio/transcriptions", 400);
+        metrics.decoded(10_000, Duration::from_millis(2000));
+        metrics.queue();
+        metrics.queue();
+        metrics.start();
+        let rendered = metrics.render();
+        for line in [
+            "transcript_requests_total{endpoint=\"/v1/audio/transcriptions\",status=\"200\"} 2",
+            "transcript_requests_total{endpoint=\"/v1/audio/transcriptions\",status=\"400\"} 1",
+            "transcript_audio_seconds_total 10",
+            "transcript_inference_seconds_bucket{le=\"1\"} 0",
+            "transcript_inference_seconds_bucket{le=\"2.5\"} 1",
+            "transcript_inference_seconds_bucket{le=\"+Inf\"} 1",
+            "transcript_realtime_factor_bucket{le=\"0.25\"} 1",
+            "transcript_queue_depth 1",
+            "transcript_requests_active 1",
+        ] {
+            assert!(rendered.lines().any(|rendered| rendered == line), "{} is missing from\n{}", line, rendered);
+        }
+    }
+}
@@ -0,0 +1,491 @@
+use std::fs;
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use transcript::error::Error;
+use transcript::output::{self, Format};
+use transcript::{audio, decode, models, Transcriber, Transcript};
+
+mod jobs;
+mod limits;
+mod metrics;
+mod stream;
+
+use jobs::Jobs;
+use limits::Clients;
+use metrics::Metrics;
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Model file or name, as for transcribe; it's loaded once and kept for every request
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    bind: String,
+
+    /// Requests decoded at the same time, each with its own whisper state
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    workers: u32,
+
+    /// Only answer requests with an Authorization: Bearer header holding this key; repeat it to
+    /// accept several
+    #[arg(long, value_name = "KEY")]
+    api_key: Vec<String>,
+
+    /// Largest upload accepted, in MB
+    #[arg(long, default_value_t = 100, value_name = "MB")]
+    max_upload: u64,
+
+    /// Requests one client address can have queued, decoding or streaming at once
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    max_per_client: Option<u32>,
+
+    /// Where POST /jobs keeps its queue and results, defaults to $XDG_DATA_HOME/transcript/jobs
+    #[arg(long, value_name = "DIR")]
+    jobs_dir: Option<PathBuf>,
+
+    /// Decode a second of silence once the model's loaded, so the first request isn't slowed by
+    /// the backend setting itself up
+    #[arg(long)]
+    preload: bool,
+
+    #[command(flatten)]
+    decode_options: decode::Options,
+
+    #[command(flatten)]
+    audio_options: audio::Options,
+}
+
+// uploads go through the same decoders as files on disk, so they're written out first
+static UPLOADS: AtomicUsize = AtomicUsize::new(0);
+
+// one part of a multipart/form-data body
+#[derive(Debug, PartialEq)]
+struct Field {
+    name: String,
+    filename: Option<String>,
+    data: Vec<u8>,
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+// a value out of a header like form-data; name="file"; filename="a.wav"
+fn parameter(header: &str, name: &str) -> Option<String> {
+    header.split(';').map(str::trim).find_map(|part| part.strip_prefix(name)?.strip_prefix('=')).map(|value| value.trim_matches('"').to_string())
+}
+
+fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<Field>, String> {
+    let delimiter = format!("--{}", boundary);
+    let mut rest = &body[find(body, delimiter.as_bytes()).ok_or("the body has no multipart boundary")? + delimiter.len()..];
+    let mut fields = Vec::new();
+    // every part follows a line break and ends where the next delimiter does, the last one with --
+    while !rest.starts_with(b"--") {
+        let part = rest.strip_prefix(b"\r\n").ok_or("a multipart boundary isn't followed by a line break")?;
+        let headers_end = find(part, b"\r\n\r\n").ok_or("a multipart part has no end to its headers")?;
+        let headers = String::from_utf8_lossy(&part[..headers_end]);
+        let data = &part[headers_end + 4..];
+        let end = find(data, format!("\r\n{}", delimiter).as_bytes()).ok_or("a multipart part has no closing boundary")?;
+        let disposition = headers.lines().find(|line| line.to_ascii_lowercase().starts_with("content-disposition:")).ok_or("a multipart part has no content-disposition")?;
+        let name = parameter(disposition, "name").ok_or("a multipart part has no name")?;
+        fields.push(Field { name, filename: parameter(disposition, "filename"), data: data[..end].to_vec() });
+        rest = &data[end + 2 + delimiter.len()..];
+    }
+    Ok(fields)
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum ResponseFormat {
+    Json,
+    Text,
+    Srt,
+    VerboseJson,
+    Vtt,
+}
+
+impl ResponseFormat {
+    fn parse(value: &str) -> Result<ResponseFormat, String> {
+        match value {
+            "json" => Ok(ResponseFormat::Json),
+            "text" => Ok(ResponseFormat::Text),
+            "srt" => Ok(ResponseFormat::Srt),
+            "verbose_json" => Ok(ResponseFormat::VerboseJson),
+            "vtt" => Ok(ResponseFormat::Vtt),
+            _ => Err(format!("unknown response_format {}, expected json, text, srt, verbose_json or vtt", value)),
+        }
+    }
+}
+
+// the shapes the openai api answers with, times in seconds
+#[derive(Serialize)]
+struct Verbose<'a> {
+    task: &'static str,
+    language: &'a str,
+    duration: f64,
+    text: String,
+    segments: Vec<VerboseSegment<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    words: Vec<VerboseWord<'a>>,
+}
+
+#[derive(Serialize)]
+struct VerboseSegment<'a> {
+    id: usize,
+    start: f64,
+    end: f64,
+    text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    avg_logprob: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compression_ratio: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    no_speech_prob: Option<f32>,
+}
+
+#[derive(Serialize)]
+struct VerboseWord<'a> {
+    word: &'a str,
+    start: f64,
+    end: f64,
+}
+
+fn seconds(ms: i64) -> f64 {
+    ms as f64 / 1000.0
+}
+
+fn text(transcript: &Transcript) -> String {
+    transcript.segments.iter().map(|segment| segment.text.trim()).filter(|text| !text.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+fn render(transcript: &Transcript, format: ResponseFormat, language: &str, duration_ms: i64) -> (String, &'static str) {
+    match format {
+        ResponseFormat::Json => (serde_json::json!({ "text": text(transcript) }).to_string(), "application/json"),
+        ResponseFormat::Text => (text(transcript) + "\n", "text/plain; charset=utf-8"),
+        ResponseFormat::Srt => (Format::Srt.render(transcript, &output::Options::default()), "text/plain; charset=utf-8"),
+        ResponseFormat::Vtt => (Format::Vtt.render(transcript, &output::Options::default()), "text/vtt; charset=utf-8"),
+        ResponseFormat::VerboseJson => {
+            let verbose = Verbose {
+                task: "transcribe",
+                language,
+                duration: seconds(duration_ms),
+                text: text(transcript),
+                segments: transcript
+                    .segments
+                    .iter()
+                    .enumerate()
+                    .map(|(id, segment)| VerboseSegment {
+                        id,
+                        start: seconds(segment.start_ms),
+                        end: seconds(segment.end_ms),
+                        text: &segment.text,
+                        avg_logprob: segment.mean_logprob(),
+                        compression_ratio: segment.compression_ratio,
+                        no_speech_prob: segment.no_speech_probability,
+                    })
+                    .collect(),
+                words: transcript.segments.iter().flat_map(|segment| &segment.words).map(|word| VerboseWord { word: &word.text, start: seconds(word.start_ms), end: seconds(word.end_ms) }).collect(),
+            };
+            (serde_json::to_string(&verbose).expect("failed to serialize transcript"), "application/json")
+        }
+    }
+}
+
+// what a request failed with, in the openai error shape
+#[derive(Debug)]
+struct Failure {
+    status: u16,
+    message: String,
+}
+
+impl Failure {
+    fn bad_request(message: impl Into<String>) -> Failure {
+        Failure { status: 400, message: message.into() }
+    }
+
+    fn too_large(max_upload: u64) -> Failure {
+        Failure { status: 413, message: format!("uploads are limited to {} MB", max_upload / 1_000_000) }
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(error: Error) -> Failure {
+        let status = match error {
+            Error::Input(_) => 400,
+            _ => 500,
+        };
+        Failure { status, message: error.to_string() }
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("header is invalid")
+}
+
+fn respond(request: Request, status: u16, body: String, content_type: &str) {
+    let response = Response::from_string(body).with_status_code(status).with_header(header("Content-Type", content_type));
+    if let Err(error) = request.respond(response) {
+        tracing::warn!("failed to send a response: {}", error);
+    }
+}
+
+// the settings a request picked from the form, kept with a queued job until it runs
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Form {
+    language: Option<String>,
+    prompt: Option<String>,
+    temperature: Option<f32>,
+    words: bool,
+    response_format: ResponseFormat,
+}
+
+// the multipart form a request carries, its file and the settings it picked
+fn read_form(request: &mut Request, max_upload: u64) -> Result<(Field, Form), Failure> {
+    let content_type = request.headers().iter().find(|header| header.field.equiv("Content-Type")).map(|header| header.value.to_string()).unwrap_or_default();
+    let boundary = parameter(&content_type, "boundary").filter(|_| content_type.starts_with("multipart/form-data")).ok_or_else(|| Failure::bad_request("expected a multipart/form-data body"))?;
+    let mut body = Vec::new();
+    // a chunked body has no length to check up front
+    request.as_reader().take(max_upload + 1).read_to_end(&mut body).map_err(|error| Failure::bad_request(format!("failed to read the body: {}", error)))?;
+    if body.len() as u64 > max_upload {
+        return Err(Failure::too_large(max_upload));
+    }
+    let mut fields = parse_multipart(&body, &boundary).map_err(Failure::bad_request)?;
+    let value = |name: &str| fields.iter().find(|field| field.name == name).map(|field| String::from_utf8_lossy(&field.data).trim().to_string());
+
+    let form = Form {
+        language: value("language").filter(|language| !language.is_empty()),
+        prompt: value("prompt").filter(|prompt| !prompt.is_empty()),
+        temperature: value("temperature").map(|temperature| temperature.parse().map_err(|_| Failure::bad_request(format!("temperature {} isn't a number", temperature)))).transpose()?,
+        words: fields.iter().any(|field| field.name == "timestamp_granularities[]" && field.data == b"word"),
+        response_format: ResponseFormat::parse(&value("response_format").unwrap_or_else(|| "json".to_string())).map_err(Failure::bad_request)?,
+    };
+    let file = fields.iter().position(|field| field.name == "file").ok_or_else(|| Failure::bad_request("file is missing"))?;
+    Ok((fields.swap_remove(file), form))
+}
+
+// keeps the upload's extension, the decoders go by it
+fn upload_extension(file: &Field) -> String {
+    file.filename.as_deref().and_then(|name| Path::new(name).extension()).map_or(String::new(), |extension| format!(".{}", extension.to_string_lossy()))
+}
+
+fn transcribe_upload(transcriber: &Transcriber, form: &Form, upload: &Path, metrics: &Metrics) -> Result<(String, &'static str), Failure> {
+    let mut transcriber = transcriber.clone();
+    if let Some(prompt) = &form.prompt {
+        // the prompt goes through the text context --no-context turns off
+        if transcriber.decode_options().no_context {
+            return Err(Failure::bad_request("the server runs with --no-context, which leaves no room for a prompt"));
+        }
+        transcriber = transcriber.initial_prompt(prompt.clone());
+    }
+    if let Some(temperature) = form.temperature {
+        let mut options = transcriber.decode_options().clone();
+        options.temperature = temperature;
+        transcriber = transcriber.options(options);
+    }
+    if form.words {
+        transcriber = transcriber.word_timestamps(true);
+    }
+    let samples = transcriber.load_audio(&upload.to_string_lossy())?;
+
+    let started = Instant::now();
+    let language = match form.language.clone().or_else(|| transcriber.decode_options().language.clone()) {
+        Some(language) if language == "auto" => transcriber.detect_language(&samples)?.0.to_string(),
+        Some(language) => language,
+        None => "en".to_string(),
+    };
+    let transcriber = transcriber.language(language.clone());
+    let transcript = transcriber.transcribe(&samples)?;
+    let duration_ms = (samples.len() * 1000 / audio::SAMPLE_RATE as usize) as i64;
+    metrics.decoded(duration_ms as u64, started.elapsed());
+    Ok(render(&transcript, form.response_format, &language, duration_ms))
+}
+
+fn transcription(transcriber: &Transcriber, request: &mut Request, metrics: &Metrics, max_upload: u64) -> Result<(String, &'static str), Failure> {
+    let (file, form) = read_form(request, max_upload)?;
+    let upload = std::env::temp_dir().join(format!("transcript-upload-{}-{}{}", std::process::id(), UPLOADS.fetch_add(1, Ordering::SeqCst), upload_extension(&file)));
+    fs::write(&upload, &file.data).map_err(|error| Failure { status: 500, message: format!("failed to store the upload: {}", error) })?;
+    let result = transcribe_upload(transcriber, &form, &upload, metrics);
+    let _ = fs::remove_file(&upload);
+    result
+}
+
+fn path(request: &Request) -> String {
+    request.url().split('?').next().unwrap_or_default().to_string()
+}
+
+fn fail(request: Request, path: &str, failure: Failure) {
+    tracing::warn!("{} {} failed: {}", request.method(), path, failure.message);
+    let kind = if failure.status < 500 { "invalid_request_error" } else { "server_error" };
+    let body = serde_json::json!({ "error": { "message": failure.message, "type": kind, "param": null, "code": null } });
+    let response = Response::from_string(body.to_string()).with_status_code(failure.status).with_header(header("Content-Type", "application/json"));
+    // tells the client how to authenticate
+    let response = if failure.status == 401 { response.with_header(header("WWW-Authenticate", "Bearer")) } else { response };
+    if let Err(error) = request.respond(response) {
+        tracing::warn!("failed to send a response: {}", error);
+    }
+}
+
+fn handle(transcriber: &Transcriber, mut request: Request, metrics: &Metrics, max_upload: u64) {
+    let path = path(&request);
+    let result = match (request.method(), path.as_str()) {
+        (Method::Post, "/v1/audio/transcriptions") => transcription(transcriber, &mut request, metrics, max_upload),
+        (_, "/v1/audio/transcriptions") => Err(Failure { status: 405, message: "use POST".to_string() }),
+        _ => Err(Failure { status: 404, message: format!("no such endpoint {}", path) }),
+    };
+    // anything else is one label, whatever the path
+    let endpoint = if path == "/v1/audio/transcriptions" { path.as_str() } else { "other" };
+    match result {
+        Ok((body, content_type)) => {
+            metrics.request(endpoint, 200);
+            respond(request, 200, body, content_type)
+        }
+        Err(failure) => {
+            metrics.request(endpoint, failure.status);
+            fail(request, &path, failure);
+        }
+    }
+}
+
+pub fn run(args: Args) -> Result<(), Error> {
+    let model = match args.model {
+        Some(model) => model,
+        None => models::discover()?,
+    };
+    let transcriber = Transcriber::new(model).options(args.decode_options).audio_options(args.audio_options);
+    if args.preload {
+        transcriber.warm_up()?;
+    } else {
+        transcriber.load()?;
+    }
+    let server = Server::http(&args.bind).map_err(|error| Error::input(format!("failed to listen on {}", args.bind), error))?;
+    tracing::info!("listening on http://{}/v1/audio/transcriptions", args.bind);
+    let metrics = Arc::new(Metrics::default());
+    let clients = Arc::new(Clients::new(args.max_per_client.map(|limit| limit as usize)));
+    let max_upload = args.max_upload * 1_000_000;
+    let jobs_dir = args.jobs_dir.or_else(jobs::default_dir).ok_or_else(|| Error::Input("no directory for the job queue, HOME isn't set; give --jobs-dir".to_string()))?;
+    let (jobs, pending) = Jobs::open(jobs_dir)?;
+    let jobs = Arc::new(jobs);
+    // queued jobs run one at a time beside the workers
+    {
+        let (jobs, transcriber, metrics) = (jobs.clone(), transcriber.clone(), metrics.clone());
+        thread::spawn(move || jobs.run(pending, &transcriber, &metrics));
+    }
+    let (queue, queued) = mpsc::channel::<(Request, limits::Admitted)>();
+    let queued = Arc::new(Mutex::new(queued));
+    let workers: Vec<_> = (0..args.workers)
+        .map(|_| {
+            let queued = queued.clone();
+            let transcriber = transcriber.clone();
+            let metrics = metrics.clone();
+            thread::spawn(move || loop {
+                let Ok((request, _admitted)) = queued.lock().expect("request queue poisoned").recv() else { break };
+                metrics.start();
+                handle(&transcriber, request, &metrics, max_upload);
+                metrics.finish();
+            })
+        })
+        .collect();
+    // only transcriptions wait for a worker, /metrics is answered even when they're all busy;
+    // whatever can be turned away is turned away before it queues
+    for request in server.incoming_requests() {
+        let path = path(&request);
+        if !limits::authorized(&request, &args.api_key) {
+            metrics.request("other", 401);
+            fail(request, &path, Failure { status: 401, message: "a valid api key is needed, as Authorization: Bearer <key>".to_string() });
+            continue;
+        }
+        if request.body_length().is_some_and(|length| length as u64 > max_upload) {
+            metrics.request("other", 413);
+            fail(request, &path, Failure::too_large(max_upload));
+            continue;
+        }
+        let address = request.remote_addr().map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |address| address.ip());
+        let Some(admitted) = clients.admit(address) else {
+            metrics.request("other", 429);
+            fail(request, &path, Failure { status: 429, message: "too many requests from this client at once".to_string() });
+            continue;
+        };
+        match (request.method(), path.as_str()) {
+            (Method::Post, "/jobs") => {
+                // the upload can take a while to come in, so it has a thread of its own
+                let (jobs, metrics) = (jobs.clone(), metrics.clone());
+                thread::spawn(move || {
+                    let mut request = request;
+                    match jobs.submit(&mut request, max_upload) {
+                        Ok(body) => {
+                            metrics.request("/jobs", 202);
+                            respond(request, 202, body, "application/json");
+                        }
+                        Err(failure) => {
+                            metrics.request("/jobs", failure.status);
+                            fail(request, "/jobs", failure);
+                        }
+                    }
+                    drop(admitted);
+                });
+            }
+            (Method::Get, _) if path.starts_with("/jobs/") => match jobs.status(&path["/jobs/".len()..]) {
+                Ok(body) => {
+                    metrics.request("/jobs", 200);
+                    respond(request, 200, body, "application/json");
+                }
+                Err(failure) => {
+                    metrics.request("/jobs", failure.status);
+                    fail(request, &path, failure);
+                }
+            },
+            (_, "/metrics") => {
+                metrics.request("/metrics", 200);
+                respond(request, 200, metrics.render(), "text/plain; version=0.0.4");
+            }
+            // a stream lasts as long as its client keeps talking, so it gets a thread of its own
+            (_, "/stream") => {
+                metrics.request("/stream", 101);
+                let transcriber = transcriber.clone();
+                thread::spawn(move || {
+                    stream::serve(transcriber, request);
+                    drop(admitted);
+                });
+            }
+            _ => {
+                metrics.queue();
+                queue.send((request, admitted)).expect("server workers stopped");
+            }
+        }
+    }
+    drop(queue);
+    for worker in workers {
+        worker.join().expect("server worker panicked");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _parse_multipart() {
+        let body = b"--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.wav\"\r\nContent-Type: audio/wav\r\n\r\nRIFF\r\n--\r\n--XyZ\r\nContent-Disposition: form-data; name=\"response_format\"\r\n\r\nsrt\r\n--XyZ--\r\n";
+        assert_eq!(
+            parse_multipart(body, "XyZ").unwrap(),
+            vec![
+                Field { name: "file".to_string(), filename: Some("a.wav".to_string()), data: b"RIFF\r\n--".to_vec() },
+                Field { name: "response_format".to_string(), filename: None, data: b"srt".to_vec() },
+            ]
+        );
+        assert!(parse_multipart(b"--XyZ\r\nContent-Disposition: form-data; name=\"file\"\r\n\r\nRIFF", "XyZ").is_err());
+        assert!(parse_multipart(b"plain", "XyZ").is_err());
+    }
+}
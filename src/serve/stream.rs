@@ -0,0 +1,70 @@
+use std::io;
+
+use tiny_http::{Header, Request, Response};
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+use transcript::Transcriber;
+
+use crate::live::{Live, Update};
+
+type Socket = WebSocket<Box<dyn tiny_http::ReadWrite + Send>>;
+
+fn send(socket: &mut Socket, update: &Update) -> Result<(), String> {
+    socket.send(Message::Text(serde_json::to_string(update).expect("failed to serialize update"))).map_err(|error| error.to_string())
+}
+
+// binary messages of pcm until the client sends "end" or closes, then the rest of the audio goes
+// out as final and the socket closes
+fn converse(socket: &mut Socket, transcriber: &Transcriber) -> Result<(), String> {
+    let mut live = Live::default();
+    loop {
+        let end = match socket.read() {
+            Ok(Message::Binary(pcm)) => {
+                live.push(&pcm);
+                false
+            }
+            Ok(Message::Text(text)) if text.trim() == "end" => true,
+            Ok(Message::Close(_)) => true,
+            Ok(_) => continue,
+            // the client went away without closing, there's nobody left to answer
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => return Ok(()),
+            Err(tungstenite::Error::Io(error)) if error.kind() == io::ErrorKind::ConnectionReset => return Ok(()),
+            Err(error) => return Err(error.to_string()),
+        };
+        if live.due(end) {
+            match live.decode(|samples| transcriber.transcribe(samples), end) {
+                Ok(updates) => updates.iter().try_for_each(|update| send(socket, update))?,
+                Err(error) => {
+                    send(socket, &Update::Error { message: error.to_string() })?;
+                    break;
+                }
+            }
+        }
+        if end {
+            break;
+        }
+    }
+    socket.close(None).map_err(|error| error.to_string())?;
+    // the close is done once the client's answer to it has been read
+    while socket.read().is_ok() {}
+    Ok(())
+}
+
+pub fn serve(transcriber: Transcriber, request: Request) {
+    let key = request.headers().iter().find(|header| header.field.equiv("Sec-WebSocket-Key")).map(|header| header.value.to_string());
+    let Some(key) = key else {
+        let response = Response::from_string("expected a websocket upgrade").with_status_code(400);
+        let _ = request.respond(response);
+        return;
+    };
+    let accept = tungstenite::handshake::derive_accept_key(key.as_bytes());
+    let response = Response::empty(101)
+        .with_header(Header::from_bytes("Upgrade", "websocket").expect("header is invalid"))
+        .with_header(Header::from_bytes("Connection", "Upgrade").expect("header is invalid"))
+        .with_header(Header::from_bytes("Sec-WebSocket-Accept", accept).expect("header is invalid"));
+    let mut socket = WebSocket::from_raw_socket(request.upgrade("websocket", response), Role::Server, None);
+    if let Err(error) = converse(&mut socket, &transcriber) {
+        tracing::warn!("stream failed: {}", error);
+    }
+}
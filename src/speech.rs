@@ -0,0 +1,106 @@
+use serde::Serialize;
+
+use transcript::error::Error;
+use transcript::{audio, vad};
+
+use crate::transcribe;
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    #[arg(short, long)]
+    input: String,
+
+    /// Audio quieter than this many dBFS is silence, as for --vad-threshold
+    #[arg(long, default_value_t = vad::THRESHOLD_DB, value_name = "DB", allow_negative_numbers = true)]
+    threshold: f32,
+
+    /// Silences shorter than this count as part of the speech around them, as for --vad-min-silence
+    #[arg(long, default_value_t = vad::MIN_SILENCE_SECS, value_name = "SECS")]
+    min_silence: f32,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+
+    #[command(flatten)]
+    audio_options: audio::Options,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Report {
+    duration_seconds: f64,
+    speech_seconds: f64,
+    silence_seconds: f64,
+    regions: usize,
+    longest_region_seconds: f64,
+    // between two regions, the silence before the first and after the last isn't a pause
+    longest_pause_seconds: f64,
+    longest_pause_at_seconds: Option<f64>,
+}
+
+fn seconds(samples: usize) -> f64 {
+    samples as f64 / audio::SAMPLE_RATE as f64
+}
+
+impl Report {
+    fn new(speech: &vad::Speech, length: usize) -> Report {
+        let ranges = speech.ranges();
+        let spoken: usize = ranges.iter().map(|range| range.len()).sum();
+        let longest_pause = ranges.windows(2).map(|pair| (pair[0].end, pair[1].start - pair[0].end)).max_by_key(|(_, length)| *length);
+        Report {
+            duration_seconds: seconds(length),
+            speech_seconds: seconds(spoken),
+            silence_seconds: seconds(length - spoken),
+            regions: ranges.len(),
+            longest_region_seconds: seconds(ranges.iter().map(|range| range.len()).max().unwrap_or(0)),
+            longest_pause_seconds: seconds(longest_pause.map_or(0, |(_, length)| length)),
+            longest_pause_at_seconds: longest_pause.map(|(start, _)| seconds(start)),
+        }
+    }
+
+    fn render(&self) -> String {
+        let share = if self.duration_seconds > 0.0 { self.speech_seconds / self.duration_seconds * 100.0 } else { 0.0 };
+        let mut lines = vec![
+            format!("duration        {:.1}s", self.duration_seconds),
+            format!("speech          {:.1}s ({:.0}%)", self.speech_seconds, share),
+            format!("silence         {:.1}s", self.silence_seconds),
+            format!("regions         {}", self.regions),
+            format!("longest region  {:.1}s", self.longest_region_seconds),
+        ];
+        if let Some(at) = self.longest_pause_at_seconds {
+            lines.push(format!("longest pause   {:.1}s at {:.1}s", self.longest_pause_seconds, at));
+        }
+        lines.join("\n")
+    }
+}
+
+pub fn run(args: &Args) -> Result<(), Error> {
+    let samples = transcribe::load_audio(&args.input, &args.audio_options)?;
+    // measured as it is, without the padding --vad keeps around speech
+    let speech = vad::Speech::detect(&samples, args.threshold, args.min_silence * 1000.0, 0.0);
+    let report = Report::new(&speech, samples.len());
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report).expect("failed to serialize report"));
+    } else {
+        println!("{}", report.render());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _new() {
+        // speech for a second, a 3s pause, half a second more and a second of trailing silence
+        let tone = |ms: usize| (0..ms * 16).map(|i| if i % 2 == 0 { 0.1 } else { -0.1 });
+        let silence = |ms: usize| std::iter::repeat_n(0.0, ms * 16);
+        let samples: Vec<f32> = tone(990).chain(silence(3000)).chain(tone(510)).chain(silence(1020)).collect();
+        let report = Report::new(&vad::Speech::detect(&samples, -45.0, 2000.0, 0.0), samples.len());
+        assert_eq!(report.regions, 2);
+        assert_eq!((report.speech_seconds, report.longest_region_seconds), (1.5, 0.99));
+        assert_eq!((report.longest_pause_seconds, report.longest_pause_at_seconds), (3.0, Some(0.99)));
+        assert!(report.render().contains("longest pause   3.0s at 1.0s"));
+    }
+}
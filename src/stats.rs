@@ -0,0 +1,91 @@
+use std::fs;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use transcript::error::Error;
+
+// what every finished file adds up to, shared by the batch workers
+#[derive(Default)]
+pub struct Tally {
+    audio_ms: AtomicU64,
+    segments: AtomicUsize,
+}
+
+impl Tally {
+    pub fn add(&self, audio_ms: u64, segments: usize) {
+        self.audio_ms.fetch_add(audio_ms, Ordering::Relaxed);
+        self.segments.fetch_add(segments, Ordering::Relaxed);
+    }
+}
+
+#[derive(Serialize)]
+pub struct Stats {
+    pub audio_seconds: f64,
+    pub wall_seconds: f64,
+    // processing time over audio time, below 1 is faster than realtime
+    pub realtime_factor: f64,
+    pub model_load_seconds: f64,
+    pub segments: usize,
+    pub peak_memory_mb: Option<f64>,
+}
+
+// the high water mark of resident memory, linux only
+fn peak_memory_mb() -> Option<f64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kb: f64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb / 1024.0)
+}
+
+impl Stats {
+    pub fn new(tally: &Tally, started: Instant, model_load: Duration) -> Stats {
+        let audio_seconds = tally.audio_ms.load(Ordering::Relaxed) as f64 / 1000.0;
+        let wall_seconds = started.elapsed().as_secs_f64();
+        Stats {
+            audio_seconds,
+            wall_seconds,
+            realtime_factor: if audio_seconds > 0.0 { wall_seconds / audio_seconds } else { 0.0 },
+            model_load_seconds: model_load.as_secs_f64(),
+            segments: tally.segments.load(Ordering::Relaxed),
+            peak_memory_mb: peak_memory_mb(),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut lines = vec![
+            format!("audio duration   {:.1}s", self.audio_seconds),
+            format!("wall clock       {:.1}s", self.wall_seconds),
+            format!("realtime factor  {:.3}", self.realtime_factor),
+            format!("model load       {:.2}s", self.model_load_seconds),
+            format!("segments         {}", self.segments),
+        ];
+        if let Some(peak_memory_mb) = self.peak_memory_mb {
+            lines.push(format!("peak memory      {:.1} MB", peak_memory_mb));
+        }
+        lines.join("\n")
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), Error> {
+        fs::write(path, serde_json::to_string_pretty(self).expect("failed to serialize stats")).map_err(|error| Error::output(format!("could not write {}", path), error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _new() {
+        let tally = Tally::default();
+        tally.add(60_000, 10);
+        tally.add(30_000, 5);
+        let stats = Stats::new(&tally, Instant::now(), Duration::from_millis(250));
+        assert_eq!(stats.audio_seconds, 90.0);
+        assert_eq!(stats.segments, 15);
+        assert_eq!(stats.model_load_seconds, 0.25);
+        assert!(stats.realtime_factor < 1.0);
+        assert!(stats.render().contains("segments         15"));
+    }
+}
@@ -0,0 +1,123 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use crate::error::Error;
+
+// object storage uris, each scheme needing its own cargo feature
+pub fn is_remote(path: &str) -> bool {
+    path.starts_with("s3://") || path.starts_with("gs://")
+}
+
+// scheme, bucket and key of an s3:// or gs:// uri
+fn split(uri: &str) -> Result<(&str, &str, &str), String> {
+    let (scheme, rest) = uri.split_once("://").ok_or_else(|| format!("{} needs a scheme", uri))?;
+    let (bucket, key) = rest.split_once('/').ok_or_else(|| format!("{} needs an object key", uri))?;
+    Ok((scheme, bucket, key))
+}
+
+#[cfg(any(feature = "s3", feature = "gcs"))]
+fn runtime() -> Result<tokio::runtime::Runtime, String> {
+    tokio::runtime::Runtime::new().map_err(|error| format!("failed to start the storage runtime: {}", error))
+}
+
+#[cfg(feature = "s3")]
+mod s3 {
+    use aws_sdk_s3::primitives::ByteStream;
+
+    async fn client() -> aws_sdk_s3::Client {
+        aws_sdk_s3::Client::new(&aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await)
+    }
+
+    pub fn get(bucket: &str, key: &str) -> Result<Vec<u8>, String> {
+        super::runtime()?.block_on(async {
+            let object = client().await.get_object().bucket(bucket).key(key).send().await.map_err(|error| error.to_string())?;
+            Ok(object.body.collect().await.map_err(|error| error.to_string())?.to_vec())
+        })
+    }
+
+    pub fn put(bucket: &str, key: &str, contents: Vec<u8>) -> Result<(), String> {
+        super::runtime()?.block_on(async {
+            client().await.put_object().bucket(bucket).key(key).body(ByteStream::from(contents)).send().await.map_err(|error| error.to_string())?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(feature = "gcs")]
+mod gcs {
+    use google_cloud_storage::client::Storage;
+
+    async fn client() -> Result<Storage, String> {
+        Storage::builder().build().await.map_err(|error| error.to_string())
+    }
+
+    pub fn get(bucket: &str, key: &str) -> Result<Vec<u8>, String> {
+        super::runtime()?.block_on(async {
+            let mut response = client().await?.read_object(format!("projects/_/buckets/{}", bucket), key).send().await.map_err(|error| error.to_string())?;
+            let mut contents = Vec::new();
+            while let Some(chunk) = response.next().await.transpose().map_err(|error| error.to_string())? {
+                contents.extend_from_slice(&chunk);
+            }
+            Ok(contents)
+        })
+    }
+
+    pub fn put(bucket: &str, key: &str, contents: Vec<u8>) -> Result<(), String> {
+        super::runtime()?.block_on(async {
+            client().await?.write_object(format!("projects/_/buckets/{}", bucket), key, bytes::Bytes::from(contents)).send_buffered().await.map_err(|error| error.to_string())?;
+            Ok(())
+        })
+    }
+}
+
+fn unsupported(scheme: &str) -> String {
+    format!("{}:// paths need transcript built with the {} feature", scheme, if scheme == "gs" { "gcs" } else { "s3" })
+}
+
+fn get(uri: &str) -> Result<Vec<u8>, String> {
+    match split(uri)? {
+        #[cfg(feature = "s3")]
+        ("s3", bucket, key) => s3::get(bucket, key),
+        #[cfg(feature = "gcs")]
+        ("gs", bucket, key) => gcs::get(bucket, key),
+        (scheme, _, _) => Err(unsupported(scheme)),
+    }
+}
+
+#[cfg_attr(not(any(feature = "s3", feature = "gcs")), allow(unused_variables))]
+pub fn put(uri: &str, contents: impl Into<Vec<u8>>) -> Result<(), Error> {
+    let put = match split(uri).map_err(Error::Output)? {
+        #[cfg(feature = "s3")]
+        ("s3", bucket, key) => s3::put(bucket, key, contents.into()),
+        #[cfg(feature = "gcs")]
+        ("gs", bucket, key) => gcs::put(bucket, key, contents.into()),
+        (scheme, _, _) => Err(unsupported(scheme)),
+    };
+    put.map_err(|error| Error::output(format!("failed to put {}", uri), error))
+}
+
+// like http downloads, the object lands in a temp file named after its key so the decoders can seek
+pub fn download(uri: &str) -> Result<PathBuf, Error> {
+    let (_, _, key) = split(uri).map_err(Error::Input)?;
+    let name = key.rsplit('/').next().unwrap_or(key);
+    let path = env::temp_dir().join(format!("transcript-{}-{}", process::id(), name));
+    let contents = get(uri).map_err(|error| Error::input(format!("failed to get {}", uri), error))?;
+    fs::write(&path, contents).map_err(|error| Error::input(format!("failed to write {}", path.display()), error))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _split() {
+        assert!(is_remote("s3://media/episodes/1.wav"));
+        assert!(is_remote("gs://media/episodes/1.wav"));
+        assert!(!is_remote("media/episodes/1.wav"));
+        assert_eq!(split("s3://media/episodes/1.wav"), Ok(("s3", "media", "episodes/1.wav")));
+        assert!(split("s3://media").is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::transcript::Transcript;
+
+// segments_fts indexes the segments' text, kept in step with them by the triggers
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS files (
+    id INTEGER PRIMARY KEY,
+    input TEXT NOT NULL UNIQUE,
+    language TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    model TEXT,
+    parameters TEXT NOT NULL,
+    version TEXT NOT NULL,
+    transcribed_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS segments (
+    id INTEGER PRIMARY KEY,
+    file_id INTEGER NOT NULL REFERENCES files(id),
+    start_ms INTEGER NOT NULL,
+    end_ms INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    speaker TEXT,
+    confidence REAL
+);
+CREATE INDEX IF NOT EXISTS segments_file ON segments(file_id, start_ms);
+CREATE VIRTUAL TABLE IF NOT EXISTS segments_fts USING fts5(text, content='segments', content_rowid='id');
+CREATE TRIGGER IF NOT EXISTS segments_insert AFTER INSERT ON segments BEGIN
+    INSERT INTO segments_fts(rowid, text) VALUES (new.id, new.text);
+END;
+CREATE TRIGGER IF NOT EXISTS segments_delete AFTER DELETE ON segments BEGIN
+    INSERT INTO segments_fts(segments_fts, rowid, text) VALUES ('delete', old.id, old.text);
+END;
+";
+
+// what's recorded about a transcribed file besides its segments
+pub struct Run<'a> {
+    pub input: &'a str,
+    pub language: &'a str,
+    pub duration_ms: u64,
+    pub model: Option<&'a str>,
+    // the command line, as a json array
+    pub parameters: &'a str,
+}
+
+// a segment that matched a search, with the text of the segments either side of it in the same file
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Hit {
+    pub input: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+// a sqlite database of transcripts, one row per file in files and one per segment in segments,
+// searchable through segments_fts
+pub struct Store {
+    connection: Connection,
+    path: String,
+}
+
+impl Store {
+    // created, tables and all, when it doesn't exist yet
+    pub fn open(path: &str) -> Result<Store, Error> {
+        let failed = |error: rusqlite::Error| Error::output(format!("failed to open the store {}", path), error);
+        let connection = Connection::open(path).map_err(failed)?;
+        // batches running several files at once each write on a connection of their own
+        connection.busy_timeout(std::time::Duration::from_secs(30)).map_err(failed)?;
+        connection.execute_batch(SCHEMA).map_err(failed)?;
+        Ok(Store { connection, path: path.to_string() })
+    }
+
+    // a file that's stored already is replaced, so the store keeps the latest transcript of it
+    pub fn add(&mut self, run: &Run, transcript: &Transcript) -> Result<(), Error> {
+        let failed = |error: rusqlite::Error| Error::output(format!("failed to store {} in {}", run.input, self.path), error);
+        let transcribed_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs() as i64);
+        let transaction = self.connection.transaction().map_err(failed)?;
+        transaction.execute("DELETE FROM segments WHERE file_id IN (SELECT id FROM files WHERE input = ?1)", params![run.input]).map_err(failed)?;
+        transaction.execute("DELETE FROM files WHERE input = ?1", params![run.input]).map_err(failed)?;
+        transaction
+            .execute(
+                "INSERT INTO files (input, language, duration_ms, model, parameters, version, transcribed_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![run.input, run.language, run.duration_ms as i64, run.model, run.parameters, env!("CARGO_PKG_VERSION"), transcribed_at],
+            )
+            .map_err(failed)?;
+        let file_id = transaction.last_insert_rowid();
+        {
+            let mut insert = transaction.prepare("INSERT INTO segments (file_id, start_ms, end_ms, text, speaker, confidence) VALUES (?1, ?2, ?3, ?4, ?5, ?6)").map_err(failed)?;
+            for segment in &transcript.segments {
+                insert.execute(params![file_id, segment.start_ms, segment.end_ms, segment.text.trim(), segment.speaker, segment.confidence()]).map_err(failed)?;
+            }
+        }
+        transaction.commit().map_err(failed)
+    }
+
+    // the best matches first, by fts5's own ranking; the query is fts5's syntax, so budget meeting
+    // matches both words anywhere in a segment and "budget meeting" the phrase. The words that
+    // matched are put between the marks
+    pub fn search(&self, query: &str, context: usize, limit: usize, marks: (&str, &str)) -> Result<Vec<Hit>, Error> {
+        let failed = |error: rusqlite::Error| Error::input(format!("failed to search {} for {}", self.path, query), error);
+        let mut matches = self
+            .connection
+            .prepare(
+                "SELECT segments.file_id, files.input, segments.start_ms, segments.end_ms, highlight(segments_fts, 0, ?2, ?3)
+                 FROM segments_fts JOIN segments ON segments.id = segments_fts.rowid JOIN files ON files.id = segments.file_id
+                 WHERE segments_fts MATCH ?1 ORDER BY rank LIMIT ?4",
+            )
+            .map_err(failed)?;
+        let rows = matches
+            .query_map(params![query, marks.0, marks.1, limit as i64], |row| Ok((row.get::<_, i64>(0)?, Hit { input: row.get(1)?, start_ms: row.get(2)?, end_ms: row.get(3)?, text: row.get(4)?, before: Vec::new(), after: Vec::new() })))
+            .map_err(failed)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(failed)?;
+        let mut before = self.connection.prepare("SELECT text FROM segments WHERE file_id = ?1 AND start_ms < ?2 ORDER BY start_ms DESC LIMIT ?3").map_err(failed)?;
+        let mut after = self.connection.prepare("SELECT text FROM segments WHERE file_id = ?1 AND start_ms > ?2 ORDER BY start_ms LIMIT ?3").map_err(failed)?;
+        let mut hits = Vec::with_capacity(rows.len());
+        for (file_id, mut hit) in rows {
+            let texts = |statement: &mut rusqlite::Statement| statement.query_map(params![file_id, hit.start_ms, context as i64], |row| row.get::<_, String>(0))?.collect::<Result<Vec<String>, _>>();
+            hit.before = texts(&mut before).map_err(failed)?;
+            hit.before.reverse();
+            hit.after = texts(&mut after).map_err(failed)?;
+            hits.push(hit);
+        }
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _add() {
+        let mut store = Store { connection: Connection::open_in_memory().unwrap(), path: ":memory:".to_string() };
+        store.connection.execute_batch(SCHEMA).unwrap();
+        let run = Run { input: "talk.wav", language: "en", duration_ms: 5000, model: Some("tiny"), parameters: "[]" };
+        let transcript = Transcript { segments: vec![Segment::new(0, 1000, " The budget meeting."), Segment::new(1000, 2000, " Lunch after.")] };
+        store.add(&run, &transcript).unwrap();
+        store.add(&run, &transcript).unwrap();
+
+        let count = |sql: &str| store.connection.query_row(sql, [], |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM files"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM segments"), 2);
+        assert_eq!(count("SELECT rowid FROM segments_fts WHERE segments_fts MATCH 'budget'"), count("SELECT id FROM segments WHERE start_ms = 0"));
+        assert_eq!(count("SELECT COUNT(*) FROM segments_fts WHERE segments_fts MATCH 'lunch'"), 1);
+    }
+
+    #[test]
+    fn _search() {
+        let mut store = Store { connection: Connection::open_in_memory().unwrap(), path: ":memory:".to_string() };
+        store.connection.execute_batch(SCHEMA).unwrap();
+        let run = Run { input: "talk.wav", language: "en", duration_ms: 5000, model: None, parameters: "[]" };
+        let texts = [" Good morning.", " The budget is late.", " So is the meeting.", " Lunch, then the budget meeting.", " Bye."];
+        let transcript = Transcript { segments: texts.iter().enumerate().map(|(i, text)| Segment::new(i as i64 * 1000, i as i64 * 1000 + 900, text)).collect() };
+        store.add(&run, &transcript).unwrap();
+
+        let hits = store.search("\"budget meeting\"", 1, 10, ("[", "]")).unwrap();
+        assert_eq!(
+            hits,
+            vec![Hit { input: "talk.wav".to_string(), start_ms: 3000, end_ms: 3900, text: "Lunch, then the [budget meeting].".to_string(), before: vec!["So is the meeting.".to_string()], after: vec!["Bye.".to_string()] }]
+        );
+        assert_eq!(store.search("budget", 0, 10, ("", "")).unwrap().len(), 2);
+        assert!(store.search("budget AND", 0, 10, ("", "")).is_err());
+    }
+}
@@ -0,0 +1,1061 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, ValueEnum};
+use indicatif::MultiProgress;
+use whisper_rs::WhisperContext;
+
+use transcript::cancel::CancellationToken;
+use transcript::error::Error;
+use transcript::output::template::Template;
+use transcript::output::{self, layout, Format};
+use transcript::fillers::Fillers;
+use transcript::redact::Redactions;
+use transcript::refine::{self, Refiner};
+use transcript::replace::Rules;
+use transcript::store::{self, Store};
+use transcript::vad::Speech;
+use transcript::{audio, decode, device, models, postprocess, storage, transcriber, Segment, Transcript};
+
+use crate::cache::{Cache, Entry};
+use crate::checkpoint::Checkpoint;
+use crate::meta::{self, Meta};
+#[cfg(unix)]
+use crate::daemon;
+use crate::live::{self, Live, Printer, Update};
+use crate::{batch, comparison, config, embed, exec, interrupt, plan, progress, remote, stats, webhook};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Model file or name (tiny, base.en, large-v3, ...) looked up in $TRANSCRIPT_MODEL_DIR, the
+    /// cache and ./models; without one, the only model found there is used
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Transcribe the input with each of these models in turn and print them side by side, how
+    /// long each took and how far its words are from the last one's, instead of writing outputs
+    #[arg(long, value_name = "MODELS", value_delimiter = ',', num_args = 1.., conflicts_with_all = ["model", "via_daemon", "backend", "prefer_cloud", "live", "window", "input_dir", "manifest"])]
+    models: Vec<String>,
+
+    /// What to make of each input: transcribe,translate writes both the transcript, to
+    /// <output>.orig, and its English translation, to <output>.en, with the model loaded once
+    #[arg(long, value_enum, value_delimiter = ',', num_args = 1.., conflicts_with_all = ["translate", "live", "models"])]
+    tasks: Vec<Task>,
+
+    /// Audio to transcribe, several files (or a shell glob) are transcribed one after another;
+    /// - reads it from stdin, and a named pipe or <(...) works as a file does
+    #[arg(short, long, required_unless_present_any = ["input_dir", "manifest", "stdin_jobs", "list_devices"], num_args = 1..)]
+    input: Vec<String>,
+
+    /// Transcribe every audio file in a directory, mirroring its layout under the output directory
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["input", "manifest"])]
+    pub input_dir: Option<String>,
+
+    /// Descend into subdirectories of --input-dir
+    #[arg(long, requires = "input_dir")]
+    pub recursive: bool,
+
+    /// Extensions picked up by --input-dir
+    #[arg(long, value_delimiter = ',', default_value = "wav,mp3,flac,ogg,m4a,aac")]
+    pub ext: Vec<String>,
+
+    /// CSV or JSON list of inputs with optional per-file language and output columns
+    #[arg(long, value_name = "FILE", conflicts_with = "input")]
+    manifest: Option<String>,
+
+    /// Output path without an extension, or a directory when there are several inputs; without
+    /// one the transcript is printed to stdout
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Directory for the outputs, each named after its input
+    #[arg(long, value_name = "DIR", conflicts_with = "output")]
+    pub output_dir: Option<String>,
+
+    /// Replace output files that already exist instead of refusing to run
+    #[arg(long, conflicts_with = "skip_existing")]
+    overwrite: bool,
+
+    /// Leave out inputs whose output files all exist, so an interrupted batch can be rerun
+    #[arg(long)]
+    skip_existing: bool,
+
+    /// With --skip-existing, still redo inputs modified after their outputs
+    #[arg(long, requires = "skip_existing")]
+    check_mtime: bool,
+
+    /// Print each input with the outputs it would write, and what's in the way (existing outputs,
+    /// inputs that can't be read), without loading the model; exits 1 if anything is
+    #[arg(long, conflicts_with_all = ["live", "models"])]
+    dry_run: bool,
+
+    /// Stay loaded and take jobs from stdin, one json object a line ({"input": ..., "output":
+    /// ..., "language": ...}, all but input optional), answering each with a json line on stdout
+    /// as --webhook is sent; runs until stdin closes
+    #[arg(long, conflicts_with_all = ["input", "input_dir", "manifest", "dry_run", "skip_existing", "live", "models", "stdout"])]
+    stdin_jobs: bool,
+
+    /// Files transcribed at once, each on its own whisper state sharing the loaded model
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    jobs: u32,
+
+    /// Transcribe every channel on its own and interleave them as separate speakers
+    #[arg(long, conflicts_with = "channel")]
+    split_channels: bool,
+
+    /// TOML file mapping speaker ids or channel numbers to names, e.g. SPEAKER_0 = "Alice"
+    #[arg(long, value_name = "FILE")]
+    speakers: Option<String>,
+
+    // --speakers as prepare read it
+    #[arg(skip)]
+    speaker_names: HashMap<String, String>,
+
+    /// Pick an interrupted run up from the checkpoint --window saves beside the output after
+    /// every window
+    #[arg(long, requires = "window")]
+    resume: bool,
+
+    /// Time the transcript from the start of the file rather than from --offset
+    #[arg(long, requires = "offset")]
+    absolute_timestamps: bool,
+
+    /// Decode a second of silence once the model's loaded, before the first input, so the
+    /// backend's setup isn't counted against it
+    #[arg(long, conflicts_with = "via_daemon")]
+    preload: bool,
+
+    /// Hand the decoding to a running `transcript daemon` instead of loading the model
+    #[arg(long, conflicts_with_all = ["model", "resume"])]
+    via_daemon: bool,
+
+    /// Socket of the daemon, defaults to $XDG_RUNTIME_DIR/transcript.sock
+    #[arg(long, value_name = "PATH", requires = "via_daemon")]
+    socket: Option<String>,
+
+    /// Where the decoding happens: a model loaded here, a whisper.cpp server at --server-url with
+    /// whatever model it was started with, or openai's transcription api when there's no model
+    /// here, with the key in OPENAI_API_KEY
+    #[arg(long, value_enum, default_value = "local", conflicts_with_all = ["via_daemon", "window"])]
+    backend: Backend,
+
+    /// whisper.cpp server to send the audio to with --backend server, as http://gpu-box:8080
+    #[arg(long, value_name = "URL", required_if_eq("backend", "server"))]
+    server_url: Option<String>,
+
+    /// Use the transcription api even when there's a model here, i.e. --backend openai without
+    /// the fallback
+    #[arg(long, conflicts_with_all = ["model", "via_daemon", "window"])]
+    prefer_cloud: bool,
+
+    /// Model the transcription api decodes with
+    #[arg(long, value_name = "MODEL", default_value = "whisper-1")]
+    openai_model: String,
+
+    /// Where the transcription api is, for one that works the same way as openai's
+    #[arg(long, value_name = "URL", default_value = "https://api.openai.com/v1")]
+    openai_url: String,
+
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "txt,srt")]
+    formats: Vec<Format>,
+
+    /// Also break the transcript into chapters at its long pauses and write them as a YouTube
+    /// chapter list and an ffmpeg metadata file, i.e. --formats with chapters,ffmetadata added
+    #[arg(long)]
+    chapters: bool,
+
+    /// Stream each segment to stdout as it is decoded
+    #[arg(long, value_enum)]
+    stdout: Option<StdoutFormat>,
+
+    /// Format the transcript is printed in when there's no output path to write files to
+    #[arg(long, value_enum, default_value = "txt", conflicts_with = "stdout")]
+    stdout_format: Format,
+
+    /// Decode --raw stdin as it comes in instead of once it ends, printing segments as they settle;
+    /// for a pipe that doesn't end such as ffmpeg -f pulse -i default -f s16le -
+    #[arg(long, requires = "raw", conflicts_with_all = ["window", "split_channels", "offset", "duration", "skip_existing", "highpass", "denoise", "trim_silence", "normalize", "cache_dir"])]
+    live: bool,
+
+    /// Keep every transcript here under a hash of its audio, model and decode options, and use a
+    /// kept one instead of decoding when they're the same; for reruns that only change the
+    /// formats or the post-processing
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<String>,
+
+    /// Write <output>.meta.json beside the outputs too, recording the input's and model's sha256,
+    /// the audio's length, the language, the command line, the version and how long it took
+    #[arg(long)]
+    meta: bool,
+
+    /// Write the transcript into the input's own tags too, as synced lyrics (id3 USLT and SYLT)
+    /// for mp3 and the lyrics atom for m4a, so players show it without a sidecar file
+    #[arg(long)]
+    embed: bool,
+
+    /// Also keep every transcript in this SQLite database, with its segments, language, model and
+    /// command line, full-text indexed
+    #[arg(long, value_name = "FILE")]
+    store: Option<String>,
+
+    /// Report the cues that break the subtitle layout limits, e.g. --max-cps, once they've been
+    /// laid out
+    #[arg(long)]
+    lint: bool,
+
+    /// Show which backends --device can pick in this build and exit
+    #[arg(long)]
+    list_devices: bool,
+
+    /// Defaults for the model, language, formats, output dir and threads, read from
+    /// ~/.config/transcript/config.toml when not given
+    #[arg(long, value_name = "FILE")]
+    config: Option<String>,
+
+    /// POST a JSON summary (input, outputs, duration, language, status) to this URL as each
+    /// file finishes or fails
+    #[arg(long, value_name = "URL")]
+    webhook: Option<String>,
+
+    /// Shell command run after each file is transcribed, e.g. 'upload {srt} {input}'; {input},
+    /// {output}, {lang} and any format's name are swapped for their (quoted) values
+    #[arg(long, value_name = "COMMAND")]
+    exec: Option<String>,
+
+    /// Print audio duration, wall clock time, realtime factor and memory use at the end
+    #[arg(long)]
+    stats: bool,
+
+    /// Write the end of run stats to a JSON file
+    #[arg(long, value_name = "FILE")]
+    stats_json: Option<String>,
+
+    #[command(flatten)]
+    audio_options: audio::Options,
+
+    #[command(flatten)]
+    pub decode_options: decode::Options,
+
+    #[command(flatten)]
+    refine_options: refine::Options,
+
+    #[command(flatten)]
+    postprocess_options: postprocess::Options,
+
+    #[command(flatten)]
+    output_options: output::Options,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Local,
+    Server,
+    Openai,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Task {
+    Transcribe,
+    Translate,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StdoutFormat {
+    Jsonl,
+}
+
+pub fn load_audio(audio_file_path_raw: &str, options: &audio::Options) -> Result<Vec<f32>, Error> {
+    let mut audio = audio::load(audio_file_path_raw, options, Some(options.channel))?;
+    debug_assert_eq!(audio.sample_rate, audio::SAMPLE_RATE);
+    Ok(audio.channels.remove(0))
+}
+
+fn load_speakers(path: &str) -> Result<HashMap<String, String>, Error> {
+    let contents = fs::read_to_string(path).map_err(|error| Error::input(format!("failed to read speakers file {}", path), error))?;
+    toml::from_str(&contents).map_err(|error| Error::input(format!("speakers file {} should map ids to names", path), error))
+}
+
+// stdout belongs to the transcript when it's streamed or printed instead of written, and
+// progress is only for a person watching a terminal
+fn chatty(args: &Args) -> bool {
+    args.stdout.is_none() && (args.output.is_some() || args.output_dir.is_some() || args.manifest.is_some()) && io::stdout().is_terminal()
+}
+
+// where the decoding happens: a model loaded here, or a daemon, whisper.cpp server or hosted api
+// that has one loaded already
+pub enum Engine {
+    Local(WhisperContext),
+    #[cfg(unix)]
+    Daemon(daemon::Client),
+    Server(remote::Client),
+    Cloud(remote::OpenAi),
+}
+
+#[cfg(unix)]
+fn daemon_engine(args: &Args) -> Result<Engine, Error> {
+    Ok(Engine::Daemon(daemon::Client::new(args.socket.as_deref())))
+}
+
+#[cfg(not(unix))]
+fn daemon_engine(_args: &Args) -> Result<Engine, Error> {
+    Err(Error::Input("--via-daemon needs unix sockets".to_string()))
+}
+
+// the post-processing passes, with --redact-index's record of them beside the outputs
+fn postprocess(args: &Args, job: &batch::Job, transcript: &mut Transcript) -> Result<(), Error> {
+    let redactions = args.postprocess_options.apply(transcript)?;
+    let Some(output_path_raw) = job.output.as_deref().filter(|_| args.postprocess_options.redact_index) else { return Ok(()) };
+    let path = format!("{}.redactions.json", output_path_raw);
+    let contents = serde_json::to_string_pretty(&redactions).expect("failed to serialize redactions");
+    if storage::is_remote(&path) {
+        storage::put(&path, contents)
+    } else {
+        output::write_to_file(Path::new(&path), &contents)
+    }
+}
+
+// --lint: the problems left in the cues as the subtitle formats lay them out
+fn lint(args: &Args, input: &str, transcript: &Transcript) {
+    if !args.lint {
+        return;
+    }
+    let options = &args.output_options.layout;
+    let laid_out = layout::apply(transcript, options);
+    for problem in layout::lint(laid_out.as_ref().unwrap_or(transcript), options) {
+        tracing::warn!("{}: {}", input, problem);
+    }
+}
+
+// what besides the audio decides the transcript --cache-dir keeps: the model file, every decode
+// option even where it only changes how fast the decode goes, and what's in the files the options
+// name, since those can change under the same path
+// what does the decoding, as engine picks it once prepare has settled it: the api when there's
+// no model here, and a daemon's or a server's model is whichever it was started with, so where
+// it is stands in for the model
+fn decoder(args: &Args) -> String {
+    match &args.model {
+        _ if args.via_daemon => format!("daemon {}", args.socket.as_deref().unwrap_or("default")),
+        _ if args.backend == Backend::Server => format!("server {}", args.server_url.as_deref().unwrap_or_default()),
+        Some(model) => {
+            let path = models::resolve(model);
+            format!("local {} {}", path.display(), fs::metadata(&path).map_or(0, |metadata| metadata.len()))
+        }
+        _ => format!("openai {} {}", args.openai_url, args.openai_model),
+    }
+}
+
+fn cache_settings(args: &Args, decode_options: &decode::Options) -> String {
+    let model = decoder(args);
+    let refine = &args.refine_options;
+    let refine = refine.refine_with.as_ref().map(|model| format!("{} {} {}", model, refine.refine_below, refine.refine_padding));
+    let files: Vec<String> = [&decode_options.prompt_file, &decode_options.glossary, &decode_options.grammar].into_iter().flatten().map(|path| fs::read_to_string(path).unwrap_or_default()).collect();
+    format!("{} {} {:?} {} {} {:?}", env!("CARGO_PKG_VERSION"), model, refine, args.split_channels, serde_json::to_string(decode_options).expect("failed to serialize decode options"), files)
+}
+
+fn command_line() -> Vec<String> {
+    env::args().skip(1).collect()
+}
+
+// --meta: beside the outputs, what went into them
+fn meta(args: &Args, job: &batch::Job, started: Instant, decode_options: &decode::Options, duration_ms: u64) -> Result<(), Error> {
+    let Some(output_path_raw) = job.output.as_deref().filter(|_| args.meta) else { return Ok(()) };
+    let meta = Meta {
+        input: job.input.clone(),
+        input_sha256: meta::sha256(&job.input),
+        duration_ms,
+        model: args.model.clone(),
+        model_sha256: args.model.as_deref().and_then(meta::model_sha256),
+        // unset is whisper's own default
+        language: decode_options.language.clone().unwrap_or_else(|| "en".to_string()),
+        parameters: command_line(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        wall_seconds: started.elapsed().as_secs_f64(),
+    };
+    meta::write(output_path_raw, &meta)
+}
+
+// --store: a file that's stored already is replaced by its new transcript
+fn store(args: &Args, input: &str, transcript: &Transcript, language: &str, duration_ms: u64) -> Result<(), Error> {
+    let Some(path) = &args.store else { return Ok(()) };
+    let parameters = serde_json::to_string(&command_line()).expect("failed to serialize the command line");
+    // by its full path, so a file transcribed from elsewhere later is the same file
+    let input = fs::canonicalize(input).map_or_else(|_| input.to_string(), |path| path.to_string_lossy().into_owned());
+    let run = store::Run { input: &input, language, duration_ms, model: args.model.as_deref(), parameters: &parameters };
+    Store::open(path)?.add(&run, transcript)
+}
+
+fn print_jsonl(segment: &Segment) {
+    println!("{}", serde_json::to_string(segment).expect("failed to serialize segment"));
+}
+
+// what a file came to, for the run's stats and --webhook
+pub struct Finished {
+    pub audio_ms: u64,
+    pub segments: usize,
+    pub language: String,
+    pub outputs: Vec<String>,
+}
+
+pub fn transcribe_file(engine: &Engine, refiner: Option<&Refiner>, args: &Args, job: &batch::Job, bars: &MultiProgress) -> Result<Finished, Error> {
+    let started = Instant::now();
+    let audio_file_path_raw = job.input.as_str();
+    let mut output_options = args.output_options.clone();
+    output_options.input = Some(audio_file_path_raw.to_string());
+    if output_options.ctm_conversation_id.is_none() {
+        output_options.ctm_conversation_id = Path::new(audio_file_path_raw).file_stem().map(|stem| stem.to_string_lossy().into_owned());
+    }
+
+    let audio = audio::load(audio_file_path_raw, &args.audio_options, (!args.split_channels).then_some(args.audio_options.channel))?;
+    debug_assert_eq!(audio.sample_rate, audio::SAMPLE_RATE);
+    let channels = audio.channels;
+
+    let chatty = chatty(args);
+
+    let mut decode_options = args.decode_options.clone();
+    if job.language.is_some() {
+        decode_options.language = job.language.clone();
+    }
+    if let Some(translate) = job.translate {
+        decode_options.translate = translate;
+    }
+    // skipped ranges are times in the file, the decode starts at --offset and past any trimmed
+    // silence
+    decode_options.skip_ranges = decode_options.skip_ranges_from(args.audio_options.offset.unwrap_or(0) + audio.start_ms);
+    // a transcript cached by a run with the same audio and settings is used as it is, language
+    // and all
+    let cache = args.cache_dir.as_deref().map(|dir| Cache::new(dir, &channels, &cache_settings(args, &decode_options)));
+    let cached = cache.as_ref().and_then(Cache::load);
+    if let (Some(entry), Some(cache)) = (&cached, &cache) {
+        tracing::info!("using the transcript of {} cached in {}", audio_file_path_raw, cache.path().display());
+        decode_options.language = entry.language.clone();
+    }
+    if decode_options.language.as_deref() == Some("auto") {
+        let (language, probability) = match engine {
+            Engine::Local(ctx) => decode::detect_language(ctx, &channels[0], decode_options.threads()).map(|(language, probability)| (language.to_string(), probability))?,
+            #[cfg(unix)]
+            Engine::Daemon(client) => client.detect_language(&channels[0])?,
+            Engine::Server(client) => client.detect_language(&channels[0])?,
+            Engine::Cloud(client) => client.detect_language(&channels[0])?,
+        };
+        if chatty {
+            tracing::info!("detected language {} ({:.2})", language, probability);
+        }
+        decode_options.language = Some(language);
+    }
+
+    output_options.language = Some(decode_options.output_language().to_string());
+
+    let duration_ms = channels[0].len() as u64 * 1000 / audio::SAMPLE_RATE as u64;
+    let progress = progress::Progress::new(bars, audio_file_path_raw, duration_ms);
+    let stdout = args.stdout;
+    let window = decode_options.window;
+    let offset_ms = if args.absolute_timestamps { args.audio_options.offset.unwrap_or(0) as i64 } else { 0 };
+    let shift_ms = offset_ms + audio.start_ms as i64 + args.output_options.shift;
+    // jsonl for --stdout, timestamped lines for a person watching
+    let emit = move |segment: &Segment, bars: &MultiProgress| {
+        let segment = Segment::new(segment.start_ms + shift_ms, segment.end_ms + shift_ms, &segment.text);
+        if stdout == Some(StdoutFormat::Jsonl) {
+            print_jsonl(&segment);
+        } else if chatty {
+            bars.suspend(|| println!("{}", output::txt::line(&segment)));
+        }
+    };
+    // with --vad whisper only hears the speech, so what it streams is timed through that
+    let configure = |params: &mut whisper_rs::FullParams, speech: Option<&Speech>| {
+        progress.apply(params);
+        // segments go out as whisper finishes them; a temperature retry streams its segments
+        // again from the start, windows only print theirs once they're stitched
+        if window.is_none() && (stdout.is_some() || chatty) {
+            let bars = bars.clone();
+            let speech = speech.cloned();
+            params.set_segment_callback_safe_lossy(move |data: whisper_rs::SegmentCallbackData| {
+                let restore = |ms: i64| speech.as_ref().map_or(ms, |speech| speech.restore_ms(ms));
+                emit(&Segment::new(restore(data.start_timestamp * 10), restore(data.end_timestamp * 10), &data.text), &bars);
+            });
+        }
+    };
+    // anything that changes the audio or where the windows fall makes an old checkpoint useless
+    let settings = format!(
+        "{} {:?} {:?} {:?} {} {:?} {:?} {} {} {} {:?} {:?} {} {:?}",
+        job.input, args.model, args.audio_options.offset, args.audio_options.duration, channels[0].len(), args.audio_options.channel, window, decode_options.window_overlap, args.split_channels, audio.start_ms, args.audio_options.normalize, args.audio_options.highpass, args.audio_options.denoise, decode_options.skip_ranges
+    );
+    let checkpoint_path = job.output.as_deref().filter(|output| window.is_some() && !storage::is_remote(output)).map(Checkpoint::path);
+    let mut checkpoint = Checkpoint::open(checkpoint_path, settings, channels.len(), args.resume)?;
+    let mut transcript = match cached {
+        Some(entry) => {
+            for segment in &entry.transcript.segments {
+                emit(segment, bars);
+            }
+            entry.transcript
+        }
+        None => {
+            let mut transcripts: Vec<Transcript> = channels
+                .iter()
+                .enumerate()
+                .map(|(channel, samples)| match (engine, window) {
+                    // the daemon sends its segments once, windowed or not
+                    #[cfg(unix)]
+                    (Engine::Daemon(client), _) => client.transcribe(&decode_options, samples, |segment| emit(segment, bars), |percent| progress.percent(percent)),
+                    // the server answers once it's done, so that's when its segments are shown
+                    (Engine::Server(client), _) => client.transcribe(&decode_options, samples).inspect(|transcript| transcript.segments.iter().for_each(|segment| emit(segment, bars))),
+                    (Engine::Cloud(client), _) => client.transcribe(&decode_options, samples).inspect(|transcript| transcript.segments.iter().for_each(|segment| emit(segment, bars))),
+                    // a segment is printed once the seam after it has been passed, and the checkpoint
+                    // is saved after every window
+                    (Engine::Local(ctx), Some(window)) => {
+                        let part = checkpoint.channels[channel].clone();
+                        let started = |range: Range<i64>| progress.window(range.start as u64, (range.end - range.start) as u64);
+                        let finished = |part: &decode::Part, finals: &[Segment]| {
+                            for segment in finals {
+                                emit(segment, bars);
+                            }
+                            checkpoint.channels[channel] = part.clone();
+                            checkpoint.save()
+                        };
+                        decode_options.transcribe_windows(ctx, window, samples, part, |params| configure(params, None), started, finished)
+                    }
+                    (Engine::Local(ctx), None) => match decode_options.chunks(samples) {
+                        // the chunks finish in whatever order, so their segments are shown once all are
+                        chunks if chunks.len() > 1 => decode_options
+                            .transcribe_parallel(ctx, samples, &chunks, |done_ms| progress.percent((done_ms as u64 * 100 / duration_ms.max(1)) as u8))
+                            .inspect(|transcript| transcript.segments.iter().for_each(|segment| emit(segment, bars))),
+                        _ => {
+                            let speech = decode_options.speech(samples);
+                            decode_options.transcribe(ctx, samples, |params| configure(params, speech.as_ref()))
+                        }
+                    },
+                })
+                .collect::<Result<_, _>>()?;
+            if let Some(refiner) = refiner {
+                for (transcript, samples) in transcripts.iter_mut().zip(&channels) {
+                    refiner.refine(&decode_options, samples, transcript)?;
+                }
+            }
+            let transcript = if args.split_channels { Transcript::interleave(transcripts) } else { transcripts.remove(0) };
+            // a decode cut short isn't what the settings give
+            if let Some(cache) = cache.as_ref().filter(|_| !decode_options.cancel.is_cancelled()) {
+                cache.save(&Entry { language: decode_options.language.clone(), transcript: transcript.clone() })?;
+            }
+            transcript
+        }
+    };
+    progress.finish();
+    transcript.shift(shift_ms);
+    transcript.rename_speakers(&args.speaker_names);
+    postprocess(args, job, &mut transcript)?;
+    lint(args, audio_file_path_raw, &transcript);
+
+    match &job.output {
+        Some(output_path_raw) => output::write_outputs(&transcript, output_path_raw, &args.formats, &output_options, decode_options.output_language())?,
+        // timestamped lines on a terminal are coloured by how sure whisper was of them
+        None if args.stdout.is_none() && args.stdout_format == Format::Txt && live::colors() => print!("{}", output::txt::colored(&transcript)),
+        None if args.stdout.is_none() => print!("{}", args.stdout_format.render(&transcript, &output_options)),
+        None => {}
+    }
+    if decode_options.cancel.is_cancelled() {
+        let reached_ms = transcript.segments.last().map_or(0, |segment| segment.end_ms - shift_ms);
+        let written = job.output.as_deref().map_or(String::new(), |output| format!(" to {}", output));
+        return Err(Error::Interrupted(format!("interrupted {}s into {}s of audio, wrote what was decoded{}", reached_ms / 1000, duration_ms / 1000, written)));
+    }
+    checkpoint.remove();
+    let language = decode_options.output_language().to_string();
+    if args.embed && !storage::is_remote(audio_file_path_raw) && !audio::stream::is_stream(audio_file_path_raw) {
+        embed::embed(audio_file_path_raw, &transcript, &language)?;
+    }
+    store(args, audio_file_path_raw, &transcript, &language, duration_ms)?;
+    meta(args, job, started, &decode_options, duration_ms)?;
+    Ok(Finished { audio_ms: duration_ms, segments: transcript.segments.len(), outputs: job.outputs(&args.formats, &output_options, &language), language })
+}
+
+// --live: stdin decoded a second of new audio at a time, settled segments printed as they go and
+// the whole transcript written once the stream ends
+fn transcribe_live(engine: &Engine, args: &Args, job: &batch::Job) -> Result<Finished, Error> {
+    let started = Instant::now();
+    let options = &args.audio_options;
+    let (format, rate, channels, channel) = (options.raw_format, options.rate, options.channels, options.channel);
+    // the pipe is read on a thread of its own, so what comes in during a decode isn't held up
+    let (sender, received) = mpsc::channel();
+    thread::spawn(move || {
+        if let Err(error) = audio::raw::follow(io::stdin().lock(), format, rate, channels, channel, |samples| sender.send(Ok(samples)).is_ok()) {
+            let _ = sender.send(Err(error));
+        }
+    });
+
+    // ctrl-c ends the stream, not the decode of what came before it
+    let stop = args.decode_options.cancel.clone();
+    let decode_options = decode::Options { cancel: CancellationToken::default(), ..args.decode_options.clone() };
+    let transcribe = |samples: &[f32]| match engine {
+        Engine::Local(ctx) => decode_options.transcribe(ctx, samples, |_| {}),
+        #[cfg(unix)]
+        Engine::Daemon(client) => client.transcribe(&decode_options, samples, |_| {}, |_| {}),
+        Engine::Server(client) => client.transcribe(&decode_options, samples),
+        Engine::Cloud(client) => client.transcribe(&decode_options, samples),
+    };
+    let printer = Printer { interim: args.stdout.is_none() && io::stdout().is_terminal(), jsonl: args.stdout == Some(StdoutFormat::Jsonl), color: args.stdout.is_none() && live::colors() };
+    let mut transcript = Transcript { segments: Vec::new() };
+    let mut show = |updates: Vec<Update>| {
+        for update in updates {
+            printer.show(&update);
+            if let Update::Final { segments } = update {
+                transcript.segments.extend(segments);
+            }
+        }
+    };
+    let mut live = Live::default();
+    let mut audio_samples = 0;
+    // whatever piled up during the last decode is taken in one go
+    let mut take = |live: &mut Live, first: Option<Result<Vec<f32>, Error>>| -> Result<(), Error> {
+        for samples in first.into_iter().chain(received.try_iter()) {
+            let samples = samples?;
+            audio_samples += samples.len();
+            live.extend(&samples);
+        }
+        Ok(())
+    };
+    while !stop.is_cancelled() {
+        let first = match received.recv_timeout(Duration::from_millis(250)) {
+            Ok(first) => first,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        take(&mut live, Some(first))?;
+        if live.due(false) {
+            show(live.decode(transcribe, false)?);
+        }
+    }
+    take(&mut live, None)?;
+    if live.due(true) {
+        show(live.decode(transcribe, true)?);
+    }
+
+    transcript.shift(args.output_options.shift);
+    transcript.rename_speakers(&args.speaker_names);
+    postprocess(args, job, &mut transcript)?;
+    lint(args, &job.input, &transcript);
+    if let Some(output_path_raw) = &job.output {
+        let output_options = output::Options { language: Some(decode_options.output_language().to_string()), ..args.output_options.clone() };
+        output::write_outputs(&transcript, output_path_raw, &args.formats, &output_options, decode_options.output_language())?;
+    }
+    let audio_ms = audio_samples as u64 * 1000 / audio::SAMPLE_RATE as u64;
+    if stop.is_cancelled() {
+        let written = job.output.as_deref().map_or(String::new(), |output| format!(", wrote it to {}", output));
+        return Err(Error::Interrupted(format!("interrupted {}s into the stream{}", audio_ms / 1000, written)));
+    }
+    let language = decode_options.output_language().to_string();
+    store(args, &job.input, &transcript, &language, audio_ms)?;
+    meta(args, job, started, &decode_options, audio_ms)?;
+    Ok(Finished { audio_ms, segments: transcript.segments.len(), outputs: job.outputs(&args.formats, &args.output_options, &language), language })
+}
+
+// the --exec hook, then --webhook told how the file went, including a hook that failed
+pub fn finish(args: &Args, job: &batch::Job, finished: Result<Finished, Error>) -> Result<Finished, Error> {
+    let finished = match (&args.exec, finished) {
+        (Some(template), Ok(finished)) => exec::run(template, job, &finished, &args.formats).map(|()| finished),
+        (_, finished) => finished,
+    };
+    if let Some(url) = &args.webhook {
+        webhook::post(url, &webhook::Payload::new(job, &finished));
+    }
+    finished
+}
+
+// the config only fills in what the command line and environment left out
+fn apply_config(args: &mut Args, matches: &ArgMatches, config: config::Config) {
+    args.model = args.model.take().or(config.model);
+    args.decode_options.language = args.decode_options.language.take().or(config.language);
+    args.decode_options.threads = args.decode_options.threads.or(config.threads);
+    if let Some(formats) = config.formats.filter(|_| matches.value_source("formats") == Some(ValueSource::DefaultValue)) {
+        args.formats = formats;
+    }
+    if args.output.is_none() && args.output_dir.is_none() {
+        args.output_dir = config.output_dir;
+    }
+}
+
+// what every run does before it looks at its inputs; returns the model to load, none with
+// --via-daemon
+pub fn prepare(args: &mut Args, matches: &ArgMatches) -> Result<Option<String>, Error> {
+    interrupt::install(args.decode_options.cancel.clone());
+    let config = config::Config::load(args.config.as_deref())?;
+    apply_config(args, matches, config);
+    if args.decode_options.window.is_some_and(|window| args.decode_options.window_overlap >= window) {
+        return Err(Error::Input("--window-overlap has to be shorter than --window".to_string()));
+    }
+    if args.prefer_cloud {
+        args.backend = Backend::Openai;
+    }
+    // a server or the api decodes however it likes, with whatever model it has on the day
+    if !args.decode_options.skip_ranges.is_empty() && matches!(args.backend, Backend::Server | Backend::Openai) {
+        return Err(Error::Input("--skip-ranges only holds for a decode here or in the daemon, not with --backend server or openai".to_string()));
+    }
+    let model_path_raw = match args.model.clone() {
+        _ if args.via_daemon || args.backend == Backend::Server || args.prefer_cloud || !args.models.is_empty() => None,
+        Some(model) => Some(model),
+        // the api is the fallback for a machine without a model
+        None if args.backend == Backend::Openai => match models::discover() {
+            Ok(model) => Some(model),
+            Err(error) => {
+                tracing::info!("{}, using the transcription api at {}", error, args.openai_url);
+                None
+            }
+        },
+        None => Some(models::discover()?),
+    };
+    // what --store records as the model, found or given
+    if args.model.is_none() {
+        args.model = model_path_raw.clone();
+    }
+    if let Some(path) = &args.store {
+        Store::open(path)?;
+    }
+
+    // what can't go to stdout, and why
+    let needs_output_location = [
+        (args.exec.is_some(), "--exec needs outputs to hand on"),
+        (args.postprocess_options.redact_index, "--redact-index is written beside the outputs"),
+        (!args.output_options.templates.is_empty(), "--template is written beside the outputs"),
+        (!args.output_options.format_commands.is_empty(), "--format-cmd is written beside the outputs"),
+        (args.output_options.split_by_speaker, "--split-by-speaker writes a file per speaker beside the outputs"),
+        (args.meta, "--meta is written beside the outputs"),
+        (both_tasks(args), "--tasks transcribe,translate writes two sets of outputs"),
+    ];
+    let has_output_location = args.output.is_some() || args.output_dir.is_some() || args.manifest.is_some();
+    if let Some((_, needs)) = needs_output_location.iter().find(|(given, _)| *given && !has_output_location) {
+        return Err(Error::Input(format!("{}, give --output or --output-dir", needs)));
+    }
+    if let Some(template) = &args.exec {
+        exec::check(template, &args.formats)?;
+    }
+    // a bad rule or list would otherwise only turn up once the first file has been decoded
+    if let Some(path) = &args.postprocess_options.replace {
+        Rules::load(path)?;
+    }
+    if let Some(path) = &args.postprocess_options.redact {
+        Redactions::load(path)?;
+    }
+    if let Some(path) = &args.postprocess_options.fillers {
+        Fillers::load(path)?;
+    }
+    if let Some(path) = &args.speakers {
+        args.speaker_names = load_speakers(path)?;
+    }
+    for template in &args.output_options.templates {
+        Template::load(template)?;
+    }
+    // a resumed run writes over what it finds as --overwrite does
+    args.output_options.keep_existing = !args.overwrite && !args.resume;
+    if args.meta {
+        args.output_options.beside.push("meta.json".to_string());
+    }
+    if args.postprocess_options.redact_index {
+        args.output_options.beside.push("redactions.json".to_string());
+    }
+    // a single task is just whether to translate
+    if args.tasks == [Task::Translate] {
+        args.decode_options.translate = true;
+    }
+    if args.live && args.input != ["-"] {
+        return Err(Error::Input("--live reads stdin, give it -i - --raw".to_string()));
+    }
+
+    if args.chapters {
+        for format in [Format::Chapters, Format::Ffmetadata] {
+            if !args.formats.contains(&format) {
+                args.formats.push(format);
+            }
+        }
+    }
+    // a dumped token's times are only there with token timestamps, which word times turn on
+    if args.output_options.dump_tokens {
+        args.decode_options.word_timestamps = true;
+    }
+    // karaoke highlighting is driven by word times
+    if args.formats.contains(&Format::Karaoke) {
+        args.decode_options.word_timestamps = true;
+    }
+    Ok(model_path_raw)
+}
+
+fn both_tasks(args: &Args) -> bool {
+    args.tasks.contains(&Task::Transcribe) && args.tasks.contains(&Task::Translate)
+}
+
+// --tasks transcribe,translate makes two jobs of every input
+pub fn tasks(args: &Args, jobs: Vec<batch::Job>) -> Vec<batch::Job> {
+    if both_tasks(args) {
+        batch::with_tasks(jobs)
+    } else {
+        jobs
+    }
+}
+
+// --refine-with's model, for watch to load once as run does
+pub fn refiner(args: &Args) -> Result<Option<Refiner>, Error> {
+    Refiner::load(&args.refine_options, &args.decode_options)
+}
+
+pub fn engine(args: &Args, model_path_raw: Option<&str>) -> Result<Engine, Error> {
+    match model_path_raw {
+        Some(model_path_raw) => {
+            tracing::info!("loading model {}", model_path_raw);
+            let loading = Instant::now();
+            let ctx = transcriber::load_model(model_path_raw, args.decode_options.context_params()?)?;
+            if args.preload {
+                decode::warm_up(&ctx, args.decode_options.threads())?;
+            }
+            tracing::debug!("model ready in {:.2}s", loading.elapsed().as_secs_f64());
+            Ok(Engine::Local(ctx))
+        }
+        None => match &args.server_url {
+            Some(url) if args.backend == Backend::Server => Ok(Engine::Server(remote::Client::new(url))),
+            _ if args.backend == Backend::Openai => Ok(Engine::Cloud(remote::OpenAi::new(&args.openai_url, &args.openai_model)?)),
+            _ => daemon_engine(args),
+        },
+    }
+}
+
+pub fn run(mut args: Args, matches: &ArgMatches, started: Instant) -> Result<ExitCode, Error> {
+    if args.list_devices {
+        println!("{}", device::report());
+        return Ok(ExitCode::SUCCESS);
+    }
+    let model_path_raw = prepare(&mut args, matches)?;
+    if !args.models.is_empty() {
+        let [input] = args.input.as_slice() else { return Err(Error::Input("--models compares the models on one input".to_string())) };
+        return comparison::run(&args.models, input, &args.decode_options, &args.audio_options).map(|()| ExitCode::SUCCESS);
+    }
+    if args.stdin_jobs {
+        return stdin_jobs(args, model_path_raw.as_deref());
+    }
+
+    // present unless a manifest names the outputs
+    let output_path_raw = args.output_dir.as_deref().or(args.output.as_deref());
+    let mut jobs = if let Some(manifest) = &args.manifest {
+        batch::from_manifest(manifest, output_path_raw)?
+    } else if let Some(dir) = &args.input_dir {
+        let output_dir = output_path_raw.ok_or_else(|| Error::Input("--input-dir needs --output-dir".to_string()))?;
+        batch::from_dir(dir, args.recursive, &args.ext, output_dir)?
+    } else {
+        batch::from_inputs(&args.input, output_path_raw, args.output_dir.is_some())
+    };
+    jobs = tasks(&args, jobs);
+
+    let language = |job: &batch::Job| {
+        let mut decode_options = args.decode_options.clone();
+        decode_options.language = job.language.clone().or(decode_options.language);
+        decode_options.translate = job.translate.unwrap_or(decode_options.translate);
+        decode_options.output_language().to_string()
+    };
+    if args.dry_run {
+        let entries: Vec<plan::Entry> = jobs
+            .iter()
+            .map(|job| {
+                let language = language(job);
+                let skipped = args.skip_existing && job.is_done(&args.formats, &args.output_options, &language, args.check_mtime);
+                let mut outputs = job.outputs(&args.formats, &args.output_options, &language);
+                // the speakers aren't known until the audio's been heard, so their files are shown by pattern
+                if let Some(output) = job.output.as_deref().filter(|_| args.output_options.split_by_speaker) {
+                    let options = output::Options { beside: Vec::new(), ..args.output_options.clone() };
+                    outputs.extend(output::paths(&format!("{}.<speaker>", output), &args.formats, &options, &language));
+                }
+                plan::Entry::new(job, outputs, skipped)
+            })
+            .collect();
+        // a resumed run writes over what it finds as --overwrite does
+        let (rendered, conflicts) = plan::render(&entries, args.overwrite || args.resume);
+        println!("{}", rendered);
+        return Ok(if conflicts == 0 { ExitCode::SUCCESS } else { ExitCode::FAILURE });
+    }
+    let mut skipped = 0;
+    if args.skip_existing {
+        jobs.retain(|job| {
+            let done = job.is_done(&args.formats, &args.output_options, &language(job), args.check_mtime);
+            if done {
+                tracing::info!("skipping {}, its outputs exist", job.input);
+                skipped += 1;
+            }
+            !done
+        });
+        if jobs.is_empty() {
+            return Ok(ExitCode::SUCCESS);
+        }
+    } else if !args.overwrite && !args.resume {
+        // checked up front so a batch doesn't stop halfway through
+        let existing: Vec<String> = jobs.iter().flat_map(|job| job.existing(&args.formats, &args.output_options, &language(job))).collect();
+        if !existing.is_empty() {
+            for path in &existing {
+                tracing::error!("{} already exists", path);
+            }
+            return Err(Error::Output("pass --overwrite to replace existing outputs, or --skip-existing to leave them".to_string()));
+        }
+    }
+
+    let engine = engine(&args, model_path_raw.as_deref())?;
+    let refiner = refiner(&args)?;
+    let model_load = started.elapsed();
+
+    // parallel jobs split the default thread count between them rather than each taking all of it
+    let workers = (args.jobs as usize).min(jobs.len());
+    if workers > 1 && args.decode_options.threads.is_none() {
+        args.decode_options.threads = Some((decode::default_threads() / workers).max(1));
+    }
+
+    let bars = MultiProgress::new();
+    let tally = stats::Tally::default();
+    let run = |job: &batch::Job| -> Result<(), Error> {
+        if let Some(parent) = job.output.as_deref().filter(|output| !storage::is_remote(output)).and_then(|output| Path::new(output).parent()) {
+            fs::create_dir_all(parent).map_err(|error| Error::output(format!("failed to create output directory {}", parent.display()), error))?;
+        }
+        let transcribed = if args.live { transcribe_live(&engine, &args, job) } else { transcribe_file(&engine, refiner.as_ref(), &args, job, &bars) };
+        let finished = finish(&args, job, transcribed)?;
+        tally.add(finished.audio_ms, finished.segments);
+        Ok(())
+    };
+    // a lone file fails with its own exit code, a batch carries on and exits 1
+    let failed = if jobs.len() == 1 && skipped == 0 {
+        run(&jobs[0])?;
+        Vec::new()
+    } else {
+        run_batch(&jobs, workers, skipped, &args.decode_options.cancel, run)
+    };
+
+    if args.stats || args.stats_json.is_some() {
+        let stats = stats::Stats::new(&tally, started, model_load);
+        if args.stats {
+            eprintln!("{}", stats.render());
+        }
+        if let Some(path) = &args.stats_json {
+            stats.write(path)?;
+        }
+    }
+    if args.decode_options.cancel.is_cancelled() {
+        return Err(Error::Interrupted("interrupted before the batch finished".to_string()));
+    }
+    Ok(if failed.is_empty() { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
+
+// --stdin-jobs: the model loaded once, then a job per line for as long as stdin stays open; a
+// job that fails, or a line that isn't one, gets its answer and the next is read
+fn stdin_jobs(args: Args, model_path_raw: Option<&str>) -> Result<ExitCode, Error> {
+    let engine = engine(&args, model_path_raw)?;
+    let refiner = refiner(&args)?;
+    let bars = MultiProgress::new();
+    let output_dir = args.output_dir.as_deref().or(args.output.as_deref());
+    tracing::info!("reading jobs from stdin");
+    for line in io::stdin().lines() {
+        let line = line.map_err(|error| Error::input("failed to read jobs from stdin", error))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let job = match batch::from_line(&line, output_dir) {
+            Ok(job) => job,
+            Err(error) => {
+                println!("{}", serde_json::to_string(&webhook::Payload::rejected(error)).expect("failed to serialize job result"));
+                continue;
+            }
+        };
+        for job in tasks(&args, vec![job]) {
+            let run = || -> Result<Finished, Error> {
+                let mut decode_options = args.decode_options.clone();
+                decode_options.language = job.language.clone().or(decode_options.language);
+                decode_options.translate = job.translate.unwrap_or(decode_options.translate);
+                let existing = job.existing(&args.formats, &args.output_options, decode_options.output_language());
+                if !args.overwrite && !args.resume && !existing.is_empty() {
+                    return Err(Error::Output(format!("{} would be written over, pass --overwrite to replace existing outputs", existing.join(", "))));
+                }
+                if let Some(parent) = job.output.as_deref().filter(|output| !storage::is_remote(output)).and_then(|output| Path::new(output).parent()) {
+                    fs::create_dir_all(parent).map_err(|error| Error::output(format!("failed to create output directory {}", parent.display()), error))?;
+                }
+                tracing::info!("transcribing {}", job.input);
+                finish(&args, &job, transcribe_file(&engine, refiner.as_ref(), &args, &job, &bars))
+            };
+            // a panic in one job shouldn't take the process the caller is keeping around with it
+            let finished = panic::catch_unwind(AssertUnwindSafe(run)).unwrap_or_else(|_| Err(Error::Inference(format!("transcribing {} panicked", job.input))));
+            if let Err(Error::Interrupted(message)) = finished {
+                return Err(Error::Interrupted(message));
+            }
+            println!("{}", serde_json::to_string(&webhook::Payload::new(&job, &finished)).expect("failed to serialize job result"));
+        }
+        if args.decode_options.cancel.is_cancelled() {
+            return Err(Error::Interrupted("interrupted while reading jobs".to_string()));
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+// one bad file shouldn't stop a batch, its error (or panic) is reported and the rest carry on;
+// returns the inputs that failed
+fn run_batch<'a>(jobs: &'a [batch::Job], workers: usize, skipped: usize, cancel: &CancellationToken, run: impl Fn(&batch::Job) -> Result<(), Error> + Sync) -> Vec<&'a str> {
+    let next = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let failures = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(job) = jobs.get(index).filter(|_| !cancel.is_cancelled()) else { break };
+                    tracing::info!("transcribing {}", job.input);
+                    let succeeded = match panic::catch_unwind(AssertUnwindSafe(|| run(job))) {
+                        Ok(Ok(())) => true,
+                        Ok(Err(error)) => {
+                            tracing::error!("{}", error);
+                            false
+                        }
+                        Err(_) => false,
+                    };
+                    if !succeeded {
+                        failures.lock().expect("failure list poisoned").push(index);
+                    }
+                    finished.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+    let mut failures = failures.into_inner().expect("failure list poisoned");
+    failures.sort();
+    let failed: Vec<&str> = failures.iter().map(|index| jobs[*index].input.as_str()).collect();
+    let finished = finished.into_inner();
+    tracing::info!("{} succeeded, {} failed, {} skipped", finished - failed.len(), failed.len(), skipped);
+    // a ctrl-c stops the batch from taking on more inputs
+    if finished < jobs.len() {
+        tracing::warn!("{} not started", jobs.len() - finished);
+    }
+    for input in &failed {
+        tracing::error!("failed: {}", input);
+    }
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{Args as _, Command, FromArgMatches};
+
+    use super::*;
+
+    #[test]
+    fn _cache_settings() {
+        let parse = |args: &[&str]| Args::from_arg_matches(&Args::augment_args(Command::new("transcribe")).get_matches_from([&["transcribe", "-i", "talk.wav"], args].concat())).unwrap();
+        let local = parse(&["--model", "models/ggml-base.bin"]);
+        // as prepare leaves them: --prefer-cloud is the api, and no model here falls back to it
+        let cloud = Args { backend: Backend::Openai, ..parse(&["--prefer-cloud"]) };
+        let fallback = parse(&["--backend", "openai"]);
+        let options = decode::Options::default();
+        assert!(decoder(&local).starts_with("local models/ggml-base.bin"));
+        assert_eq!(decoder(&cloud), "openai https://api.openai.com/v1 whisper-1");
+        assert_eq!(decoder(&fallback), decoder(&cloud));
+        assert_ne!(cache_settings(&local, &options), cache_settings(&cloud, &options));
+        assert_eq!(decoder(&parse(&["--via-daemon", "--socket", "/run/t.sock"])), "daemon /run/t.sock");
+    }
+}
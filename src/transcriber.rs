@@ -0,0 +1,364 @@
+use std::cell::Cell;
+use std::ffi::{c_int, c_void, CStr};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, OnceLock};
+
+use whisper_rs::{FullParams, WhisperContext, WhisperContextParameters};
+use whisper_rs_sys::{whisper_context, whisper_state};
+
+use crate::audio;
+use crate::cancel::CancellationToken;
+use crate::decode;
+use crate::device::Device;
+use crate::error::Error;
+use crate::models;
+use crate::transcript::{Segment, Transcript};
+use crate::vad::Speech;
+
+// a model name is looked up among the downloaded ones
+pub fn load_model(model_path_raw: &str, context_params: WhisperContextParameters) -> Result<WhisperContext, Error> {
+    let info = models::inspect(model_path_raw)?;
+    tracing::debug!("{} is {} {}", model_path_raw, info.kind(), info.quantization());
+    let model_path = models::resolve(model_path_raw);
+    WhisperContext::new_with_params(&model_path.to_string_lossy(), context_params).map_err(|error| Error::model(format!("failed to load model {}", model_path_raw), error))
+}
+
+/// What a transcription is up to, for a frontend showing its progress
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The model is loaded, which only happens before the first transcription
+    ModelLoaded,
+    /// A part of the audio is about to be decoded: a window when the options set one, otherwise
+    /// all of it
+    ChunkStarted { start_ms: i64, end_ms: i64 },
+    /// A segment as whisper finishes it, or once it's stitched when decoding in windows
+    SegmentDecoded(Segment),
+    /// How much of the audio has been decoded, in percent
+    Progress(u8),
+    /// The transcription is done, with how many segments it came to
+    Finished { segments: usize },
+}
+
+/// Transcribes with one model, loaded the first time it's needed and kept for every call after.
+/// Clones share the model and the cancellation token.
+///
+/// ```no_run
+/// let transcriber = transcript::Transcriber::new("small.en").threads(4).word_timestamps(true);
+/// let samples = vec![0.0; 16000];
+/// for segment in transcriber.transcribe(&samples)?.segments {
+///     println!("{} {}", segment.start_ms, segment.text);
+/// }
+/// # Ok::<(), transcript::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct Transcriber {
+    model: String,
+    options: decode::Options,
+    audio_options: audio::Options,
+    ctx: Arc<OnceLock<WhisperContext>>,
+    events: Option<Sender<Event>>,
+}
+
+impl Transcriber {
+    /// A model file, or a name looked up among the downloaded models as on the command line
+    pub fn new(model: impl Into<String>) -> Transcriber {
+        Transcriber { model: model.into(), options: decode::Options::default(), audio_options: audio::Options::default(), ctx: Arc::new(OnceLock::new()), events: None }
+    }
+
+    /// A whisper language code, or auto to detect it from the audio
+    pub fn language(mut self, language: impl Into<String>) -> Transcriber {
+        self.options.language = Some(language.into());
+        self
+    }
+
+    pub fn translate(mut self, translate: bool) -> Transcriber {
+        self.options.translate = translate;
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> Transcriber {
+        self.options.threads = Some(threads);
+        self
+    }
+
+    pub fn initial_prompt(mut self, prompt: impl Into<String>) -> Transcriber {
+        self.options.initial_prompt = Some(prompt.into());
+        self
+    }
+
+    pub fn word_timestamps(mut self, word_timestamps: bool) -> Transcriber {
+        self.options.word_timestamps = word_timestamps;
+        self
+    }
+
+    /// Leaves out long silences before decoding, found by loudness; the times still count from
+    /// the start of the audio
+    pub fn vad(mut self, vad: bool) -> Transcriber {
+        self.options.vad = vad;
+        self
+    }
+
+    /// Takes effect when the model is loaded, before the first transcription
+    pub fn device(mut self, device: Device) -> Transcriber {
+        self.options.device = device;
+        self
+    }
+
+    /// Every decode option the command line has; the cancellation token is kept
+    pub fn options(mut self, options: decode::Options) -> Transcriber {
+        self.options = decode::Options { cancel: self.options.cancel, ..options };
+        self
+    }
+
+    /// How transcribe_file decodes its input: channel, offset, duration, raw pcm
+    pub fn audio_options(mut self, options: audio::Options) -> Transcriber {
+        self.audio_options = options;
+        self
+    }
+
+    /// Sends what every transcription is up to, see Event
+    pub fn events(mut self, events: Sender<Event>) -> Transcriber {
+        self.events = Some(events);
+        self
+    }
+
+    pub fn decode_options(&self) -> &decode::Options {
+        &self.options
+    }
+
+    /// Stops with this token instead of the one the transcriber came with
+    pub fn cancel_with(mut self, token: CancellationToken) -> Transcriber {
+        self.options.cancel = token;
+        self
+    }
+
+    /// Cancelling it stops a transcription in progress, which returns the segments it finished
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.options.cancel.clone()
+    }
+
+    /// Loads the model now rather than with the first transcription
+    pub fn load(&self) -> Result<(), Error> {
+        self.context().map(|_| ())
+    }
+
+    /// Loads the model and decodes a second of silence with it, so the first transcription
+    /// doesn't pay for the backend's setup either
+    pub fn warm_up(&self) -> Result<(), Error> {
+        decode::warm_up(self.context()?, self.options.threads())
+    }
+
+    /// The spoken language of 16kHz mono samples and its probability, needs a multilingual model
+    pub fn detect_language(&self, samples: &[f32]) -> Result<(&'static str, f32), Error> {
+        decode::detect_language(self.context()?, samples, self.options.threads())
+    }
+
+    fn context(&self) -> Result<&WhisperContext, Error> {
+        if let Some(ctx) = self.ctx.get() {
+            return Ok(ctx);
+        }
+        let ctx = load_model(&self.model, self.options.context_params()?)?;
+        if let Some(events) = &self.events {
+            let _ = events.send(Event::ModelLoaded);
+        }
+        Ok(self.ctx.get_or_init(|| ctx))
+    }
+
+    /// 16kHz mono samples, see audio::SAMPLE_RATE
+    pub fn transcribe(&self, samples: &[f32]) -> Result<Transcript, Error> {
+        self.transcribe_with(samples, &|_| {})
+    }
+
+    /// Runs on tokio's blocking pool and yields each segment as it would go out as
+    /// Event::SegmentDecoded. A low confidence decode retried at a higher temperature yields its
+    /// segments again from the start, as --stdout jsonl prints them.
+    #[cfg(feature = "tokio")]
+    pub fn transcribe_async(&self, samples: Vec<f32>) -> impl futures_core::Stream<Item = Result<Segment, Error>> + Send + 'static {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let transcriber = self.clone();
+        tokio::task::spawn_blocking(move || {
+            // a dropped stream only means nobody is listening any more
+            let decoded = transcriber.transcribe_with(&samples, &|segment| {
+                let _ = sender.send(Ok(segment.clone()));
+            });
+            if let Err(error) = decoded {
+                let _ = sender.send(Err(error));
+            }
+        });
+        Segments { receiver }
+    }
+
+    // segment sees every segment that goes out as an event, whether or not anyone listens to them
+    fn transcribe_with(&self, samples: &[f32], segment: &dyn Fn(&Segment)) -> Result<Transcript, Error> {
+        let mut options = self.options.clone();
+        if let Some(language) = &options.language {
+            decode::parse_language(language).map_err(Error::Input)?;
+        }
+        let ctx = self.context()?;
+        if options.language.as_deref() == Some("auto") {
+            let (language, probability) = decode::detect_language(ctx, samples, options.threads())?;
+            tracing::debug!("detected language {} ({:.2})", language, probability);
+            options.language = Some(language.to_string());
+        }
+
+        let duration_ms = (samples.len() * 1000 / audio::SAMPLE_RATE as usize) as i64;
+        let speech = options.speech(samples).filter(|_| options.window.is_none());
+        let reporter = Reporter { events: self.events.as_ref(), on_segment: segment, duration_ms, chunk: Cell::new((0, duration_ms)), streaming: options.window.is_none(), speech };
+        let configure = |params: &mut FullParams| reporter.apply(params);
+        let transcript = match options.window {
+            Some(window) => {
+                let started = |range: std::ops::Range<i64>| reporter.start(range.start, range.end);
+                let finished = |_: &decode::Part, finals: &[Segment]| {
+                    for segment in finals {
+                        reporter.segment(segment.clone());
+                    }
+                    Ok(())
+                };
+                options.transcribe_windows(ctx, window, samples, decode::Part::default(), configure, started, finished)?
+            }
+            None => {
+                reporter.start(0, duration_ms);
+                options.transcribe(ctx, samples, configure)?
+            }
+        };
+        reporter.send(Event::Finished { segments: transcript.segments.len() });
+        Ok(transcript)
+    }
+
+    /// Any audio the command line reads, decoded and resampled for transcribe; samples trimmed
+    /// with trim_silence start past the silence they lost
+    pub fn load_audio(&self, path: &str) -> Result<Vec<f32>, Error> {
+        let mut audio = audio::load(path, &self.audio_options, Some(self.audio_options.channel))?;
+        Ok(audio.channels.remove(0))
+    }
+
+    /// Timed from the start of the file, whatever the audio options trimmed off it
+    pub fn transcribe_file(&self, path: &str) -> Result<Transcript, Error> {
+        let mut audio = audio::load(path, &self.audio_options, Some(self.audio_options.channel))?;
+        let mut transcript = self.transcribe(&audio.channels.remove(0))?;
+        transcript.shift(audio.start_ms as i64);
+        Ok(transcript)
+    }
+}
+
+// what whisper's callbacks report to; it lives on the stack for the whole decode, unlike the
+// params the callbacks are set on, and leaves nothing behind once the decode is done
+struct Reporter<'a> {
+    events: Option<&'a Sender<Event>>,
+    on_segment: &'a dyn Fn(&Segment),
+    duration_ms: i64,
+    // the part of the audio being decoded, in ms
+    chunk: Cell<(i64, i64)>,
+    // windows only report their segments once they're stitched
+    streaming: bool,
+    // with --vad, how the speech whisper hears maps back onto the audio
+    speech: Option<Speech>,
+}
+
+impl Reporter<'_> {
+    fn send(&self, event: Event) {
+        if let Some(events) = self.events {
+            let _ = events.send(event);
+        }
+    }
+
+    fn start(&self, start_ms: i64, end_ms: i64) {
+        self.chunk.set((start_ms, end_ms));
+        self.send(Event::ChunkStarted { start_ms, end_ms });
+    }
+
+    fn segment(&self, segment: Segment) {
+        (self.on_segment)(&segment);
+        self.send(Event::SegmentDecoded(segment));
+    }
+
+    // the safe callbacks box their closures into the params and never free them, so these go
+    // through the raw ones as the progress bar's do
+    fn apply(&self, params: &mut FullParams) {
+        let user_data = self as *const Reporter as *mut c_void;
+        unsafe {
+            params.set_progress_callback(Some(progress));
+            params.set_progress_callback_user_data(user_data);
+            if self.streaming {
+                params.set_new_segment_callback(Some(new_segment));
+                params.set_new_segment_callback_user_data(user_data);
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn progress(_ctx: *mut whisper_context, _state: *mut whisper_state, percent: c_int, user_data: *mut c_void) {
+    let reporter = &*(user_data as *const Reporter);
+    let (start_ms, end_ms) = reporter.chunk.get();
+    let reached_ms = start_ms + (end_ms - start_ms) * percent.clamp(0, 100) as i64 / 100;
+    reporter.send(Event::Progress((reached_ms * 100 / reporter.duration_ms.max(1)).min(100) as u8));
+}
+
+// whisper's times are in 10ms steps
+unsafe extern "C" fn new_segment(_ctx: *mut whisper_context, state: *mut whisper_state, new: c_int, user_data: *mut c_void) {
+    let reporter = &*(user_data as *const Reporter);
+    let segments = whisper_rs_sys::whisper_full_n_segments_from_state(state);
+    for i in segments - new..segments {
+        let text = CStr::from_ptr(whisper_rs_sys::whisper_full_get_segment_text_from_state(state, i)).to_string_lossy();
+        let restore = |ms: i64| reporter.speech.as_ref().map_or(ms, |speech| speech.restore_ms(ms));
+        let start_ms = restore(whisper_rs_sys::whisper_full_get_segment_t0_from_state(state, i) * 10);
+        let end_ms = restore(whisper_rs_sys::whisper_full_get_segment_t1_from_state(state, i) * 10);
+        reporter.segment(Segment::new(start_ms, end_ms, &text));
+    }
+}
+
+// the segments of transcribe_async, ending once the decode is done
+#[cfg(feature = "tokio")]
+struct Segments {
+    receiver: tokio::sync::mpsc::UnboundedReceiver<Result<Segment, Error>>,
+}
+
+#[cfg(feature = "tokio")]
+impl futures_core::Stream for Segments {
+    type Item = Result<Segment, Error>;
+
+    fn poll_next(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _transcriber() {
+        let transcriber = Transcriber::new("tiny").language("de");
+        let token = transcriber.cancellation_token();
+        let transcriber = transcriber.options(decode::Options::default());
+        token.cancel();
+        assert!(transcriber.cancellation_token().is_cancelled());
+        assert_eq!(transcriber.options.language, None);
+        // the language is checked before the model is looked for
+        assert!(matches!(Transcriber::new("missing").language("xx").transcribe(&[]), Err(Error::Input(_))));
+    }
+
+    #[test]
+    fn _reporter() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let reporter = Reporter { events: Some(&sender), on_segment: &|_| {}, duration_ms: 40000, chunk: Cell::new((0, 40000)), streaming: false, speech: None };
+        reporter.start(10000, 30000);
+        unsafe { progress(std::ptr::null_mut(), std::ptr::null_mut(), 50, &reporter as *const Reporter as *mut c_void) };
+        reporter.segment(Segment::new(0, 1000, " Hello."));
+        let events: Vec<Event> = receiver.try_iter().collect();
+        assert_eq!(events, vec![Event::ChunkStarted { start_ms: 10000, end_ms: 30000 }, Event::Progress(50), Event::SegmentDecoded(Segment::new(0, 1000, " Hello."))]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn _transcribe_async() {
+        use futures_core::Stream;
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        // spawning onto the blocking pool needs the runtime
+        let mut segments = Box::pin(runtime.block_on(async { Transcriber::new("missing").language("xx").transcribe_async(Vec::new()) }));
+        let mut next = || runtime.block_on(std::future::poll_fn(|cx| segments.as_mut().poll_next(cx)));
+        assert!(matches!(next(), Some(Err(Error::Input(_)))));
+        assert!(next().is_none());
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+
+use std::io::Write;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use whisper_rs::{WhisperContext, WhisperError, WhisperState};
+
+use crate::error::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Token {
+    pub text: String,
+    pub probability: f32,
+    // the vocabulary id, for --dump-tokens; not kept in a transcript written out and read back
+    #[serde(skip)]
+    pub id: Option<i32>,
+    // only known when whisper ran with token timestamps
+    #[serde(skip)]
+    pub start_ms: Option<i64>,
+    #[serde(skip)]
+    pub end_ms: Option<i64>,
+}
+
+impl Token {
+    pub fn new(text: &str, probability: f32) -> Token {
+        Token { text: text.to_string(), probability, id: None, start_ms: None, end_ms: None }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Word {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+    pub probability: f32,
+}
+
+// whisper's tokens carry their leading space, so a word runs from one spaced token to the next
+pub fn words(tokens: &[Token]) -> Vec<Word> {
+    let mut words: Vec<(Word, usize)> = Vec::new();
+    for token in tokens {
+        let (Some(start_ms), Some(end_ms)) = (token.start_ms, token.end_ms) else {
+            continue;
+        };
+        match words.last_mut() {
+            Some((word, count)) if !token.text.starts_with(' ') => {
+                word.text.push_str(&token.text);
+                word.end_ms = end_ms.max(word.start_ms);
+                word.probability += token.probability;
+                *count += 1;
+            }
+            _ => words.push((Word { start_ms, end_ms: end_ms.max(start_ms), text: token.text.trim_start().to_string(), probability: token.probability }, 1)),
+        }
+    }
+    words
+        .into_iter()
+        .filter(|(word, _)| !word.text.is_empty())
+        .map(|(word, count)| Word { probability: word.probability / count as f32, ..word })
+        .collect()
+}
+
+// the time from start_ms to end_ms split between the texts by their length
+pub(crate) fn spread(texts: &[&str], start_ms: i64, end_ms: i64) -> Vec<(i64, i64)> {
+    let total = texts.iter().map(|text| text.chars().count().max(1)).sum::<usize>().max(1) as i64;
+    let duration = (end_ms - start_ms).max(0);
+    let mut before = 0;
+    texts
+        .iter()
+        .map(|text| {
+            let from = start_ms + duration * before / total;
+            before += text.chars().count().max(1) as i64;
+            (from, start_ms + duration * before / total)
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tokens: Vec<Token>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<Word>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+    // a different speaker from the previous segment takes over here
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub speaker_turn: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_speech_probability: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression_ratio: Option<f32>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hallucination: bool,
+    // below --min-confidence, for a person to look over
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub low_confidence: bool,
+}
+
+impl Segment {
+    pub fn new(start_ms: i64, end_ms: i64, text: &str) -> Segment {
+        Segment {
+            start_ms,
+            end_ms,
+            text: text.to_string(),
+            tokens: Vec::new(),
+            words: Vec::new(),
+            speaker: None,
+            speaker_turn: false,
+            no_speech_probability: None,
+            compression_ratio: None,
+            hallucination: false,
+            low_confidence: false,
+        }
+    }
+
+    // text bytes over zlib-compressed bytes, looping text compresses far better than speech
+    pub fn compression_ratio(&self) -> f32 {
+        if self.text.is_empty() {
+            return 0.0;
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(self.text.as_bytes()).expect("failed to compress text");
+        let compressed = encoder.finish().expect("failed to compress text");
+        self.text.len() as f32 / compressed.len() as f32
+    }
+
+    pub fn mean_logprob(&self) -> Option<f32> {
+        if self.tokens.is_empty() {
+            return None;
+        }
+        Some(self.tokens.iter().map(|token| token.probability.ln()).sum::<f32>() / self.tokens.len() as f32)
+    }
+
+    pub fn middle_ms(&self) -> i64 {
+        (self.start_ms + self.end_ms) / 2
+    }
+
+    // mean token probability, None when the segment carries no token data
+    pub fn confidence(&self) -> Option<f32> {
+        if self.tokens.is_empty() {
+            return None;
+        }
+        let total: f32 = self.tokens.iter().map(|token| token.probability).sum();
+        Some(total / self.tokens.len() as f32)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Transcript {
+    pub segments: Vec<Segment>,
+}
+
+impl Transcript {
+    // tinydiarize only says where the speaker changes, not who speaks, with turns[i]
+    // being true when someone else speaks after segment i
+    pub fn mark_speaker_turns(&mut self, turns: &[bool]) {
+        for (i, segment) in self.segments.iter_mut().enumerate().skip(1) {
+            segment.speaker_turn = turns[i - 1];
+        }
+    }
+
+    // labels every channel's segments with its speaker and merges them by start time
+    pub fn interleave(channels: Vec<Transcript>) -> Transcript {
+        let mut segments: Vec<Segment> = channels
+            .into_iter()
+            .enumerate()
+            .flat_map(|(channel, transcript)| {
+                transcript.segments.into_iter().map(move |segment| Segment { speaker: Some(format!("SPEAKER_{}", channel)), ..segment })
+            })
+            .collect();
+        segments.sort_by_key(|segment| segment.start_ms);
+        for i in 0..segments.len() {
+            segments[i].speaker_turn = i > 0 && segments[i - 1].speaker != segments[i].speaker;
+        }
+        Transcript { segments }
+    }
+
+    // names are keyed by speaker id or by bare channel number, so SPEAKER_1 and 1 both match
+    pub fn rename_speakers(&mut self, names: &HashMap<String, String>) {
+        for segment in &mut self.segments {
+            let Some(speaker) = &segment.speaker else {
+                continue;
+            };
+            let channel = speaker.trim_start_matches("SPEAKER_");
+            if let Some(name) = names.get(speaker).or_else(|| names.get(channel)) {
+                segment.speaker = Some(name.clone());
+            }
+        }
+    }
+
+    // moves every time along by offset_ms, for a transcript of audio that started there
+    pub fn shift(&mut self, offset_ms: i64) {
+        self.map_times(|ms| ms + offset_ms);
+    }
+
+    // every segment, token and word time put through map
+    pub fn map_times(&mut self, map: impl Fn(i64) -> i64) {
+        for segment in &mut self.segments {
+            segment.start_ms = map(segment.start_ms);
+            segment.end_ms = map(segment.end_ms);
+            for token in &mut segment.tokens {
+                token.start_ms = token.start_ms.map(&map);
+                token.end_ms = token.end_ms.map(&map);
+            }
+            for word in &mut segment.words {
+                word.start_ms = map(word.start_ms);
+                word.end_ms = map(word.end_ms);
+            }
+        }
+    }
+
+    // joins on the next overlapping window at seam_ms: segments centred past the seam come from
+    // next, those before it from self. whisper often repeats the words either side of a cut, so
+    // a first segment saying what the last one kept said is dropped
+    pub fn stitch(&mut self, next: Transcript, seam_ms: i64) {
+        let normalized = |text: &str| text.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect::<String>();
+        self.segments.retain(|segment| segment.middle_ms() < seam_ms);
+        for (i, mut segment) in next.segments.into_iter().filter(|segment| segment.middle_ms() >= seam_ms).enumerate() {
+            if let Some(last) = self.segments.last() {
+                if i == 0 && normalized(&last.text) == normalized(&segment.text) {
+                    continue;
+                }
+                segment.start_ms = segment.start_ms.max(last.end_ms).min(segment.end_ms);
+            }
+            self.segments.push(segment);
+        }
+    }
+
+    // every word with its time; a segment without word times spreads its words over its own time
+    // by their length, with no probability
+    pub fn timed_words(&self) -> Vec<Word> {
+        let mut words = Vec::new();
+        for segment in &self.segments {
+            if !segment.words.is_empty() {
+                words.extend(segment.words.iter().cloned());
+                continue;
+            }
+            let texts: Vec<&str> = segment.text.split_whitespace().collect();
+            for (text, (start_ms, end_ms)) in texts.iter().zip(spread(&texts, segment.start_ms, segment.end_ms)) {
+                words.push(Word { start_ms, end_ms, text: text.to_string(), probability: 0.0 });
+            }
+        }
+        words
+    }
+
+    // average log probability over every token, None for a transcript without token data
+    pub fn mean_logprob(&self) -> Option<f32> {
+        let logprobs: Vec<f32> = self.segments.iter().flat_map(|segment| &segment.tokens).map(|token| token.probability.ln()).collect();
+        if logprobs.is_empty() {
+            return None;
+        }
+        Some(logprobs.iter().sum::<f32>() / logprobs.len() as f32)
+    }
+
+    pub fn from_state(ctx: &WhisperContext, state: &mut WhisperState) -> Result<Transcript, Error> {
+        let failed = |error: WhisperError| Error::inference("failed to read the decoded segments", error);
+        let num_segments = state.full_n_segments().map_err(failed)?;
+        let mut segments = Vec::new();
+        let mut turns = Vec::new();
+
+        for i in 0..num_segments {
+            let text = state.full_get_segment_text_lossy(i).map_err(failed)?;
+            // whisper reports segment times in 10ms ticks
+            let start_ms = state.full_get_segment_t0(i).map_err(failed)? * 10;
+            let end_ms = state.full_get_segment_t1(i).map_err(failed)? * 10;
+
+            let num_tokens = state.full_n_tokens(i).map_err(failed)?;
+            let mut tokens = Vec::new();
+            for j in 0..num_tokens {
+                let data = state.full_get_token_data(i, j).map_err(failed)?;
+                // ids from eot upwards are special tokens (timestamps, language, task markers)
+                if data.id >= ctx.token_eot() {
+                    continue;
+                }
+                // a single token can hold part of a multi-byte character, so don't insist on valid utf-8
+                let text = ctx.token_to_cstr(data.id).map_err(failed)?.to_string_lossy().into_owned();
+                // t0/t1 stay -1 without token timestamps, a dtw time is the better start when there is one
+                let start = if data.t_dtw >= 0 { data.t_dtw } else { data.t0 };
+                let (start_ms, end_ms) = match (start, data.t1) {
+                    (start, end) if start >= 0 && end >= 0 => (Some(start * 10), Some(end * 10)),
+                    _ => (None, None),
+                };
+                tokens.push(Token { id: Some(data.id), start_ms, end_ms, ..Token::new(&text, data.p) });
+            }
+
+            let words = words(&tokens);
+            segments.push(Segment { tokens, words, ..Segment::new(start_ms, end_ms, &text) });
+            turns.push(state.full_get_segment_speaker_turn_next(i));
+        }
+
+        let mut transcript = Transcript { segments };
+        transcript.mark_speaker_turns(&turns);
+        Ok(transcript)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _mean_logprob() {
+        let token = |probability: f32| Token::new("a", probability);
+        let segment = Segment { tokens: vec![token(1.0), token(0.5)], ..Segment::new(0, 1000, "a a") };
+        let transcript = Transcript { segments: vec![segment, Segment::new(1000, 2000, "")] };
+        assert!((transcript.mean_logprob().unwrap() - 0.5f32.ln() / 2.0).abs() < 1e-6);
+        assert_eq!(Transcript { segments: vec![] }.mean_logprob(), None);
+    }
+
+    #[test]
+    fn _mark_speaker_turns() {
+        let mut transcript = Transcript { segments: vec![Segment::new(0, 1, "a"), Segment::new(1, 2, "b"), Segment::new(2, 3, "c")] };
+        transcript.mark_speaker_turns(&[true, false, false]);
+        let turns: Vec<_> = transcript.segments.iter().map(|segment| segment.speaker_turn).collect();
+        assert_eq!(turns, vec![false, true, false]);
+    }
+
+    #[test]
+    fn _interleave() {
+        let left = Transcript { segments: vec![Segment::new(0, 1000, "a"), Segment::new(3000, 4000, "c")] };
+        let right = Transcript { segments: vec![Segment::new(1500, 2500, "b")] };
+        let transcript = Transcript::interleave(vec![left, right]);
+        let merged: Vec<_> = transcript.segments.iter().map(|segment| (segment.text.as_str(), segment.speaker.as_deref(), segment.speaker_turn)).collect();
+        assert_eq!(merged, vec![("a", Some("SPEAKER_0"), false), ("b", Some("SPEAKER_1"), true), ("c", Some("SPEAKER_0"), true)]);
+    }
+
+    #[test]
+    fn _rename_speakers() {
+        let segment = |speaker: &str| Segment { speaker: Some(speaker.to_string()), ..Segment::new(0, 1, "a") };
+        let mut transcript = Transcript { segments: vec![segment("SPEAKER_0"), segment("SPEAKER_1"), segment("SPEAKER_2")] };
+        let names = HashMap::from([("SPEAKER_0".to_string(), "Alice".to_string()), ("1".to_string(), "Bob".to_string())]);
+        transcript.rename_speakers(&names);
+        let speakers: Vec<_> = transcript.segments.iter().map(|segment| segment.speaker.as_deref().unwrap()).collect();
+        assert_eq!(speakers, vec!["Alice", "Bob", "SPEAKER_2"]);
+    }
+
+    #[test]
+    fn _stitch() {
+        let transcript = |segments: &[(i64, i64, &str)]| Transcript { segments: segments.iter().map(|(start, end, text)| Segment::new(*start, *end, text)).collect() };
+        let mut stitched = transcript(&[(0, 10000, " One."), (10000, 27000, " Two, three."), (27000, 30000, " fo")]);
+        let mut next = transcript(&[(1000, 4000, " two three"), (4000, 5500, " Four."), (5500, 10000, " Five.")]);
+        next.shift(25000);
+        stitched.stitch(next, 27500);
+        let segments: Vec<_> = stitched.segments.iter().map(|segment| (segment.start_ms, segment.end_ms, segment.text.as_str())).collect();
+        assert_eq!(segments, vec![(0, 10000, " One."), (10000, 27000, " Two, three."), (29000, 30500, " Four."), (30500, 35000, " Five.")]);
+    }
+
+    #[test]
+    fn _compression_ratio() {
+        let looping = Segment::new(0, 1, &" Thank you.".repeat(20));
+        let speech = Segment::new(0, 1, " The quick brown fox jumps over the lazy dog.");
+        assert!(looping.compression_ratio() > 2.4);
+        assert!(speech.compression_ratio() < 2.4);
+    }
+
+    #[test]
+    fn _words() {
+        let token = |text: &str, start_ms: i64, end_ms: i64, probability: f32| Token { start_ms: Some(start_ms), end_ms: Some(end_ms), ..Token::new(text, probability) };
+        let tokens = vec![token(" Hel", 0, 200, 0.5), token("lo", 200, 400, 1.0), token(",", 400, 400, 1.0), token(" world", 500, 900, 0.25)];
+        assert_eq!(
+            words(&tokens),
+            vec![
+                Word { start_ms: 0, end_ms: 400, text: "Hello,".to_string(), probability: 2.5 / 3.0 },
+                Word { start_ms: 500, end_ms: 900, text: "world".to_string(), probability: 0.25 },
+            ]
+        );
+        let untimed = Token { start_ms: None, end_ms: None, ..token(" a", 0, 0, 1.0) };
+        assert_eq!(words(&[untimed]), vec![]);
+    }
+}
@@ -0,0 +1,337 @@
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use ratatui::crossterm::event::{self, Event as Input, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use transcript::error::Error;
+use transcript::output::{self, vtt, Format};
+use transcript::{audio, decode, models, Event, Segment, Transcriber, Transcript};
+
+use crate::{batch, interrupt};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Model file or name, as for transcribe
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Audio to transcribe, one file after another
+    #[arg(short, long, required = true, num_args = 1..)]
+    input: Vec<String>,
+
+    /// Directory to write each file's outputs to, named after it; without one the transcripts
+    /// are only shown
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<String>,
+
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "txt,srt", requires = "output_dir")]
+    formats: Vec<Format>,
+
+    #[command(flatten)]
+    decode_options: decode::Options,
+
+    #[command(flatten)]
+    audio_options: audio::Options,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Status {
+    Queued,
+    Decoding(u8),
+    Done,
+    Failed(String),
+}
+
+struct File {
+    input: String,
+    status: Status,
+    segments: Vec<Segment>,
+}
+
+// what the worker thread tells the screen, by the index of the file
+enum Message {
+    Started(usize),
+    Event(usize, Event),
+    Finished(usize, Result<Transcript, Error>),
+}
+
+// everything on the screen; the transcript shown is the selected file's, following its end as
+// segments come in until it's scrolled up
+struct App {
+    files: Vec<File>,
+    selected: usize,
+    // the first line shown, none to follow the end
+    scroll: Option<usize>,
+    // what's being typed after /
+    typing: Option<String>,
+    query: String,
+    height: usize,
+}
+
+fn name(input: &str) -> String {
+    Path::new(input).file_name().map_or_else(|| input.to_string(), |name| name.to_string_lossy().into_owned())
+}
+
+impl App {
+    fn new(inputs: &[String]) -> App {
+        let files = inputs.iter().map(|input| File { input: input.clone(), status: Status::Queued, segments: Vec::new() }).collect();
+        App { files, selected: 0, scroll: None, typing: None, query: String::new(), height: 1 }
+    }
+
+    fn handle(&mut self, message: Message) {
+        match message {
+            Message::Started(i) => {
+                self.files[i].status = Status::Decoding(0);
+                // the file being decoded is the one to watch, unless another has been picked
+                if self.scroll.is_none() && self.files[self.selected].status == Status::Done {
+                    self.selected = i;
+                }
+            }
+            // what comes in after the file is finished is already in its transcript
+            Message::Event(i, _) if !matches!(self.files[i].status, Status::Decoding(_)) => {}
+            Message::Event(i, Event::Progress(percent)) => self.files[i].status = Status::Decoding(percent),
+            // a retry at a higher temperature starts over, so whatever it streams replaces what
+            // the attempt before it got to
+            Message::Event(i, Event::SegmentDecoded(segment)) => {
+                let segments = &mut self.files[i].segments;
+                segments.retain(|other| other.start_ms < segment.start_ms);
+                segments.push(segment);
+            }
+            Message::Event(..) => {}
+            Message::Finished(i, Ok(transcript)) => {
+                self.files[i].segments = transcript.segments;
+                self.files[i].status = Status::Done;
+            }
+            Message::Finished(i, Err(error)) => self.files[i].status = Status::Failed(error.to_string()),
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.files[self.selected].segments.iter().map(|segment| format!("{} {}", vtt::timestamp(segment.start_ms), segment.text.trim())).collect()
+    }
+
+    fn top(&self) -> usize {
+        let last = self.lines().len().saturating_sub(self.height);
+        self.scroll.map_or(last, |scroll| scroll.min(last))
+    }
+
+    fn scroll_by(&mut self, lines: isize) {
+        let last = self.lines().len().saturating_sub(self.height);
+        let top = self.top().saturating_add_signed(lines).min(last);
+        self.scroll = (top < last).then_some(top);
+    }
+
+    // the next line after the top one with the query in it, or the one before going back;
+    // round to the other end when there's none
+    fn find(&mut self, forward: bool) {
+        let query = self.query.to_lowercase();
+        if query.is_empty() {
+            return;
+        }
+        let matching: Vec<usize> = self.lines().iter().enumerate().filter(|(_, line)| line.to_lowercase().contains(&query)).map(|(i, _)| i).collect();
+        let top = self.top();
+        let found = match forward {
+            true => matching.iter().find(|i| **i > top).or(matching.first()),
+            false => matching.iter().rev().find(|i| **i < top).or(matching.last()),
+        };
+        if let Some(line) = found {
+            self.scroll = Some(*line);
+        }
+    }
+
+    // false once it's time to quit
+    fn key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        if let Some(typing) = &mut self.typing {
+            match code {
+                KeyCode::Enter => {
+                    self.query = self.typing.take().unwrap_or_default();
+                    self.find(true);
+                }
+                KeyCode::Esc => self.typing = None,
+                KeyCode::Backspace => {
+                    typing.pop();
+                }
+                KeyCode::Char(c) => typing.push(c),
+                _ => {}
+            }
+            return true;
+        }
+        let page = self.height.max(1) as isize;
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.scroll_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.scroll_by(1),
+            KeyCode::PageUp => self.scroll_by(-page),
+            KeyCode::PageDown => self.scroll_by(page),
+            KeyCode::Home | KeyCode::Char('g') => self.scroll = Some(0),
+            KeyCode::End | KeyCode::Char('G') => self.scroll = None,
+            KeyCode::Tab => self.select((self.selected + 1) % self.files.len()),
+            KeyCode::BackTab => self.select((self.selected + self.files.len() - 1) % self.files.len()),
+            KeyCode::Char('/') => self.typing = Some(String::new()),
+            KeyCode::Char('n') => self.find(true),
+            KeyCode::Char('N') => self.find(false),
+            _ => {}
+        }
+        true
+    }
+
+    fn select(&mut self, selected: usize) {
+        self.selected = selected;
+        self.scroll = None;
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let list_height = (self.files.len() as u16 + 2).min(10);
+        let [files_area, transcript_area, footer_area] = Layout::vertical([Constraint::Length(list_height), Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+
+        let items: Vec<ListItem> = self
+            .files
+            .iter()
+            .map(|file| {
+                let (mark, state, color) = match &file.status {
+                    Status::Queued => ("·", "queued".to_string(), Color::DarkGray),
+                    Status::Decoding(percent) => ("▶", format!("{}%", percent), Color::Yellow),
+                    Status::Done => ("✓", format!("{} segments", file.segments.len()), Color::Green),
+                    Status::Failed(error) => ("✗", error.clone(), Color::Red),
+                };
+                ListItem::new(Line::from(vec![Span::styled(format!("{} ", mark), Style::new().fg(color)), Span::raw(format!("{}  ", name(&file.input))), Span::styled(state, Style::new().fg(color))]))
+            })
+            .collect();
+        let done = self.files.iter().filter(|file| matches!(file.status, Status::Done | Status::Failed(_))).count();
+        let list = List::new(items).block(Block::bordered().title(format!(" {} of {} files ", done, self.files.len()))).highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, files_area, &mut ListState::default().with_selected(Some(self.selected)));
+
+        self.height = transcript_area.height.saturating_sub(2) as usize;
+        let query = self.query.to_lowercase();
+        let top = self.top();
+        let lines: Vec<Line> = self
+            .lines()
+            .into_iter()
+            .skip(top)
+            .take(self.height)
+            .map(|line| match !query.is_empty() && line.to_lowercase().contains(&query) {
+                true => Line::styled(line, Style::new().fg(Color::Black).bg(Color::Yellow)),
+                false => Line::raw(line),
+            })
+            .collect();
+        let following = if self.scroll.is_none() { ", following" } else { "" };
+        let title = format!(" {}{} ", name(&self.files[self.selected].input), following);
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(title)), transcript_area);
+
+        let footer = match &self.typing {
+            Some(typing) => format!("/{}", typing),
+            None => "q quit  tab next file  ↑↓ pgup pgdn scroll  end follow  / search  n N next previous match".to_string(),
+        };
+        frame.render_widget(Paragraph::new(footer).style(Style::new().fg(Color::DarkGray)), footer_area);
+    }
+}
+
+// the files one after another on one model, each streaming its segments through the messages
+fn work(transcriber: Transcriber, jobs: Vec<batch::Job>, formats: Vec<Format>, language: String, messages: Sender<Message>) {
+    for (i, job) in jobs.iter().enumerate() {
+        if transcriber.cancellation_token().is_cancelled() {
+            break;
+        }
+        let _ = messages.send(Message::Started(i));
+        let (events, received) = mpsc::channel();
+        let forward = messages.clone();
+        let forwarder = thread::spawn(move || received.iter().for_each(|event| drop(forward.send(Message::Event(i, event)))));
+        let transcribed = transcriber.clone().events(events).transcribe_file(&job.input).and_then(|transcript| {
+            if let Some(output) = &job.output {
+                output::write_outputs(&transcript, output, &formats, &output::Options::default(), &language)?;
+            }
+            Ok(transcript)
+        });
+        let _ = forwarder.join();
+        let _ = messages.send(Message::Finished(i, transcribed));
+    }
+}
+
+fn show(terminal: &mut DefaultTerminal, app: &mut App, received: &Receiver<Message>) -> Result<(), Error> {
+    let failed = |error: std::io::Error| Error::output("failed to draw the terminal", error);
+    loop {
+        for message in received.try_iter() {
+            app.handle(message);
+        }
+        terminal.draw(|frame| app.draw(frame)).map_err(failed)?;
+        if event::poll(Duration::from_millis(100)).map_err(failed)? {
+            if let Input::Key(key) = event::read().map_err(failed)? {
+                if key.kind == KeyEventKind::Press && !app.key(key.code, key.modifiers) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+pub fn run(args: Args) -> Result<(), Error> {
+    let model = match args.model.clone() {
+        Some(model) => model,
+        None => models::discover()?,
+    };
+    let language = args.decode_options.output_language().to_string();
+    let transcriber = Transcriber::new(model).options(args.decode_options).audio_options(args.audio_options);
+    let cancel = transcriber.cancellation_token();
+    interrupt::install(cancel.clone());
+    let jobs = batch::from_inputs(&args.input, args.output_dir.as_deref(), true);
+    let (messages, received) = mpsc::channel();
+    let formats = args.formats;
+    let worker = thread::spawn(move || work(transcriber, jobs, formats, language, messages));
+
+    let mut app = App::new(&args.input);
+    let mut terminal = ratatui::init();
+    let shown = show(&mut terminal, &mut app, &received);
+    ratatui::restore();
+    // quitting stops the decode in progress and leaves the files after it
+    cancel.cancel();
+    let _ = worker.join();
+    shown?;
+    for file in &app.files {
+        if let Status::Failed(error) = &file.status {
+            tracing::error!("{}: {}", file.input, error);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _handle() {
+        let mut app = App::new(&["a.wav".to_string(), "b.wav".to_string()]);
+        app.height = 2;
+        app.handle(Message::Started(0));
+        for (start_ms, text) in [(0, " One fish."), (1000, " Two fish."), (0, " One fish."), (1000, " Red fish."), (2000, " Blue fish.")] {
+            app.handle(Message::Event(0, Event::SegmentDecoded(Segment::new(start_ms, start_ms + 1000, text))));
+        }
+        app.handle(Message::Event(0, Event::Progress(40)));
+        assert_eq!(app.files[0].status, Status::Decoding(40));
+        assert_eq!(app.lines(), vec!["00:00:00.000 One fish.", "00:00:01.000 Red fish.", "00:00:02.000 Blue fish."]);
+        assert_eq!(app.top(), 1);
+
+        app.key(KeyCode::Char('/'), KeyModifiers::NONE);
+        for c in "ONE".chars() {
+            app.key(KeyCode::Char(c), KeyModifiers::NONE);
+        }
+        app.key(KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!((app.query.as_str(), app.scroll), ("ONE", Some(0)));
+        app.key(KeyCode::End, KeyModifiers::NONE);
+        assert_eq!(app.scroll, None);
+
+        app.handle(Message::Finished(0, Ok(Transcript { segments: vec![Segment::new(0, 3000, " One red fish.")] })));
+        app.handle(Message::Event(0, Event::SegmentDecoded(Segment::new(5000, 6000, " Late."))));
+        app.handle(Message::Started(1));
+        assert_eq!((app.files[0].segments.len(), app.selected), (1, 1));
+        assert!(!app.key(KeyCode::Char('q'), KeyModifiers::NONE));
+    }
+}
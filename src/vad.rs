@@ -0,0 +1,174 @@
+use std::ops::Range;
+
+use crate::audio::SAMPLE_RATE;
+use crate::transcript::Transcript;
+
+// loudness is measured over frames this long
+const FRAME_MS: usize = 30;
+
+// the defaults of --vad's flags
+pub const THRESHOLD_DB: f32 = -45.0;
+pub const MIN_SILENCE_SECS: f32 = 2.0;
+pub const PAD_SECS: f32 = 0.25;
+
+// a pause this long is somewhere --parallel can cut without splitting a word
+pub const SPLIT_SILENCE_MS: f32 = 300.0;
+
+fn samples(ms: f32) -> usize {
+    (ms.max(0.0) * SAMPLE_RATE as f32 / 1000.0) as usize
+}
+
+// the frame's rms in dBFS, silence being -inf
+fn loudness(frame: &[f32]) -> f32 {
+    let power = frame.iter().map(|sample| sample * sample).sum::<f32>() / frame.len() as f32;
+    10.0 * power.log10()
+}
+
+// the stretches of 16kHz audio with speech in them, found by their loudness, and the way back from
+// a time in them strung together to the same time in the whole audio
+#[derive(Debug, Clone, PartialEq)]
+pub struct Speech {
+    ranges: Vec<Range<usize>>,
+}
+
+impl Speech {
+    // frames above threshold_db are speech; silences shorter than min_silence_ms stay in, and
+    // every stretch that's kept starts and ends pad_ms early and late so no word is clipped
+    pub fn detect(audio: &[f32], threshold_db: f32, min_silence_ms: f32, pad_ms: f32) -> Speech {
+        let frame = samples(FRAME_MS as f32);
+        let (min_silence, pad) = (samples(min_silence_ms), samples(pad_ms));
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for (i, chunk) in audio.chunks(frame).enumerate() {
+            if loudness(chunk) <= threshold_db {
+                continue;
+            }
+            let start = (i * frame).saturating_sub(pad);
+            let end = (i * frame + chunk.len() + pad).min(audio.len());
+            match ranges.last_mut() {
+                Some(last) if start <= last.end.saturating_add(min_silence) => last.end = end,
+                _ => ranges.push(start..end),
+            }
+        }
+        Speech { ranges }
+    }
+
+    // all of it, for --skip-ranges to take stretches out of without --vad
+    pub fn whole(length: usize) -> Speech {
+        Speech { ranges: std::iter::once(0..length).filter(|range| !range.is_empty()).collect() }
+    }
+
+    // what's left once the skipped ranges are taken out
+    pub fn without(&self, skipped: &[Range<usize>]) -> Speech {
+        let mut ranges = self.ranges.clone();
+        for skip in skipped {
+            ranges = ranges
+                .into_iter()
+                .flat_map(|range| [range.start..range.end.min(skip.start), range.start.max(skip.end)..range.end])
+                .filter(|range| !range.is_empty())
+                .collect();
+        }
+        Speech { ranges }
+    }
+
+    // from the start of the first stretch to the end of the last
+    pub fn span(&self) -> Option<Range<usize>> {
+        Some(self.ranges.first()?.start..self.ranges.last()?.end)
+    }
+
+    // in samples
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    // the audio that's kept, in ms
+    pub fn kept_ms(&self) -> i64 {
+        (self.ranges.iter().map(|range| range.len()).sum::<usize>() * 1000 / SAMPLE_RATE as usize) as i64
+    }
+
+    // the speech strung together, what whisper is given instead of the whole audio
+    pub fn gather(&self, audio: &[f32]) -> Vec<f32> {
+        self.ranges.iter().flat_map(|range| &audio[range.clone()]).copied().collect()
+    }
+
+    // a time in the gathered audio as a time in the whole audio; a time past the end stays past
+    // the end of the last stretch
+    pub fn restore_ms(&self, ms: i64) -> i64 {
+        let rate = SAMPLE_RATE as i64;
+        let mut position = ms.max(0) * rate / 1000;
+        for (i, range) in self.ranges.iter().enumerate() {
+            let length = range.len() as i64;
+            if position < length || i == self.ranges.len() - 1 {
+                return (range.start as i64 + position) * 1000 / rate;
+            }
+            position -= length;
+        }
+        ms
+    }
+
+    pub fn restore(&self, transcript: &mut Transcript) {
+        transcript.map_times(|ms| self.restore_ms(ms));
+    }
+
+    // the audio, length samples of it, in count chunks about as long as each other: each cut goes
+    // in the middle of the silence nearest where an even split would put it, or right there when
+    // there's none within half a chunk
+    pub fn split(&self, length: usize, count: usize) -> Vec<Range<usize>> {
+        let silences: Vec<usize> = self.ranges.windows(2).map(|pair| (pair[0].end + pair[1].start) / 2).collect();
+        let chunk = length / count.max(1);
+        let mut cuts = vec![0];
+        for i in 1..count {
+            let even = length * i / count;
+            let last = *cuts.last().expect("cuts start at 0");
+            let nearest = silences.iter().copied().filter(|cut| *cut > last && cut.abs_diff(even) <= chunk / 2).min_by_key(|cut| cut.abs_diff(even));
+            let cut = nearest.unwrap_or(even);
+            if cut > last && cut < length {
+                cuts.push(cut);
+            }
+        }
+        cuts.push(length);
+        cuts.windows(2).map(|pair| pair[0]..pair[1]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    #[test]
+    fn _detect() {
+        // a second of speech, 3s of silence, 0.2s of speech, 0.5s of silence and a last 0.3s
+        let tone = |ms: usize| (0..ms * 16).map(|i| if i % 2 == 0 { 0.1 } else { -0.1 });
+        let silence = |ms: usize| std::iter::repeat_n(0.0, ms * 16);
+        let audio: Vec<f32> = tone(990).chain(silence(3000)).chain(tone(210)).chain(silence(480)).chain(tone(300)).collect();
+        let speech = Speech::detect(&audio, -40.0, 1000.0, 90.0);
+        assert_eq!(speech.ranges, vec![0..1080 * 16, 3900 * 16..audio.len()]);
+        assert_eq!(speech.kept_ms(), 1080 + 1080);
+        assert_eq!(speech.gather(&audio).len(), (1080 + 1080) * 16);
+
+        assert_eq!(speech.restore_ms(500), 500);
+        assert_eq!(speech.restore_ms(1080), 3900);
+        assert_eq!(speech.restore_ms(1500), 4320);
+        assert_eq!(speech.restore_ms(2500), 5320);
+        let mut transcript = Transcript { segments: vec![Segment::new(1000, 1200, " so")] };
+        speech.restore(&mut transcript);
+        assert_eq!((transcript.segments[0].start_ms, transcript.segments[0].end_ms), (1000, 4020));
+
+        assert!(Speech::detect(&silence(5000).collect::<Vec<f32>>(), -40.0, 1000.0, 90.0).is_empty());
+
+        // silences in the middle of 1200 and 5100, too far from 3000 for the first cut
+        let speech = Speech { ranges: vec![0..1000, 1400..5000, 5200..9000] };
+        assert_eq!(speech.split(9000, 3), vec![0..3000, 3000..5100, 5100..9000]);
+        assert_eq!(speech.split(9000, 2), vec![0..5100, 5100..9000]);
+        assert_eq!(Speech { ranges: Vec::new() }.split(3000, 3), vec![0..1000, 1000..2000, 2000..3000]);
+        assert_eq!(speech.split(9000, 1), vec![0..9000]);
+
+        assert_eq!(speech.without(&[500..1500, 4000..6000]).ranges, vec![0..500, 1500..4000, 6000..9000]);
+        assert_eq!(Speech::whole(9000).without(&[0..3000, 8000..10_000]).ranges, vec![3000..8000]);
+        assert!(Speech::whole(9000).without(&[0..4000, 3000..9000]).is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use clap::ArgMatches;
+use indicatif::MultiProgress;
+use notify::{RecursiveMode, Watcher};
+
+use transcript::error::Error;
+
+use crate::{batch, transcribe};
+
+#[derive(clap::Args, Debug)]
+#[group(skip)]
+#[command(mut_args(watch_flag))]
+pub struct Args {
+    /// Seconds a new file has to go untouched, at the same size, before it's picked up, so files
+    /// still being copied in are left alone
+    #[arg(long, default_value_t = 2.0, value_name = "SECONDS")]
+    settle: f64,
+
+    /// The transcribe flags, --input-dir is watched and what's done goes to --output-dir
+    #[command(flatten)]
+    transcribe: transcribe::Args,
+}
+
+// files seen changing lately, each with the size it had when last looked at
+#[derive(Default)]
+struct Settling {
+    files: HashMap<PathBuf, (Instant, Option<u64>)>,
+}
+
+// the flags about choosing inputs or a batch's outputs mean nothing to a watch
+const UNUSED: &[&str] = &["input", "output", "manifest", "stdin_jobs", "jobs", "overwrite", "skip_existing", "check_mtime", "resume", "list_devices", "stats", "stats_json"];
+
+fn watch_flag(arg: clap::Arg) -> clap::Arg {
+    match arg.get_id().as_str() {
+        "input_dir" | "output_dir" => arg.required(true),
+        id if UNUSED.contains(&id) => arg.hide(true),
+        _ => arg,
+    }
+}
+
+impl Settling {
+    fn touch(&mut self, path: PathBuf, now: Instant) {
+        let size = self.files.get(&path).and_then(|(_, size)| *size);
+        self.files.insert(path, (now, size));
+    }
+
+    // the files quiet for settle whose size held since the last look; the others are looked at
+    // again after another settle, and ones that went away are forgotten
+    fn ready(&mut self, now: Instant, settle: Duration, size: impl Fn(&Path) -> Option<u64>) -> Vec<PathBuf> {
+        let mut ready = Vec::new();
+        self.files.retain(|path, (seen, last)| {
+            if now.duration_since(*seen) < settle {
+                return true;
+            }
+            match size(path) {
+                None => false,
+                Some(size) if *last == Some(size) => {
+                    ready.push(path.clone());
+                    false
+                }
+                Some(size) => {
+                    (*seen, *last) = (now, Some(size));
+                    true
+                }
+            }
+        });
+        ready.sort();
+        ready
+    }
+}
+
+// transcripts go to the mirrored place under the output directory and the audio joins them; a
+// file that fails gets .failed added, so it's neither lost nor tried again
+fn finish(job: &batch::Job, result: Result<transcribe::Finished, Error>) -> Result<(), Error> {
+    let input = Path::new(&job.input);
+    let (done, moved) = match result {
+        Ok(_) => {
+            let outputs = Path::new(job.output.as_deref().expect("watched files have outputs"));
+            let name = input.file_name().expect("watched files have names");
+            (true, outputs.with_file_name(name))
+        }
+        Err(Error::Interrupted(message)) => return Err(Error::Interrupted(message)),
+        Err(error) => {
+            tracing::error!("{}", error);
+            (false, PathBuf::from(format!("{}.failed", job.input)))
+        }
+    };
+    fs::rename(input, &moved).map_err(|error| Error::output(format!("failed to move {} to {}", input.display(), moved.display()), error))?;
+    if done {
+        tracing::info!("transcribed {}, moved to {}", job.input, moved.display());
+    }
+    Ok(())
+}
+
+pub fn run(mut args: Args, matches: &ArgMatches) -> Result<ExitCode, Error> {
+    let model_path_raw = transcribe::prepare(&mut args.transcribe, matches)?;
+    let settle = Duration::try_from_secs_f64(args.settle).map_err(|_| Error::Input("--settle has to be a number of seconds".to_string()))?;
+    let input_dir = args.transcribe.input_dir.as_deref().expect("--input-dir is required");
+    let output_dir = args.transcribe.output_dir.as_deref().expect("--output-dir is required");
+    // events name files by the path watched, kept canonical so they're always under it
+    let dir = fs::canonicalize(input_dir).map_err(|error| Error::input(format!("failed to watch {}", input_dir), error))?;
+    let dir_raw = dir.to_string_lossy().into_owned();
+    let recursive = args.transcribe.recursive;
+    let extensions = &args.transcribe.ext;
+
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(|error| Error::input(format!("failed to watch {}", input_dir), error))?;
+    let mode = if recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+    watcher.watch(&dir, mode).map_err(|error| Error::input(format!("failed to watch {}", input_dir), error))?;
+    let engine = transcribe::engine(&args.transcribe, model_path_raw.as_deref())?;
+    let refiner = transcribe::refiner(&args.transcribe)?;
+
+    // whatever was waiting before the watch started is picked up too
+    let mut settling = Settling::default();
+    for job in batch::from_dir(&dir_raw, recursive, extensions, output_dir)? {
+        settling.touch(PathBuf::from(job.input), Instant::now());
+    }
+    tracing::info!("watching {} for audio", input_dir);
+
+    let cancel = &args.transcribe.decode_options.cancel;
+    let bars = MultiProgress::new();
+    let wanted = |path: &Path| path.starts_with(&dir) && batch::has_extension(path, extensions) && (recursive || path.parent() == Some(dir.as_path()));
+    // looks in often enough for ctrl-c and a short settle to be noticed
+    let tick = settle.min(Duration::from_millis(250));
+    while !cancel.is_cancelled() {
+        match events.recv_timeout(tick) {
+            Ok(Ok(event)) => {
+                for path in event.paths.into_iter().filter(|path| wanted(path)) {
+                    settling.touch(path, Instant::now());
+                }
+            }
+            Ok(Err(error)) => tracing::warn!("watching {}: {}", input_dir, error),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        for path in settling.ready(Instant::now(), settle, |path| fs::metadata(path).ok().filter(|metadata| metadata.is_file()).map(|metadata| metadata.len())) {
+            let job = batch::mirrored(&dir_raw, &path, output_dir);
+            if let Some(parent) = job.output.as_deref().and_then(|output| Path::new(output).parent()) {
+                fs::create_dir_all(parent).map_err(|error| Error::output(format!("failed to create output directory {}", parent.display()), error))?;
+            }
+            tracing::info!("transcribing {}", job.input);
+            // with --tasks the audio is moved once every task is done, or the first fails
+            let jobs = transcribe::tasks(&args.transcribe, vec![job]);
+            let mut finished = Err(Error::Input("there's nothing to do".to_string()));
+            for job in &jobs {
+                finished = transcribe::finish(&args.transcribe, job, transcribe::transcribe_file(&engine, refiner.as_ref(), &args.transcribe, job, &bars));
+                if finished.is_err() {
+                    break;
+                }
+            }
+            finish(&jobs[0], finished)?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _settling() {
+        let start = Instant::now();
+        let settle = Duration::from_secs(2);
+        let at = |seconds| start + Duration::from_secs(seconds);
+        let mut settling = Settling::default();
+        settling.touch(PathBuf::from("a.wav"), start);
+        settling.touch(PathBuf::from("b.wav"), start);
+        settling.touch(PathBuf::from("gone.wav"), start);
+        let sizes = |sizes: &'static [(&'static str, u64)]| move |path: &Path| sizes.iter().find(|(name, _)| Path::new(name) == path).map(|(_, size)| *size);
+
+        assert!(settling.ready(at(1), settle, sizes(&[("a.wav", 10), ("b.wav", 10)])).is_empty());
+        // the first look only notes the sizes
+        assert!(settling.ready(at(2), settle, sizes(&[("a.wav", 10), ("b.wav", 10)])).is_empty());
+        assert_eq!(settling.files.len(), 2);
+        // b is still being written
+        assert_eq!(settling.ready(at(4), settle, sizes(&[("a.wav", 10), ("b.wav", 20)])), vec![PathBuf::from("a.wav")]);
+        settling.touch(PathBuf::from("b.wav"), at(5));
+        assert!(settling.ready(at(6), settle, sizes(&[("b.wav", 20)])).is_empty());
+        assert_eq!(settling.ready(at(7), settle, sizes(&[("b.wav", 20)])), vec![PathBuf::from("b.wav")]);
+        assert!(settling.files.is_empty());
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use transcript::audio::http;
+use transcript::error::Error;
+
+use crate::batch;
+use crate::transcribe::Finished;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// what --webhook is sent once a file is done with, whichever way it went
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Payload<'a> {
+    input: &'a str,
+    status: &'static str,
+    outputs: &'a [String],
+    // seconds of audio
+    duration: Option<f64>,
+    language: Option<&'a str>,
+    error: Option<String>,
+}
+
+impl<'a> Payload<'a> {
+    pub fn new(job: &'a batch::Job, finished: &'a Result<Finished, Error>) -> Payload<'a> {
+        match finished {
+            Ok(finished) => Payload {
+                input: &job.input,
+                status: "done",
+                outputs: &finished.outputs,
+                duration: Some(finished.audio_ms as f64 / 1000.0),
+                language: Some(&finished.language),
+                error: None,
+            },
+            Err(error) => Payload { input: &job.input, status: "failed", outputs: &[], duration: None, language: job.language.as_deref(), error: Some(error.to_string()) },
+        }
+    }
+
+    // a --stdin-jobs line that isn't a job at all, so has no input to name
+    pub fn rejected(error: String) -> Payload<'a> {
+        Payload { input: "", status: "failed", outputs: &[], duration: None, language: None, error: Some(error) }
+    }
+}
+
+// a webhook that's down shouldn't cost the transcript, so failing to reach it is only a warning
+pub fn post(url: &str, payload: &Payload) {
+    let body = serde_json::to_string(payload).expect("failed to serialize webhook payload");
+    if let Err(error) = ureq::post(url).timeout(TIMEOUT).set("Content-Type", "application/json").send_string(&body) {
+        tracing::warn!("webhook {} for {}: {}", url, payload.input, http::describe(error));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _payload() {
+        let job = batch::Job { input: "a.wav".to_string(), output: Some("out/a".to_string()), language: None, translate: None };
+        let finished = Ok(Finished { audio_ms: 1500, segments: 2, language: "en".to_string(), outputs: vec!["out/a.txt".to_string()] });
+        assert_eq!(
+            serde_json::to_string(&Payload::new(&job, &finished)).unwrap(),
+            r#"{"input":"a.wav","status":"done","outputs":["out/a.txt"],"duration":1.5,"language":"en","error":null}"#
+        );
+        let failed = Err(Error::Input("failed to decode a.wav".to_string()));
+        let payload = Payload::new(&job, &failed);
+        assert_eq!((payload.status, payload.outputs.len(), payload.error.as_deref()), ("failed", 0, Some("failed to decode a.wav")));
+    }
+}